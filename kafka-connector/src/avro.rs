@@ -0,0 +1,96 @@
+//Avro decoding for Confluent-framed messages, only compiled with the `avro` cargo feature
+//Producers using Confluent serializers don't put the schema in every message, they prefix the Avro bytes with:
+//  byte 0     magic byte, always 0
+//  bytes 1-4  schema id, big-endian u32, the key of the writer's schema in the Schema Registry
+//  bytes 5..  the Avro-encoded record
+//The schema is fetched from SCHEMA_REGISTRY_URL the first time an id is seen and cached after that
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use apache_avro::Schema;
+
+const MAGIC_BYTE: u8 = 0;
+const HEADER_LEN: usize = 5;
+
+pub struct SchemaRegistry {
+    pub url: String,
+    client: reqwest::Client,
+    //std RwLock is fine here because it is never held across an .await
+    cache: RwLock<HashMap<u32, Arc<Schema>>>,
+}
+
+//one registry shared by every processing task, created on first use from SCHEMA_REGISTRY_URL
+static REGISTRY: OnceLock<Option<SchemaRegistry>> = OnceLock::new();
+
+//None when SCHEMA_REGISTRY_URL isn't set, payloads are then treated as plain text
+pub fn registry() -> Option<&'static SchemaRegistry> {
+    REGISTRY
+        .get_or_init(|| std::env::var("SCHEMA_REGISTRY_URL").ok().map(SchemaRegistry::new))
+        .as_ref()
+}
+
+//Some(text) for a Confluent-framed payload: the record as JSON, or a description of why decoding failed
+//None if there is no registry configured or the payload doesn't start with the Confluent header
+pub async fn decode_payload(payload: Option<&[u8]>) -> Option<String> {
+    let registry = registry()?;
+    let (schema_id, body) = split_confluent_frame(payload?)?;
+    Some(match registry.decode(schema_id, body).await {
+        Ok(json) => json.to_string(),
+        Err(e) => format!("<avro decode failed: {}>", e),
+    })
+}
+
+//(schema id, Avro body) if the payload carries the magic byte and a full header
+pub fn split_confluent_frame(payload: &[u8]) -> Option<(u32, &[u8])> {
+    if payload.len() < HEADER_LEN || payload[0] != MAGIC_BYTE {
+        return None;
+    }
+    let schema_id = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+    Some((schema_id, &payload[HEADER_LEN..]))
+}
+
+impl SchemaRegistry {
+    pub fn new(url: String) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    //decode one Avro record written with the schema registered under schema_id
+    pub async fn decode(&self, schema_id: u32, mut body: &[u8]) -> Result<serde_json::Value, String> {
+        let schema = self.schema(schema_id).await?;
+        //&mut &[u8] implements io::Read, reading advances the slice
+        let value = apache_avro::from_avro_datum(&schema, &mut body, None).map_err(|e| e.to_string())?;
+        serde_json::Value::try_from(value).map_err(|e| e.to_string())
+    }
+
+    //cached schema, or fetch it on a cache miss
+    //two tasks missing the same id at once both fetch it, which is harmless and avoids holding a lock across the request
+    async fn schema(&self, schema_id: u32) -> Result<Arc<Schema>, String> {
+        if let Some(schema) = self.cache.read().unwrap().get(&schema_id) {
+            return Ok(schema.clone());
+        }
+
+        let schema = Arc::new(self.fetch(schema_id).await?);
+        self.cache.write().unwrap().insert(schema_id, schema.clone());
+        Ok(schema)
+    }
+
+    //GET /schemas/ids/{id} answers {"schema": "<the Avro schema as a JSON string>"}
+    async fn fetch(&self, schema_id: u32) -> Result<Schema, String> {
+        let url = format!("{}/schemas/ids/{}", self.url, schema_id);
+        let response = self.client.get(&url).send().await.map_err(|e| format!("{}: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("{}: HTTP {}", url, response.status()));
+        }
+        let body = response.text().await.map_err(|e| format!("{}: {}", url, e))?;
+
+        let json: serde_json::Value = serde_json::from_str(&body).map_err(|e| format!("{}: {}", url, e))?;
+        let schema = json["schema"]
+            .as_str()
+            .ok_or_else(|| format!("{}: response has no \"schema\" field", url))?;
+        Schema::parse_str(schema).map_err(|e| format!("schema {}: {}", schema_id, e))
+    }
+}
@@ -0,0 +1,86 @@
+//Backpressure: at most --max-in-flight messages are handled at once, each task holds a permit from a semaphore until its
+//handler returns
+//
+//While every permit is taken run() stops reading the stream, which is enough for a short hiccup. But a stream that isn't
+//read for longer than max.poll.interval.ms gets this consumer kicked out of the group, and its partitions handed to
+//someone else mid-processing. So when the permits stay used up for longer than --pause-after-ms the assigned partitions
+//are paused instead: the broker stops sending them, run() can go back to reading the stream (which now stays quiet)
+//and the consumer stays in the group. Once in-flight has drained to half the limit they are resumed, half rather than
+//one free permit so a slow handler doesn't flip between paused and resumed on every message
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transition {
+    Pause,
+    Resume,
+}
+
+pub struct Backpressure {
+    permits: Arc<Semaphore>,
+    limit: usize,
+    pause_after: Duration,
+    saturated_since: Option<Instant>, //when the last free permit was taken, None while any are free
+    paused: bool,
+}
+
+impl Backpressure {
+    pub fn new(limit: usize, pause_after: Duration) -> Self {
+        Backpressure {
+            permits: Arc::new(Semaphore::new(limit)),
+            limit,
+            pause_after,
+            saturated_since: None,
+            paused: false,
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.limit - self.permits.available_permits()
+    }
+
+    //whether run() should poll the stream: while paused it has to keep polling to stay in the group, and the messages
+    //librdkafka had already fetched before the pause may still arrive and wait in acquire()
+    pub fn can_read(&self) -> bool {
+        self.paused || self.permits.available_permits() > 0
+    }
+
+    //the permit for one message, moved into its task and dropped when the handler is done
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        //the semaphore is never closed, so acquiring can only fail if that changes
+        Arc::clone(&self.permits).acquire_owned().await.expect("in-flight semaphore closed")
+    }
+
+    //when check() will pause if nothing finishes before then, for run() to wake up at
+    pub fn pause_deadline(&self) -> Option<Instant> {
+        match self.saturated_since {
+            Some(since) if !self.paused => Some(since + self.pause_after),
+            _ => None,
+        }
+    }
+
+    //called by run() every time round its loop, says whether the partitions should be paused or resumed now
+    pub fn check(&mut self, now: Instant) -> Option<Transition> {
+        let in_flight = self.in_flight();
+        if self.paused {
+            if in_flight <= self.limit / 2 {
+                self.paused = false;
+                self.saturated_since = None;
+                return Some(Transition::Resume);
+            }
+            return None;
+        }
+        if in_flight < self.limit {
+            self.saturated_since = None;
+            return None;
+        }
+        let since = *self.saturated_since.get_or_insert(now);
+        if now.duration_since(since) >= self.pause_after {
+            self.paused = true;
+            return Some(Transition::Pause);
+        }
+        None
+    }
+}
@@ -0,0 +1,271 @@
+//Settings for the consumer, read from environment variables first and then overridden by command-line flags
+//usage: kafka-connector [--max-messages N] [--group-id ID] [--group-instance-id ID] [--metrics-port PORT]
+//                       [--delivery at-most-once|at-least-once] [--max-in-flight N] [--pause-after-ms MS]
+//                       [--dedup-window N] [--bench-seconds N] [--work-delay-ms MS] [--output text|jsonl]
+//                       [--otlp-endpoint URL] [--statsd-host HOST[:PORT]]
+//                       [--fetch-min-bytes N] [--fetch-wait-max-ms MS] [--max-poll-interval-ms MS]
+//                       [--session-timeout-ms MS] [--queued-max-messages-kbytes N] [--connect-attempts N]
+//env: KAFKA_BROKERS (default localhost:9092), KAFKA_TOPIC (default test-topic), MAX_MESSAGES,
+//     KAFKA_GROUP_ID (default rust-consumer-group), KAFKA_GROUP_INSTANCE_ID, METRICS_PORT,
+//     KAFKA_DELIVERY (default at-most-once), KAFKA_MAX_IN_FLIGHT (default 1000), KAFKA_PAUSE_AFTER_MS (default 5000),
+//     KAFKA_DEDUP_WINDOW, KAFKA_BENCH_SECONDS, KAFKA_WORK_DELAY_MS (default 0), KAFKA_OUTPUT (default text),
+//     OTEL_EXPORTER_OTLP_ENDPOINT, STATSD_HOST, KAFKA_FETCH_MIN_BYTES, KAFKA_FETCH_WAIT_MAX_MS,
+//     KAFKA_MAX_POLL_INTERVAL_MS, KAFKA_SESSION_TIMEOUT_MS, KAFKA_QUEUED_MAX_MESSAGES_KBYTES (librdkafka's defaults),
+//     KAFKA_CONNECT_ATTEMPTS (default 10)
+//hidden: --seed N publishes N test messages instead of consuming, see seed.rs
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::time::Duration;
+
+use crate::delivery::Delivery;
+use crate::output::Output;
+use crate::statsd;
+use crate::tuning::Tuning;
+
+pub struct Config {
+    pub brokers: String,
+    pub topic: String,
+    //Some(n): stop after n messages, commit and exit, handy for tests and CI. None: consume forever
+    pub max_messages: Option<u64>,
+    //consumers with the same group id share the topic's partitions, give each deployment its own id to avoid collisions
+    pub group_id: String,
+    //setting group.instance.id makes this a static member: a restart within session.timeout.ms gets its old partitions
+    //back instead of triggering a rebalance of the whole group
+    pub group_instance_id: Option<String>,
+    //Some(port): serve Prometheus metrics at http://0.0.0.0:port/metrics, None: no HTTP server at all
+    pub metrics_port: Option<u16>,
+    //when offsets are committed relative to processing, see delivery.rs for the tradeoff
+    pub delivery: Delivery,
+    //how many messages can be in the handler at once, and how long that many can be before the partitions are paused,
+    //see backpressure.rs
+    pub max_in_flight: usize,
+    pub pause_after: Duration,
+    //Some(n): skip messages whose key was among the last n distinct keys seen, see dedup.rs. None: process everything
+    pub dedup_window: Option<NonZeroUsize>,
+    //Some(duration): consume for that long without printing, then report the throughput, see bench.rs
+    pub bench: Option<Duration>,
+    //how long the handler pretends to work on each message, zero so the consumer runs at full speed unless asked otherwise
+    pub work_delay: Duration,
+    //human readable lines or one JSON object per message on stdout, see output.rs. --bench-seconds prints neither
+    pub output: Output,
+    //Some(url): send a trace span per message to this OTLP collector, see telemetry.rs (needs the otel feature)
+    //the env var is OpenTelemetry's standard one, so it can be shared with the other services in the trace
+    pub otlp_endpoint: Option<String>,
+    //Some(host:port): send the consumed count and processing times to this statsd daemon over UDP, see statsd.rs
+    //None: nothing is sent, the port defaults to 8125 when only a host is given
+    pub statsd_host: Option<String>,
+    //librdkafka fetch and group settings, only the ones that were given are passed on, see tuning.rs
+    pub tuning: Tuning,
+    //how many times to try reaching a broker at startup before giving up, with a growing wait in between, see connect.rs
+    pub connect_attempts: NonZeroU32,
+    //Some(n): produce n numbered messages to the topic and exit without consuming, see seed.rs. A flag only, no env var,
+    //so a stray variable can't turn a consumer into a producer
+    pub seed: Option<u64>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let mut config = Config {
+            brokers: std::env::var("KAFKA_BROKERS").unwrap_or("localhost:9092".into()),
+            topic: std::env::var("KAFKA_TOPIC").unwrap_or("test-topic".into()),
+            //Option<Result<..>> -> Result<Option<..>> so a bad value is an error instead of being ignored
+            max_messages: std::env::var("MAX_MESSAGES").ok().map(|value| parse_max_messages(&value)).transpose()?,
+            group_id: std::env::var("KAFKA_GROUP_ID").unwrap_or("rust-consumer-group".into()),
+            group_instance_id: std::env::var("KAFKA_GROUP_INSTANCE_ID").ok(),
+            metrics_port: std::env::var("METRICS_PORT").ok().map(|value| parse_port(&value)).transpose()?,
+            //at-most-once is what the consumer always did with auto commit on
+            delivery: std::env::var("KAFKA_DELIVERY").ok().map(|value| Delivery::parse(&value)).transpose()?.unwrap_or(Delivery::AtMostOnce),
+            max_in_flight: std::env::var("KAFKA_MAX_IN_FLIGHT").ok().map(|value| parse_max_in_flight(&value)).transpose()?.unwrap_or(1000),
+            //well under librdkafka's default max.poll.interval.ms of 5 minutes
+            pause_after: std::env::var("KAFKA_PAUSE_AFTER_MS").ok().map(|value| parse_pause_after(&value)).transpose()?.unwrap_or(Duration::from_secs(5)),
+            dedup_window: std::env::var("KAFKA_DEDUP_WINDOW").ok().map(|value| parse_dedup_window(&value)).transpose()?,
+            bench: std::env::var("KAFKA_BENCH_SECONDS").ok().map(|value| parse_bench_seconds(&value)).transpose()?,
+            work_delay: std::env::var("KAFKA_WORK_DELAY_MS").ok().map(|value| parse_work_delay(&value)).transpose()?.unwrap_or(Duration::ZERO),
+            output: std::env::var("KAFKA_OUTPUT").ok().map(|value| Output::parse(&value)).transpose()?.unwrap_or(Output::Text),
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().map(|value| parse_otlp_endpoint(&value)).transpose()?,
+            statsd_host: std::env::var("STATSD_HOST").ok().map(|value| parse_statsd_host(&value)).transpose()?,
+            tuning: Tuning::from_env()?,
+            connect_attempts: std::env::var("KAFKA_CONNECT_ATTEMPTS")
+                .ok()
+                .map(|value| parse_connect_attempts(&value))
+                .transpose()?
+                .unwrap_or(NonZeroU32::new(10).unwrap()),
+            seed: None,
+        };
+
+        //skip(1) drops the program name, the remaining arguments are the flags
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--max-messages" => {
+                    let value = args.next().ok_or("--max-messages expects a value")?;
+                    config.max_messages = Some(parse_max_messages(&value)?);
+                }
+                "--group-id" => config.group_id = args.next().ok_or("--group-id expects a value")?,
+                "--group-instance-id" => {
+                    config.group_instance_id = Some(args.next().ok_or("--group-instance-id expects a value")?)
+                }
+                "--metrics-port" => {
+                    let value = args.next().ok_or("--metrics-port expects a value")?;
+                    config.metrics_port = Some(parse_port(&value)?);
+                }
+                "--delivery" => {
+                    let value = args.next().ok_or("--delivery expects a value")?;
+                    config.delivery = Delivery::parse(&value)?;
+                }
+                "--max-in-flight" => {
+                    let value = args.next().ok_or("--max-in-flight expects a value")?;
+                    config.max_in_flight = parse_max_in_flight(&value)?;
+                }
+                "--pause-after-ms" => {
+                    let value = args.next().ok_or("--pause-after-ms expects a value")?;
+                    config.pause_after = parse_pause_after(&value)?;
+                }
+                "--dedup-window" => {
+                    let value = args.next().ok_or("--dedup-window expects a value")?;
+                    config.dedup_window = Some(parse_dedup_window(&value)?);
+                }
+                "--bench-seconds" => {
+                    let value = args.next().ok_or("--bench-seconds expects a value")?;
+                    config.bench = Some(parse_bench_seconds(&value)?);
+                }
+                "--work-delay-ms" => {
+                    let value = args.next().ok_or("--work-delay-ms expects a value")?;
+                    config.work_delay = parse_work_delay(&value)?;
+                }
+                "--output" => {
+                    let value = args.next().ok_or("--output expects a value")?;
+                    config.output = Output::parse(&value)?;
+                }
+                "--otlp-endpoint" => {
+                    let value = args.next().ok_or("--otlp-endpoint expects a value")?;
+                    config.otlp_endpoint = Some(parse_otlp_endpoint(&value)?);
+                }
+                "--statsd-host" => {
+                    let value = args.next().ok_or("--statsd-host expects a value")?;
+                    config.statsd_host = Some(parse_statsd_host(&value)?);
+                }
+                "--connect-attempts" => {
+                    let value = args.next().ok_or("--connect-attempts expects a value")?;
+                    config.connect_attempts = parse_connect_attempts(&value)?;
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed expects a value")?;
+                    config.seed = Some(parse_seed(&value)?);
+                }
+                flag if Tuning::is_flag(flag) => {
+                    let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+                    config.tuning.set_flag(flag, &value)?;
+                }
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+
+        //an empty group id is rejected by the broker with a confusing error much later, catch it here instead
+        if config.group_id.trim().is_empty() {
+            return Err("group id must not be empty (KAFKA_GROUP_ID / --group-id)".into());
+        }
+        if config.group_instance_id.as_ref().is_some_and(|id| id.trim().is_empty()) {
+            return Err("group instance id must not be empty when set (KAFKA_GROUP_INSTANCE_ID / --group-instance-id)".into());
+        }
+        //after the flags, an env var for one of the pair can be fixed by a flag for the other
+        config.tuning.validate()?;
+
+        Ok(config)
+    }
+}
+
+//0 would mean exiting before reading anything, which is never what the caller wanted
+fn parse_max_messages(value: &str) -> Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("max messages must be a positive number, got '{}'", value)),
+    }
+}
+
+//port 0 would let the OS pick a random one, which nothing could then be pointed at
+fn parse_port(value: &str) -> Result<u16, String> {
+    match value.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("metrics port must be a number from 1 to 65535, got '{}'", value)),
+    }
+}
+
+//0 would never let a single message through
+fn parse_max_in_flight(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("max in flight must be a positive number, got '{}'", value)),
+    }
+}
+
+//0 is allowed: pause as soon as the limit is reached instead of first waiting for the handlers to catch up
+fn parse_pause_after(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("pause after must be a number of milliseconds, got '{}'", value))
+}
+
+//a window of 0 would remember nothing, leave the option out to turn dedup off
+fn parse_dedup_window(value: &str) -> Result<NonZeroUsize, String> {
+    value
+        .parse::<NonZeroUsize>()
+        .map_err(|_| format!("dedup window must be a positive number of keys, got '{}'", value))
+}
+
+//0 is the default, no simulated work at all
+fn parse_work_delay(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("work delay must be a number of milliseconds, got '{}'", value))
+}
+
+//0 would stop before the first message had a chance to arrive
+fn parse_bench_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(format!("bench seconds must be a positive number, got '{}'", value)),
+    }
+}
+
+//the exporter's own error for a bad URL only shows up once the first batch fails to send, long after startup
+fn parse_otlp_endpoint(value: &str) -> Result<String, String> {
+    if value.starts_with("http://") || value.starts_with("https://") {
+        Ok(value.to_string())
+    } else {
+        Err(format!("OTLP endpoint must be an http:// or https:// URL, got '{}'", value))
+    }
+}
+
+//host or host:port, the host itself is only looked up at startup, this catches a port that can't be one
+//an IPv6 address needs brackets like in a URL, [::1]:8125, or the last part of it would be taken for the port
+fn parse_statsd_host(value: &str) -> Result<String, String> {
+    let (host, port) = match value.rsplit_once(':') {
+        Some((host, port)) if !value.ends_with(']') => (host, Some(port)),
+        _ => (value, None),
+    };
+    if host.is_empty() {
+        return Err(format!("statsd host must be HOST or HOST:PORT, got '{}'", value));
+    }
+    match port.map(str::parse::<u16>) {
+        None => Ok(format!("{}:{}", host, statsd::DEFAULT_PORT)),
+        Some(Ok(port)) if port > 0 => Ok(format!("{}:{}", host, port)),
+        Some(_) => Err(format!("statsd port must be a number from 1 to 65535, got '{}'", value)),
+    }
+}
+
+//0 attempts would give up without trying, 1 is no retrying at all
+fn parse_connect_attempts(value: &str) -> Result<NonZeroU32, String> {
+    value
+        .parse::<NonZeroU32>()
+        .map_err(|_| format!("connect attempts must be a positive number, got '{}'", value))
+}
+
+//seeding nothing would succeed without telling the test harness anything
+fn parse_seed(value: &str) -> Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("seed must be a positive number of messages, got '{}'", value)),
+    }
+}
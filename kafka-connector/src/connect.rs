@@ -0,0 +1,67 @@
+//Creating the consumer and subscribing it, retried with exponential backoff while the broker can't be reached, so the
+//connector can start before its broker does (docker compose, a Kubernetes pod next to the broker's) instead of
+//crashing and relying on whatever restarts it
+//
+//Neither create() nor subscribe() talks to the broker: librdkafka connects on its own thread afterwards and keeps
+//retrying forever, so a broker that isn't there would only show up as a stream that never yields anything. Each
+//attempt therefore also asks for the topic's metadata, which needs an answer from a broker within METADATA_TIMEOUT
+//Only that is retried, a create() that fails is a bad setting in the ClientConfig and fails the same way every time
+//
+//KAFKA_CONNECT_ATTEMPTS / --connect-attempts (default 10) is how many attempts there are before giving up, with
+//INITIAL_BACKOFF, 2x, 4x... up to MAX_BACKOFF between them, about two minutes in all with the default
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::ClientConfig;
+
+use crate::metrics::{Metrics, MetricsContext};
+use crate::output::status;
+
+//how long one attempt waits for a broker to answer the metadata request
+const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub async fn connect(
+    client_config: &ClientConfig,
+    metrics: &Arc<Metrics>,
+    topic: &str,
+    attempts: NonZeroU32,
+) -> Result<StreamConsumer<MetricsContext>, String> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let consumer: StreamConsumer<MetricsContext> = client_config
+            .create_with_context(MetricsContext { metrics: Arc::clone(metrics) })
+            .map_err(|e| format!("Consumer creation failed: {}", e))?;
+        consumer.subscribe(&[topic]).map_err(|e| format!("Failed to subscribe to {}: {}", topic, e))?;
+
+        //fetch_metadata() blocks for up to the timeout, on a blocking thread so the runtime's workers aren't held up
+        //the consumer goes there and comes back, spawn_blocking() can't borrow it
+        let topic_name = topic.to_string();
+        let (consumer, result) = tokio::task::spawn_blocking(move || {
+            let result = consumer.fetch_metadata(Some(&topic_name), METADATA_TIMEOUT);
+            (consumer, result)
+        })
+        .await
+        .map_err(|e| format!("Broker check failed: {}", e))?;
+
+        match result {
+            Ok(_) => return Ok(consumer),
+            Err(e) if attempt >= attempts.get() => {
+                return Err(format!("Couldn't reach a broker after {} attempt(s), giving up: {}", attempts, e));
+            }
+            Err(e) => {
+                status!("Couldn't reach a broker (attempt {}/{}): {}, retrying in {} ms", attempt, attempts, e, backoff.as_millis());
+                //the failed consumer is dropped here, the next attempt starts over with a fresh one
+                drop(consumer);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//Dedup by message key: with --dedup-window N (or KAFKA_DEDUP_WINDOW) the consumer remembers the keys of the last N
+//distinct messages and skips a message whose key is among them, for topics where producers may send the same message
+//twice (a retry after a timeout that actually went through, an at-least-once producer restarting)
+//This is best effort: only duplicates that arrive within the window are caught, the memory is lost on restart, and with
+//several consumers in the group each one only sees the keys of its own partitions, which is fine as long as producers
+//key by what identifies a message since the same key always goes to the same partition
+//Messages without a key can't be told apart and are always processed
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+pub struct Dedup {
+    //only the keys matter, LruCache drops the least recently seen one once it holds `window` of them
+    seen: LruCache<Vec<u8>, ()>,
+    skipped: u64,
+}
+
+impl Dedup {
+    pub fn new(window: NonZeroUsize) -> Self {
+        Dedup { seen: LruCache::new(window), skipped: 0 }
+    }
+
+    //true if `key` was seen within the window, the message should be skipped
+    //the key is compared as raw bytes, keys are often binary (Avro, hashes, big-endian ids) so nothing is decoded
+    //a repeat counts as seen again, so a key that keeps coming back stays in the window
+    pub fn is_duplicate(&mut self, key: Option<&[u8]>) -> bool {
+        let Some(key) = key else { return false };
+        let duplicate = self.seen.put(key.to_vec(), ()).is_some();
+        if duplicate {
+            self.skipped += 1;
+        }
+        duplicate
+    }
+
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+//A key for printing: as text if it is valid UTF-8 without control characters, otherwise as hex
+pub fn display_key(key: &[u8]) -> String {
+    match std::str::from_utf8(key) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => {
+            let hex: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("0x{}", hex)
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//Delivery guarantee: when offsets are committed relative to when the handler runs, chosen with --delivery or KAFKA_DELIVERY
+//
+//at-most-once (default): librdkafka auto-commits on a timer the offsets of everything already read from the stream,
+//whether or not the handler has finished with it. A crash loses the messages that were still being processed, but
+//nothing is ever handled twice
+//at-least-once: auto commit is off and run() commits an offset only after the handler has finished with that message
+//and every earlier one in its partition. A crash re-delivers whatever was in flight, so a message can be handled twice
+//and the handler should be idempotent
+use std::collections::{BTreeSet, HashMap};
+
+use rdkafka::{Offset, TopicPartitionList};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    AtMostOnce,
+    AtLeastOnce,
+}
+
+impl Delivery {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "at-most-once" => Ok(Delivery::AtMostOnce),
+            "at-least-once" => Ok(Delivery::AtLeastOnce),
+            _ => Err(format!("delivery must be 'at-most-once' or 'at-least-once', got '{}'", value)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Delivery::AtMostOnce => "at-most-once",
+            Delivery::AtLeastOnce => "at-least-once",
+        }
+    }
+
+    //value for librdkafka's enable.auto.commit
+    pub fn auto_commit(self) -> &'static str {
+        match self {
+            Delivery::AtMostOnce => "true",
+            Delivery::AtLeastOnce => "false",
+        }
+    }
+
+    //printed at startup so whoever reads the logs knows what a crash would cost
+    pub fn tradeoff(self) -> &'static str {
+        match self {
+            Delivery::AtMostOnce => {
+                "offsets are committed as messages are read, a crash may lose messages still being processed but never repeats one"
+            }
+            Delivery::AtLeastOnce => {
+                "offsets are committed after messages are processed, a crash never loses a message but may process some twice"
+            }
+        }
+    }
+}
+
+//Messages are handled in parallel and can finish in any order, but a Kafka commit is a single offset per partition meaning
+//"everything before this is done". So for at-least-once the offsets still in flight are remembered per partition and the
+//commit only moves up to the oldest of them: committing past it would skip that message if we crashed before it finished
+#[derive(Default)]
+pub struct OffsetTracker {
+    partitions: HashMap<(String, i32), PartitionOffsets>,
+}
+
+#[derive(Default)]
+struct PartitionOffsets {
+    in_flight: BTreeSet<i64>, //started but not finished, sorted so the oldest is first
+    next: Option<i64>,        //offset after the highest finished message
+    committed: Option<i64>,   //last offset handed out by committable(), to only commit when it moved
+}
+
+impl OffsetTracker {
+    //a message was read and handed to the handler
+    pub fn start(&mut self, topic: &str, partition: i32, offset: i64) {
+        self.partitions.entry((topic.to_string(), partition)).or_default().in_flight.insert(offset);
+    }
+
+    //the handler is done with it, successfully or not: a failed message is reported and counted but not retried, so
+    //holding the commit back for it would stall its partition for good
+    pub fn finish(&mut self, topic: &str, partition: i32, offset: i64) {
+        if let Some(state) = self.partitions.get_mut(&(topic.to_string(), partition)) {
+            state.in_flight.remove(&offset);
+            state.next = state.next.max(Some(offset + 1));
+        }
+    }
+
+    //offsets that can be committed now and weren't already, None if nothing moved
+    //a committed offset is the next one to read, so it is the oldest message still in flight, or one past the highest
+    //finished message when nothing is in flight
+    pub fn committable(&mut self) -> Option<TopicPartitionList> {
+        let mut list = TopicPartitionList::new();
+        for ((topic, partition), state) in &mut self.partitions {
+            let next = match state.in_flight.first().copied().or(state.next) {
+                Some(next) if Some(next) != state.committed => next,
+                _ => continue,
+            };
+            //add_partition_offset only fails for an invalid offset, these all come from real messages
+            let _ = list.add_partition_offset(topic, *partition, Offset::Offset(next));
+            state.committed = Some(next);
+        }
+        (list.count() > 0).then_some(list)
+    }
+}
@@ -0,0 +1,122 @@
+//What happens to each consumed message is decided by a MessageHandler, the consume loop in main only reads, spawns and commits
+//Plugging in different logic means writing a struct that implements the trait and passing it to run() instead of PrintHandler
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use rdkafka::message::{Message, OwnedMessage};
+
+//A handler failing on one message is reported and counted, the consumer keeps going with the next one
+#[derive(Debug)]
+pub struct HandlerError(pub String);
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+//`async fn` in a trait can't be called through a Box<dyn ...>: every implementation would return its own future type
+//and a trait object needs one known type. So handle() is written out by hand to return the future boxed on the heap,
+//Pin<Box<dyn Future>> is that one type no matter which handler made it (this is what the async-trait crate generates)
+//Send + Sync because handlers are shared by every processing task and those can run on any tokio worker thread
+//
+//The message is an OwnedMessage rather than the stream's BorrowedMessage<'_>: each message is processed in its own spawned
+//task, which may outlive the borrow, so main detaches it first (see the comment above run() in main.rs)
+pub trait MessageHandler: Send + Sync {
+    fn handle<'a>(&'a self, msg: &'a OwnedMessage) -> Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send + 'a>>;
+}
+
+//The default handler: print the payload (decoded from Avro with the avro feature)
+//with --work-delay-ms each message also takes that long, the demo's stand-in for real work
+pub struct PrintHandler {
+    pub work_delay: Duration,
+}
+
+impl MessageHandler for PrintHandler {
+    fn handle<'a>(&'a self, msg: &'a OwnedMessage) -> Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send + 'a>> {
+        //Box::pin(async move { .. }) turns the block's anonymous future into the boxed type the trait asks for
+        Box::pin(async move {
+            println!("Processing message: {}", payload_text(msg).await);
+            simulate_work(self.work_delay).await;
+            Ok(())
+        })
+    }
+}
+
+//--output jsonl: the message and where it came from as one line of JSON, see output.rs
+pub struct JsonLinesHandler {
+    pub work_delay: Duration,
+}
+
+impl MessageHandler for JsonLinesHandler {
+    fn handle<'a>(&'a self, msg: &'a OwnedMessage) -> Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send + 'a>> {
+        Box::pin(async move {
+            //one println! per line, stdout is locked for the whole call so lines from different tasks never interleave
+            println!("{}", crate::output::json_line(msg));
+            simulate_work(self.work_delay).await;
+            Ok(())
+        })
+    }
+}
+
+//Does nothing with the message, --bench-seconds uses it so printing thousands of payloads a second isn't what gets measured
+pub struct DiscardHandler {
+    pub work_delay: Duration,
+}
+
+impl MessageHandler for DiscardHandler {
+    fn handle<'a>(&'a self, _msg: &'a OwnedMessage) -> Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send + 'a>> {
+        Box::pin(async move {
+            simulate_work(self.work_delay).await;
+            Ok(())
+        })
+    }
+}
+
+//a sleep rather than busy work: it holds the task (and its permit) without using a CPU, like waiting on a database would
+//a zero delay returns straight away instead of sleeping, even a 0 ms sleep goes through tokio's timer
+async fn simulate_work(delay: Duration) {
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+//The payload as text for printing
+pub async fn payload_text(m: &OwnedMessage) -> String {
+    //m.payload_view::<str>() is a method from the Message trait and it returns an Option<Result<&T, ErrorType>>
+    //Try to convert bit stream to UTF-8 encoded string and actually returns following type: Option<Result<&str, Utf8Error>>
+    //Following definitions apply:
+    /*
+
+        enum Option<T> {
+            Some(T),
+            None,
+        }
+
+        enum Result<T, E> {
+            Ok(T),   // success, contains value of type T
+            Err(E),  // failure, contains error of type E
+        }
+
+     */
+    //So if valid UTF-8 payload: Some(Ok("hello"))
+    //if invalid UTF-8 payload: Some(Err(Utf8Error))
+    //if no payload: None
+
+    //With the avro feature, Confluent-framed Avro payloads are decoded to JSON through the Schema Registry first
+    //#[cfg] picks one of the two lines at compile time, so without the feature avro.rs isn't even built
+    #[cfg(feature = "avro")]
+    let avro_payload = crate::avro::decode_payload(m.payload()).await;
+    #[cfg(not(feature = "avro"))]
+    let avro_payload: Option<String> = None;
+
+    match (avro_payload, m.payload_view::<str>()) {
+        (Some(json), _) => json,
+        //Knowing the above : pattern-match -> Some(Ok(s)) means valid payload make a string
+        //Otherwise invalid
+        (None, Some(Ok(s))) => s.to_string(),
+        _ => "<invalid utf8>".into(),
+    }
+}
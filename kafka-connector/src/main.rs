@@ -1,7 +1,36 @@
-use rdkafka::consumer::{Consumer, StreamConsumer}; 
-use rdkafka::message::BorrowedMessage;
+#[cfg(feature = "avro")]
+mod avro;
+mod backpressure;
+mod bench;
+mod config;
+mod connect;
+mod dedup;
+mod delivery;
+mod handler;
+mod metrics;
+mod output;
+mod seed;
+mod statsd;
+#[cfg(feature = "otel")]
+mod telemetry;
+mod tuning;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use backpressure::{Backpressure, Transition};
+use bench::Bench;
+use config::Config;
+use dedup::Dedup;
+use delivery::{Delivery, OffsetTracker};
+use handler::{DiscardHandler, HandlerError, JsonLinesHandler, MessageHandler, PrintHandler};
+use metrics::{ErrorStage, Metrics, MetricsContext};
+use output::{status, Output};
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{Message, OwnedMessage};
 use rdkafka::ClientConfig;
-use tokio::task;
+use tokio::task::{Id, JoinError, JoinSet};
 use tokio_stream::StreamExt;
 
 /*
@@ -31,69 +60,348 @@ match s {
 
 */
 
-//Script doesn't own the Kafka message, m, it borrows it and contains a reference to it
-//the <`_> return type is a lifetime, it means the message, m, cannot live longer than the Kafka lib owner to prevent invalid access 
+//The stream hands out BorrowedMessage<'_>, which borrows from the consumer: the <'_> lifetime means it cannot outlive the
+//Kafka lib owner to prevent invalid access. A spawned task can outlive anything, so run() calls detach() to copy the message
+//into an OwnedMessage that the task owns outright
 //The message contains payload, topic, partition, offset, key, headers, timestamp, other metadata
-//BorrowedMessage is a struct
-async fn process_message(m: BorrowedMessage<'_>) {
+//OwnedMessage is a struct
+//
+//Consume until the stream ends or --max-messages is reached, giving every message to `handler` in its own task,
+//then commit and print how many were handled, `metrics` is updated along the way
+//With --delivery at-least-once offsets are also committed as tasks finish, see delivery.rs
+//With --dedup-window messages with a recently seen key are skipped before they reach a task, see dedup.rs
+//With --bench-seconds reading stops once the time is up, counting from the first message, see bench.rs
+async fn run(consumer: &StreamConsumer<MetricsContext>, config: &Config, handler: Box<dyn MessageHandler>, metrics: Arc<Metrics>) {
+    //every task needs the handler, Arc shares the one boxed handler between them instead of copying it
+    let handler: Arc<dyn MessageHandler> = Arc::from(handler);
+    let mut stream = consumer.stream();
 
-    //m.payload_view::<str>() is a method from BorrowedMessage trait and it returns an Option<Result<&T, ErrorType>>
-    //Try to convert bit stream to UTF-8 encoded string and actually returns following type: Option<Result<&str, Utf8Error>>
-    //Following definitions apply:
-    /*
+    //JoinSet keeps a handle to every spawned task so they can be awaited before exiting
+    let mut tasks = JoinSet::new();
+    let mut received: u64 = 0;
+    let mut processed: u64 = 0;
+    //where each running task's message came from, by task id: a task that panicked returns no value, only its id
+    let mut positions: HashMap<Id, (String, i32, i64)> = HashMap::new();
+    let mut offsets = OffsetTracker::default();
+    let mut backpressure = Backpressure::new(config.max_in_flight, config.pause_after);
+    let mut dedup = config.dedup_window.map(Dedup::new);
+    let mut bench = config.bench.map(Bench::new);
 
-        enum Option<T> {
-            Some(T),
-            None,
+    loop {
+        if let Some(transition) = backpressure.check(Instant::now()) {
+            pause_or_resume(consumer, transition, &backpressure);
         }
+        //woken at this point if the handlers are still all busy then, so check() can pause
+        let pause_deadline = backpressure.pause_deadline();
+        let bench_deadline = bench.as_ref().and_then(Bench::deadline);
 
-        enum Result<T, E> {
-            Ok(T),   // success, contains value of type T
-            Err(E),  // failure, contains error of type E
+        //select! waits on whichever is ready first: a finished task, the next message or the pause deadline
+        //reaping finished tasks as we go keeps the JoinSet from growing forever when there is no limit
+        tokio::select! {
+            Some(result) = tasks.join_next_with_id(), if !tasks.is_empty() => {
+                processed += finish_task(result, &mut positions, &mut offsets, &metrics);
+                if config.delivery == Delivery::AtLeastOnce {
+                    commit_finished(consumer, &mut offsets, CommitMode::Async);
+                }
+            }
+            _ = tokio::time::sleep_until(pause_deadline.unwrap_or_else(Instant::now).into()), if pause_deadline.is_some() => {}
+            //the messages already spawned still finish below, like with --max-messages
+            _ = tokio::time::sleep_until(bench_deadline.unwrap_or_else(Instant::now).into()), if bench_deadline.is_some() => break,
+            //with every permit taken the stream isn't read at all until a task finishes or the partitions are paused
+            message_result = stream.next(), if backpressure.can_read() => match message_result {
+                Some(Ok(msg)) => {
+                    metrics.message_consumed();
+                    received += 1;
+                    let msg = msg.detach();
+                    if let Some(bench) = bench.as_mut() {
+                        bench.record(msg.payload().map_or(0, <[u8]>::len));
+                    }
+                    if dedup.as_mut().is_some_and(|dedup| dedup.is_duplicate(msg.key())) {
+                        skip_duplicate(&msg, &mut offsets);
+                    } else {
+                        let permit = backpressure.acquire().await;
+                        let position = (msg.topic().to_string(), msg.partition(), msg.offset());
+                        offsets.start(&position.0, position.1, position.2);
+                        let handler = Arc::clone(&handler);
+                        let metrics = Arc::clone(&metrics);
+                        //made before the message moves into the task, the headers say which trace it belongs to
+                        #[cfg(feature = "otel")]
+                        let span = telemetry::process_span(&msg);
+                        let task = async move {
+                            let start = Instant::now();
+                            let result = handler.handle(&msg).await;
+                            metrics.observe_processing(start.elapsed());
+                            drop(permit);
+                            result
+                        };
+                        //the span is entered every time the task is polled, so it covers the handler and its awaits
+                        #[cfg(feature = "otel")]
+                        let task = tracing::Instrument::instrument(task, span);
+                        let task = tasks.spawn(task);
+                        positions.insert(task.id(), position);
+                    }
+                    //stop reading once the limit is hit, the messages already spawned still finish below
+                    if config.max_messages.is_some_and(|max| received >= max) {
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    eprintln!("Error reading message: {:?}", e);
+                    metrics.error(ErrorStage::Read);
+                }
+                None => break,
+            },
         }
+    }
 
-     */
-    //So if valid UTF-8 payload: Some(Ok("hello"))
-    //if invalid UTF-8 payload: Some(Err(Utf8Error))
-    //if no payload: None
+    if let Some(bench) = bench.as_mut() {
+        bench.stop();
+    }
+
+    //only reached with --max-messages or --bench-seconds (or if the stream ends), wait for every in-flight message
+    //before committing
+    while let Some(result) = tasks.join_next_with_id().await {
+        processed += finish_task(result, &mut positions, &mut offsets, &metrics);
+    }
 
-    let payload = match m.payload_view::<str>() {
-        //Knowing the above : pattern-match -> Some(Ok(s)) means valid payload make a string
-        //Otherwise invalid
-        Some(Ok(s)) => s.to_string(),
-        _ => "<invalid utf8>".into(),
+    match config.delivery {
+        //auto commit runs on a timer, commit synchronously so the offsets of everything consumed are stored before exiting
+        Delivery::AtMostOnce => {
+            if let Err(e) = consumer.commit_consumer_state(CommitMode::Sync) {
+                eprintln!("Failed to commit offsets: {:?}", e);
+            }
+        }
+        //every task has finished, so this commits up to the last message read
+        Delivery::AtLeastOnce => commit_finished(consumer, &mut offsets, CommitMode::Sync),
+    }
+
+    status!("Processed {} of {} messages received", processed, received);
+    if let Some(dedup) = &dedup {
+        status!("Skipped {} duplicate messages", dedup.skipped());
+    }
+    if let Some(bench) = &bench {
+        bench.print_report();
+    }
+}
+
+//A duplicate is never handled, but its offset still counts as done so at-least-once commits can move past it
+fn skip_duplicate(msg: &OwnedMessage, offsets: &mut OffsetTracker) {
+    let key = msg.key().map(dedup::display_key).unwrap_or_default();
+    status!("Skipping duplicate message with key {} ({} [{}] at offset {})", key, msg.topic(), msg.partition(), msg.offset());
+    offsets.start(msg.topic(), msg.partition(), msg.offset());
+    offsets.finish(msg.topic(), msg.partition(), msg.offset());
+}
+
+//Pause or resume every partition currently assigned to this consumer, a partition assigned by a rebalance while paused
+//starts out unpaused, so the resume call only has to cover whatever is assigned at the time
+fn pause_or_resume(consumer: &StreamConsumer<MetricsContext>, transition: Transition, backpressure: &Backpressure) {
+    let assignment = match consumer.assignment() {
+        Ok(assignment) => assignment,
+        Err(e) => {
+            eprintln!("Failed to read partition assignment: {:?}", e);
+            return;
+        }
+    };
+    let result = match transition {
+        Transition::Pause => {
+            status!("Pausing {} partitions, {} messages still in flight", assignment.count(), backpressure.in_flight());
+            consumer.pause(&assignment)
+        }
+        Transition::Resume => {
+            status!("Resuming {} partitions, {} messages still in flight", assignment.count(), backpressure.in_flight());
+            consumer.resume(&assignment)
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{:?} failed: {:?}", transition, e);
+    }
+}
+
+//A task ended: mark its message done in `offsets` and count it
+fn finish_task(
+    result: Result<(Id, Result<(), HandlerError>), JoinError>,
+    positions: &mut HashMap<Id, (String, i32, i64)>,
+    offsets: &mut OffsetTracker,
+    metrics: &Metrics,
+) -> u64 {
+    //the id comes with the value, or with the error when the task panicked
+    let (id, result) = match result {
+        Ok((id, result)) => (id, Ok(result)),
+        Err(e) => (e.id(), Err(e)),
     };
+    if let Some((topic, partition, offset)) = positions.remove(&id) {
+        offsets.finish(&topic, partition, offset);
+    }
+    count_processed(result, metrics)
+}
 
-    println!("Processing message: {}", payload);
+//Commit whatever OffsetTracker says is safe, Async in the loop so the next message isn't held up waiting for the broker
+//(a later commit covers an earlier one that failed), Sync at the end so it is done before exiting
+fn commit_finished(consumer: &StreamConsumer<MetricsContext>, offsets: &mut OffsetTracker, mode: CommitMode) {
+    if let Some(list) = offsets.committable() {
+        if let Err(e) = consumer.commit(&list, mode) {
+            eprintln!("Failed to commit offsets: {:?}", e);
+        }
+    }
+}
 
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+//1 when the task ran and the handler succeeded, failures are printed and count as 0
+//the outer Result is the task itself (Err if it panicked), the inner one is what the handler returned
+fn count_processed(result: Result<Result<(), HandlerError>, JoinError>, metrics: &Metrics) -> u64 {
+    match result {
+        Ok(Ok(())) => 1,
+        Ok(Err(e)) => {
+            eprintln!("Handler failed: {}", e);
+            metrics.error(ErrorStage::Handler);
+            0
+        }
+        Err(e) => {
+            eprintln!("Processing task failed: {:?}", e);
+            metrics.error(ErrorStage::Task);
+            0
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    let brokers = std::env::var("KAFKA_BROKERS").unwrap_or("localhost:9092".into());
-    let topic = std::env::var("KAFKA_TOPIC").unwrap_or("test-topic".into());
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let topic = &config.topic;
+    output::use_output(config.output);
 
-    let consumer: StreamConsumer = ClientConfig::new()
-        .set("bootstrap.servers", &brokers)
-        .set("group.id", "rust-consumer-group")
-        .set("enable.auto.commit", "true")
-        .set("auto.offset.reset", "earliest")
-        .create()
-        .expect("Consumer creation failed");
+    #[cfg(not(feature = "otel"))]
+    if config.otlp_endpoint.is_some() {
+        eprintln!("--otlp-endpoint / OTEL_EXPORTER_OTLP_ENDPOINT needs a build with the otel feature");
+        std::process::exit(2);
+    }
 
-    consumer.subscribe(&[&topic]).expect("Failed to subscribe");
+    //--seed only produces, the consumer below is never created
+    if let Some(count) = config.seed {
+        if let Err(err) = seed::seed(&config.brokers, topic, count).await {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    println!("Listening for messages on topic: {}", topic);
+    let mut client_config = ClientConfig::new();
+    client_config
+        .set("bootstrap.servers", &config.brokers)
+        .set("group.id", &config.group_id)
+        .set("enable.auto.commit", config.delivery.auto_commit())
+        .set("auto.offset.reset", "earliest");
+    //static membership is opt-in, without it the broker assigns a fresh member id on every start
+    if let Some(instance_id) = &config.group_instance_id {
+        client_config.set("group.instance.id", instance_id);
+    }
+    //fetch and group tuning, anything not given keeps librdkafka's default
+    config.tuning.apply(&mut client_config);
 
-    let mut stream = consumer.stream();
+    //a daemon that is down isn't noticed here, UDP doesn't wait for anyone, only a host that can't be resolved is
+    let statsd = match config.statsd_host.as_deref().map(statsd::Statsd::connect).transpose() {
+        Ok(statsd) => statsd,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
 
-    while let Some(message_result) = stream.next().await {
-        match message_result {
-            Ok(msg) => {
-                task::spawn(process_message(msg.detach()));
-            }
-            Err(e) => eprintln!("Error reading message: {:?}", e),
+    //collected either way, only served when a port is configured
+    let metrics = Arc::new(Metrics::with_statsd(statsd));
+    //librdkafka only reports statistics (where the partition lag comes from) when asked to, and only someone scraping needs them
+    if config.metrics_port.is_some() {
+        client_config.set("statistics.interval.ms", metrics::STATISTICS_INTERVAL_MS);
+    }
+
+    //waits for the broker if it isn't up yet, see connect.rs
+    let consumer = match connect::connect(&client_config, &metrics, topic, config.connect_attempts).await {
+        Ok(consumer) => consumer,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
+    };
+
+    status!("Listening for messages on topic: {} (group: {})", topic, config.group_id);
+    status!("Delivery: {}, {}", config.delivery.name(), config.delivery.tradeoff());
+    if let Some(duration) = config.bench {
+        status!("Benchmark: consuming for {} s from the first message, payloads aren't printed", duration.as_secs());
+    }
+    if !config.work_delay.is_zero() {
+        status!("Simulating {} ms of work per message", config.work_delay.as_millis());
+    }
+    if let Some(window) = config.dedup_window {
+        status!("Dedup: skipping messages whose key is among the last {} distinct keys", window);
+    }
+    let tuning: Vec<String> = config.tuning.set().map(|(property, value)| format!("{}={}", property, value)).collect();
+    if !tuning.is_empty() {
+        status!("Tuning: {}", tuning.join(", "));
+    }
+    //--output jsonl shows the raw payloads, only the text output decodes them
+    #[cfg(feature = "avro")]
+    if let (Output::Text, Some(registry)) = (config.output, avro::registry()) {
+        status!("Decoding Avro payloads with schema registry: {}", registry.url);
+    }
+
+    #[cfg(feature = "otel")]
+    let tracer_provider = match config.otlp_endpoint.as_deref().map(telemetry::init).transpose() {
+        Ok(provider) => provider,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = &config.otlp_endpoint {
+        status!("Exporting a trace span per message to {}", endpoint);
+    }
+
+    if let Some(port) = config.metrics_port {
+        let metrics = Arc::clone(&metrics);
+        //a background task, a metrics server that fails to start is reported but doesn't stop the consumer
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(metrics, port).await {
+                eprintln!("Metrics server on port {} failed: {}", port, e);
+            }
+        });
+        status!("Serving Prometheus metrics on http://0.0.0.0:{}/metrics", port);
+    }
+    if let Some(host) = &config.statsd_host {
+        let metrics = Arc::clone(&metrics);
+        //the buffer is also sent whenever it fills up, this is for a quiet topic
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(statsd::FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Some(statsd) = metrics.statsd() {
+                    statsd.flush();
+                }
+            }
+        });
+        status!("Sending statsd metrics to {}", host);
+    }
+
+    //PrintHandler reproduces the original behavior, swap in another MessageHandler to do something else with each message
+    let work_delay = config.work_delay;
+    let handler: Box<dyn MessageHandler> = match (config.bench, config.output) {
+        (Some(_), _) => Box::new(DiscardHandler { work_delay }),
+        (None, Output::Text) => Box::new(PrintHandler { work_delay }),
+        (None, Output::JsonLines) => Box::new(JsonLinesHandler { work_delay }),
+    };
+    run(&consumer, &config, handler, Arc::clone(&metrics)).await;
+    //the last second's worth would otherwise still be in the buffer
+    if let Some(statsd) = metrics.statsd() {
+        statsd.flush();
+    }
+
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider {
+        telemetry::shutdown(provider);
     }
 }
@@ -0,0 +1,178 @@
+//Prometheus metrics served over HTTP at /metrics when METRICS_PORT (or --metrics-port) is set
+//Prometheus scrapes plain text in its exposition format, one line per value:
+//  # TYPE kafka_messages_consumed_total counter
+//  kafka_messages_consumed_total 42
+//The values are plain atomics updated by the consume loop and the processing tasks, the HTTP handler only reads them
+//Partition lag comes from librdkafka itself: with statistics.interval.ms set it reports its internal state as JSON on a
+//timer, including how far behind each assigned partition is, and MetricsContext copies that out
+//With STATSD_HOST set the consumed count and processing times also go out as statsd metrics, see statsd.rs
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use rdkafka::consumer::ConsumerContext;
+use rdkafka::{ClientContext, Statistics};
+
+use crate::statsd::Statsd;
+
+//how often librdkafka reports statistics, and so how fresh the lag gauge is
+pub const STATISTICS_INTERVAL_MS: &str = "5000";
+
+//upper bounds of the processing time histogram in seconds, --work-delay-ms can make it take seconds so the buckets go well past 1 s
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+//where a message failed, each one is a separate `stage` label on the error counter
+#[derive(Copy, Clone)]
+pub enum ErrorStage {
+    Read,    //the stream returned an error instead of a message
+    Handler, //the MessageHandler returned Err
+    Task,    //the processing task panicked
+}
+
+impl ErrorStage {
+    const ALL: [ErrorStage; 3] = [ErrorStage::Read, ErrorStage::Handler, ErrorStage::Task];
+
+    fn label(self) -> &'static str {
+        match self {
+            ErrorStage::Read => "read",
+            ErrorStage::Handler => "handler",
+            ErrorStage::Task => "task",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    consumed: AtomicU64,
+    errors: [AtomicU64; 3], //indexed by ErrorStage
+    //histogram: how many durations fell into each bucket (not cumulative, render() adds them up), plus +Inf at the end
+    buckets: [AtomicU64; BUCKETS.len() + 1],
+    duration_sum_micros: AtomicU64, //sum of all durations, microseconds so it fits an integer atomic
+    duration_count: AtomicU64,
+    //messages behind the end of each partition, keyed by (topic, partition)
+    //a Mutex since partitions come and go with rebalances, it is only held while updating or rendering
+    lag: Mutex<BTreeMap<(String, i32), i64>>,
+    //Some while statsd is configured, every update is sent there as well
+    statsd: Option<Statsd>,
+}
+
+impl Metrics {
+    pub fn with_statsd(statsd: Option<Statsd>) -> Self {
+        Self { statsd, ..Self::default() }
+    }
+
+    pub fn statsd(&self) -> Option<&Statsd> {
+        self.statsd.as_ref()
+    }
+
+    pub fn message_consumed(&self) {
+        self.consumed.fetch_add(1, Ordering::Relaxed);
+        if let Some(statsd) = &self.statsd {
+            statsd.message_consumed();
+        }
+    }
+
+    pub fn error(&self, stage: ErrorStage) {
+        self.errors[stage as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_processing(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        //first bucket the duration fits under, or the last slot (+Inf) if it is longer than all of them
+        let index = BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(BUCKETS.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.duration_sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.duration_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(statsd) = &self.statsd {
+            statsd.processing_time(elapsed);
+        }
+    }
+
+    //replaces the whole map so partitions that were revoked in a rebalance disappear from the output
+    fn set_lag(&self, lag: BTreeMap<(String, i32), i64>) {
+        *self.lag.lock().unwrap() = lag;
+    }
+
+    //everything in Prometheus' text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        //writeln! into a String can't fail, the results are ignored
+        let _ = writeln!(out, "# HELP kafka_messages_consumed_total Messages read from Kafka.");
+        let _ = writeln!(out, "# TYPE kafka_messages_consumed_total counter");
+        let _ = writeln!(out, "kafka_messages_consumed_total {}", self.consumed.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP kafka_message_errors_total Messages that failed, by the stage they failed in.");
+        let _ = writeln!(out, "# TYPE kafka_message_errors_total counter");
+        for stage in ErrorStage::ALL {
+            let count = self.errors[stage as usize].load(Ordering::Relaxed);
+            let _ = writeln!(out, "kafka_message_errors_total{{stage=\"{}\"}} {}", stage.label(), count);
+        }
+
+        //Prometheus buckets are cumulative: each one counts everything at or below its bound
+        let _ = writeln!(out, "# HELP kafka_message_processing_seconds Time the handler spent on each message.");
+        let _ = writeln!(out, "# TYPE kafka_message_processing_seconds histogram");
+        let mut cumulative = 0;
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            cumulative += self.buckets[i].load(Ordering::Relaxed);
+            let _ = writeln!(out, "kafka_message_processing_seconds_bucket{{le=\"{}\"}} {}", bound, cumulative);
+        }
+        cumulative += self.buckets[BUCKETS.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "kafka_message_processing_seconds_bucket{{le=\"+Inf\"}} {}", cumulative);
+        let sum = self.duration_sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "kafka_message_processing_seconds_sum {}", sum);
+        let _ = writeln!(out, "kafka_message_processing_seconds_count {}", self.duration_count.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP kafka_consumer_lag Messages between the last one consumed and the end of the partition.");
+        let _ = writeln!(out, "# TYPE kafka_consumer_lag gauge");
+        for ((topic, partition), lag) in self.lag.lock().unwrap().iter() {
+            let _ = writeln!(out, "kafka_consumer_lag{{topic=\"{}\",partition=\"{}\"}} {}", topic, partition, lag);
+        }
+        out
+    }
+}
+
+//The consumer's context: rdkafka calls back into it for client events, here only for the periodic statistics
+//create_with_context() makes the consumer a StreamConsumer<MetricsContext> instead of the default context
+pub struct MetricsContext {
+    pub metrics: Arc<Metrics>,
+}
+
+impl ClientContext for MetricsContext {
+    //runs on librdkafka's own thread every STATISTICS_INTERVAL_MS
+    fn stats(&self, statistics: Statistics) {
+        let mut lag = BTreeMap::new();
+        for (name, topic) in &statistics.topics {
+            for (&id, partition) in &topic.partitions {
+                //partition -1 is librdkafka's bucket for messages without a partition yet, and a lag of -1 means it
+                //isn't known yet (nothing fetched from that partition so far), neither is worth a gauge
+                if id >= 0 && partition.consumer_lag >= 0 {
+                    lag.insert((name.clone(), id), partition.consumer_lag);
+                }
+            }
+        }
+        self.metrics.set_lag(lag);
+    }
+}
+
+//no rebalance or commit callbacks needed, the defaults are fine
+impl ConsumerContext for MetricsContext {}
+
+//Serve /metrics on every interface at `port` until the process exits, runs as its own tokio task next to the consumer
+pub async fn serve(metrics: Arc<Metrics>, port: u16) -> std::io::Result<()> {
+    //with_state hands the Arc to every request, the handler gets it back through the State extractor
+    let app = Router::new().route("/metrics", get(metrics_handler)).with_state(metrics);
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    axum::serve(listener, app).await
+}
+
+async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    //version=0.0.4 is the text format's version, it tells the scraper how to parse the body
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+}
@@ -0,0 +1,85 @@
+//What the consumer writes to stdout, chosen with --output or KAFKA_OUTPUT
+//
+//text (default): "Processing message: ..." for every message, with the consumer's own status lines in between
+//jsonl: one JSON object per line per message and nothing else, so stdout can be piped straight into jq or a file
+//  {"topic":"test-topic","partition":0,"offset":42,"key":"7","key_base64":false,"timestamp":1718000000000,
+//   "payload":"{\"seq\":7}","payload_base64":false} (all on one line)
+//  a key or payload that isn't valid UTF-8 is base64 encoded and its _base64 field is true, one that is missing is null,
+//  and so is the timestamp when there isn't one
+//  the status lines ("Listening for messages...", "Processed ...") go to stderr instead so they don't get in the way
+//
+//The payload is the raw bytes even with the avro feature: this is a tap for seeing exactly what is on the topic
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rdkafka::message::{Message, OwnedMessage};
+use serde_json::{json, Value};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Output {
+    Text,
+    JsonLines,
+}
+
+impl Output {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "text" => Ok(Output::Text),
+            "jsonl" => Ok(Output::JsonLines),
+            _ => Err(format!("output must be 'text' or 'jsonl', got '{}'", value)),
+        }
+    }
+}
+
+//set once at startup by use_output(), read by status! from wherever a status line is printed, which is all over the
+//place and in tasks that have no Config to look at
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn use_output(output: Output) {
+    STATUS_TO_STDERR.store(output == Output::JsonLines, Ordering::Relaxed);
+}
+
+pub fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+//println! for the consumer's own messages: stdout in text mode, stderr when stdout is reserved for the JSON lines
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::output::status_to_stderr() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use status;
+
+//One message as a line of JSON, without the trailing newline
+//written out field by field rather than through a serde_json Map, which would sort the keys alphabetically
+pub fn json_line(msg: &OwnedMessage) -> String {
+    format!(
+        "{{\"topic\":{},\"partition\":{},\"offset\":{},{},\"timestamp\":{},{}}}",
+        json!(msg.topic()),
+        msg.partition(),
+        msg.offset(),
+        bytes_fields("key", msg.key()),
+        //milliseconds since the epoch, whether the producer set it (CreateTime) or the broker did (LogAppendTime)
+        json!(msg.timestamp().to_millis()),
+        bytes_fields("payload", msg.payload()),
+    )
+}
+
+//"name":.. as a string if `bytes` is valid UTF-8, otherwise in base64, then "name_base64":true or false to say which
+//null and false if there are no bytes at all
+fn bytes_fields(name: &str, bytes: Option<&[u8]>) -> String {
+    let (value, base64) = match bytes {
+        None => (Value::Null, false),
+        Some(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => (json!(text), false),
+            Err(_) => (json!(STANDARD.encode(bytes)), true),
+        },
+    };
+    format!("\"{}\":{},\"{}_base64\":{}", name, value, name, base64)
+}
@@ -0,0 +1,90 @@
+//--seed N: publish N numbered JSON messages to the topic and exit instead of consuming, so a test harness can fill a
+//topic on a local broker and then run the consumer against it without kafka-console-producer or similar
+//Left out of the usage in config.rs on purpose, it is for tests rather than something to run in a deployment
+//Message n (counting from 1) has the key "n" and the payload {"seq":n,"total":N}: every key is different so --dedup-window
+//skips none of them, and a test can tell from the payloads which ones were handled and whether any came twice
+//
+//Nothing counts as seeded until the broker has acknowledged it: every message's delivery is awaited and any that
+//failed are reported, so the consumer run that follows can rely on all N being there
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
+use rdkafka::ClientConfig;
+
+//how long librdkafka keeps retrying a message before giving up on it, well under its default of 5 minutes so a
+//broker that isn't there fails the seeding quickly instead of leaving the harness waiting
+const MESSAGE_TIMEOUT_MS: &str = "30000";
+
+pub async fn seed(brokers: &str, topic: &str, count: u64) -> Result<(), String> {
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        //acknowledged by every in-sync replica, not only the partition leader, before a delivery counts
+        .set("acks", "all")
+        .set("message.timeout.ms", MESSAGE_TIMEOUT_MS)
+        .create()
+        .map_err(|e| format!("Producer creation failed: {}", e))?;
+
+    //send_result() only queues the message and hands back a future for its delivery report, so many can be on their way
+    //at once instead of waiting for a round trip to the broker per message
+    let mut pending: VecDeque<(u64, DeliveryFuture)> = VecDeque::new();
+    let mut failed: u64 = 0;
+    for n in 1..=count {
+        let key = n.to_string();
+        let payload = format!("{{\"seq\":{},\"total\":{}}}", n, count);
+        let mut record = FutureRecord::to(topic).key(&key).payload(&payload);
+        loop {
+            match producer.send_result(record) {
+                Ok(delivery) => {
+                    pending.push_back((n, delivery));
+                    break;
+                }
+                //librdkafka's local queue is full: wait for the oldest message to be delivered to make room, then retry
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    record = returned;
+                    match pending.pop_front() {
+                        Some((oldest, delivery)) => failed += report(oldest, delivery).await,
+                        None => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                }
+                Err((e, _)) => {
+                    eprintln!("Failed to queue message {}: {}", n, e);
+                    failed += 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    //every message is queued, now wait for the rest of the delivery reports
+    while let Some((n, delivery)) = pending.pop_front() {
+        failed += report(n, delivery).await;
+    }
+    //nothing should be left by now, but flush() makes sure of it before the producer is dropped
+    if let Err(e) = producer.flush(Duration::from_secs(10)) {
+        eprintln!("Failed to flush producer: {}", e);
+    }
+
+    println!("Seeded {} of {} messages to topic {}", count - failed, count, topic);
+    if failed > 0 {
+        return Err(format!("{} of {} messages were not delivered", failed, count));
+    }
+    Ok(())
+}
+
+//Wait for message n's delivery report: 1 if it wasn't delivered, after saying why, 0 if it was
+//the outer Result is Err when the producer went away before reporting, the inner one is the broker's answer
+async fn report(n: u64, delivery: DeliveryFuture) -> u64 {
+    match delivery.await {
+        Ok(Ok(_)) => 0,
+        Ok(Err((e, _))) => {
+            eprintln!("Message {} was not delivered: {}", n, e);
+            1
+        }
+        Err(_) => {
+            eprintln!("Message {} was dropped before its delivery was reported", n);
+            1
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//StatsD metrics sent over UDP when STATSD_HOST (or --statsd-host) is set, for setups that already aggregate with a
+//statsd daemon (or a Datadog/Telegraf agent speaking its protocol) instead of scraping /metrics
+//Each metric is a line of text in a UDP packet, nothing waits for an answer and a daemon that isn't there costs nothing:
+//  kafka_connector.messages.consumed:1|c
+//  kafka_connector.message.processing:12|ms
+//The daemon adds up the counters and works out the timers' percentiles itself, every flush interval
+//
+//Lines are collected in a buffer and sent a packet at a time, when it is full or on the next flush() (main.rs calls it
+//every FLUSH_INTERVAL and on exit), so a busy consumer doesn't send a packet per message
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use cadence::prelude::*;
+use cadence::{BufferedUdpMetricSink, MetricError, StatsdClient};
+
+//every metric name starts with this, so they are grouped in the daemon's namespace
+const PREFIX: &str = "kafka_connector";
+
+//the standard statsd port, used when the address doesn't give one
+pub const DEFAULT_PORT: u16 = 8125;
+
+//how long a half-full buffer can wait before it is sent anyway
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+//a send can only fail locally (no route, buffer full), once is enough to know, after that it would repeat per message
+static SEND_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+pub struct Statsd {
+    client: StatsdClient,
+}
+
+impl Statsd {
+    //`addr` is host:port, resolved once here rather than for every packet
+    pub fn connect(addr: &str) -> Result<Self, String> {
+        let target = addr
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve statsd address {}: {}", addr, e))?
+            .next()
+            .ok_or_else(|| format!("Statsd address {} resolved to nothing", addr))?;
+        //any local port, non-blocking so a full socket buffer drops metrics instead of stalling a task
+        let bind = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind).map_err(|e| format!("Failed to open a UDP socket for statsd: {}", e))?;
+        socket.set_nonblocking(true).map_err(|e| format!("Failed to open a UDP socket for statsd: {}", e))?;
+        let sink = BufferedUdpMetricSink::from(target, socket).map_err(|e| format!("Failed to set up statsd sink: {}", e))?;
+        let client = StatsdClient::builder(PREFIX, sink).with_error_handler(report_send_error).build();
+        Ok(Self { client })
+    }
+
+    pub fn message_consumed(&self) {
+        //send() rather than incr(): it hands a failure to the error handler instead of returning it to every caller
+        self.client.incr_with_tags("messages.consumed").send();
+    }
+
+    pub fn processing_time(&self, elapsed: Duration) {
+        self.client.time_with_tags("message.processing", elapsed).send();
+    }
+
+    //send whatever is in the buffer now
+    pub fn flush(&self) {
+        if let Err(e) = self.client.flush() {
+            report_send_error(e);
+        }
+    }
+}
+
+fn report_send_error(e: MetricError) {
+    if !SEND_ERROR_REPORTED.swap(true, Ordering::Relaxed) {
+        eprintln!("Failed to send statsd metrics (further errors are not shown): {}", e);
+    }
+}
@@ -0,0 +1,92 @@
+//OpenTelemetry tracing, only compiled with the `otel` cargo feature and only switched on by an OTLP endpoint
+//(OTEL_EXPORTER_OTLP_ENDPOINT or --otlp-endpoint, e.g. http://localhost:4317 for a collector or Jaeger)
+//
+//Every message gets a "process" span covering the handler. A producer that is traced itself puts its span's context in a
+//W3C `traceparent` header on the message:
+//  traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01
+//               version-trace id (the whole request)-parent span id-flags (01 = sampled)
+//so the span is made a child of that one and the processing shows up under the producer's request in the trace view
+//A message without the header (or with one that doesn't parse) starts a trace of its own
+//
+//The spans are plain `tracing` spans, tracing-opentelemetry turns them into OpenTelemetry ones and the OTLP exporter sends
+//them in batches over gRPC from a background task
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use rdkafka::message::{Headers, Message, OwnedHeaders, OwnedMessage};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::prelude::*;
+
+//what the spans are filed under in the trace view
+const SERVICE_NAME: &str = "kafka-connector";
+
+//Start exporting to `endpoint`, the returned provider has to be passed to shutdown() before exiting
+//must be called from inside the tokio runtime, the batch exporter spawns its task on it
+pub fn init(endpoint: &str) -> Result<TracerProvider, String> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| format!("Failed to create the OTLP exporter for {}: {}", endpoint, e))?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+    //the subscriber is global: every tracing span from here on, in any task, goes through this layer to the exporter
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    tracing_subscriber::registry()
+        .with(layer)
+        .try_init()
+        .map_err(|e| format!("Failed to install the tracing subscriber: {}", e))?;
+    Ok(provider)
+}
+
+//The batch exporter sends on a timer, without this the spans of the last few seconds would be lost on exit
+pub fn shutdown(provider: TracerProvider) {
+    if let Err(e) = provider.shutdown() {
+        eprintln!("Failed to flush traces: {}", e);
+    }
+}
+
+//The span one message is processed in, made a child of the producer's span when the message says which one that was
+//the attribute names are OpenTelemetry's messaging conventions, so tracing backends know how to display them
+pub fn process_span(msg: &OwnedMessage) -> Span {
+    let span = tracing::info_span!(
+        "process",
+        otel.kind = "consumer",
+        messaging.system = "kafka",
+        messaging.operation = "process",
+        messaging.destination.name = msg.topic(),
+        messaging.kafka.destination.partition = msg.partition(),
+        messaging.kafka.message.offset = msg.offset(),
+    );
+    //with no headers, or no traceparent among them, extract() returns an empty context and the span stays a root
+    if let Some(headers) = msg.headers() {
+        span.set_parent(TraceContextPropagator::new().extract(&HeaderExtractor(headers)));
+    }
+    span
+}
+
+//lets the propagator read the message's headers the way it reads HTTP ones, by name
+//Kafka header values are bytes, a traceparent is always ASCII so one that isn't UTF-8 is treated as missing
+struct HeaderExtractor<'a>(&'a OwnedHeaders);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        //a key can appear more than once in Kafka, the last one is the most recent
+        self.0
+            .iter()
+            .filter(|header| header.key.eq_ignore_ascii_case(key))
+            .last()
+            .and_then(|header| std::str::from_utf8(header.value?).ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.iter().map(|header| header.key).collect()
+    }
+}
@@ -0,0 +1,108 @@
+//librdkafka settings that trade latency against throughput, passed to the ClientConfig only when they are set so
+//everything else keeps librdkafka's defaults
+//
+//fetch.min.bytes / fetch.wait.max.ms: the broker holds a fetch until it has that many bytes or that much time has
+//  passed. Raising both means fewer, bigger fetches (throughput), lowering them gets each message out sooner (latency)
+//queued.max.messages.kbytes: how much librdkafka prefetches per partition ahead of what the stream has handed out.
+//  More keeps the consumer busy through slow fetches, at the cost of memory
+//session.timeout.ms: how long the broker waits without a heartbeat before deciding this consumer is dead and
+//  rebalancing, shorter notices a crash sooner but a long GC pause or network blip costs a rebalance
+//max.poll.interval.ms: how long the stream can go unread before this consumer leaves the group, see backpressure.rs
+//
+//each one is a KAFKA_* env var and a --flag named after the property, the flag wins like everywhere else in config.rs
+//the ranges are librdkafka's own, checked here so a typo fails at startup instead of when the consumer is created
+use rdkafka::ClientConfig;
+
+struct Setting {
+    property: &'static str,
+    env: &'static str,
+    flag: &'static str,
+    min: u64,
+    max: u64,
+}
+
+const SETTINGS: [Setting; 5] = [
+    Setting { property: "fetch.min.bytes", env: "KAFKA_FETCH_MIN_BYTES", flag: "--fetch-min-bytes", min: 1, max: 100_000_000 },
+    Setting { property: "fetch.wait.max.ms", env: "KAFKA_FETCH_WAIT_MAX_MS", flag: "--fetch-wait-max-ms", min: 0, max: 300_000 },
+    Setting { property: "max.poll.interval.ms", env: "KAFKA_MAX_POLL_INTERVAL_MS", flag: "--max-poll-interval-ms", min: 1, max: 86_400_000 },
+    Setting { property: "session.timeout.ms", env: "KAFKA_SESSION_TIMEOUT_MS", flag: "--session-timeout-ms", min: 1, max: 3_600_000 },
+    Setting {
+        property: "queued.max.messages.kbytes",
+        env: "KAFKA_QUEUED_MAX_MESSAGES_KBYTES",
+        flag: "--queued-max-messages-kbytes",
+        min: 1,
+        max: 2_097_151,
+    },
+];
+
+//librdkafka's defaults for the two that have to agree, used when only one of them is set
+const DEFAULT_MAX_POLL_INTERVAL_MS: u64 = 300_000;
+const DEFAULT_SESSION_TIMEOUT_MS: u64 = 45_000;
+
+//one slot per entry of SETTINGS, None where librdkafka's default is kept
+#[derive(Default)]
+pub struct Tuning {
+    values: [Option<u64>; SETTINGS.len()],
+}
+
+impl Tuning {
+    pub fn from_env() -> Result<Self, String> {
+        let mut tuning = Tuning::default();
+        for (slot, setting) in tuning.values.iter_mut().zip(&SETTINGS) {
+            *slot = std::env::var(setting.env).ok().map(|value| setting.parse(&value)).transpose()?;
+        }
+        Ok(tuning)
+    }
+
+    //whether `flag` is one of ours, so config.rs can hand it over instead of calling it unknown
+    pub fn is_flag(flag: &str) -> bool {
+        SETTINGS.iter().any(|setting| setting.flag == flag)
+    }
+
+    pub fn set_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let Some(index) = SETTINGS.iter().position(|setting| setting.flag == flag) else {
+            return Err(format!("unknown option '{}'", flag));
+        };
+        self.values[index] = Some(SETTINGS[index].parse(value)?);
+        Ok(())
+    }
+
+    //librdkafka refuses to create a consumer whose max.poll.interval.ms is shorter than its session.timeout.ms, the
+    //error only names the properties, this also says which option to change
+    pub fn validate(&self) -> Result<(), String> {
+        let max_poll = self.get("max.poll.interval.ms").unwrap_or(DEFAULT_MAX_POLL_INTERVAL_MS);
+        let session = self.get("session.timeout.ms").unwrap_or(DEFAULT_SESSION_TIMEOUT_MS);
+        if max_poll < session {
+            return Err(format!(
+                "max.poll.interval.ms ({} ms) must not be shorter than session.timeout.ms ({} ms) \
+                 (KAFKA_MAX_POLL_INTERVAL_MS / --max-poll-interval-ms, KAFKA_SESSION_TIMEOUT_MS / --session-timeout-ms)",
+                max_poll, session
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, client_config: &mut ClientConfig) {
+        for (property, value) in self.set() {
+            client_config.set(property, value.to_string());
+        }
+    }
+
+    //the properties that were set, for the startup line
+    pub fn set(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        SETTINGS.iter().zip(&self.values).filter_map(|(setting, value)| Some((setting.property, (*value)?)))
+    }
+
+    fn get(&self, property: &str) -> Option<u64> {
+        self.set().find(|(name, _)| *name == property).map(|(_, value)| value)
+    }
+}
+
+impl Setting {
+    fn parse(&self, value: &str) -> Result<u64, String> {
+        match value.parse::<u64>() {
+            Ok(n) if (self.min..=self.max).contains(&n) => Ok(n),
+            _ => Err(format!("{} must be a number from {} to {}, got '{}'", self.property, self.min, self.max, value)),
+        }
+    }
+}
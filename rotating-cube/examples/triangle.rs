@@ -0,0 +1,165 @@
+// the smallest App: one triangle with a red, a green and a blue corner, the colors blend across it
+// everything the cube needs on top (camera, depth buffer, uniforms) is left out, this is what every demo starts from
+//   cargo run --example triangle
+use rotating_cube::error::RenderError;
+use rotating_cube::framework::{run_app, App};
+use rotating_cube::gpu::Context;
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoop;
+use winit::window::{Window, WindowBuilder, WindowId};
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 2], // clip space, (-1, -1) is the bottom left of the window and (1, 1) the top right
+    color: [f32; 3],
+}
+
+// counter-clockwise, the front face by wgpu's default
+const VERTICES: [Vertex; 3] = [
+    Vertex { position: [0.0, 0.6], color: [1.0, 0.0, 0.0] },
+    Vertex { position: [-0.6, -0.6], color: [0.0, 1.0, 0.0] },
+    Vertex { position: [0.6, -0.6], color: [0.0, 0.0, 1.0] },
+];
+
+struct Triangle {
+    surface: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    gpu: Context,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    window: Window, // after the surface that draws into it so it is dropped last
+}
+
+impl Triangle {
+    async fn new(window: Window) -> Result<Self, RenderError> {
+        let instance = wgpu::Instance::default();
+        let surface = unsafe { instance.create_surface(&window) }?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions { compatible_surface: Some(&surface), ..Default::default() })
+            .await
+            .ok_or(RenderError::NoAdapter)?;
+        let gpu = Context::new(&adapter, false, None).await?;
+
+        let caps = surface.get_capabilities(&adapter);
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: caps.formats.iter().copied().find(|format| format.is_srgb()).unwrap_or(caps.formats[0]),
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo, // every platform has it
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(&gpu.device, &config);
+
+        let shader = gpu.device.create_shader_module(wgpu::include_wgsl!("triangle.wgsl"));
+        let layout = gpu.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Triangle Pipeline Layout"),
+            bind_group_layouts: &[], // no uniforms
+            push_constant_ranges: &[],
+        });
+        let pipeline = gpu.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Triangle Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(), // a triangle list
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let vertex_buffer = gpu.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Triangle Vertices"),
+            contents: bytemuck::cast_slice(&VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Ok(Self { surface, config, gpu, pipeline, vertex_buffer, window })
+    }
+}
+
+impl App for Triangle {
+    fn input(&mut self, _window: WindowId, _event: &WindowEvent) -> Result<(), RenderError> {
+        Ok(()) // nothing to control
+    }
+
+    fn resize(&mut self, _window: WindowId, size: PhysicalSize<u32>) -> Result<(), RenderError> {
+        // a minimized window is 0x0, which a surface can't be configured with
+        if size.width > 0 && size.height > 0 {
+            self.config.width = size.width;
+            self.config.height = size.height;
+            self.surface.configure(&self.gpu.device, &self.config);
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, _dt: f32) {} // nothing moves
+
+    fn render(&mut self, _alpha: f32) -> Result<(), RenderError> {
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // the swapchain no longer matches the window, set it up again and draw next frame
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.gpu.device, &self.config);
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Triangle Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            pass.draw(0..VERTICES.len() as u32, 0..1);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+}
+
+fn main() {
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().with_title("Triangle").build(&event_loop).expect("Couldn't open a window");
+    let triangle = match pollster::block_on(Triangle::new(window)) {
+        Ok(triangle) => triangle,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    run_app(event_loop, triangle);
+}
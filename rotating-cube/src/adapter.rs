@@ -0,0 +1,92 @@
+// helpers for picking which graphics API (backend) and which GPU (adapter) wgpu runs on
+
+// map a --backend name onto wgpu's backend bit flags
+pub fn parse_backend(value: &str) -> Result<wgpu::Backends, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "vulkan" | "vk" => Ok(wgpu::Backends::VULKAN),
+        "dx12" | "d3d12" => Ok(wgpu::Backends::DX12),
+        "metal" | "mtl" => Ok(wgpu::Backends::METAL),
+        "gl" | "opengl" | "gles" => Ok(wgpu::Backends::GL),
+        _ => Err(format!("expected vulkan, dx12, metal or gl, got '{}'", value)),
+    }
+}
+
+// map a --power value onto wgpu's power preference, on laptops low = integrated GPU and high = discrete GPU
+pub fn parse_power_preference(value: &str) -> Result<wgpu::PowerPreference, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "low" => Ok(wgpu::PowerPreference::LowPower),
+        "high" => Ok(wgpu::PowerPreference::HighPerformance),
+        _ => Err(format!("expected low or high, got '{}'", value)),
+    }
+}
+
+// enumerate_adapters() doesn't exist in the browser, which only hands out one adapter through request_adapter(),
+// so listing and picking by name are desktop-only
+
+// print every adapter the instance can see for the given backends, used by --list-adapters
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_adapters(instance: &wgpu::Instance, backends: wgpu::Backends) {
+    let mut count = 0;
+    for (index, adapter) in instance.enumerate_adapters(backends).enumerate() {
+        let info = adapter.get_info();
+        println!("[{}] {} ({:?}, {:?}, driver: {} {})", index, info.name, info.device_type, info.backend, info.driver, info.driver_info);
+        count += 1;
+    }
+    if count == 0 {
+        println!("No adapters found for {:?}", backends);
+    }
+}
+
+// make sure at least one adapter exists for the requested backends before we open a window and fail later
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check_backend_available(instance: &wgpu::Instance, backends: wgpu::Backends) -> Result<(), String> {
+    if instance.enumerate_adapters(backends).next().is_none() {
+        return Err(format!(
+            "No GPU adapter available for backend {:?}, run with --list-adapters to see what is installed",
+            backends
+        ));
+    }
+    Ok(())
+}
+
+// how the adapter was chosen on startup (--backend, --adapter, --power), kept so the same choice can be made again
+// when the device is lost and has to be recreated
+pub struct AdapterRequest {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // only the search by name needs it
+    pub backends: wgpu::Backends,
+    pub name: Option<String>,
+    pub power_preference: wgpu::PowerPreference,
+}
+
+impl AdapterRequest {
+    // --adapter picks a GPU by name, otherwise let wgpu choose one that can present to `surface`
+    pub async fn pick(&self, instance: &wgpu::Instance, surface: &wgpu::Surface) -> Option<wgpu::Adapter> {
+        match &self.name {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(name) => find_adapter_by_name(instance, self.backends, surface, name),
+            _ => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: self.power_preference,
+                        compatible_surface: Some(surface),
+                        ..Default::default()
+                    })
+                    .await
+            }
+        }
+    }
+}
+
+// pick the first adapter whose name contains `name` (case-insensitive) and that can draw to `surface`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_adapter_by_name(
+    instance: &wgpu::Instance,
+    backends: wgpu::Backends,
+    surface: &wgpu::Surface,
+    name: &str,
+) -> Option<wgpu::Adapter> {
+    let name = name.to_ascii_lowercase();
+    instance
+        .enumerate_adapters(backends)
+        .find(|adapter| adapter.get_info().name.to_ascii_lowercase().contains(&name) && adapter.is_surface_supported(surface))
+}
@@ -0,0 +1,286 @@
+// keyframe animation: a Track holds (time, value) keys sorted by time and can be sampled at any time in between,
+// an AnimationClip groups one track each for translation, rotation and scale and turns them into a model matrix
+use glam::{Mat4, Quat, Vec3};
+
+// names accepted by --anim
+pub const CLIP_NAMES: &[&str] = &["demo", "tick"];
+
+// how the value moves from one key to the next
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,   // hold each key's value until the next key
+    Linear, // straight line between the two surrounding keys (slerp for rotations)
+    Cubic,  // smooth curve through the keys, uses the neighbouring keys as well so the speed doesn't jump at a key
+}
+
+// anything a track can animate
+pub trait Keyable: Copy {
+    fn lerp(a: Self, b: Self, t: f32) -> Self;
+
+    // Catmull-Rom through p1 and p2, with p0 and p3 shaping the tangents
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self;
+}
+
+impl Keyable for f32 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+        catmull_rom(p0, p1, p2, p3, t)
+    }
+}
+
+impl Keyable for Vec3 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+        catmull_rom(p0, p1, p2, p3, t)
+    }
+}
+
+impl Keyable for Quat {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a.slerp(b, t)
+    }
+
+    // a true spline on the rotation sphere (squad) is a lot of code for little visible gain here,
+    // slerp with an eased t already starts and stops smoothly at every key
+    fn cubic(_: Self, p1: Self, p2: Self, _: Self, t: f32) -> Self {
+        p1.slerp(p2, t * t * (3.0 - 2.0 * t))
+    }
+}
+
+// works for f32 and Vec3 alike since both support + - and * f32
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + std::ops::Add<Output = T> + std::ops::Sub<Output = T> + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+pub struct Track<T: Keyable> {
+    keys: Vec<(f32, T)>, // sorted by time
+    interpolation: Interpolation,
+}
+
+impl<T: Keyable> Track<T> {
+    // keys may be given in any order, they are sorted here; panics on an empty list since there is nothing to sample
+    pub fn new(interpolation: Interpolation, mut keys: Vec<(f32, T)>) -> Self {
+        assert!(!keys.is_empty(), "a track needs at least one key");
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keys, interpolation }
+    }
+
+    // time of the last key
+    pub fn end(&self) -> f32 {
+        self.keys[self.keys.len() - 1].0
+    }
+
+    // value at time t, held at the first/last key's value before/after the track
+    pub fn sample(&self, t: f32) -> T {
+        let last = self.keys.len() - 1;
+        if t <= self.keys[0].0 {
+            return self.keys[0].1;
+        }
+        if t >= self.keys[last].0 {
+            return self.keys[last].1;
+        }
+
+        // index of the first key after t, t is strictly between keys[next - 1] and keys[next]
+        // a t exactly on a key lands on that key as `prev` with a blend of 0, so it returns the key's own value
+        let next = self.keys.partition_point(|(time, _)| *time <= t);
+        let prev = next - 1;
+        let (t0, v0) = self.keys[prev];
+        let (t1, v1) = self.keys[next];
+        let blend = (t - t0) / (t1 - t0);
+
+        match self.interpolation {
+            Interpolation::Step => v0,
+            Interpolation::Linear => T::lerp(v0, v1, blend),
+            Interpolation::Cubic => {
+                // the curve's ends have no outer neighbour, repeating the end key keeps it from overshooting there
+                let before = self.keys[prev.saturating_sub(1)].1;
+                let after = self.keys[(next + 1).min(last)].1;
+                T::cubic(before, v0, v1, after, blend)
+            }
+        }
+    }
+}
+
+pub struct AnimationClip {
+    pub translation: Option<Track<Vec3>>,
+    pub rotation: Option<Track<Quat>>,
+    pub scale: Option<Track<Vec3>>,
+    pub looping: bool, // wrap time around the clip's length instead of holding the last pose
+}
+
+impl AnimationClip {
+    // end of the longest track
+    pub fn duration(&self) -> f32 {
+        let ends = [
+            self.translation.as_ref().map(Track::end),
+            self.rotation.as_ref().map(Track::end),
+            self.scale.as_ref().map(Track::end),
+        ];
+        ends.into_iter().flatten().fold(0.0, f32::max)
+    }
+
+    // model matrix at `time` seconds, missing tracks leave that part of the transform at identity
+    pub fn sample(&self, time: f32) -> Mat4 {
+        let duration = self.duration();
+        let t = if self.looping && duration > 0.0 { time.rem_euclid(duration) } else { time };
+
+        let translation = self.translation.as_ref().map_or(Vec3::ZERO, |track| track.sample(t));
+        let rotation = self.rotation.as_ref().map_or(Quat::IDENTITY, |track| track.sample(t));
+        let scale = self.scale.as_ref().map_or(Vec3::ONE, |track| track.sample(t));
+        Mat4::from_scale_rotation_translation(scale, rotation, translation)
+    }
+}
+
+// clip for one of CLIP_NAMES
+pub fn clip(name: &str) -> Option<AnimationClip> {
+    match name {
+        "demo" => Some(demo_clip()),
+        "tick" => Some(tick_clip()),
+        _ => None,
+    }
+}
+
+// rise, spin a full turn, drop back down with a little bounce, repeat every 4 seconds
+fn demo_clip() -> AnimationClip {
+    // slerp takes the shortest way round, so a full turn needs a key at least every half turn
+    let turn = |fraction: f32| Quat::from_rotation_y(fraction * std::f32::consts::TAU);
+
+    AnimationClip {
+        translation: Some(Track::new(
+            Interpolation::Cubic,
+            vec![
+                (0.0, Vec3::ZERO),
+                (1.0, Vec3::new(0.0, 1.0, 0.0)), // rise
+                (3.0, Vec3::new(0.0, 1.0, 0.0)), // hover while spinning
+                (3.5, Vec3::ZERO),               // settle
+                (4.0, Vec3::ZERO),
+            ],
+        )),
+        rotation: Some(Track::new(
+            Interpolation::Linear,
+            vec![(0.0, turn(0.0)), (1.0, turn(0.0)), (1.5, turn(0.25)), (2.0, turn(0.5)), (2.5, turn(0.75)), (3.0, turn(1.0))],
+        )),
+        // uniform scale only, the shader's normal transform assumes the model doesn't stretch
+        scale: Some(Track::new(
+            Interpolation::Cubic,
+            vec![(0.0, Vec3::ONE), (3.4, Vec3::ONE), (3.6, Vec3::splat(0.85)), (3.8, Vec3::splat(1.05)), (4.0, Vec3::ONE)],
+        )),
+        looping: true,
+    }
+}
+
+// jumps an eighth of a turn every half second like a clock's second hand, shows off step interpolation
+fn tick_clip() -> AnimationClip {
+    let keys = (0..=8)
+        .map(|i| (i as f32 * 0.5, Quat::from_rotation_y(i as f32 / 8.0 * std::f32::consts::TAU)))
+        .collect();
+
+    AnimationClip {
+        translation: None,
+        rotation: Some(Track::new(Interpolation::Step, keys)),
+        scale: None,
+        looping: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [Interpolation; 3] = [Interpolation::Step, Interpolation::Linear, Interpolation::Cubic];
+
+    // given out of order on purpose, new() sorts them
+    fn track(interpolation: Interpolation) -> Track<f32> {
+        Track::new(interpolation, vec![(2.0, 10.0), (1.0, 0.0), (3.0, 5.0), (4.0, 5.0)])
+    }
+
+    #[test]
+    fn before_the_first_key_holds_it() {
+        for mode in MODES {
+            assert_eq!(track(mode).sample(0.0), 0.0, "{:?}", mode);
+            assert_eq!(track(mode).sample(-100.0), 0.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn after_the_last_key_holds_it() {
+        for mode in MODES {
+            assert_eq!(track(mode).sample(4.0), 5.0, "{:?}", mode);
+            assert_eq!(track(mode).sample(100.0), 5.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn exactly_on_a_key_is_that_key() {
+        for mode in MODES {
+            let track = track(mode);
+            assert_eq!(track.sample(1.0), 0.0, "{:?}", mode);
+            assert_eq!(track.sample(2.0), 10.0, "{:?}", mode);
+            assert_eq!(track.sample(3.0), 5.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn between_keys() {
+        assert_eq!(track(Interpolation::Step).sample(2.9), 10.0);
+        assert_eq!(track(Interpolation::Linear).sample(1.25), 2.5);
+        assert_eq!(track(Interpolation::Linear).sample(2.5), 7.5);
+        // Catmull-Rom through 0 -> 10 -> 5: still climbing just after the 10, then on the way down to the 5
+        let cubic = track(Interpolation::Cubic);
+        assert!(cubic.sample(2.1) > 10.0);
+        assert!(cubic.sample(2.9) > 5.0 && cubic.sample(2.9) < 10.0);
+        // and flat between the two equal keys at the end, repeating the end key keeps it from overshooting
+        assert!((cubic.sample(3.5) - 5.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn a_single_key_is_constant() {
+        let track = Track::new(Interpolation::Cubic, vec![(1.0, Vec3::X)]);
+        assert_eq!(track.sample(0.0), Vec3::X);
+        assert_eq!(track.sample(5.0), Vec3::X);
+    }
+
+    #[test]
+    fn rotations_slerp() {
+        let track = Track::new(Interpolation::Linear, vec![(0.0, Quat::IDENTITY), (1.0, Quat::from_rotation_y(1.0))]);
+        assert!(track.sample(0.5).abs_diff_eq(Quat::from_rotation_y(0.5), 1e-6));
+    }
+
+    #[test]
+    fn looping_wraps_around_the_duration() {
+        let clip = AnimationClip {
+            translation: Some(Track::new(Interpolation::Linear, vec![(0.0, Vec3::ZERO), (2.0, Vec3::new(4.0, 0.0, 0.0))])),
+            rotation: None,
+            scale: None,
+            looping: true,
+        };
+        assert_eq!(clip.duration(), 2.0);
+        let at = |time: f32| clip.sample(time).w_axis.truncate();
+        assert!(at(0.5).abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-6));
+        // a lap later is the same pose, and the end of one lap is the start of the next
+        assert!(at(2.5).abs_diff_eq(at(0.5), 1e-6));
+        assert!(at(4.0).abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!(at(-1.5).abs_diff_eq(at(0.5), 1e-6));
+
+        let held = AnimationClip { looping: false, ..clip };
+        assert!(held.sample(2.5).w_axis.truncate().abs_diff_eq(Vec3::new(4.0, 0.0, 0.0), 1e-6));
+    }
+
+    #[test]
+    fn demo_clip_loops_back_to_its_start() {
+        let demo = clip("demo").unwrap();
+        assert!(demo.sample(demo.duration()).abs_diff_eq(demo.sample(0.0), 1e-5));
+        assert!(clip("nope").is_none());
+    }
+}
@@ -0,0 +1,1670 @@
+// the app itself: the windows, the scene's animation state and what every input action does, recorded into the GPU resources
+// from renderer.rs each frame
+// main.rs only creates the windows and feeds the event loop's events and timing into State
+// each window's own state is in app/window.rs, the HUD's text in app/hud_text.rs and --record's capture in app/recording.rs
+mod hud_text;
+mod recording;
+mod window;
+
+pub use window::WindowState;
+use recording::Recording;
+use window::{panes, Target};
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use instant::{Duration, Instant};
+
+use bytemuck::Zeroable;
+use glam::{DVec3, Mat4, Quat, Vec2, Vec3};
+
+use tracing::{error, info, info_span, warn};
+use winit::{dpi::PhysicalSize, event::*};
+
+use crate::adapter::AdapterRequest;
+use crate::animation::{self, AnimationClip};
+use crate::camera::{self, Camera};
+use crate::camera_control::CameraRig;
+use crate::debug_lines;
+use crate::debug_view::DebugView;
+use crate::depth::{self, DepthBuffer};
+use crate::dpi;
+use crate::easing::{Easing, Tween};
+use crate::effects::Effect;
+use crate::gamepad::{Gamepads, PadInput};
+use crate::error::RenderError;
+use crate::gizmo;
+use crate::hud::FpsCounter;
+use crate::instances;
+use crate::input::{axis_preset, Action, Binding, InputState, Keymap, CAMERA_PRESETS};
+use crate::lights::{self, MAX_LIGHTS};
+use crate::lod::{self, Buckets, Level, LodBuffers, Thresholds};
+use crate::material::{self, MaterialState};
+use crate::options::Options;
+use crate::picking;
+use crate::pip;
+use crate::pipelines::{DrawMode, PassKind, ShaderKind};
+use crate::render_stats::{CountingPass, RenderStats};
+use crate::renderer::{FrameUniform, Gpu, LightUniform, ModelUniform};
+use crate::scene::{self, Scene};
+use crate::scene_file::srgb_to_linear;
+use crate::scene_reload::SceneWatcher;
+use crate::shadow::{self, ShadowUniform};
+use crate::sky::GlobalsUniform;
+use crate::transparency;
+use crate::viewport::{self, Viewport};
+
+// how fast the cube spins around its current rotation axis in radians per second
+const ROTATION_SPEED: f32 = 0.6;
+
+// how fast a stick pushed all the way turns the cube by hand (cube-turn-*), radians per second
+const CUBE_TURN_SPEED: f32 = 2.5;
+
+// how long switching between rotation axis presets (keys 1/2/3) takes, in seconds
+const AXIS_TRANSITION_TIME: f32 = 0.5;
+
+// how long Home takes to turn the cube back to its starting orientation
+const RESET_TIME: f32 = 0.8;
+
+// shininess bounds for the [ ] keys, each press halves or doubles it
+const MIN_SHININESS: f32 = 1.0;
+const MAX_SHININESS: f32 = 256.0;
+
+pub struct State {
+    instance: wgpu::Instance, // kept to recreate surfaces after a suspend and to find an adapter again after device loss
+    adapter_request: AdapterRequest,
+    pub gpu: Gpu,
+    device_lost: Arc<AtomicBool>, // set by the device's error handler, the next frame recreates the device
+    pub windows: Vec<WindowState>, // --windows N, the first one also feeds --record
+
+    scene: Scene,                // CPU copy of the mesh, instances and light the GPU buffers are built from
+    draw_mode: DrawMode,         // triangles, points or lines, cycled with M
+    lod: Option<Thresholds>,     // --lod, simpler meshes for the cubes further from each camera in the triangles mode
+    cube_shader: ShaderKind,     // which shader file the opaque cubes' faces use, toggled with U
+    glass_shader: ShaderKind,    // and the glass cube's, toggled with G
+    depth_prepass: bool,         // draw the cubes depth-only first, then shade with an Equal depth test, toggled with Z
+    bench: bool,                 // --bench or --stress, asks the device for timestamp queries
+    reverse_z: bool,             // --reverse-z, flips the depth clear value and test, see depth.rs
+    clear_color: wgpu::Color,    // --clear-color or the scene's background, already converted for the surface format
+    clear_color_arg: Option<[f64; 3]>, // --clear-color, which stays when a reloaded scene changes its background
+    scene_watcher: Option<SceneWatcher>, // --scene, checked every frame for edits
+    keymap: Keymap,              // which key triggers which Action, the built-in keys or --keymap
+    gamepads: Option<Gamepads>,  // None without --features gamepad or when the OS has no gamepad support
+    pad_input: InputState,       // the gamepads' buttons and sticks, acting on the window with focus
+    particle_time: f32,           // animation time the particles were last stepped to
+    render_size: Option<(u32, u32)>, // --render-size, the scene is letterboxed into this inside the window
+    light_dirty: bool,           // scene.light changed since the windows last uploaded it
+    uploaded_model: Option<Mat4>, // model matrix currently in model_buffer, None forces the next upload
+    uploaded_time: Option<f32>,   // same for the time in frame_buffer (and the deformer's params), also cleared when the normals view toggles
+    uploaded_shadow: Option<ShadowUniform>, // same for the shadow map's light box, see shadow.rs
+    uniform_writes: u32,          // uniform buffers written by the last write_uniforms(), shown in the HUD
+    render_stats: RenderStats,    // the last render()'s draws and uploads, shown in the HUD and added up by --bench
+    render_cpu: Duration,         // the last render()'s own work, without the waits for swapchain textures, for --stress
+    paused: bool,                 // Space freezes the spin and the hue animation, the cameras still move
+
+    materials: Vec<MaterialState>, // hover glow per instance, same order as the instance buffer (scene.instances, then scene.glass)
+    materials_dirty: bool,         // a glow changed since the emissive buffer was last written
+
+    show_normals: bool,                  // toggled with N
+    show_bounds: bool,                   // bounding box, local axes and light direction, toggled with B
+    debug_view: DebugView,               // final image, depth, normals or wireframe overlay, cycled with D
+    effect: Effect,                      // procedural color effect of the lit shader, cycled with E
+    demo_material: Option<u32>,          // K: one of material::DEMOS for every opaque cube in place of their own
+    selected: bool,                      // the cube is selected and gets an outline, toggled with X
+    shadows: bool,                       // the scene file's first light casts shadows, toggled with S
+    frozen: Option<FrozenCamera>,        // V: a camera left behind to see its frustum and what it culls from outside
+
+    recording: Option<Recording>, // --record's capture, see app/recording.rs
+
+    pub fps: FpsCounter,     // real frames per second shown in the HUD
+
+    animation: Option<AnimationClip>, // --anim: keyframed transform that replaces the free spin below
+
+    orientation: Quat,      // cube orientation after the latest fixed simulation step
+    prev_orientation: Quat, // orientation one step earlier, rendering blends between the two
+    reset_tween: Option<Tween<Quat>>, // Home: easing back to the identity orientation, the spin pauses meanwhile
+
+    axis_from: Quat,      // rotation axis (as a rotation of +Y) when the current transition started
+    axis_to: Quat,        // rotation axis preset being transitioned to
+    axis_blend: f32,      // 0..1 progress from axis_from to axis_to
+
+    time: f32,      // simulation time after the latest step, drives the hue animation
+    prev_time: f32, // time one step earlier, blended like the orientation
+
+    spin: f32,      // radians spun around the rotation axis so far, for --log-frames
+    prev_spin: f32, // spin one step earlier, blended like the orientation
+}
+
+// V's camera, kept as the matrices it had when it was frozen
+struct FrozenCamera {
+    view_proj: Mat4,    // what it culls with
+    corners: [Vec3; 8], // its frustum, out to the far plane even with --reverse-z
+    culled: Vec<bool>,  // per opaque cube, whether it was left out when the instance buffer was last tinted
+}
+
+// magenta, none of the other debug lines use it
+const FROZEN_FRUSTUM_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+
+// --clear-color is given in sRGB like any color picker shows it, but an sRGB surface expects linear values and
+// encodes them itself, so convert for those or the background comes out washed-out
+fn clear_color([r, g, b]: [f64; 3], format: wgpu::TextureFormat) -> wgpu::Color {
+    let channel = |c: f64| if format.is_srgb() { srgb_to_linear(c) } else { c };
+    wgpu::Color { r: channel(r), g: channel(g), b: channel(b), a: 1.0 }
+}
+
+impl State {
+    #[tracing::instrument(name = "init", skip_all)]
+    pub async fn new(instance: wgpu::Instance, windows: Vec<winit::window::Window>, options: &Options) -> Result<Self, RenderError> {
+        // ----- Surfaces + Adapter -----
+        // one surface per window, the adapter only has to be compatible with the first, the rest are checked below
+        let surfaces = windows
+            .iter()
+            .map(|window| unsafe { instance.create_surface(window) })
+            .collect::<Result<Vec<wgpu::Surface>, _>>()?;
+        let surface = &surfaces[0];
+
+        let adapter_request = AdapterRequest {
+            backends: options.gpu.backends,
+            name: options.gpu.adapter.clone(),
+            power_preference: options.gpu.power_preference,
+        };
+        let adapter = adapter_request.pick(&instance, surface).await.ok_or_else(|| match &options.gpu.adapter {
+            Some(name) => RenderError::NoMatchingAdapter(name.clone()),
+            None => RenderError::NoAdapter,
+        })?;
+
+        // ----- Swapchain config -----
+        // not every platform supports every present mode, fall back to Fifo which is always available
+        let surface_caps = surface.get_capabilities(&adapter);
+        let present_mode = if surface_caps.present_modes.contains(&options.gpu.present_mode) {
+            options.gpu.present_mode
+        } else {
+            warn!("Present mode {:?} not supported by this surface, using Fifo", options.gpu.present_mode);
+            wgpu::PresentMode::Fifo
+        };
+        // the pipelines are built once for every window, so all surfaces have to take the first one's format
+        let format = surface_caps.formats[0];
+        if !surfaces[1..].iter().all(|surface| surface.get_capabilities(&adapter).formats.contains(&format)) {
+            return Err(RenderError::UnsupportedFormat(format));
+        }
+
+        let targets = windows.into_iter().zip(surfaces).map(|(window, surface)| Target::window(window, surface)).collect();
+        Self::with_targets(instance, adapter_request, adapter, format, present_mode, targets, options).await
+    }
+
+    // the app without any window on screen, drawing into one offscreen window of `size` on the default adapter (or the
+    // one --backend and --power lead to), for the headless tests: render() draws the frame, read_frame() reads it back
+    pub async fn headless(options: &Options, size: PhysicalSize<u32>) -> Result<Self, RenderError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: options.gpu.backends,
+            ..Default::default()
+        });
+        let adapter_request = AdapterRequest {
+            backends: options.gpu.backends,
+            name: None,
+            power_preference: options.gpu.power_preference,
+        };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: options.gpu.power_preference,
+                ..Default::default()
+            })
+            .await
+            .ok_or(RenderError::NoAdapter)?;
+        // sRGB like most surfaces are, so the colors come out as they would on screen
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        Self::with_targets(instance, adapter_request, adapter, format, wgpu::PresentMode::Fifo, vec![Target::offscreen(size)], options).await
+    }
+
+    // everything after the adapter is picked, the same with or without windows on screen
+    async fn with_targets(
+        instance: wgpu::Instance,
+        adapter_request: AdapterRequest,
+        adapter: wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        present_mode: wgpu::PresentMode,
+        targets: Vec<Target>,
+        options: &Options,
+    ) -> Result<Self, RenderError> {
+        // ----- Device + static buffers -----
+        let scene = Scene::new(&options.scene);
+        // GPU timestamps for the --bench report and --stress's bottleneck
+        let bench = options.bench.is_some() || options.stress.is_some();
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let gpu = Gpu::new(
+            adapter,
+            format,
+            &scene,
+            bench,
+            options.gpu.reverse_z,
+            &device_lost,
+            options.gpu.trace_dir.as_deref(),
+        )
+        .await?;
+        let materials = vec![MaterialState::default(); scene.instances.len() + scene.glass.len()];
+
+        // ----- Windows -----
+        // every camera starts framed on the whole scene like after F, each from the next camera preset, so
+        // --split-screen's two halves start out looking from different sides
+        // the cubes aren't turned yet, an empty scene is framed as if the plain cube were there
+        let (min, max) = scene.bounds(Mat4::IDENTITY).unwrap_or((Vec3::NEG_ONE, Vec3::ONE));
+        // a [camera] in the scene file places the first window's (first) camera, the others still go around its target
+        let start = options.scene.file.camera.as_ref();
+        let cameras_per_window = if options.window.split_screen { 2 } else { 1 };
+        let shared = gpu.shared_bindings(&scene.light);
+        let windows: Vec<WindowState> = targets
+            .into_iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let size = target.size;
+                let config = wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format,
+                    width: size.width,
+                    height: size.height,
+                    present_mode,
+                    alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                    view_formats: vec![],
+                };
+                //define starting position, field of view, and near/far-clipping limits to encapsulate frustum
+                let lens = options.window.lens;
+                let fovy = lens.fov_deg.or(start.map(|camera| camera.fov as f32)).unwrap_or(camera::DEFAULT_FOV);
+                let viewport = viewport::fit(config.width, config.height, options.window.render_size);
+                let rigs = panes(viewport, cameras_per_window)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(pane, viewport)| {
+                        let n = i * cameras_per_window + pane; //counting every camera of every window
+                        let aspect = viewport.aspect(); //shape of the area drawn into, not the window
+                        let framing = camera::frame_bounds(min, max, CAMERA_PRESETS[n % CAMERA_PRESETS.len()], fovy, aspect);
+                        let (eye, target) = match start {
+                            Some(camera) => {
+                                let target = DVec3::from(camera.target).as_vec3();
+                                match n {
+                                    0 => (DVec3::from(camera.eye).as_vec3(), target),
+                                    _ => (target + framing.eye - framing.target, target),
+                                }
+                            }
+                            None => (framing.eye, framing.target),
+                        };
+                        // the planes still have to reach the whole scene from wherever the scene file put the camera
+                        let radius = (max - min).length() / 2.0;
+                        let (znear, zfar) = lens.clip_planes(camera::clip_planes(radius, eye.distance((min + max) / 2.0)));
+                        let camera = Camera {
+                            eye,                           // camera position
+                            target,                        // looks at the middle of the scene unless the scene file says otherwise
+                            up: Vec3::Y,                   // up direction
+                            fovy,
+                            aspect,
+                            znear,
+                            zfar,
+                            ortho: 0.0, // perspective until O is pressed
+                            reverse_z: options.gpu.reverse_z,
+                        };
+                        CameraRig::new(camera, options.window.camera, lens)
+                    })
+                    .collect();
+                WindowState::new(&gpu.device, &gpu.queue, target, config, rigs, &shared)
+            })
+            .collect();
+        let config = &windows[0].config;
+
+        // ----- Recording -----
+        // capture at --render-size if given, otherwise at the first window's size when recording starts
+        // later resizes don't change the output dimensions
+        let capture_size = options.window.render_size.unwrap_or((config.width, config.height));
+        let recording = options
+            .record
+            .as_ref()
+            .map(|settings| Recording::new(&gpu.device, config.format, gpu.depth_format, capture_size, settings))
+            .transpose()?;
+
+        Ok(Self {
+            instance,
+            adapter_request,
+            gpu,
+            device_lost,
+            windows,
+
+            scene,
+            draw_mode: options.scene.draw_mode,
+            lod: options.scene.lod,
+            // one of each to start with, so both pipelines are in use in the same pass
+            cube_shader: ShaderKind::Lit,
+            glass_shader: ShaderKind::Unlit,
+            depth_prepass: false,
+            bench,
+            reverse_z: options.gpu.reverse_z,
+            clear_color: clear_color(options.scene.clear_color.unwrap_or(options.scene.file.background()), format),
+            clear_color_arg: options.scene.clear_color,
+            scene_watcher: options.scene.path.clone().map(|path| SceneWatcher::new(path, options.scene.file.clone())),
+            keymap: options.window.keymap.clone(),
+            gamepads: Gamepads::new(options.window.gamepad),
+            pad_input: InputState::default(),
+            particle_time: 0.0,
+            render_size: options.window.render_size,
+            light_dirty: false,
+            uploaded_model: None,
+            uploaded_time: None,
+            uploaded_shadow: None,
+            uniform_writes: 0,
+            render_stats: RenderStats::default(),
+            render_cpu: Duration::ZERO,
+            paused: false,
+
+            materials,
+            materials_dirty: false,
+
+            show_normals: false,
+            frozen: None,
+            debug_view: DebugView::Final,
+            effect: Effect::Off,
+            demo_material: None,
+            selected: false,
+            shadows: false,
+            show_bounds: false,
+
+            recording,
+
+            fps: FpsCounter::default(),
+
+            animation: options.scene.anim.as_deref().and_then(animation::clip),
+
+            orientation: Quat::IDENTITY,
+            prev_orientation: Quat::IDENTITY,
+            reset_tween: None,
+
+            axis_from: Quat::IDENTITY,
+            axis_to: Quat::IDENTITY,
+            axis_blend: 1.0, // no transition in progress, spin around +Y
+
+            time: 0.0,
+            prev_time: 0.0,
+
+            spin: 0.0,
+            prev_spin: 0.0,
+        })
+    }
+
+    // the device is gone (driver update or reset, GPU removed, out of memory): pick an adapter again, upload the
+    // Scene into a new device and point every window at it, the animation and cameras carry on where they were
+    // needs a surface to find a compatible adapter, so while suspended it returns false and waits for resume()
+    // an error means no adapter can take over, the window can't show anything anymore
+    fn recreate_device(&mut self) -> Result<bool, RenderError> {
+        // the browser can't block on the new device, the error handler has already logged the loss and reloading
+        // the page starts over
+        if cfg!(target_arch = "wasm32") {
+            return Ok(false);
+        }
+        let surface = match self.windows.iter().find_map(|window| window.surface.as_ref()) {
+            Some(surface) => surface,
+            None => return Ok(false),
+        };
+        let _span = info_span!("recreate_device").entered();
+        info!("Recreating the GPU device");
+        let adapter = pollster::block_on(self.adapter_request.pick(&self.instance, surface)).ok_or(RenderError::NoAdapter)?;
+        let format = self.windows[0].config.format;
+        if !surface.get_capabilities(&adapter).formats.contains(&format) {
+            return Err(RenderError::UnsupportedFormat(format));
+        }
+
+        self.stop_recording();
+        // the new buffers start out with the identity model and time 0, not what was last uploaded
+        self.uploaded_model = None;
+        self.uploaded_time = None;
+        self.uploaded_shadow = None;
+        self.materials_dirty = true;
+        self.retint_culled();
+
+        self.device_lost.store(false, Ordering::SeqCst);
+        // no --trace-dir here, a new trace would overwrite the one that shows how the old device got lost
+        self.gpu = pollster::block_on(Gpu::new(
+            adapter,
+            format,
+            &self.scene,
+            self.bench,
+            self.reverse_z,
+            &self.device_lost,
+            None,
+        ))?;
+        let shared = self.gpu.shared_bindings(&self.scene.light);
+        for window in &mut self.windows {
+            window.recreate_resources(&self.gpu.device, &self.gpu.queue, &shared);
+        }
+        Ok(true)
+    }
+
+    // --scene was saved with changes: apply them to the running scene, touching only the GPU resources they concern
+    // see scene_reload.rs, a file that doesn't parse has been reported there and leaves everything as it is
+    pub fn reload_scene(&mut self) {
+        let Some((file, diff)) = self.scene_watcher.as_mut().and_then(SceneWatcher::poll) else { return };
+        info!("Scene reloaded: {}", diff);
+
+        if diff.lights {
+            // the file's lights and shininess replace what [ ] made of it, as many lights as . switched on (or , off)
+            // on top of the file's own stay that way
+            let switched = self.scene.light.count as isize - lights::starting_count(self.scene.file_lights) as isize;
+            let light = scene::light(&file, self.scene.attenuation);
+            let count = (light.count as isize + switched).clamp(0, MAX_LIGHTS as isize) as u32;
+            self.scene.light = LightUniform { count, ..light };
+            self.scene.file_lights = file.lights.len();
+            self.light_dirty = true;
+        }
+        if diff.background {
+            let background = self.clear_color_arg.unwrap_or(file.background());
+            self.clear_color = clear_color(background, self.windows[0].config.format);
+        }
+        if diff.camera {
+            info!("[camera] only places the cameras at startup, the change shows on the next run");
+        }
+
+        if diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty() && !diff.materials {
+            return;
+        }
+        (self.scene.instances, self.scene.glass) = scene::objects(&file);
+        // a handful of bind groups at most, made again only if a material's settings changed or one came into use
+        (self.scene.materials, self.scene.instance_materials, self.scene.glass_materials) = scene::materials(&file);
+        self.gpu.set_materials(&self.scene);
+        // cubes coming or going can leave the scene bigger than the view or lost in a corner of it, frame it again
+        // a cube that only moved or changed color doesn't, the camera stays where it was put
+        if !diff.added.is_empty() || !diff.removed.is_empty() {
+            self.reframe_all();
+            self.reframe_pips();
+        }
+        if diff.slots_changed {
+            self.gpu.set_instances(&self.scene);
+            // the glow goes by slot, which may now hold a different cube, so it starts over like the new emissive buffer
+            self.materials = vec![MaterialState::default(); self.scene.instances.len() + self.scene.glass.len()];
+        } else {
+            // the whole buffer in one write, it's a few bytes per cube
+            self.gpu.write_instances(&self.scene.instances, &self.scene.glass);
+        }
+        // --lod's copies are out of date either way, the next frame makes and fills them again
+        for window in &mut self.windows {
+            window.gpu.lod = None;
+        }
+        self.retint_culled();
+    }
+
+    // Event::Suspended: surfaces are no longer valid (e.g. Android sends the app to the background), drop them all
+    pub fn suspend(&mut self) {
+        for window in &mut self.windows {
+            window.suspend();
+        }
+    }
+
+    // Event::Resumed: new surfaces for every window, the device and everything on it survived the suspend
+    pub fn resume(&mut self) -> Result<(), RenderError> {
+        for window in &mut self.windows {
+            window.resume(&self.instance, &self.gpu.device, &self.gpu.queue, self.render_size)?;
+        }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, id: winit::window::WindowId, new_size: PhysicalSize<u32>) -> Result<(), RenderError> {
+        if let Some(window) = self.windows.iter_mut().find(|window| window.id() == Some(id)) {
+            window.resize(&self.gpu.device, &self.gpu.queue, new_size, self.render_size)?;
+        }
+        Ok(())
+    }
+
+    // moving to a monitor with a different DPI changes both the size and how big the HUD text should be
+    // `new_size` is the physical size winit picked to keep the window's logical size, the surface follows it
+    pub fn rescale(&mut self, id: winit::window::WindowId, scale_factor: f64, new_size: PhysicalSize<u32>) -> Result<(), RenderError> {
+        if let Some(window) = self.windows.iter_mut().find(|window| window.id() == Some(id)) {
+            info!("Scale factor changed: {}", dpi::describe(new_size, scale_factor));
+            window.scale_factor = scale_factor;
+            window.resize(&self.gpu.device, &self.gpu.queue, new_size, self.render_size)?;
+        }
+        Ok(())
+    }
+
+    // every window asks for a RedrawRequested, render() draws them all in the first one that comes
+    pub fn request_redraw(&self) {
+        for window in self.windows.iter().filter_map(|window| window.window.as_ref()) {
+            window.request_redraw();
+        }
+    }
+
+    // drop a closed window with its surface, returns true once the last one is gone
+    pub fn close_window(&mut self, id: winit::window::WindowId) -> bool {
+        self.windows.retain(|window| window.id() != Some(id));
+        self.windows.is_empty()
+    }
+
+    // keys, mouse buttons and the wheel go through the keymap into actions for the window the event came from, or else
+    // for the shared scene, returns an action neither of them handles (the frame limiter) for the event loop
+    pub fn input(&mut self, id: winit::window::WindowId, event: &WindowEvent) -> Option<Action> {
+        let index = self.windows.iter().position(|window| window.id() == Some(id))?;
+        let window = &mut self.windows[index];
+        window.keep_grab(event);
+        window.track_cursor(&self.keymap, event, self.render_size);
+        let action = window.input.event(&self.keymap, event)?;
+        if self.dispatch(index, action) {
+            return None;
+        }
+        Some(action)
+    }
+
+    // an action for the window at `index` (or for nothing but the scene if it is gone): its own camera's actions first,
+    // then F, which needs the scene's bounds for the window's camera, then the shared scene's, returns true when one of
+    // them handled it
+    fn dispatch(&mut self, index: usize, action: Action) -> bool {
+        if self.windows.get_mut(index).is_some_and(|window| window.act(action)) {
+            return true;
+        }
+        if action == Action::FrameAll {
+            self.frame_all(index);
+            return true;
+        }
+        if action == Action::TogglePip {
+            self.toggle_pip(index);
+            return true;
+        }
+        if action == Action::FreezeCamera {
+            self.toggle_frozen_camera(index);
+            return true;
+        }
+        self.act(action)
+    }
+
+    // V: leave a copy of the camera of the window at `index` (the half being steered with --split-screen) where it is,
+    // or let go of the copy and give the cubes their own colors back
+    // the real camera keeps moving, so the frozen one's culling can be looked at from outside
+    fn toggle_frozen_camera(&mut self, index: usize) {
+        if self.frozen.take().is_some() {
+            self.gpu.write_instances(&self.scene.instances, &self.scene.glass);
+            // --lod's copies have the tint in them too
+            self.materials_dirty = true;
+            info!("Camera unfrozen");
+            return;
+        }
+        let Some(window) = self.windows.get(index) else { return };
+        let camera = &window.rigs[window.active].camera;
+        // --reverse-z's perspective has no far plane to draw, the same camera without it does
+        let drawn = Camera { reverse_z: false, ..*camera };
+        self.frozen = Some(FrozenCamera { view_proj: camera.view_proj(), corners: lod::frustum_corners(drawn.view_proj()), culled: Vec::new() });
+        info!("Camera frozen, its frustum is drawn and the cubes it would cull are tinted");
+    }
+
+    // the instance buffer was just written with the cubes' own colors, the frozen camera's tint goes back on next frame
+    fn retint_culled(&mut self) {
+        if let Some(frozen) = &mut self.frozen {
+            frozen.culled.clear();
+        }
+    }
+
+    // F: frame the scene as it is right now in the window at `index`, a spinning cube's corners included wherever
+    // they happen to point
+    // with --split-screen only the half being steered, the other keeps its view
+    fn frame_all(&mut self, index: usize) {
+        let Some((min, max)) = self.scene.bounds(self.interpolated_model(1.0)) else { return };
+        if let Some(window) = self.windows.get_mut(index) {
+            window.rig().frame(min, max);
+        }
+    }
+
+    // every camera of every window framed on the scene again, after the cubes changed under all of them
+    fn reframe_all(&mut self) {
+        let Some((min, max)) = self.scene.bounds(self.interpolated_model(1.0)) else { return };
+        for rig in self.windows.iter_mut().flat_map(|window| &mut window.rigs) {
+            rig.frame(min, max);
+        }
+    }
+
+    // P: show or hide the picture-in-picture in the window at `index`, framed on the scene as it is right now like F
+    // hiding it frees its texture, showing it again makes a new one
+    fn toggle_pip(&mut self, index: usize) {
+        let bounds = self.scene.bounds(self.interpolated_model(1.0)).unwrap_or((Vec3::NEG_ONE, Vec3::ONE));
+        let Some(window) = self.windows.get_mut(index) else { return };
+        window.pip = match window.pip {
+            Some(_) => None,
+            None => Some(bounds),
+        };
+        window.pip_dirty = true;
+        if window.pip.is_none() {
+            window.gpu.pip = None;
+        }
+        info!("Picture-in-picture {}", if window.pip.is_some() { "on" } else { "off" });
+    }
+
+    // the scene grew or shrank, every picture-in-picture that is shown is framed on it again
+    // the overhead camera is otherwise fixed, it doesn't follow F or anything the window's own camera does
+    fn reframe_pips(&mut self) {
+        let Some(bounds) = self.scene.bounds(self.interpolated_model(1.0)) else { return };
+        for window in self.windows.iter_mut().filter(|window| window.pip.is_some()) {
+            window.pip = Some(bounds);
+            window.pip_dirty = true;
+        }
+    }
+
+    // read the gamepads and act on their presses, with the window that has focus (or the first one) taking the
+    // window actions like it would for keys, the held actions are picked up in update() from pad_input
+    fn poll_gamepads(&mut self) {
+        let Some(gamepads) = &mut self.gamepads else { return };
+        let focused = self.windows.iter().position(WindowState::has_focus).unwrap_or(0);
+        for input in gamepads.poll() {
+            let action = match input {
+                PadInput::Button(button, state) => self.pad_input.button(&self.keymap, Binding::Pad(button), state),
+                PadInput::Axis(axis, value) => self.pad_input.axis(&self.keymap, axis, value),
+            };
+            // the frame limiter belongs to the event loop, which only sees the keyboard's actions
+            if let Some(action) = action {
+                self.dispatch(focused, action);
+            }
+        }
+    }
+
+    // raw mouse movement, which turns the camera of the window that has the mouse captured (if any)
+    // device events belong to no window, but only the focused window can hold the capture
+    pub fn mouse_motion(&mut self, (x, y): (f64, f64)) {
+        if let Some(window) = self.windows.iter_mut().find(|window| window.grab.is_captured()) {
+            window.rig().look(Vec2::new(x as f32, y as f32));
+        }
+    }
+
+    // once per frame, see CursorGrab::recenter()
+    pub fn recenter_cursors(&self) {
+        for window in &self.windows {
+            if let Some(os_window) = &window.window {
+                window.grab.recenter(os_window);
+            }
+        }
+    }
+
+    // actions that change the shared scene, returns true when the action was one of them
+    fn act(&mut self, action: Action) -> bool {
+        match action {
+            Action::ToggleNormals => self.show_normals = !self.show_normals,
+            Action::CycleDebugView => {
+                self.debug_view = self.debug_view.next();
+                // the normals view is a flag in the frame uniform, which is otherwise only rewritten when the time moves
+                self.uploaded_time = None;
+                info!("Debug view: {:?}", self.debug_view);
+            }
+            // the windows upload the new index with their globals on the next frame
+            Action::CycleEffect => {
+                self.effect = self.effect.next();
+                info!("Effect: {:?}", self.effect);
+            }
+            // every demo is on the device from the start, this only changes which bind group the cubes are drawn with
+            Action::CycleMaterial => {
+                self.demo_material = match self.demo_material {
+                    None => Some(material::FIRST_DEMO),
+                    Some(demo) if demo + 1 < material::FIRST_DEMO + material::DEMOS.len() as u32 => Some(demo + 1),
+                    Some(_) => None,
+                };
+                info!("Material: {}", self.material_label());
+            }
+            Action::ToggleSelection => self.selected = !self.selected,
+            // the map is drawn and read from the next frame on, its texture is there the whole time either way
+            Action::ToggleShadows => {
+                self.shadows = !self.shadows;
+                info!("Shadows {}", if self.shadows { "on" } else { "off" });
+            }
+            Action::ToggleBounds => self.show_bounds = !self.show_bounds,
+            Action::CycleDrawMode => {
+                self.draw_mode = self.draw_mode.next();
+                info!("Draw mode: {:?}", self.draw_mode);
+            }
+            Action::ToggleCubeShader => {
+                // the other shader's pipelines are built on the next render() if they don't exist yet, then it is
+                // only a matter of binding a different cached pipeline
+                self.cube_shader = self.cube_shader.toggle();
+                info!("Cube shader: {:?}", self.cube_shader);
+            }
+            Action::ToggleGlassShader => {
+                self.glass_shader = self.glass_shader.toggle();
+                info!("Glass shader: {:?}", self.glass_shader);
+            }
+            Action::ToggleDepthPrepass => {
+                self.depth_prepass = !self.depth_prepass;
+                info!("Depth prepass {}", if self.depth_prepass { "on" } else { "off" });
+            }
+            Action::ShininessDown | Action::ShininessUp => {
+                let factor = if action == Action::ShininessDown { 0.5 } else { 2.0 };
+                self.scene.light.shininess = (self.scene.light.shininess * factor).clamp(MIN_SHININESS, MAX_SHININESS);
+                self.light_dirty = true;
+                info!("Shininess: {}", self.scene.light.shininess);
+            }
+            Action::AddLight | Action::RemoveLight => {
+                // the slots are all filled in already, only how many of them the shader loops over changes
+                let count = self.scene.light.count as usize;
+                let count = if action == Action::AddLight { (count + 1).min(MAX_LIGHTS) } else { count.saturating_sub(1) };
+                self.scene.light.count = count as u32;
+                self.light_dirty = true;
+                info!("Lights: {}/{}", count, MAX_LIGHTS);
+            }
+            Action::Pause => {
+                self.paused = !self.paused;
+                info!("{}", if self.paused { "Paused" } else { "Resumed" });
+            }
+            Action::ResetOrientation => {
+                self.reset_tween = Some(Tween::new(self.orientation, Quat::IDENTITY, RESET_TIME, Easing::CubicInOut));
+            }
+            _ => match axis_preset(action) {
+                Some(target) => self.set_axis_target(target),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    // advance the simulation by exactly dt seconds, called zero or more times per frame by the fixed-timestep loop
+    // trace level so the per-frame spans cost next to nothing at the default info level
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn update(&mut self, dt: f32) {
+        self.prev_orientation = self.orientation;
+        self.prev_time = self.time;
+        self.prev_spin = self.spin;
+        self.poll_gamepads();
+        let focused = self.windows.iter().position(WindowState::has_focus).unwrap_or(0);
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            window.move_camera(&self.keymap, (i == focused).then_some(&self.pad_input), dt);
+            // the half not being steered can still be finishing a tween
+            for rig in &mut window.rigs {
+                rig.update(dt);
+            }
+        }
+        self.turn_cube(focused, dt);
+        // hovering keeps working while paused, like the cameras
+        self.update_hover(dt);
+        // both steps equal, so the interpolated model and time stop changing and write_uniforms() has nothing to upload
+        if self.paused {
+            return;
+        }
+        self.time += dt;
+
+        // move towards the target axis, slerp keeps the axis on the unit sphere the whole way
+        self.axis_blend = (self.axis_blend + dt / AXIS_TRANSITION_TIME).min(1.0);
+        let axis = self.current_axis() * Vec3::Y;
+
+        if let Some(tween) = &mut self.reset_tween {
+            // the reset owns the orientation until it is done, the spin picks up again from identity afterwards
+            self.orientation = tween.advance(dt);
+            if tween.is_finished() {
+                self.reset_tween = None;
+            }
+        } else {
+            // apply this step's small rotation on top of the current orientation, so changing the axis
+            // only changes the direction of spin instead of snapping the cube to a new pose
+            self.orientation = (Quat::from_axis_angle(axis, ROTATION_SPEED * dt) * self.orientation).normalize();
+            self.spin += ROTATION_SPEED * dt;
+        }
+    }
+
+    // cube-turn-* (the right stick by default) turn the cube by hand on top of its spin, around the focused window's
+    // screen axes so pushing right turns the side facing the camera to the right from wherever it is looked at
+    fn turn_cube(&mut self, focused: usize, dt: f32) {
+        // Home is turning it back to the start, that gets to finish first
+        if self.reset_tween.is_some() {
+            return;
+        }
+        let Some(window) = self.windows.get(focused) else { return };
+        let amount = |action| (window.input.amount(&self.keymap, action) + self.pad_input.amount(&self.keymap, action)).min(1.0);
+        let yaw = amount(Action::CubeTurnRight) - amount(Action::CubeTurnLeft);
+        let pitch = amount(Action::CubeTurnDown) - amount(Action::CubeTurnUp);
+        if yaw == 0.0 && pitch == 0.0 {
+            return;
+        }
+        let camera = &window.rigs[window.active].camera;
+        let right = (camera.target - camera.eye).cross(camera.up).normalize();
+        let turn = Quat::from_axis_angle(camera.up, yaw * CUBE_TURN_SPEED * dt) * Quat::from_axis_angle(right, pitch * CUBE_TURN_SPEED * dt);
+        self.orientation = (turn * self.orientation).normalize();
+    }
+
+    // point every cube's glow at whether the cursor is over it and step the fades
+    // picked every step rather than on CursorMoved since the cubes and cameras move under a still cursor too
+    fn update_hover(&mut self, dt: f32) {
+        // the model after this step, the same one interpolated_model() reaches at the end of the frame
+        let model = self.interpolated_model(1.0);
+        // only one window (and half of it with --split-screen) can have the cursor over it, that camera and viewport
+        // turn the cursor into a ray
+        let hovered = self.windows.iter().find_map(|window| {
+            let cursor = window.cursor?;
+            window.panes(window.viewport(self.render_size)).into_iter().find_map(|pane| {
+                let ray = picking::cursor_ray(pane.camera, pane.viewport, cursor)?;
+                picking::pick(ray, model, self.scene.instances.iter().chain(&self.scene.glass))
+            })
+        });
+        for (i, material) in self.materials.iter_mut().enumerate() {
+            material.target = if hovered == Some(i) { 1.0 } else { 0.0 };
+            if material.advance(dt) {
+                self.materials_dirty = true;
+            }
+        }
+    }
+
+    // spin axis right now, eased between the start and end of the current transition
+    fn current_axis(&self) -> Quat {
+        let t = self.axis_blend * self.axis_blend * (3.0 - 2.0 * self.axis_blend); // smoothstep so it starts and stops gently
+        self.axis_from.slerp(self.axis_to, t)
+    }
+
+    // start a transition to a new rotation axis from wherever the axis is right now
+    fn set_axis_target(&mut self, target: Quat) {
+        self.axis_from = self.current_axis();
+        self.axis_to = target;
+        self.axis_blend = 0.0;
+    }
+
+    // upload uniforms for this frame, alpha (0..1) says how far between the previous and current simulation step we are
+    // each buffer is only written when its value differs from what was uploaded last, so a paused cube under a still
+    // camera costs no uploads at all, the HUD shows the count to compare
+    fn write_uniforms(&mut self, alpha: f32) {
+        let mut writes = 0;
+        let rot = self.interpolated_model(alpha);
+
+        if self.uploaded_model != Some(rot) {
+            let model = ModelUniform {
+                model: rot.to_cols_array_2d(), //convert to 2D array again for GPU to understand
+            };
+            self.gpu.queue.write_buffer(&self.gpu.model_buffer, 0, bytemuck::bytes_of(&model)); //load the model information to buffer after rotation changes applied
+            self.uploaded_model = Some(rot);
+            writes += 1;
+        }
+
+        let time = self.interpolated_time(alpha);
+        if self.uploaded_time != Some(time) {
+            let frame = FrameUniform {
+                time,
+                hue_mix: self.scene.hue_mix,
+                normal_colors: if self.debug_view == DebugView::Normals { 1.0 } else { 0.0 },
+                _padding: 0.0,
+            };
+            self.gpu.queue.write_buffer(&self.gpu.frame_buffer, 0, bytemuck::bytes_of(&frame));
+            writes += 1;
+            if let Some(deformer) = &self.gpu.deformer {
+                deformer.set_time(&self.gpu.queue, time);
+                writes += 1;
+            }
+            self.uploaded_time = Some(time);
+        }
+
+        let glow_changed = self.materials_dirty;
+        if self.materials_dirty {
+            let glow: Vec<f32> = self.materials.iter().map(MaterialState::glow).collect();
+            self.gpu.queue.write_buffer(&self.gpu.emissive_buffer, 0, bytemuck::cast_slice(&glow));
+            self.materials_dirty = false;
+            writes += 1;
+        }
+
+        // every cube is the same mesh under the same model transform, so one sphere around it fits them all once
+        // moved to their offsets: the model's translation (--anim) moves its centre, its largest scale grows it
+        let radius = self.scene.cube.aabb().map_or(0.0, |(min, max)| min.abs().max(max.abs()).length());
+        let scale = rot.x_axis.truncate().length().max(rot.y_axis.truncate().length()).max(rot.z_axis.truncate().length());
+        let sphere = (rot.w_axis.truncate(), radius * scale);
+
+        // S: the light's box around every cube, uploaded again only when it moves (--anim), the cubes or the light change
+        // or shadows go on or off; while they are off nothing reads the rest of it
+        let shadow = if self.shadows {
+            let (shift, radius) = sphere;
+            let offsets = self.scene.instances.iter().chain(&self.scene.glass).map(|instance| Vec3::from(instance.offset));
+            // big enough for the most stretched cube
+            let reach = self.scene.instances.iter().chain(&self.scene.glass).map(instances::Instance::reach).fold(0.0, f32::max);
+            let (center, radius) = shadow::bounding_sphere(offsets, shift, radius * reach).unwrap_or(sphere);
+            ShadowUniform::new(Vec3::from(self.scene.light.sources[0].vector), center, radius)
+        } else {
+            ShadowUniform::zeroed()
+        };
+        if self.uploaded_shadow != Some(shadow) {
+            self.gpu.shadow.write(&self.gpu.queue, &shadow);
+            self.uploaded_shadow = Some(shadow);
+            writes += 1;
+        }
+
+        // V: the same test --lod culls with, from the frozen camera, the instances are only written again when the
+        // set of cubes it would leave out changes
+        let mut tint_changed = false;
+        if let Some(frozen) = &mut self.frozen {
+            let (shift, radius) = sphere;
+            let culled: Vec<bool> =
+                self.scene.instances.iter().map(|instance| !lod::in_view(frozen.view_proj, Vec3::from(instance.offset) + shift, radius * instance.reach())).collect();
+            if culled != frozen.culled {
+                let tinted = lod::tint_culled(&self.scene.instances, &culled);
+                self.gpu.write_instances(&tinted, &self.scene.glass);
+                frozen.culled = culled;
+                tint_changed = true;
+                writes += 1;
+            }
+        }
+
+        // sorted in every draw mode so the HUD's counts are there to look at, only the triangles mode draws with them
+        if let Some(thresholds) = self.lod {
+            let glow: Vec<f32> = self.materials[..self.scene.instances.len()].iter().map(MaterialState::glow).collect();
+            let instances = match &self.frozen {
+                Some(frozen) => lod::tint_culled(&self.scene.instances, &frozen.culled),
+                None => self.scene.instances.clone(),
+            };
+            for window in &mut self.windows {
+                // a new tint is written like a new glow, the order alone doesn't say anything changed
+                writes += window.write_lod(&self.gpu, thresholds, sphere, &instances, &glow, glow_changed || tint_changed);
+            }
+        }
+
+        self.build_debug_lines(rot);
+
+        // the orbiting light moves with the animation's time, so it stops while paused and the light buffers with it
+        let orbit = lights::orbit_position(time).to_array();
+        if let Some(slot) = lights::orbiting(self.scene.file_lights) {
+            let orbiting = &mut self.scene.light.sources[slot];
+            if orbiting.vector != orbit {
+                orbiting.vector = orbit;
+                // switched off it can move without anything being uploaded, . marks the light dirty when it comes on
+                self.light_dirty |= self.scene.light.count as usize > slot;
+            }
+        }
+
+        let globals = GlobalsUniform::new(time, self.scene.day_length, self.effect);
+        for window in &mut self.windows {
+            writes += window.write_uniforms(&self.gpu.queue, &self.scene.light, self.light_dirty, globals, self.render_size);
+            writes += window.write_pip(&self.gpu, &self.scene.light, self.light_dirty, self.render_size, self.reverse_z);
+        }
+        self.light_dirty = false;
+        self.uniform_writes = writes;
+    }
+
+    // blend the last two simulation states so motion stays smooth even when frames and steps don't line up
+    fn interpolated_model(&self, alpha: f32) -> Mat4 {
+        // a clip can be sampled at any time directly, no need to blend two steps
+        if let Some(clip) = &self.animation {
+            return clip.sample(self.interpolated_time(alpha));
+        }
+        let orientation = self.prev_orientation.slerp(self.orientation, alpha);
+        Mat4::from_quat(orientation) //turn the quaternion into a rotation matrix
+    }
+
+    fn interpolated_time(&self, alpha: f32) -> f32 {
+        self.prev_time + (self.time - self.prev_time) * alpha
+    }
+
+    // collect this frame's debug lines in world space, `model` is the cube's transform for this frame
+    fn build_debug_lines(&mut self, model: Mat4) {
+        self.gpu.debug_lines.clear();
+        if let Some(frozen) = &self.frozen {
+            self.gpu.debug_lines.add_frustum(&frozen.corners, FROZEN_FRUSTUM_COLOR);
+        }
+        if self.show_normals {
+            self.gpu.debug_lines.add_normals(&self.scene.cube.vertices, model, 0.5);
+        }
+        if self.show_bounds {
+            // box around the rotated cube, it grows and shrinks as the corners swing out
+            let (min, max) = debug_lines::transformed_bounds(&self.scene.cube.vertices, model);
+            self.gpu.debug_lines.add_aabb(min, max, [1.0, 1.0, 1.0]);
+            self.gpu.debug_lines.add_axes(model, 1.5);
+            // a line from the origin towards each directional light, a small cross where each point light is
+            for source in self.scene.light.active() {
+                let vector = Vec3::from(source.vector);
+                if source.kind == lights::POINT {
+                    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                        self.gpu.debug_lines.add_line(vector - axis * 0.15, vector + axis * 0.15, [1.0, 1.0, 0.0]);
+                    }
+                } else {
+                    self.gpu.debug_lines.add_line(Vec3::ZERO, vector * 2.5, [1.0, 1.0, 0.0]);
+                }
+            }
+        }
+        self.gpu.debug_lines.upload(&self.gpu.device, &self.gpu.queue);
+    }
+
+    // record the render passes (clear, cubes, debug lines, then HUD) targeting `view`, `depth` must be the same size
+    // the scene only covers `viewport`, the clear still fills the whole target so the bars around it are background
+    // `window` supplies the camera and HUD, `view` and `depth` are usually its own but are the capture targets for --record
+    // with --split-screen each pass draws the scene twice, once per half with that half's camera; the halves don't
+    // overlap, so they share the one depth buffer that was cleared for both
+    // every draw is added to `stats`
+    fn encode_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        window: &WindowState,
+        view: &wgpu::TextureView,
+        depth: &DepthBuffer,
+        viewport: Viewport,
+        stats: &mut RenderStats,
+    ) {
+        let panes = window.panes(viewport);
+        // prepass: only depth, no color target and no fragment shader, so hidden surfaces cost almost nothing
+        if self.depth_prepass {
+            let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth Prepass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(depth::clear_value(self.reverse_z)),
+                        store: true, //the color pass below tests against it
+                    }),
+                    // the cube marks its pixels here already, the outline in the color pass reads them
+                    stencil_ops: depth.stencil_ops(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: true,
+                    }),
+                }),
+            }), stats);
+            for pane in &panes {
+                pane.viewport.apply(&mut pass);
+                self.draw_cubes(&mut pass, &pane.gpu.bind_group, pane.lod, self.draw_mode, PassKind::DepthOnly);
+            }
+        }
+
+        {
+            // with a prepass the depth buffer is already filled in, keep it instead of clearing
+            let depth_load = if self.depth_prepass { wgpu::LoadOp::Load } else { wgpu::LoadOp::Clear(depth::clear_value(self.reverse_z)) }; //far plane, anything drawn is closer
+            let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor { //render pass to black out view
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: depth_load,
+                        store: self.debug_view == DebugView::Depth, //only the depth view reads it once the pass is done
+                    }),
+                    stencil_ops: depth.stencil_ops(wgpu::Operations {
+                        load: if self.depth_prepass { wgpu::LoadOp::Load } else { wgpu::LoadOp::Clear(0) },
+                        store: false, //the outline is drawn in this same pass
+                    }),
+                }),
+            }), stats);
+
+            for pane in &panes {
+                pane.viewport.apply(&mut pass);
+                // --day-length paints the viewport over the clear color before anything else, the bars around it stay
+                if let Some(sky) = &self.gpu.sky {
+                    sky.draw(&mut pass, &pane.gpu.bind_group);
+                }
+                let kind = if self.depth_prepass { PassKind::ColorAfterPrepass } else { PassKind::Single };
+                self.draw_cubes(&mut pass, &pane.gpu.bind_group, pane.lod, self.draw_mode, kind);
+
+                // the same meshes' edges over what was just drawn, the depth test against the faces hides the back edges
+                if self.debug_view == DebugView::WireframeOverlay {
+                    self.draw_cubes(&mut pass, &pane.gpu.bind_group, pane.lod, DrawMode::Lines, PassKind::Overlay);
+                }
+
+                // a rim around the cube where the stencil isn't marked, right after it since without a depth test it would
+                // paint over particles and debug lines drawn before it
+                if self.outline_visible() {
+                    self.draw_cubes(&mut pass, &pane.gpu.bind_group, pane.lod, DrawMode::Triangles, PassKind::Outline);
+                }
+
+                // after the cube so the depth test can hide particles behind it, they bind their own pipeline and camera group
+                if let (Some(particles), Some(camera)) = (&self.gpu.particles, &pane.gpu.particle_bind_group) {
+                    particles.draw(&mut pass, camera);
+                    pass.set_bind_group(0, &pane.gpu.bind_group, &[]);
+                }
+
+                // blended over everything opaque, so last of the scene, only the debug lines stay on top of it
+                if self.glass_visible() {
+                    self.draw_glass(&mut pass, &pane.gpu.bind_group, pane.camera.view());
+                }
+
+                // same bind group, different pipeline and vertex buffer, drawn after the cube so lines sit on top
+                pass.set_pipeline(&self.gpu.line_pipeline, wgpu::PrimitiveTopology::LineList);
+                self.gpu.debug_lines.draw(&mut pass);
+
+                // same pipeline again, in a corner of its own
+                self.gpu.axis_gizmo.draw(&mut pass, &pane.gpu.gizmo_bind_group, gizmo::viewport(pane.viewport, window.scale_factor));
+            }
+        }
+
+        // depth view: replace what was just drawn with the depth buffer it left behind, the HUD still goes on top
+        if self.debug_view == DebugView::Depth {
+            // each half's own near and far planes turn its depth back into distance
+            let bind_groups: Vec<_> =
+                panes.iter().map(|pane| self.gpu.depth_debug.bind_group(&self.gpu.device, &pane.gpu.depth_debug_buffer, &depth.depth_view)).collect();
+            let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth View Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load, //outside the viewport stays as the scene pass cleared it
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            }), stats);
+            for (pane, bind_group) in panes.iter().zip(&bind_groups) {
+                pane.viewport.apply(&mut pass);
+                self.gpu.depth_debug.draw(&mut pass, bind_group);
+            }
+        }
+
+        // the picture-in-picture over the corner of the scene (and of the depth view), under the HUD
+        if let Some(target) = window.gpu.pip.as_ref().filter(|_| window.pip.is_some()) {
+            let inset = pip::viewport(viewport, window.scale_factor);
+            // bounded by the viewport, which is inside the target, rather than by the whole target
+            let right = (viewport.x + viewport.width) as u32;
+            let bottom = (viewport.y + viewport.height) as u32;
+            if let Some(scissor) = pip::scissor(inset, right, bottom) {
+                let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Picture-in-Picture Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                }), stats);
+                self.gpu.pip_compositor.draw(&mut pass, &target.composite_bind_group, inset, scissor);
+            }
+        }
+
+        // HUD in its own pass without a depth buffer, loading what was just drawn so it ends up on top
+        let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("HUD Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        }), stats);
+        window.gpu.hud.draw(&mut pass);
+    }
+
+    // the picture-in-picture's scene from the overhead camera into its own texture, done before encode_scene() copies it
+    // the scene's own passes in short: no prepass, overlays or gizmo, and all the cubes whatever --lod would leave out
+    fn encode_pip(&self, encoder: &mut wgpu::CommandEncoder, window: &WindowState, stats: &mut RenderStats) {
+        let (Some(target), Some(_)) = (&window.gpu.pip, window.pip) else { return };
+        let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Picture-in-Picture Scene Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &target.depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(depth::clear_value(self.reverse_z)),
+                    store: false,
+                }),
+                stencil_ops: target.depth.stencil_ops(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: false,
+                }),
+            }),
+        }), stats);
+        if let Some(sky) = &self.gpu.sky {
+            sky.draw(&mut pass, &target.bind_group);
+        }
+        self.draw_cubes(&mut pass, &target.bind_group, None, self.draw_mode, PassKind::Single);
+        if let (Some(particles), Some(camera)) = (&self.gpu.particles, &target.particle_bind_group) {
+            particles.draw(&mut pass, camera);
+            pass.set_bind_group(0, &target.bind_group, &[]);
+        }
+        if self.glass_visible() {
+            self.draw_glass(&mut pass, &target.bind_group, target.camera.view());
+        }
+        pass.set_pipeline(&self.gpu.line_pipeline, wgpu::PrimitiveTopology::LineList);
+        self.gpu.debug_lines.draw(&mut pass);
+    }
+
+    // the outline is shown for the first time on a device that has no float depth format with stencil: switch every
+    // depth buffer to one with stencil, and what draws into them along with it. It stays that way from then on, turning
+    // the outline off again isn't worth rebuilding everything a second time, a new device starts without it again
+    fn add_stencil(&mut self) -> Result<(), RenderError> {
+        let depth_format = depth::format(self.gpu.device.features(), true);
+        info!("Switching the depth buffers to {:?} for the outline", depth_format);
+        self.gpu.set_depth_format(self.windows[0].config.format, depth_format)?;
+        let device = &self.gpu.device;
+        for window in &mut self.windows {
+            window.gpu.depth = depth::create_depth_buffer(device, window.config.width, window.config.height, depth_format);
+            // made again at the next write_pip(), which sees it missing
+            window.gpu.pip = None;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.set_depth_format(device, depth_format);
+        }
+        Ok(())
+    }
+
+    // the outline grows the cube's triangles, around points or lines it would fill everything between them
+    fn outline_visible(&self) -> bool {
+        self.selected && self.draw_mode == DrawMode::Triangles
+    }
+
+    // the glass is faces only, the points and lines modes leave it out
+    fn glass_visible(&self) -> bool {
+        self.gpu.num_glass > 0 && self.draw_mode == DrawMode::Triangles
+    }
+
+    // the glass cubes furthest from this window's camera first, each one's back faces and then its front faces
+    // sorted by their offsets alone: the model transform moves every cube's centre by the same amount, which shifts all
+    // their view depths equally and leaves the order as it is
+    fn draw_glass<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, bind_group: &'a wgpu::BindGroup, view: Mat4) {
+        let positions: Vec<Vec3> = self.scene.glass.iter().map(|glass| Vec3::from(glass.offset)).collect();
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_vertex_buffer(1, self.gpu.instance_buffer.slice(..));
+        pass.set_vertex_buffer(2, self.gpu.emissive_buffer.slice(..));
+        for i in transparency::back_to_front(view, &positions) {
+            let instance = self.gpu.num_instances + i as u32;
+            pass.set_bind_group(1, self.gpu.materials.get(self.scene.glass_materials[i]), &[]);
+            for kind in [PassKind::GlassBack, PassKind::GlassFront] {
+                pass.set_pipeline(self.gpu.pipelines.get(DrawMode::Triangles, kind, self.glass_shader), DrawMode::Triangles.topology());
+                for mesh in &self.gpu.meshes {
+                    mesh.draw(pass, DrawMode::Triangles, instance..instance + 1);
+                }
+            }
+        }
+    }
+
+    // draw every instance of every mesh, one call per mesh, with the pipeline for `mode` and `kind` of pass
+    // `bind_group` has the camera, with --lod in the triangles mode `lod` has that camera's visible cubes to draw
+    // instead, one call per level (the picture-in-picture passes None and draws them all)
+    fn draw_cubes<'a>(
+        &'a self,
+        pass: &mut CountingPass<'_, 'a>,
+        bind_group: &'a wgpu::BindGroup,
+        lod: Option<(&'a LodBuffers, &'a Buckets)>,
+        mode: DrawMode,
+        kind: PassKind,
+    ) {
+        pass.set_pipeline(self.gpu.pipelines.get(mode, kind, self.cube_shader), mode.topology()); //set up the pipeline and bindings, then fetch vertex information from buffer after shader has applied position and color transformations
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_stencil_reference(depth::STENCIL_CUBE); //what the cube writes and the outline compares against
+        if let (true, Some((buffers, buckets))) = (self.lod_active(), lod) {
+            // the levels are sorted by distance, which mixes up the materials' runs, so they all get the first cube's
+            // (--grid's cubes only ever have the default one)
+            let first = self.gpu.materials.runs.first().map_or(material::CUBE, |(material, _)| *material);
+            pass.set_bind_group(1, self.gpu.materials.get(self.demo_material.unwrap_or(first)), &[]);
+            buffers.bind(pass);
+            for (level, range) in Level::ALL.into_iter().zip(buckets.ranges()) {
+                self.draw_level(pass, level, range, mode, kind);
+            }
+            return;
+        }
+        pass.set_vertex_buffer(1, self.gpu.instance_buffer.slice(..));
+        pass.set_vertex_buffer(2, self.gpu.emissive_buffer.slice(..));
+        // a demo material covers every cube, so it is one draw per mesh again
+        if let Some(demo) = self.demo_material {
+            pass.set_bind_group(1, self.gpu.materials.get(demo), &[]);
+            for mesh in &self.gpu.meshes {
+                mesh.draw(pass, mode, 0..self.gpu.num_instances);
+            }
+            return;
+        }
+        for (material, range) in &self.gpu.materials.runs {
+            pass.set_bind_group(1, self.gpu.materials.get(*material), &[]);
+            for mesh in &self.gpu.meshes {
+                mesh.draw(pass, mode, range.clone());
+            }
+        }
+    }
+
+    // the HUD's and the log's name for what K has the cubes in
+    fn material_label(&self) -> &'static str {
+        match self.demo_material {
+            Some(demo) => material::DEMOS[(demo - material::FIRST_DEMO) as usize].0,
+            None => "scene",
+        }
+    }
+
+    // one --lod level's range of the window's sorted instances, which draw_cubes() has bound
+    // `mode` is the pass's, triangles or the overlay's lines: the points are points whatever it is and only go into the
+    // passes that make the image, the wireframe and outline of a single pixel would cover it or be lost under it
+    fn draw_level<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, level: Level, range: Range<u32>, mode: DrawMode, kind: PassKind) {
+        if range.is_empty() {
+            return;
+        }
+        match level {
+            Level::Full => {
+                for mesh in &self.gpu.meshes {
+                    mesh.draw(pass, mode, range.clone());
+                }
+            }
+            Level::Simple => self.gpu.lod_meshes[0].draw(pass, mode, range),
+            Level::Point if matches!(kind, PassKind::Overlay | PassKind::Outline) => {}
+            Level::Point => {
+                pass.set_pipeline(self.gpu.pipelines.get(DrawMode::Points, kind, self.cube_shader), DrawMode::Points.topology());
+                self.gpu.lod_meshes[1].draw(pass, DrawMode::Points, range);
+            }
+        }
+    }
+
+    // the levels only stand in for triangles, see lod.rs
+    fn lod_active(&self) -> bool {
+        self.lod.is_some() && self.draw_mode == DrawMode::Triangles
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn render(&mut self, alpha: f32) -> Result<(), RenderError> {
+        let started = Instant::now();
+        // nothing made on a lost device works anymore, replace it before touching any of its buffers
+        if self.device_lost.load(Ordering::SeqCst) && !self.recreate_device()? {
+            return Ok(());
+        }
+        // before write_uniforms(), which makes the picture-in-picture's target again in the new format
+        if self.outline_visible() && !self.gpu.depth_format.has_stencil_aspect() {
+            self.add_stencil()?;
+        }
+        self.write_uniforms(alpha);
+        self.update_hud();
+        // builds the pipeline the first time a draw mode is used, a cache hit afterwards
+        self.gpu.pipelines.prepare(&self.gpu.device, &self.gpu.pipeline_cache, self.draw_mode, self.depth_prepass, self.cube_shader)?;
+        // --lod's points are drawn in the same passes as the faces around them
+        if self.lod_active() {
+            self.gpu.pipelines.prepare(&self.gpu.device, &self.gpu.pipeline_cache, DrawMode::Points, self.depth_prepass, self.cube_shader)?;
+        }
+        if self.debug_view == DebugView::WireframeOverlay {
+            self.gpu.pipelines.prepare_variant(&self.gpu.device, &self.gpu.pipeline_cache, DrawMode::Lines, PassKind::Overlay, self.cube_shader)?;
+        }
+        if self.outline_visible() {
+            self.gpu.pipelines.prepare_variant(&self.gpu.device, &self.gpu.pipeline_cache, DrawMode::Triangles, PassKind::Outline, self.cube_shader)?;
+        }
+        if self.glass_visible() {
+            self.gpu.pipelines.prepare_variant(&self.gpu.device, &self.gpu.pipeline_cache, DrawMode::Triangles, PassKind::GlassBack, self.glass_shader)?;
+            self.gpu.pipelines.prepare_variant(&self.gpu.device, &self.gpu.pipeline_cache, DrawMode::Triangles, PassKind::GlassFront, self.glass_shader)?;
+        }
+        // the picture-in-picture always draws without the prepass
+        if self.depth_prepass && self.windows.iter().any(|window| window.gpu.pip.is_some()) {
+            self.gpu.pipelines.prepare_variant(&self.gpu.device, &self.gpu.pipeline_cache, self.draw_mode, PassKind::Single, self.cube_shader)?;
+        }
+
+        // one swapchain texture per window, a window without one this frame (or without a surface while suspended) is skipped
+        // an offscreen window has its own texture instead, which is always there
+        let mut frames = Vec::with_capacity(self.windows.len());
+        // blocks while the GPU is behind, which is the GPU's time rather than the CPU's
+        let mut acquiring = Duration::ZERO;
+        for (i, window) in self.windows.iter().enumerate() {
+            if window.offscreen.is_some() {
+                frames.push((i, None));
+                continue;
+            }
+            let surface = match &window.surface {
+                Some(surface) => surface,
+                None => continue,
+            };
+            let acquire_started = Instant::now();
+            let texture = surface.get_current_texture();
+            acquiring += acquire_started.elapsed();
+            match texture {
+                Ok(frame) => frames.push((i, Some(frame))),
+                // the swapchain no longer matches the window (e.g. mid-resize), set it up again and draw next frame
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => surface.configure(&self.gpu.device, &window.config),
+                Err(wgpu::SurfaceError::Timeout) => {}
+                // out of memory usually means the device is gone too, recreate it before the next frame
+                // the browser can't, there it is the end
+                Err(err @ wgpu::SurfaceError::OutOfMemory) if cfg!(target_arch = "wasm32") => return Err(err.into()),
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    error!("Out of memory getting the next swapchain texture");
+                    self.device_lost.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+
+        let mut encoder = self.gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }); //write GPU commands and encode them 
+        if let Some(timer) = &self.gpu.gpu_timer {
+            timer.begin(&mut encoder);
+        }
+
+        // compute first, the render passes below read the vertices it just wrote
+        if let Some(deformer) = &self.gpu.deformer {
+            deformer.dispatch(&mut encoder);
+        }
+        if self.gpu.particles.is_some() {
+            // step by however much animation time passed since the last frame, in sync with the cube
+            let time = self.interpolated_time(alpha);
+            let dt = (time - self.particle_time).max(0.0);
+            self.particle_time = time;
+            let model = self.interpolated_model(alpha);
+            if let Some(particles) = self.gpu.particles.as_mut() {
+                particles.step(&self.gpu.queue, &mut encoder, dt, model);
+            }
+        }
+
+        // every window goes into the same encoder, so the whole frame is a single submit
+        let mut stats = RenderStats::default();
+        // every window and camera reads the same map, the light doesn't move with them, so it is drawn once for all
+        if self.shadows {
+            self.gpu.shadow.encode(&mut encoder, &self.gpu.meshes, &self.gpu.instance_buffer, self.gpu.num_instances, &mut stats);
+        }
+        for (i, frame) in &frames {
+            let window = &self.windows[*i];
+            let texture = match (frame, &window.offscreen) {
+                (Some(frame), _) => &frame.texture,
+                (None, Some(texture)) => texture,
+                (None, None) => continue,
+            };
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default()); //get current texture and display it (vertices proc by shader)
+            self.encode_pip(&mut encoder, window, &mut stats);
+            self.encode_scene(&mut encoder, window, &view, &window.gpu.depth, window.viewport(self.render_size), &mut stats);
+        }
+
+        // --record draws the scene a second time, see app/recording.rs
+        let captured_slot = self.capture(&mut encoder, &mut stats);
+
+        if let Some(timer) = &self.gpu.gpu_timer {
+            timer.end(&mut encoder);
+        }
+
+        self.gpu.queue.submit(Some(encoder.finish())); //send to encoder and call on GPU to present it
+        // the uploads since the last frame, the ones made between frames (a reload, --stress) included
+        self.render_stats = RenderStats { uploaded_bytes: self.gpu.queue.take_uploaded(), ..stats };
+        // present() can wait for the GPU as well, so it is left out
+        self.render_cpu = started.elapsed().saturating_sub(acquiring);
+        for frame in frames.into_iter().filter_map(|(_, frame)| frame) {
+            frame.present();
+        }
+        self.captured(captured_slot);
+        Ok(())
+    }
+
+    // radians the cube has spun in the frame drawn with `alpha`, only the free spin counts: it stands still while
+    // paused or turning back with Home, and neither the hand turns (cube-turn-*) nor an --anim clip add to it
+    // the angle keeps growing past a full turn, so the rate it grows at can be read straight off a plot
+    pub fn spin_angle(&self, alpha: f32) -> f32 {
+        self.prev_spin + (self.spin - self.prev_spin) * alpha
+    }
+
+    // GPU time of the last submitted frame in ms, None unless timestamp queries are enabled (--bench and --stress)
+    pub fn gpu_frame_ms(&self) -> Option<f64> {
+        self.gpu.gpu_timer.as_ref().and_then(|timer| timer.read_ms(&self.gpu.device))
+    }
+
+    // CPU time of the last render(): uploads, encoding and the submit, but not waiting for the GPU before or after
+    pub fn render_cpu_time(&self) -> Duration {
+        self.render_cpu
+    }
+
+    // --stress: `count` cubes in place of the scene's, laid out like --grid and cycling through the hues like it, with
+    // every window framed on them again
+    // the glass goes, this is about how many instanced cubes can be drawn
+    pub fn set_cube_count(&mut self, count: u32) {
+        self.scene.instances = instances::field(count);
+        self.scene.glass.clear();
+        self.scene.hue_mix = 1.0;
+        self.uploaded_time = None; //the hue mix is in the frame uniform
+        self.scene.instance_materials = vec![material::CUBE; self.scene.instances.len()];
+        self.scene.glass_materials.clear();
+        self.gpu.set_instances(&self.scene);
+        self.gpu.set_materials(&self.scene);
+        self.materials = vec![MaterialState::default(); self.scene.instances.len()];
+        for window in &mut self.windows {
+            window.gpu.lod = None;
+        }
+        self.retint_culled();
+        self.reframe_all();
+        self.reframe_pips();
+    }
+
+    // what the last render() drew and uploaded, every window and pass of it
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    // the frame the last render() drew into the first window when that is offscreen (see headless()), 4 bytes per pixel
+    // in the window's format and top row first; None for a window on screen, its frame is gone once it is presented
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_frame(&self) -> Option<Vec<u8>> {
+        let texture = self.windows.first()?.offscreen.as_ref()?;
+        Some(crate::gpu::read_texture(&self.gpu.device, &self.gpu.queue, texture))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: PhysicalSize<u32> = PhysicalSize::new(160, 120);
+
+    fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * SIZE.width + x) * 4) as usize;
+        pixels[start..start + 4].try_into().unwrap()
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    // the device's error handler panics on any validation error, so getting to the end is most of the test
+    #[test]
+    #[ignore]
+    fn headless_state_draws_a_frame() {
+        let options = Options::parse_from(["--clear-color".to_string(), "204060".to_string()]).unwrap();
+        let mut state = pollster::block_on(State::headless(&options, SIZE)).expect("no GPU adapter");
+        // the HUD's text would cover most of a window this small
+        state.dispatch(0, Action::ToggleHud);
+        state.update(1.0 / 60.0);
+        state.render(1.0).unwrap();
+        let pixels = state.read_frame().expect("the headless window is offscreen");
+        assert_eq!(pixels.len(), (SIZE.width * SIZE.height * 4) as usize);
+
+        // the camera starts framed on the cube, which fills the middle and leaves the corner away from the axis gizmo to
+        // the clear color, back in sRGB after the surface's encoding (give or take rounding)
+        let corner = pixel(&pixels, SIZE.width - 1, SIZE.height - 1);
+        for (channel, expected) in corner.iter().zip([0x20, 0x40, 0x60, 0xff]) {
+            assert!(channel.abs_diff(expected) <= 1, "corner is {:?}", corner);
+        }
+        assert_ne!(pixel(&pixels, SIZE.width / 2, SIZE.height / 2), corner);
+        assert!(state.render_stats().draw_calls > 0);
+    }
+
+    // the checked-in picture golden_image_matches() compares against, BLESS=1 writes a new one from this build
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/cube.png");
+
+    // how far the frame may drift from the golden image, as the mean difference over every channel of every pixel
+    // GPUs and drivers rasterize and blend slightly differently along the edges, while the cube turned 3 degrees
+    // further already comes to about 0.4 on a frame this small and mostly background
+    const MAX_MEAN_DIFFERENCE: f64 = 0.3;
+
+    fn read_png(path: &str) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(std::fs::File::open(path).unwrap_or_else(|err| {
+            panic!("couldn't open {}: {}, run with BLESS=1 to make it", path, err)
+        }));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Eight), "{}", path);
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    fn write_png(path: &str, pixels: &[u8]) {
+        std::fs::create_dir_all(std::path::Path::new(path).parent().unwrap()).unwrap();
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(std::fs::File::create(path).unwrap()), SIZE.width, SIZE.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    // one frame a quarter of a second into the spin, with the default scene and camera and no HUD (its FPS changes)
+    #[test]
+    #[ignore]
+    fn golden_image_matches() {
+        let options = Options::parse_from(Vec::new()).unwrap();
+        let mut state = pollster::block_on(State::headless(&options, SIZE)).expect("no GPU adapter");
+        state.dispatch(0, Action::ToggleHud);
+        for _ in 0..30 {
+            state.update(crate::timestep::FIXED_DT);
+        }
+        state.render(1.0).unwrap();
+        let pixels = state.read_frame().expect("the headless window is offscreen");
+
+        if std::env::var_os("BLESS").is_some_and(|value| value == "1") {
+            write_png(GOLDEN, &pixels);
+            return;
+        }
+        let (width, height, golden) = read_png(GOLDEN);
+        assert_eq!((width, height), (SIZE.width, SIZE.height), "{} is a different size, run with BLESS=1", GOLDEN);
+        let total: u64 = pixels.iter().zip(&golden).map(|(a, b)| a.abs_diff(*b) as u64).sum();
+        let mean = total as f64 / pixels.len() as f64;
+        assert!(
+            mean < MAX_MEAN_DIFFERENCE,
+            "the frame differs from {} by {:.2} per channel on average, run with BLESS=1 if that is intended",
+            GOLDEN,
+            mean
+        );
+    }
+
+    // the render stats of the first frame drawn with `args`, without the HUD, whose text depends on the scene
+    // only the difference between two of these is compared: the gizmo and the debug lines add draws of their own, the
+    // same ones whatever the scene is
+    fn first_frame(args: &[&str]) -> RenderStats {
+        let options = Options::parse_from(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut state = pollster::block_on(State::headless(&options, SIZE)).expect("no GPU adapter");
+        state.dispatch(0, Action::ToggleHud);
+        state.render(1.0).unwrap();
+        state.render_stats()
+    }
+
+    // a scene file of one opaque cube per material, in a row
+    fn scene_with(materials: &[&str]) -> std::path::PathBuf {
+        let objects: String = materials
+            .iter()
+            .enumerate()
+            .map(|(i, material)| format!("[[objects]]\nposition = [{}.0, 0.0, 0.0]\nmaterial = \"{}\"\n", i * 2, material))
+            .collect();
+        let path = std::env::temp_dir().join(format!("render-stats-{}-{}.toml", std::process::id(), materials.len()));
+        std::fs::write(&path, objects).unwrap();
+        path
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn each_material_is_a_draw_of_its_objects() {
+        let one = scene_with(&["plastic"]);
+        let five = scene_with(&["plastic", "gold", "chrome", "rubber", "lava"]);
+        let (base, more) = (first_frame(&["--scene", one.to_str().unwrap()]), first_frame(&["--scene", five.to_str().unwrap()]));
+        std::fs::remove_file(one).unwrap();
+        std::fs::remove_file(five).unwrap();
+        // four more cubes, each in a material of its own, so four more draws of one instance and 12 triangles each
+        assert_eq!(more.draw_calls - base.draw_calls, 4, "{:?} vs {:?}", base, more);
+        assert_eq!(more.instances - base.instances, 4);
+        assert_eq!(more.triangles - base.triangles, 4 * 12);
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn the_grid_is_one_instanced_draw() {
+        let (small, large) = (first_frame(&["--grid", "4"]), first_frame(&["--grid", "8"]));
+        // all of a frame's triangles: the 16 cubes and the default scene's glass cube drawn twice, the gizmo is lines
+        assert_eq!(small.triangles, 16 * 12 + 2 * 12, "{:?}", small);
+        // 64 cubes instead of 16 in the same single draw
+        assert_eq!(large.draw_calls, small.draw_calls, "{:?} vs {:?}", small, large);
+        assert_eq!(large.instances - small.instances, 64 - 16);
+        assert_eq!(large.triangles - small.triangles, (64 - 16) * 12);
+        // subdividing the cube gives every copy of it 48 triangles instead of 12, in the same draws: the 16 in the grid
+        // and the default scene's glass cube, drawn twice (back faces, then front)
+        let subdivided = first_frame(&["--grid", "4", "--subdivisions", "2"]);
+        assert_eq!(subdivided.draw_calls, small.draw_calls);
+        assert_eq!(subdivided.triangles - small.triangles, (16 + 2) * (48 - 12));
+    }
+}
@@ -0,0 +1,55 @@
+// the HUD's lines of text: this frame's numbers and toggles from State, and the window's own camera
+// the Hud in hud.rs draws whatever lines it is given, everything it shows about the app is picked here
+use super::State;
+use crate::dpi;
+use crate::lights::MAX_LIGHTS;
+use crate::pipelines::DrawMode;
+
+impl State {
+    // refresh the HUD text with this frame's numbers
+    // every window shows the same numbers apart from its own camera
+    pub(super) fn update_hud(&mut self) {
+        let (_, angle) = self.orientation.to_axis_angle();
+        let material = self.material_label().to_uppercase();
+        for window in self.windows.iter_mut().filter(|window| window.gpu.hud.visible) {
+            // with --split-screen the half the keys steer, the one last clicked
+            let camera = &window.rigs[window.active].camera;
+            let half = match (window.rigs.len(), window.active) {
+                (1, _) => "",
+                (_, 0) => " (LEFT)",
+                _ => " (RIGHT)",
+            };
+            let eye = camera.eye;
+            let lines = [
+                // note the prepass next to the FPS so the two can be compared by toggling Z
+                format!("FPS: {:.1}{}", self.fps.fps(), if self.depth_prepass { " (DEPTH PREPASS)" } else { "" }),
+                format!("ROTATION: {:.1} DEG{}", angle.to_degrees(), if self.paused { " (PAUSED)" } else { "" }),
+                format!("UNIFORM WRITES: {}", self.uniform_writes),
+                // the frame before this one, this one's HUD is part of what is being counted
+                self.render_stats.label(),
+                format!("CAMERA{}: ({:.2}, {:.2}, {:.2})", half, eye.x, eye.y, eye.z),
+                format!("{}  FOV {:.0} DEG", if camera.ortho > 0.5 { "ORTHOGRAPHIC" } else { "PERSPECTIVE" }, camera.fovy),
+                format!("VIEW: {}  EFFECT: {}", self.debug_view.label(), self.effect.label()),
+                format!("SHADERS: CUBE {}  GLASS {}  MATERIAL: {}", self.cube_shader.label(), self.glass_shader.label(), material),
+                format!("LIGHTS: {}/{}  SHADOWS: {}", self.scene.light.count, MAX_LIGHTS, if self.shadows { "ON" } else { "OFF" }),
+                match self.lod {
+                    Some(_) => {
+                        let [full, simple, point] = window.lod.counts;
+                        let note = if self.draw_mode == DrawMode::Triangles { "" } else { " (TRIANGLES ONLY)" };
+                        format!("LOD: FULL {}  SIMPLE {}  POINT {}  CULLED {}{}", full, simple, point, window.lod.culled, note)
+                    }
+                    None => "LOD: OFF".to_string(),
+                },
+                match &self.frozen {
+                    Some(frozen) => format!("FROZEN CAMERA: {} CULLED", frozen.culled.iter().filter(|&&culled| culled).count()),
+                    None => "FROZEN CAMERA: OFF".to_string(),
+                },
+                "KEYS: H HUD  N NORMALS  B BOUNDS  M MODE  Z PREPASS  D VIEW  E EFFECT  U/G SHADERS".to_string(),
+                "      1/2/3 AXIS  +/- FOV  O ORTHO  X OUTLINE  S SHADOWS  [ ] SHININESS  K MATERIAL  L FPS LIMIT  P PIP".to_string(),
+                "      HOME RESET  F1-F4 VIEWS  F FRAME ALL  F11 FULLSCREEN  SPACE PAUSE  , . LIGHTS  V FREEZE".to_string(),
+            ];
+            // whole physical pixels per font pixel keeps the bitmap font crisp, bigger on HiDPI screens
+            window.gpu.hud.set_text(&self.gpu.device, &self.gpu.queue, &lines, dpi::hud_scale(window.scale_factor));
+        }
+    }
+}
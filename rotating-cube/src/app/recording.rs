@@ -0,0 +1,78 @@
+// --record's part of the app: the Recorder's capture texture and a depth buffer of the same size, which the first
+// window's cameras draw every frame into a second time, at a size that stays put when the window is resized
+use tracing::warn;
+
+use super::State;
+use crate::depth::{self, DepthBuffer};
+use crate::error::RenderError;
+use crate::gpu;
+use crate::recorder::{RecordSettings, Recorder};
+use crate::render_stats::RenderStats;
+use crate::viewport;
+
+pub(super) struct Recording {
+    recorder: Recorder,
+    depth: DepthBuffer,
+}
+
+impl Recording {
+    // capture `size`, which is --render-size or else the first window's size when recording starts
+    pub(super) fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        settings: &RecordSettings,
+    ) -> Result<Self, RenderError> {
+        let recorder = gpu::scoped(device, "Capture Texture", || Recorder::new(device, format, width, height, settings))?;
+        let depth = gpu::scoped(device, "Capture Depth Buffer", || depth::create_depth_buffer(device, width, height, depth_format))?;
+        Ok(Self { recorder, depth })
+    }
+
+    // the windows' depth buffers changed format for the outline, see State::add_stencil()
+    pub(super) fn set_depth_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        let (width, height) = self.recorder.size();
+        self.depth = depth::create_depth_buffer(device, width, height, format);
+    }
+}
+
+impl State {
+    // draw the scene again from the first window's cameras into the capture texture and copy it out for readback
+    // the capture is already the render size (or the window's shape when it started), so it fills the whole texture
+    // returns the readback slot to hand to captured() once the encoder is submitted, None when nothing is recording
+    pub(super) fn capture(&mut self, encoder: &mut wgpu::CommandEncoder, stats: &mut RenderStats) -> Option<usize> {
+        let recording = self.recording.as_ref().filter(|recording| !recording.recorder.is_done())?;
+        let window = self.windows.first()?;
+        let (width, height) = recording.recorder.size();
+        self.encode_scene(encoder, window, recording.recorder.view(), &recording.depth, viewport::fit(width, height, self.render_size), stats);
+        let recording = self.recording.as_mut()?;
+        Some(recording.recorder.copy_frame(&self.gpu.device, encoder))
+    }
+
+    // the frame capture() copied has been submitted, start reading it back
+    pub(super) fn captured(&mut self, slot: Option<usize>) {
+        if let (Some(recording), Some(slot)) = (self.recording.as_mut(), slot) {
+            recording.recorder.after_submit(&self.gpu.device, slot);
+        }
+    }
+
+    // a half-written capture can't be continued on a new device, its texture and pending readbacks died with the old one
+    pub(super) fn stop_recording(&mut self) {
+        if self.recording.take().is_some() {
+            warn!("Recording stopped, the GPU device was lost");
+        }
+    }
+
+    // true once --record has captured every frame it was asked for
+    pub fn recording_done(&self) -> bool {
+        self.recording.as_ref().is_some_and(|recording| recording.recorder.is_done())
+    }
+
+    // flush outstanding readbacks and wait for the encoder thread to write the file(s)
+    pub fn finish_recording(&mut self) -> Result<(), RenderError> {
+        match self.recording.take() {
+            Some(recording) => recording.recorder.finish(&self.gpu.device),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,457 @@
+// one window of the app: what it draws into and its depth buffer, its cameras, the keys held in it and its HUD
+// the cube's buffers, pipelines and animation are shared by all windows and live in State
+// a window can also be offscreen, a texture of a given size with nothing on screen, which State::headless() draws into
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, WindowEvent};
+use winit::window::{Fullscreen, Window, WindowId};
+
+use glam::{Vec2, Vec3};
+use tracing::info;
+
+use crate::camera::Camera;
+use crate::camera_control::CameraRig;
+use crate::cursor_grab::CursorGrab;
+use crate::debug_view::DepthView;
+use crate::depth;
+use crate::dpi;
+use crate::error::RenderError;
+use crate::gizmo;
+use crate::gpu;
+use crate::input::{Action, InputState, Keymap};
+use crate::lod::{self, Buckets, LodBuffers, Thresholds};
+use crate::instances::Instance;
+use crate::pip::{self, PipTarget};
+use crate::render_stats::CountingQueue;
+use crate::renderer::{CameraGpu, CameraUniform, Gpu, LightUniform, SharedBindings, WindowGpu};
+use crate::sky::GlobalsUniform;
+use crate::viewport::{self, Viewport};
+
+// what a new WindowState draws into: a window on screen through its surface, or without one a texture that is read
+// back instead of presented
+pub(super) struct Target {
+    pub size: PhysicalSize<u32>, // physical pixels, like the surface's and depth buffer's
+    pub window: Option<(Window, wgpu::Surface)>,
+}
+
+impl Target {
+    pub(super) fn window(window: Window, surface: wgpu::Surface) -> Self {
+        Self { size: window.inner_size(), window: Some((window, surface)) }
+    }
+
+    pub(super) fn offscreen(size: PhysicalSize<u32>) -> Self {
+        Self { size, window: None }
+    }
+}
+
+pub struct WindowState {
+    pub(super) surface: Option<wgpu::Surface>, // target for rendering, usually screen, None while the app is suspended
+    pub(super) offscreen: Option<wgpu::Texture>, // drawn into in place of a surface by a window that isn't on screen
+    pub config: wgpu::SurfaceConfiguration, // store surface settings (res, px format)
+
+    pub rigs: Vec<CameraRig>, // the camera and what steers it, see camera_control.rs, one for each half with --split-screen
+    pub(super) active: usize,        // the rig the keys, mouse and gamepad steer, the half last clicked in with --split-screen
+    pub(super) scale_factor: f64,   // physical pixels per logical pixel, HUD text is scaled by this
+    pub(super) cursor: Option<Vec2>, // mouse position in physical pixels while it is over this window, for hover highlighting
+    pub(super) windowed_size: Option<PhysicalSize<u32>>, // size before F11 went fullscreen, restored when it comes back
+    pub(super) input: InputState,    // which keys and buttons are down in this window, they go to the window with focus
+    pub(super) grab: CursorGrab,     // whether the mouse is captured for mouse-look (C), see cursor_grab.rs
+    pub(super) uploaded_globals: Option<GlobalsUniform>, // what is in the globals buffer, None forces the next upload
+    pub(super) lod: Buckets,         // --lod: which cubes this camera sees at which level, kept for the hysteresis
+    pub(super) pip: Option<(Vec3, Vec3)>, // the box the picture-in-picture's overhead camera is framed on, while it is shown (P)
+    pub(super) pip_dirty: bool,      // that box changed, the overhead camera is uploaded again
+
+    pub(super) gpu: WindowGpu, // this window's buffers on the current device, rebuilt when the device is recreated
+
+    pub window: Option<Window>, // None offscreen, last so it is dropped after the surface that draws into it
+}
+
+impl WindowState {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &CountingQueue,
+        target: Target,
+        config: wgpu::SurfaceConfiguration,
+        rigs: Vec<CameraRig>,
+        shared: &SharedBindings,
+    ) -> Self {
+        let (window, surface, offscreen) = match target.window {
+            Some((window, surface)) => {
+                // inner_size() is already in physical pixels, log it next to the logical size to make scaling problems obvious
+                info!("Window size: {}", dpi::describe(window.inner_size(), window.scale_factor()));
+                surface.configure(device, &config);
+                (Some(window), Some(surface), None)
+            }
+            None => (None, None, Some(offscreen_texture(device, &config))),
+        };
+        let gpu = WindowGpu::new(device, queue, &config, &cameras(&rigs), shared);
+
+        Self {
+            surface,
+            offscreen,
+            config,
+            rigs,
+            active: 0,
+            // offscreen there is no screen to match, the HUD gets the size it has at 100%
+            scale_factor: window.as_ref().map_or(1.0, Window::scale_factor),
+            cursor: None,
+            windowed_size: None,
+            input: InputState::default(),
+            grab: CursorGrab::Free,
+            uploaded_globals: None,
+            lod: Buckets::default(),
+            pip: None,
+            pip_dirty: false,
+            gpu,
+            window,
+        }
+    }
+
+    pub(super) fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &CountingQueue,
+        new_size: PhysicalSize<u32>,
+        render_size: Option<(u32, u32)>,
+    ) -> Result<(), RenderError> {
+        // a minimized window reports 0x0, configuring a surface with zero size is invalid so skip it
+        if new_size.width == 0 || new_size.height == 0 {
+            return Ok(());
+        }
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        if let Some(surface) = &self.surface {
+            surface.configure(device, &self.config);
+        }
+        if self.offscreen.is_some() {
+            self.offscreen = Some(offscreen_texture(device, &self.config));
+        }
+        // a huge window can be past the adapter's texture size limit, better to hear that here than at the next draw
+        self.gpu.depth = gpu::scoped(device, "Depth Buffer", || {
+            depth::create_depth_buffer(device, new_size.width, new_size.height, self.gpu.depth.format)
+        })?;
+        self.gpu.hud.resize(queue, new_size.width, new_size.height);
+
+        // new window shape means a new aspect ratio (unless the viewport has a fixed size), flag the cameras so the next
+        // frame's write_uniforms() uploads the projection for exactly this size, framework.rs draws that frame right away
+        let panes = panes(self.viewport(render_size), self.rigs.len());
+        for (rig, pane) in self.rigs.iter_mut().zip(panes) {
+            rig.set_aspect(pane.aspect());
+        }
+        Ok(())
+    }
+
+    // the OS took the native surface away (suspend), nothing can be drawn into this window until resume()
+    pub(super) fn suspend(&mut self) {
+        self.surface = None;
+    }
+
+    // make a new surface for the window, which may have changed size while it had none
+    pub(super) fn resume(
+        &mut self,
+        instance: &wgpu::Instance,
+        device: &wgpu::Device,
+        queue: &CountingQueue,
+        render_size: Option<(u32, u32)>,
+    ) -> Result<(), RenderError> {
+        // an offscreen window never had a surface to lose
+        let Some(window) = &self.window else { return Ok(()) };
+        if self.surface.is_some() {
+            return Ok(()); // winit also sends Resumed once at startup, when the surface already exists
+        }
+        self.surface = Some(unsafe { instance.create_surface(window) }?);
+        let size = window.inner_size();
+        self.config.width = size.width.max(1);
+        self.config.height = size.height.max(1);
+        self.resize(device, queue, PhysicalSize::new(self.config.width, self.config.height), render_size)
+    }
+
+    // point the window at a new device: reconfigure the surface and rebuild its buffers, the camera and HUD toggle carry over
+    pub(super) fn recreate_resources(&mut self, device: &wgpu::Device, queue: &CountingQueue, shared: &SharedBindings) {
+        if let Some(surface) = &self.surface {
+            surface.configure(device, &self.config);
+        }
+        if self.offscreen.is_some() {
+            self.offscreen = Some(offscreen_texture(device, &self.config));
+        }
+        let hud_visible = self.gpu.hud.visible;
+        self.gpu = WindowGpu::new(device, queue, &self.config, &cameras(&self.rigs), shared);
+        self.gpu.hud.visible = hud_visible;
+        self.uploaded_globals = None;
+    }
+
+    // part of the window the scene is drawn into, all of it without --render-size
+    pub(super) fn viewport(&self, render_size: Option<(u32, u32)>) -> Viewport {
+        viewport::fit(self.config.width, self.config.height, render_size)
+    }
+
+    // what each camera draws into `viewport`, the window's own or --record's, with the buffers it draws with
+    pub(super) fn panes(&self, viewport: Viewport) -> Vec<Pane<'_>> {
+        panes(viewport, self.rigs.len())
+            .into_iter()
+            .zip(self.rigs.iter().zip(&self.gpu.cameras))
+            .enumerate()
+            .map(|(i, (viewport, (rig, gpu)))| Pane {
+                viewport,
+                camera: &rig.camera,
+                gpu,
+                // the levels are sorted for the first camera only, the other half draws every cube like the picture-in-picture
+                lod: if i == 0 { self.lod_draw() } else { None },
+            })
+            .collect()
+    }
+
+    // the camera that is being steered
+    pub(super) fn rig(&mut self) -> &mut CameraRig {
+        &mut self.rigs[self.active]
+    }
+
+    // tracked whether or not a button is held, State::update() picks the cube under it every step
+    // while the mouse-look button is held the movement also turns the camera
+    pub(super) fn track_cursor(&mut self, keymap: &Keymap, event: &WindowEvent, render_size: Option<(u32, u32)>) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = Vec2::new(position.x as f32, position.y as f32);
+                // the first move after entering the window has nothing to be measured from
+                // a captured mouse turns the camera through mouse_motion() instead, and recentering it moves the cursor too
+                if let (Some(previous), false) = (self.cursor, self.grab.is_captured()) {
+                    if self.input.held(keymap, Action::MouseLook) {
+                        self.rig().look(cursor - previous);
+                    }
+                }
+                self.cursor = Some(cursor);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            // --split-screen: any button pressed over a half hands it the controls, before the press itself is acted on so
+            // a drag to look around turns the camera it started on
+            WindowEvent::MouseInput { state: ElementState::Pressed, .. } if self.rigs.len() > 1 => {
+                let halves = panes(self.viewport(render_size), self.rigs.len());
+                if let Some(half) = self.cursor.and_then(|cursor| halves.iter().position(|half| half.contains(cursor))) {
+                    if half != self.active {
+                        self.active = half;
+                        info!("Steering the {} half", if half == 0 { "left" } else { "right" });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // give the cursor back whenever the user leaves the window: alt-tab (focus loss), minimizing (which some platforms
+    // only report as a 0x0 resize) or the window being covered, it isn't captured again by coming back
+    pub(super) fn keep_grab(&mut self, event: &WindowEvent) {
+        let leaving = match event {
+            WindowEvent::Focused(focused) => !focused,
+            WindowEvent::Occluded(occluded) => *occluded,
+            WindowEvent::Resized(size) => size.width == 0 || size.height == 0,
+            _ => false,
+        };
+        if let (true, Some(window)) = (leaving, &self.window) {
+            self.grab.release(window);
+        }
+    }
+
+    // None for an offscreen window, no event is ever meant for it
+    pub(super) fn id(&self) -> Option<WindowId> {
+        self.window.as_ref().map(Window::id)
+    }
+
+    pub(super) fn has_focus(&self) -> bool {
+        self.window.as_ref().is_some_and(Window::has_focus)
+    }
+
+    // actions that only concern this window's view, returns true when the action was one of them
+    // the camera's own go to the one being steered
+    pub(super) fn act(&mut self, action: Action) -> bool {
+        match (action, &self.window) {
+            (Action::ToggleHud, _) => self.gpu.hud.visible = !self.gpu.hud.visible,
+            (Action::Fullscreen, Some(_)) => self.toggle_fullscreen(),
+            (Action::ToggleMouseCapture, Some(window)) if self.grab.is_captured() => self.grab.release(window),
+            (Action::ToggleMouseCapture, Some(window)) => self.grab = CursorGrab::capture(window),
+            (Action::ReleaseMouse, Some(window)) => self.grab.release(window),
+            // offscreen there is no screen to fill and no mouse to capture
+            (Action::Fullscreen | Action::ToggleMouseCapture | Action::ReleaseMouse, None) => {}
+            _ => return self.rig().act(action),
+        }
+        true
+    }
+
+    // the held camera actions (arrow keys and the left stick by default) circle the camera around its target and move
+    // it closer or further away, for as long as they are held and as fast as the stick is pushed
+    // `pad` is the gamepads' input when this window has focus, keys and stick add up so either works at any time
+    pub(super) fn move_camera(&mut self, keymap: &Keymap, pad: Option<&InputState>, dt: f32) {
+        let held = |action| (self.input.amount(keymap, action) + pad.map_or(0.0, |pad| pad.amount(keymap, action))).min(1.0);
+        let turn = held(Action::CameraRight) - held(Action::CameraLeft);
+        let dolly = held(Action::CameraBack) - held(Action::CameraForward);
+        self.rig().move_by(turn, dolly, dt);
+    }
+
+    // F11: borderless fullscreen on the monitor the window is on, or back to a window of the size it had before
+    // either way the window system answers with a Resized event, which reconfigures the surface like any other resize
+    fn toggle_fullscreen(&mut self) {
+        let Some(window) = &self.window else { return };
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+            // most platforms put the old size back by themselves, not all of them do
+            if let Some(size) = self.windowed_size.take() {
+                window.set_inner_size(size);
+            }
+        } else {
+            self.windowed_size = Some(window.inner_size());
+            // borderless rather than exclusive: no video mode switch, so it is instant and alt-tab keeps working
+            window.set_fullscreen(Some(Fullscreen::Borderless(None))); //None = whichever monitor the window is on
+        }
+    }
+
+    // upload this window's cameras, and their copies of the light whenever a camera or the shared light settings changed
+    // `globals` is this frame's time of day, the window adds its own resolution
+    // returns how many buffers were written, for the HUD's upload counter
+    pub(super) fn write_uniforms(
+        &mut self,
+        queue: &CountingQueue,
+        light: &LightUniform,
+        light_dirty: bool,
+        globals: GlobalsUniform,
+        render_size: Option<(u32, u32)>,
+    ) -> u32 {
+        let mut writes = 0;
+        for (rig, gpu) in self.rigs.iter_mut().zip(&self.gpu.cameras) {
+            // only re-upload the camera matrix when something actually changed it
+            if rig.dirty {
+                let camera_uniform = CameraUniform {
+                    view_proj: rig.camera.view_proj().to_cols_array_2d(),
+                };
+                queue.write_buffer(&gpu.camera_buffer, 0, bytemuck::bytes_of(&camera_uniform));
+                // the depth view undoes the projection, so it needs the new one too
+                DepthView::write(queue, &gpu.depth_debug_buffer, &rig.camera);
+                // and the gizmo turns with it
+                let gizmo_uniform = CameraUniform {
+                    view_proj: gizmo::view_proj(&rig.camera).to_cols_array_2d(),
+                };
+                queue.write_buffer(&gpu.gizmo_camera_buffer, 0, bytemuck::bytes_of(&gizmo_uniform));
+                writes += 3;
+            }
+
+            // the specular term needs to know where this camera's eye is
+            if rig.dirty || light_dirty {
+                let light = LightUniform {
+                    eye_position: rig.camera.eye.to_array(),
+                    ..*light
+                };
+                queue.write_buffer(&gpu.light_buffer, 0, bytemuck::bytes_of(&light));
+                writes += 1;
+            }
+            rig.dirty = false;
+        }
+
+        // the viewport rather than the window, the sky fills only that (--record draws with the first window's, which
+        // has the same shape whenever --render-size is given)
+        // split in two the sky is drawn into each half, which are the same size give or take the odd pixel
+        let viewport = panes(self.viewport(render_size), self.rigs.len())[0];
+        let globals = GlobalsUniform { resolution: [viewport.width, viewport.height], ..globals };
+        if self.uploaded_globals != Some(globals) {
+            queue.write_buffer(&self.gpu.globals_buffer, 0, bytemuck::bytes_of(&globals));
+            self.uploaded_globals = Some(globals);
+            writes += 1;
+        }
+        writes
+    }
+
+    // --lod: sort the opaque cubes into levels by their distance from the first camera, leaving out the ones it can't see,
+    // and upload them grouped by level if that changed anything
+    // `sphere` is the centre (relative to each cube's offset) and radius of a sphere around any plain cube this frame,
+    // a stretched one's radius grows with it
+    pub(super) fn write_lod(&mut self, gpu: &Gpu, thresholds: Thresholds, sphere: (Vec3, f32), instances: &[Instance], glow: &[f32], glow_changed: bool) -> u32 {
+        let (shift, radius) = sphere;
+        let camera = &self.rigs[0].camera;
+        let (view_proj, eye) = (camera.view_proj(), camera.eye);
+        self.lod.update(
+            thresholds,
+            instances.iter().map(|instance| {
+                let center = Vec3::from(instance.offset) + shift;
+                lod::in_view(view_proj, center, radius * instance.reach()).then(|| center.distance(eye))
+            }),
+        );
+        // a reload can add cubes, the buffers are made again with room for them all
+        if self.gpu.lod.as_ref().is_some_and(|buffers| buffers.capacity() < instances.len()) {
+            self.gpu.lod = None;
+        }
+        let buffers = self.gpu.lod.get_or_insert_with(|| LodBuffers::new(&gpu.device, instances.len()));
+        buffers.write(&gpu.queue, &self.lod, instances, glow, glow_changed)
+    }
+
+    // the picture-in-picture's target and camera, while it is shown
+    // the camera is framed again whenever the box or the inset's size changes, a resize keeps the inset the same share
+    // of the window and the camera has to fit the scene into its new shape, which also needs a new texture
+    pub(super) fn write_pip(&mut self, gpu: &Gpu, light: &LightUniform, light_dirty: bool, render_size: Option<(u32, u32)>, reverse_z: bool) -> u32 {
+        let Some((min, max)) = self.pip else { return 0 };
+        let inset = pip::viewport(self.viewport(render_size), self.scale_factor);
+        let size = (inset.width as u32, inset.height as u32);
+        let resized = self.gpu.pip.as_ref().map(|target| target.size) != Some(size);
+        if !resized && !self.pip_dirty && !light_dirty {
+            return 0;
+        }
+
+        let camera = pip::camera(min, max, inset.aspect(), reverse_z);
+        let reframed = std::mem::take(&mut self.pip_dirty);
+        if resized {
+            // made with the camera and light already in its buffers
+            self.gpu.pip = Some(PipTarget::new(
+                &gpu.device,
+                size,
+                self.config.format,
+                camera,
+                &gpu.shared_bindings(light),
+                &self.gpu.globals_buffer,
+                &gpu.pip_compositor,
+            ));
+            return 2;
+        }
+        let Some(target) = &mut self.gpu.pip else { return 0 };
+        // the light carries the eye, so a new camera means a new light too
+        if reframed {
+            target.write_camera(&gpu.queue, camera);
+        }
+        target.write_light(&gpu.queue, light);
+        1 + reframed as u32
+    }
+
+    // the first camera's cube draws' instances: the ones --lod sorted for it, once they are there
+    pub(super) fn lod_draw(&self) -> Option<(&LodBuffers, &Buckets)> {
+        self.gpu.lod.as_ref().map(|buffers| (buffers, &self.lod))
+    }
+}
+
+// one camera's part of a window and what it is drawn with, see WindowState::panes()
+pub(super) struct Pane<'a> {
+    pub viewport: Viewport,
+    pub camera: &'a Camera,
+    pub gpu: &'a CameraGpu,
+    pub lod: Option<(&'a LodBuffers, &'a Buckets)>,
+}
+
+// where each of `count` cameras draws inside `viewport`: all of it, or --split-screen's halves
+pub(super) fn panes(viewport: Viewport, count: usize) -> Vec<Viewport> {
+    match count {
+        1 => vec![viewport],
+        _ => viewport::halves(viewport).to_vec(),
+    }
+}
+
+// the cameras for the GPU buffers to start out with
+fn cameras(rigs: &[CameraRig]) -> Vec<&Camera> {
+    rigs.iter().map(|rig| &rig.camera).collect()
+}
+
+// a color target for an offscreen window, read back with gpu::read_texture() instead of presented
+fn offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target"),
+        size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
@@ -0,0 +1,206 @@
+// benchmark mode: render a fixed number of frames, record how long each took and print statistics at the end
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::render_stats::RenderStats;
+
+// number of bars in the frame time histogram
+const HISTOGRAM_BUCKETS: usize = 10;
+// length of the longest bar in characters
+const HISTOGRAM_WIDTH: usize = 40;
+
+// the first frames include shader compilation, driver warm-up and window mapping, so they aren't counted
+pub const WARMUP_FRAMES: u32 = 30;
+
+pub struct Bench {
+    frames: u32,       // frames to measure (after warm-up)
+    seen: u32,         // frames recorded so far, including warm-up
+    cpu_ms: Vec<f64>,  // CPU time per measured frame (update + encode + submit + present)
+    gpu_ms: Vec<f64>,  // GPU time per measured frame, empty if timestamps aren't supported
+    totals: RenderStats, // draws, triangles, uploads... added up over the measured frames
+    started: Option<Instant>, // end of the last warm-up frame, start of the measured wall time
+    finished: Option<Instant>, // end of the latest measured frame
+}
+
+// min/avg/percentiles/max over a set of frame times
+pub struct Summary {
+    pub min: f64,
+    pub avg: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Bench {
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames,
+            seen: 0,
+            cpu_ms: Vec::with_capacity(frames as usize),
+            gpu_ms: Vec::with_capacity(frames as usize),
+            totals: RenderStats::default(),
+            started: None,
+            finished: None,
+        }
+    }
+
+    // store one frame's measurements, ignored while still warming up
+    pub fn record(&mut self, cpu: Duration, gpu_ms: Option<f64>, stats: RenderStats) {
+        self.seen += 1;
+        let now = Instant::now();
+        if self.seen <= WARMUP_FRAMES {
+            self.started = Some(now);
+            return;
+        }
+        // with no warm-up frames the clock starts at the first measured frame
+        self.started.get_or_insert(now - cpu);
+        self.finished = Some(now);
+        self.cpu_ms.push(cpu.as_secs_f64() * 1000.0);
+        if let Some(gpu_ms) = gpu_ms {
+            self.gpu_ms.push(gpu_ms);
+        }
+        self.totals.add(&stats);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.seen >= WARMUP_FRAMES + self.frames
+    }
+
+    // wall-clock time of the measured frames, includes time spent outside render() such as event handling
+    pub fn wall_time(&self) -> Duration {
+        match (self.started, self.finished) {
+            (Some(started), Some(finished)) => finished - started,
+            _ => Duration::ZERO,
+        }
+    }
+
+    // measured frames divided by wall time
+    pub fn average_fps(&self) -> f64 {
+        let seconds = self.wall_time().as_secs_f64();
+        if seconds > 0.0 {
+            self.cpu_ms.len() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub fn print_report(&self) {
+        println!("Benchmark: {} frames ({} warm-up frames excluded)", self.cpu_ms.len(), WARMUP_FRAMES);
+        println!("Wall time: {:.3} s, {:.1} fps", self.wall_time().as_secs_f64(), self.average_fps());
+        print_summary("CPU frame time", summarize(&self.cpu_ms).as_ref());
+        print_summary("GPU frame time", summarize(&self.gpu_ms).as_ref());
+        println!("Triangles drawn: {}", self.totals.triangles);
+        if let Some(frame) = self.per_frame() {
+            println!(
+                "Per frame: {:.1} draw calls, {:.1} instances, {:.0} triangles, {:.0} bytes uploaded, {:.1} pipeline switches",
+                frame[0], frame[1], frame[2], frame[3], frame[4]
+            );
+        }
+        print_histogram("CPU frame time histogram", &self.cpu_ms);
+    }
+
+    // single-line JSON so the output can be piped into jq or stored by a CI job
+    pub fn print_json(&self) {
+        println!(
+            "{{\"frames\":{},\"warmup_frames\":{},\"wall_time_s\":{:.4},\"fps\":{:.2},\"triangles\":{},\"per_frame\":{},\"cpu_ms\":{},\"gpu_ms\":{}}}",
+            self.cpu_ms.len(),
+            WARMUP_FRAMES,
+            self.wall_time().as_secs_f64(),
+            self.average_fps(),
+            self.totals.triangles,
+            match self.per_frame() {
+                Some([draws, instances, triangles, bytes, switches]) => format!(
+                    "{{\"draw_calls\":{:.2},\"instances\":{:.2},\"triangles\":{:.2},\"uploaded_bytes\":{:.2},\"pipeline_switches\":{:.2}}}",
+                    draws, instances, triangles, bytes, switches
+                ),
+                None => "null".to_string(),
+            },
+            summary_json(summarize(&self.cpu_ms).as_ref()),
+            summary_json(summarize(&self.gpu_ms).as_ref()),
+        );
+    }
+
+    // the totals' draw calls, instances, triangles, uploaded bytes and pipeline switches divided by the measured frames
+    // averages rather than one frame's, the uploads come and go with what changed and a --lod frame depends on the view
+    fn per_frame(&self) -> Option<[f64; 5]> {
+        let frames = self.cpu_ms.len() as f64;
+        if frames == 0.0 {
+            return None;
+        }
+        let totals = &self.totals;
+        Some([
+            totals.draw_calls as f64 / frames,
+            totals.instances as f64 / frames,
+            totals.triangles as f64 / frames,
+            totals.uploaded_bytes as f64 / frames,
+            totals.pipeline_switches as f64 / frames,
+        ])
+    }
+}
+
+// None for an empty sample set, e.g. GPU times on an adapter without timestamp queries
+pub fn summarize(samples: &[f64]) -> Option<Summary> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    Some(Summary {
+        min: sorted[0],
+        avg: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p95: percentile(&sorted, 95.0),
+        p99: percentile(&sorted, 99.0),
+        max: sorted[sorted.len() - 1],
+    })
+}
+
+// nearest-rank percentile: the smallest sample that at least p% of the samples are less than or equal to
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn print_summary(name: &str, summary: Option<&Summary>) {
+    match summary {
+        Some(s) => println!(
+            "{}: min {:.3} ms, avg {:.3} ms, p95 {:.3} ms, p99 {:.3} ms, max {:.3} ms",
+            name, s.min, s.avg, s.p95, s.p99, s.max
+        ),
+        None => println!("{}: not available", name),
+    }
+}
+
+// equal-width buckets between the fastest and slowest frame, one text bar per bucket
+// shows the shape of the distribution, e.g. two humps when every few frames hit a slow path
+fn print_histogram(name: &str, samples: &[f64]) {
+    let Some(summary) = summarize(samples) else {
+        return;
+    };
+    let width = (summary.max - summary.min) / HISTOGRAM_BUCKETS as f64;
+    let mut counts = [0usize; HISTOGRAM_BUCKETS];
+    for &sample in samples {
+        // all samples equal gives width 0, everything then lands in the first bucket
+        let bucket = if width > 0.0 { ((sample - summary.min) / width) as usize } else { 0 };
+        counts[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1; // the max sample sits exactly on the last bucket's upper edge
+    }
+
+    let largest = counts.iter().copied().max().unwrap_or(0).max(1);
+    println!("{}:", name);
+    for (i, count) in counts.iter().enumerate() {
+        let low = summary.min + width * i as f64;
+        let bar = "#".repeat(count * HISTOGRAM_WIDTH / largest);
+        println!("  {:8.3} - {:8.3} ms | {:<width$} {}", low, low + width, bar, count, width = HISTOGRAM_WIDTH);
+    }
+}
+
+fn summary_json(summary: Option<&Summary>) -> String {
+    match summary {
+        Some(s) => format!(
+            "{{\"min\":{:.4},\"avg\":{:.4},\"p95\":{:.4},\"p99\":{:.4},\"max\":{:.4}}}",
+            s.min, s.avg, s.p95, s.p99, s.max
+        ),
+        None => "null".to_string(),
+    }
+}
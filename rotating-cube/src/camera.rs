@@ -0,0 +1,446 @@
+// camera owns everything needed to build the view-projection matrix that the vertex shader multiplies each vertex by
+// the plain perspective math is in free functions at the bottom, they only take numbers so they can be checked
+// without a window or GPU, Camera adds the orthographic blend and reverse-Z on top
+// frame_bounds() is the same kind of function for F (frame all): where a camera has to be to see a whole box
+use glam::{Mat4, Vec3, Vec4};
+
+// starting field of view for when neither --fov-deg nor the scene file's [camera] gives one
+pub const DEFAULT_FOV: f32 = 45.0;
+
+// glam's perspective_rh_gl() produces OpenGL clip space where depth (z) runs from -1 to 1,
+// wgpu (like DirectX/Metal/Vulkan) expects depth from 0 to 1, so this matrix squashes z into half the range and shifts it by 0.5
+// x and y are left alone since both conventions agree on [-1, 1] for them
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(&[
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+]);
+
+pub struct Camera {
+    pub eye: Vec3,    // camera position in world space
+    pub target: Vec3, // point the camera looks at
+    pub up: Vec3,     // which way is "up" for the camera, usually +Y
+    pub fovy: f32,    // vertical field of view in degrees
+    pub aspect: f32,  // width / height of the surface being rendered to
+    pub znear: f32,   // near clipping plane, anything closer is cut off
+    pub zfar: f32,    // far clipping plane, anything further is cut off
+    pub ortho: f32,   // 0 = perspective, 1 = orthographic, in between while the O key blends from one to the other
+    pub reverse_z: bool, // --reverse-z: near plane at depth 1, far at 0, and no far plane at all in perspective, see depth.rs
+}
+
+impl Camera {
+    // view matrix moves the world so the camera sits at the origin looking down -Z
+    pub fn view(&self) -> Mat4 {
+        view(self.eye, self.target, self.up)
+    }
+
+    // projection matrix for the current mode, mid-switch it is a mix of both
+    // blending the matrices (rather than the projected points) keeps whatever is at the target's distance exactly the
+    // same size the whole way, since both matrices agree there, nearer and further things grow/shrink into place
+    pub fn proj(&self) -> Mat4 {
+        if self.ortho <= 0.0 {
+            self.perspective()
+        } else if self.ortho >= 1.0 {
+            self.orthographic()
+        } else {
+            self.perspective() * (1.0 - self.ortho) + self.orthographic() * self.ortho
+        }
+    }
+
+    // adds perspective (far things shrink) and maps the view frustum into clip space
+    // glam's reversed version already targets wgpu's 0..1 depth range and pushes the far plane out to infinity
+    pub fn perspective(&self) -> Mat4 {
+        if self.reverse_z {
+            Mat4::perspective_infinite_reverse_rh(self.fovy.to_radians(), self.aspect, self.znear)
+        } else {
+            perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        }
+    }
+
+    // parallel projection, a box as tall as the perspective frustum is at the target's distance so the cube keeps its
+    // apparent size when switching, the FOV keys still zoom since they change that height
+    // glam's orthographic_rh() already produces wgpu's 0..1 depth range, no conversion needed
+    pub fn orthographic(&self) -> Mat4 {
+        let half_height = (self.eye - self.target).length() * (self.fovy.to_radians() / 2.0).tan();
+        let half_width = half_height * self.aspect;
+        // an orthographic box needs a far side, reversing just swaps which plane ends up at depth 0
+        let (near, far) = if self.reverse_z { (self.zfar, self.znear) } else { (self.znear, self.zfar) };
+        Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, near, far)
+    }
+
+    // distance in front of the camera of a value read back from the depth buffer, the inverse of what proj() did to it
+    // going through the inverse matrix means it doesn't matter whether that was perspective, orthographic, reverse-Z or
+    // a blend of them, the depth view's shader (depth_view.wgsl) does the same with the matrix it is given
+    pub fn linear_depth(&self, depth: f32) -> f32 {
+        let view = self.proj().inverse() * Vec4::new(0.0, 0.0, depth, 1.0);
+        -view.z / view.w
+    }
+
+    // combined camera matrix, projection is applied after view so it goes on the left
+    pub fn view_proj(&self) -> Mat4 {
+        self.proj() * self.view()
+    }
+}
+
+// world to camera space: `position` moves to the origin and `target` ends up straight ahead on -Z
+pub fn view(position: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    Mat4::look_at_rh(position, target, up)
+}
+
+// camera space to wgpu clip space, depth 0 at `near` and 1 at `far`, `fov_deg` is the vertical field of view
+pub fn perspective(fov_deg: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh_gl(fov_deg.to_radians(), aspect, near, far)
+}
+
+// world straight to clip space for a perspective camera, what Camera::view_proj() gives without O or --reverse-z
+// after the divide by w the target is at x = y = 0, the middle of the screen, and y = 1 is the top edge
+pub fn view_proj(position: Vec3, target: Vec3, up: Vec3, fov_deg: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    perspective(fov_deg, aspect, near, far) * view(position, target, up)
+}
+
+// how much room frame_bounds() leaves around the scene, 1.1 keeps it 10% clear of the edges of the view
+const FRAME_MARGIN: f32 = 1.1;
+
+// a box smaller than this is framed as if it were this big, so a single point doesn't put the camera on top of it
+const MIN_FRAME_RADIUS: f32 = 0.01;
+
+// --fov-deg, --near and --far: whatever is given here wins over the scene file and over the clip planes
+// frame_bounds() and clip_planes() work out, so a camera can be set up to match another one exactly
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lens {
+    pub fov_deg: Option<f32>, // the starting vertical field of view, +/- still zoom from it
+    pub near: Option<f32>,
+    pub far: Option<f32>,
+}
+
+impl Lens {
+    // `planes` (near, far) with the flags' values in their place
+    // with only one of the two given the other is kept on the right side of it, a factor of 10 away, since the worked
+    // out one knows nothing about the flag and could otherwise end up in front of the near plane or behind the far one
+    pub fn clip_planes(&self, (near, far): (f32, f32)) -> (f32, f32) {
+        match (self.near, self.far) {
+            (Some(near), Some(far)) => (near, far),
+            (Some(near), None) => (near, far.max(near * 10.0)),
+            (None, Some(far)) => (near.min(far / 10.0), far),
+            (None, None) => (near, far),
+        }
+    }
+}
+
+// where frame_bounds() puts the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Framing {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+// how far from the middle of a sphere of `radius` a camera has to be for all of it to be in view
+// the narrower of the vertical and horizontal field of view decides, and the sphere touches the edges of the view at
+// that distance: its outline seen from there is a circle whose tangent rays make half the FOV with the view direction
+pub fn fit_distance(radius: f32, fov_deg: f32, aspect: f32) -> f32 {
+    let half_vertical = fov_deg.to_radians() / 2.0;
+    let half_horizontal = (half_vertical.tan() * aspect).atan();
+    radius / half_vertical.min(half_horizontal).sin()
+}
+
+// near and far planes for a camera `distance` from the middle of a scene of `radius`
+// both scale with the scene so the ratio between them, which is what the depth buffer's precision depends on, is the
+// same for a tiny model and a huge one. The far plane leaves room to back off to 10x the distance (the arrow keys stop
+// at half of it), the near one room to come in close
+pub fn clip_planes(radius: f32, distance: f32) -> (f32, f32) {
+    (radius * 0.05, (distance + radius) * 20.0)
+}
+
+// F: look at the middle of the box min..max from `direction` (towards the camera, e.g. eye - target), just far enough
+// back that the sphere around the box fits in a `fov_deg` x `aspect` view with FRAME_MARGIN to spare
+// a sphere rather than the box itself, so the distance doesn't depend on which way the camera faces the box
+pub fn frame_bounds(min: Vec3, max: Vec3, direction: Vec3, fov_deg: f32, aspect: f32) -> Framing {
+    let target = (min + max) / 2.0;
+    let radius = ((max - min).length() / 2.0).max(MIN_FRAME_RADIUS);
+    let distance = fit_distance(radius * FRAME_MARGIN, fov_deg, aspect);
+    let (znear, zfar) = clip_planes(radius, distance);
+    Framing { eye: target + direction.try_normalize().unwrap_or(Vec3::Z) * distance, target, znear, zfar }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the camera the cube starts with, looking at the origin from (3, 3, 3)
+    fn camera() -> Camera {
+        Camera {
+            eye: Vec3::new(3.0, 3.0, 3.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            fovy: DEFAULT_FOV,
+            aspect: 16.0 / 9.0,
+            znear: 0.1,
+            zfar: 100.0,
+            ortho: 0.0,
+            reverse_z: false,
+        }
+    }
+
+    // world point to normalized device coordinates, after the divide by w
+    fn ndc(view_proj: Mat4, point: Vec3) -> Vec3 {
+        view_proj.project_point3(point)
+    }
+
+    fn inside(ndc: Vec3) -> bool {
+        ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z)
+    }
+
+    #[test]
+    fn target_projects_to_the_center() {
+        let camera = camera();
+        let center = ndc(camera.view_proj(), camera.target);
+        assert!(center.x.abs() < 1e-5 && center.y.abs() < 1e-5, "{}", center);
+        assert!(center.z > 0.0 && center.z < 1.0, "{}", center);
+    }
+
+    #[test]
+    fn points_outside_the_frustum_project_outside() {
+        let camera = camera();
+        let view_proj = camera.view_proj();
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        let distance = (camera.target - camera.eye).length();
+        // half the frustum's height at the target's distance, anything further out than that is cut off
+        let half_height = distance * (camera.fovy.to_radians() / 2.0).tan();
+        let half_width = half_height * camera.aspect;
+
+        assert!(inside(ndc(view_proj, camera.target + right * half_width * 0.9)));
+        let cases = [
+            (camera.target + right * half_width * 1.1, "right"),
+            (camera.target - right * half_width * 1.1, "left"),
+            (camera.target + up * half_height * 1.1, "above"),
+            (camera.target - up * half_height * 1.1, "below"),
+            (camera.eye + forward * camera.znear * 0.5, "in front of the near plane"),
+            (camera.eye + forward * camera.zfar * 1.5, "beyond the far plane"),
+        ];
+        for (point, side) in cases {
+            assert!(!inside(ndc(view_proj, point)), "{} projected to {}", side, ndc(view_proj, point));
+        }
+        // y = 1 is the top of the screen
+        assert!(ndc(view_proj, camera.target + up * half_height * 0.5).y > 0.0);
+    }
+
+    #[test]
+    fn free_view_proj_centers_the_target_from_anywhere() {
+        for eye in [Vec3::new(3.0, 3.0, 3.0), Vec3::new(-5.0, 1.0, 0.5), Vec3::new(0.2, -4.0, 7.0), Vec3::new(10.0, 0.0, 0.0)] {
+            let target = Vec3::new(0.5, -0.25, 1.0);
+            let center = ndc(view_proj(eye, target, Vec3::Y, 60.0, 1.5, 0.1, 100.0), target);
+            assert!(center.x.abs() < 1e-5 && center.y.abs() < 1e-5, "from {}: {}", eye, center);
+            assert!(inside(center), "from {}: {}", eye, center);
+        }
+        // the same matrix the Camera builds when it isn't blending towards orthographic or reversing Z
+        let camera = camera();
+        let free = view_proj(camera.eye, camera.target, camera.up, camera.fovy, camera.aspect, camera.znear, camera.zfar);
+        assert!(free.abs_diff_eq(camera.view_proj(), 1e-6));
+    }
+
+    #[test]
+    fn free_view_proj_puts_known_points_where_expected() {
+        // from the origin down -Z with a 90 degree FOV the frustum's edges are the lines x = +-z and y = +-z
+        let view_proj = view_proj(Vec3::ZERO, -Vec3::Z, Vec3::Y, 90.0, 1.0, 1.0, 10.0);
+        // where on screen, x and y after the divide by w
+        let screen = |view_proj: Mat4, point: Vec3| ndc(view_proj, point).truncate();
+        assert!(screen(view_proj, Vec3::new(2.0, 0.0, -2.0)).abs_diff_eq(glam::Vec2::new(1.0, 0.0), 1e-5));
+        assert!(screen(view_proj, Vec3::new(0.0, 3.0, -3.0)).abs_diff_eq(glam::Vec2::new(0.0, 1.0), 1e-5));
+        assert!(screen(view_proj, Vec3::new(-1.5, -1.5, -3.0)).abs_diff_eq(glam::Vec2::new(-0.5, -0.5), 1e-5));
+        // depth 0 on the near plane and 1 on the far one
+        assert!(ndc(view_proj, Vec3::new(0.0, 0.0, -1.0)).z.abs() < 1e-5);
+        assert!((ndc(view_proj, Vec3::new(0.0, 0.0, -10.0)).z - 1.0).abs() < 1e-5);
+        // a wider aspect squeezes x only
+        let wide = super::view_proj(Vec3::ZERO, -Vec3::Z, Vec3::Y, 90.0, 2.0, 1.0, 10.0);
+        assert!(screen(wide, Vec3::new(2.0, 2.0, -2.0)).abs_diff_eq(glam::Vec2::new(0.5, 1.0), 1e-5));
+    }
+
+    #[test]
+    fn aspect_only_scales_x() {
+        let narrow = camera();
+        let wide = Camera { aspect: narrow.aspect * 2.0, ..camera() };
+        let point = Vec3::new(0.4, -0.3, 0.7);
+        let (a, b) = (ndc(narrow.view_proj(), point), ndc(wide.view_proj(), point));
+        assert!((b.x - a.x / 2.0).abs() < 1e-5, "{} vs {}", a, b);
+        assert!((b.y - a.y).abs() < 1e-6 && (b.z - a.z).abs() < 1e-6, "{} vs {}", a, b);
+    }
+
+    // a point straight ahead of the camera at `distance`
+    fn ahead(camera: &Camera, distance: f32) -> Vec3 {
+        camera.eye + (camera.target - camera.eye).normalize() * distance
+    }
+
+    #[test]
+    fn proj_is_perspective_or_orthographic_at_the_ends_of_the_blend() {
+        let perspective = camera();
+        assert_eq!(perspective.proj(), perspective.perspective());
+        let orthographic = Camera { ortho: 1.0, ..camera() };
+        assert_eq!(orthographic.proj(), orthographic.orthographic());
+    }
+
+    #[test]
+    fn only_perspective_shrinks_things_further_away() {
+        // the height on screen of a unit tall stick standing at `distance` from the camera
+        let height = |camera: &Camera, distance: f32| {
+            let view_proj = camera.view_proj();
+            let foot = ahead(camera, distance);
+            ndc(view_proj, foot + Vec3::new(-1.0, 2.0, -1.0).normalize()).y - ndc(view_proj, foot).y
+        };
+        let distance = camera().eye.length();
+        let perspective = height(&camera(), distance);
+        for ortho in [0.0, 0.5, 1.0] {
+            let blended = Camera { ortho, ..camera() };
+            let (near, at_target, far) = (height(&blended, distance / 2.0), height(&blended, distance), height(&blended, distance * 2.0));
+            // all three agree at the target's distance, so switching doesn't make the cube jump
+            assert!((at_target - perspective).abs() < 1e-5, "ortho {}", ortho);
+            if ortho == 1.0 {
+                assert!((near - at_target).abs() < 1e-5 && (far - at_target).abs() < 1e-5);
+            } else {
+                assert!(near > at_target && at_target > far, "ortho {}: {} {} {}", ortho, near, at_target, far);
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_z_tells_far_depths_apart() {
+        // a wide depth range like a big scene's, looking straight down -Z so the view matrix adds no rounding of its own
+        let conventional = Camera { eye: Vec3::ZERO, target: -Vec3::Z, znear: 0.01, zfar: 10_000.0, ..camera() };
+        let reversed = Camera { reverse_z: true, ..conventional };
+        let depth = |camera: &Camera, distance: f32| ndc(camera.view_proj(), ahead(camera, distance)).z;
+        // how many f32 values there are between two depths, 0 or 1 is the same depth give or take the last bit's rounding
+        let steps = |a: f32, b: f32| a.to_bits().abs_diff(b.to_bits());
+
+        // conventional Z spends almost all of its precision right at the near plane, out here 1000 and 1001 are both
+        // squeezed onto the same f32 just below 1 (or its neighbour, depending on rounding) and would z-fight
+        assert!(steps(depth(&conventional, 1000.0), depth(&conventional, 1001.0)) <= 1);
+        // reverse-Z puts them thousands of values apart, the nearer one in front (greater)
+        let (near, far) = (depth(&reversed, 1000.0), depth(&reversed, 1001.0));
+        assert!(near > far && steps(near, far) > 1000, "{} vs {}", near, far);
+        // and the near plane still ends up at 1
+        assert!((depth(&reversed, reversed.znear) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn linear_depth_undoes_the_projection() {
+        for (ortho, reverse_z) in [(0.0, false), (0.0, true), (1.0, false), (1.0, true), (0.5, false)] {
+            let camera = Camera { ortho, reverse_z, ..camera() };
+            for distance in [0.2, 1.0, camera.eye.length(), 20.0, 90.0] {
+                let depth = ndc(camera.view_proj(), ahead(&camera, distance)).z;
+                let linear = camera.linear_depth(depth);
+                // relative, far away a depth buffer value stands for a wider slice of distances
+                assert!(
+                    (linear - distance).abs() < distance * 1e-3,
+                    "ortho {} reverse_z {}: {} came back as {}",
+                    ortho,
+                    reverse_z,
+                    distance,
+                    linear
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn linear_depth_at_the_clip_planes() {
+        let camera = camera();
+        assert!((camera.linear_depth(0.0) - camera.znear).abs() < 1e-4);
+        assert!((camera.linear_depth(1.0) - camera.zfar).abs() < 0.5);
+        // reverse-Z swaps the ends, and its perspective has no far plane: depth 0 is infinitely far away
+        let reversed = Camera { reverse_z: true, ..camera };
+        assert!((reversed.linear_depth(1.0) - reversed.znear).abs() < 1e-4);
+        assert_eq!(reversed.linear_depth(0.0), f32::INFINITY);
+    }
+
+    // boxes of different shapes and places, seen through different lenses from different sides
+    const BOXES: [(Vec3, Vec3); 4] = [
+        (Vec3::NEG_ONE, Vec3::ONE),
+        (Vec3::new(2.0, -1.0, 5.0), Vec3::new(2.5, 3.0, 5.5)),
+        (Vec3::new(-50.0, 0.0, -20.0), Vec3::new(50.0, 1.0, 20.0)),
+        (Vec3::new(0.1, 0.1, 0.1), Vec3::new(0.2, 0.15, 0.1)),
+    ];
+    const LENSES: [(f32, f32); 4] = [(45.0, 16.0 / 9.0), (90.0, 1.0), (30.0, 0.5), (100.0, 2.5)];
+    const DIRECTIONS: [Vec3; 3] = [Vec3::new(1.0, 1.0, 1.0), Vec3::new(-2.0, 0.5, 1.0), Vec3::new(0.0, 0.3, -1.0)];
+
+    fn framed_view_proj(framing: Framing, fov_deg: f32, aspect: f32) -> Mat4 {
+        view_proj(framing.eye, framing.target, Vec3::Y, fov_deg, aspect, framing.znear, framing.zfar)
+    }
+
+    #[test]
+    fn frame_bounds_keeps_the_whole_box_in_view() {
+        for (min, max) in BOXES {
+            for (fov_deg, aspect) in LENSES {
+                for direction in DIRECTIONS {
+                    let framing = frame_bounds(min, max, direction, fov_deg, aspect);
+                    let case = format!("{}..{} at {} degrees x {} from {}", min, max, fov_deg, aspect, direction);
+                    assert_eq!(framing.target, (min + max) / 2.0, "{}", case);
+                    assert!((framing.eye - framing.target).normalize().abs_diff_eq(direction.normalize(), 1e-5), "{}", case);
+                    // every corner on screen and between the clip planes, with the margin still clear of the edges
+                    let view_proj = framed_view_proj(framing, fov_deg, aspect);
+                    for i in 0..8 {
+                        let corner = Vec3::select(glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+                        let projected = ndc(view_proj, corner);
+                        assert!(inside(projected), "{}: corner {} at {}", case, corner, projected);
+                        assert!(projected.x.abs() < 1.0 / FRAME_MARGIN + 1e-4 && projected.y.abs() < 1.0 / FRAME_MARGIN + 1e-4, "{}", case);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frame_bounds_backs_off_just_far_enough() {
+        // the sphere around the box, grown by the margin, touches the edge of the view on its narrower side: the ray
+        // from the eye grazing it up (or to the right) lands exactly on y = 1 (or x = 1)
+        for (min, max) in BOXES {
+            for (fov_deg, aspect) in LENSES {
+                let framing = frame_bounds(min, max, Vec3::new(1.0, 0.5, 2.0), fov_deg, aspect);
+                let radius = (max - min).length() / 2.0 * FRAME_MARGIN;
+                let distance = framing.eye.distance(framing.target);
+                let forward = (framing.target - framing.eye) / distance;
+                let right = forward.cross(Vec3::Y).normalize();
+                let up = right.cross(forward);
+                let graze = (radius / distance).asin();
+                let view_proj = framed_view_proj(framing, fov_deg, aspect);
+                let towards = |side: Vec3| ndc(view_proj, framing.eye + (forward * graze.cos() + side * graze.sin()) * distance);
+                let (vertical, horizontal) = (towards(up).y, towards(right).x);
+                let case = format!("{}..{} at {} degrees x {}", min, max, fov_deg, aspect);
+                // whichever side is narrower is filled, the other has room to spare
+                if aspect >= 1.0 {
+                    assert!((vertical - 1.0).abs() < 1e-3 && horizontal <= 1.0 + 1e-3, "{}: {} {}", case, vertical, horizontal);
+                } else {
+                    assert!((horizontal - 1.0).abs() < 1e-3 && vertical <= 1.0 + 1e-3, "{}: {} {}", case, vertical, horizontal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frame_bounds_of_a_point_or_without_a_direction() {
+        // a single point is framed as a tiny sphere instead of putting the camera on it
+        let point = Vec3::new(1.0, 2.0, 3.0);
+        let framing = frame_bounds(point, point, Vec3::X, DEFAULT_FOV, 1.0);
+        let distance = framing.eye.distance(point);
+        assert!((distance - fit_distance(MIN_FRAME_RADIUS * FRAME_MARGIN, DEFAULT_FOV, 1.0)).abs() < 1e-6);
+        assert!(framing.znear > 0.0 && framing.znear < distance && framing.zfar > distance);
+        // no direction to back off along falls back to +Z
+        let framing = frame_bounds(Vec3::NEG_ONE, Vec3::ONE, Vec3::ZERO, DEFAULT_FOV, 1.0);
+        assert_eq!(framing.eye.truncate(), glam::Vec2::ZERO);
+        assert!(framing.eye.z > 0.0);
+    }
+
+    #[test]
+    fn frame_bounds_clip_planes_scale_with_the_box() {
+        // the same box at 1000 times the size is framed from 1000 times as far, with planes 1000 times apart
+        let small = frame_bounds(Vec3::NEG_ONE, Vec3::ONE, Vec3::Z, DEFAULT_FOV, 1.0);
+        let large = frame_bounds(Vec3::NEG_ONE * 1000.0, Vec3::ONE * 1000.0, Vec3::Z, DEFAULT_FOV, 1.0);
+        assert!((large.eye.z / small.eye.z - 1000.0).abs() < 0.1);
+        assert!((large.znear / small.znear - 1000.0).abs() < 0.1);
+        assert!((large.zfar / small.zfar - 1000.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,332 @@
+// how a window's camera follows the input: the keys, presets and mouse-look don't move the camera itself but a goal
+// position and look-at target, and every fixed step the camera covers part of the way to them
+// that turns the jumps of a key repeat or a coarse mouse into a smooth glide, with a short lag as the price
+// CameraRig is one camera with all of that state, a window has one, or one per half with --split-screen
+use glam::{Quat, Vec2, Vec3};
+use tracing::info;
+
+use crate::camera::{self, Camera, Lens};
+use crate::easing::{Easing, Tween};
+use crate::input::{camera_preset, Action};
+
+// field of view limits and step for the +/- keys and the wheel, in degrees, each press eases to the new value over FOV_TWEEN_TIME
+const MIN_FOV: f32 = 20.0;
+const MAX_FOV: f32 = 120.0;
+const FOV_STEP: f32 = 5.0;
+const FOV_TWEEN_TIME: f32 = 0.25;
+
+// how long O takes to blend between the perspective and orthographic projections
+const PROJECTION_BLEND_TIME: f32 = 0.3;
+
+// how long F1-F4 take to move the camera
+const CAMERA_SNAP_TIME: f32 = 0.6;
+
+// held camera movement (arrow keys): radians per second around the target, and how fast the distance changes, e to the
+// power of this per second, e.g. 1.2 covers a bit over 3x the distance in a second
+const ORBIT_SPEED: f32 = 1.5;
+const DOLLY_SPEED: f32 = 1.2;
+const MIN_CAMERA_DISTANCE: f32 = 0.5;
+
+// how close the smoothed camera has to get to its goal to be put there, far below a pixel at any sensible distance
+const SETTLED_DISTANCE: f32 = 1e-4;
+
+// the part of the way to the goal still left after `dt` seconds
+// every half-life halves what is left, so after 80 ms with the default half the distance remains, no matter if that
+// was 5 steps of 16 ms or 20 of 4 ms since 0.5^a * 0.5^b = 0.5^(a + b)
+// (a fixed "close 10% of the gap per step" would instead move twice as fast with twice the steps per second)
+pub fn remaining(half_life: f32, dt: f32) -> f32 {
+    if half_life <= 0.0 {
+        return 0.0; // smoothing off, straight to the goal
+    }
+    (-dt / half_life).exp2()
+}
+
+// one step from `current` towards `goal`
+pub fn damp(current: Vec3, goal: Vec3, half_life: f32, dt: f32) -> Vec3 {
+    goal + (current - goal) * remaining(half_life, dt)
+}
+
+// the settings from the command line, every window uses the same ones
+#[derive(Clone, Copy, Debug)]
+pub struct CameraControls {
+    pub half_life: f32,         // seconds, --camera-smoothing, 0 = the camera goes straight to the goal
+    pub mouse_sensitivity: f32, // radians per physical pixel the cursor moves while the look button is held
+    pub mouse_smoothing: bool,  // mouse-look goes through the smoothing too, off so the camera sticks to the mouse
+}
+
+// the same as the command line's defaults
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self { half_life: 0.08, mouse_sensitivity: 0.3f32.to_radians(), mouse_smoothing: false }
+    }
+}
+
+// how close in radians mouse-look gets to straight above or below the target, going over the top would line the view
+// direction up with `up` and flip the picture
+const POLE_MARGIN: f32 = 0.05;
+
+// `offset` (eye - target) turned `yaw` radians around `up` and `pitch` radians down from it, so the camera circles its
+// target, pitch stops short of straight above or below
+pub fn orbit(offset: Vec3, up: Vec3, yaw: f32, pitch: f32) -> Vec3 {
+    let offset = Quat::from_axis_angle(up, yaw) * offset;
+    // angle between up and the offset, which positive pitch makes larger
+    let angle = offset.angle_between(up);
+    let pitch = pitch.clamp(POLE_MARGIN - angle, std::f32::consts::PI - POLE_MARGIN - angle);
+    // no side to pitch towards when looking exactly along up, the F4 preset is tipped a little for this reason
+    let Some(right) = up.cross(offset).try_normalize() else { return offset };
+    Quat::from_axis_angle(right, pitch) * offset
+}
+
+// a camera and the goals and transitions steering it
+pub struct CameraRig {
+    pub camera: Camera,          // eye/target/projection settings the view matrix is built from
+    pub dirty: bool,             // set whenever camera changes so the uniform is only re-uploaded when needed
+    eye_goal: Vec3,              // where the keys, presets and mouse-look want the camera, it glides there
+    target_goal: Vec3,           // and what they want it to look at
+    controls: CameraControls,    // --camera-smoothing, --mouse-sensitivity and --mouse-smoothing
+    lens: Lens,                  // --near and --far, which F keeps instead of fitting the planes to the scene
+    fov_tween: Option<Tween<f32>>, // field of view change in progress (+/-)
+    eye_tween: Option<Tween<Vec3>>, // eye_goal's move to a preset viewpoint in progress (F1-F4), as an offset from target_goal
+    ortho_tween: Option<Tween<f32>>, // perspective/orthographic switch in progress (O)
+}
+
+impl CameraRig {
+    // `lens` is only needed after the camera was made, when F fits it to the scene again
+    pub fn new(camera: Camera, controls: CameraControls, lens: Lens) -> Self {
+        Self {
+            dirty: false, // the buffers are made from the current camera
+            eye_goal: camera.eye, // starts out where it wants to be
+            target_goal: camera.target,
+            controls,
+            lens,
+            camera,
+            fov_tween: None,
+            eye_tween: None,
+            ortho_tween: None,
+        }
+    }
+
+    // the area the camera draws into changed shape
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.camera.aspect = aspect;
+        self.dirty = true;
+    }
+
+    // zoom, projection and the viewpoint presets, returns true when the action was one of them
+    pub fn act(&mut self, action: Action) -> bool {
+        match action {
+            Action::ZoomIn | Action::ZoomOut => {
+                // narrower field of view to zoom in
+                let step = if action == Action::ZoomOut { FOV_STEP } else { -FOV_STEP };
+                // step from where a running zoom is heading, so quick presses add up instead of getting lost
+                let current_target = self.fov_tween.as_ref().map_or(self.camera.fovy, Tween::target);
+                // a --fov-deg outside the range isn't pulled into it by the first press, only kept from going further out
+                let target = (current_target + step).clamp(MIN_FOV.min(current_target), MAX_FOV.max(current_target));
+                self.fov_tween = Some(Tween::new(self.camera.fovy, target, FOV_TWEEN_TIME, Easing::QuadOut));
+                info!("FOV: {}", target);
+            }
+            Action::ToggleProjection => {
+                // head for the other end from wherever a running switch has got to, so pressing O twice turns back smoothly
+                let target = if self.ortho_tween.as_ref().map_or(self.camera.ortho, Tween::target) < 0.5 { 1.0 } else { 0.0 };
+                self.ortho_tween = Some(Tween::new(self.camera.ortho, target, PROJECTION_BLEND_TIME, Easing::CubicInOut));
+                info!("Projection: {}", if target > 0.5 { "orthographic" } else { "perspective" });
+            }
+            _ => match camera_preset(action) {
+                Some(direction) => {
+                    let offset = self.eye_goal - self.target_goal;
+                    let preset = direction.normalize() * offset.length();
+                    self.eye_tween = Some(Tween::new(offset, preset, CAMERA_SNAP_TIME, Easing::CubicInOut));
+                }
+                None => return false,
+            },
+        }
+        true
+    }
+
+    // the held camera actions: `turn` circles the camera around its target (positive to the right) and `dolly` moves it
+    // further away (positive) or closer, both -1..1 for how far the keys or stick are pushed
+    pub fn move_by(&mut self, turn: f32, dolly: f32, dt: f32) {
+        if turn == 0.0 && dolly == 0.0 {
+            return;
+        }
+        self.eye_tween = None; // the held key takes over from a preset that is still on its way
+        // only the goal moves, update() glides the camera after it, which smooths out the start and stop
+        let offset = Quat::from_axis_angle(self.camera.up, turn * ORBIT_SPEED * dt) * (self.eye_goal - self.target_goal);
+        // the distance changes by a factor per second, so moving in feels the same close up and far away
+        // a close --far (or a tiny scene) can put half the far plane nearer than the closest the camera may come
+        let max_distance = (self.camera.zfar / 2.0).max(MIN_CAMERA_DISTANCE);
+        let distance = (offset.length() * (dolly * DOLLY_SPEED * dt).exp()).clamp(MIN_CAMERA_DISTANCE, max_distance);
+        self.eye_goal = self.target_goal + offset.normalize() * distance;
+    }
+
+    // mouse-look: `delta` physical pixels of cursor movement turn the camera around its target, right and down on
+    // screen swing the view the same way
+    pub fn look(&mut self, delta: Vec2) {
+        let sensitivity = self.controls.mouse_sensitivity;
+        let offset = orbit(self.eye_goal - self.target_goal, self.camera.up, -delta.x * sensitivity, -delta.y * sensitivity);
+        self.eye_tween = None;
+        self.eye_goal = self.target_goal + offset;
+        // unsmoothed the camera sticks to the mouse, the hand already moves it smoothly and any lag feels like drag
+        if !self.controls.mouse_smoothing {
+            self.camera.eye = self.eye_goal;
+            self.dirty = true;
+        }
+    }
+
+    // F: look at the middle of the box min..max from the side the camera is on now, far enough back to see all of it
+    // the eye and target glide there like after any other move, the clip planes change straight away since they only
+    // need to fit the scene, see camera::frame_bounds()
+    pub fn frame(&mut self, min: Vec3, max: Vec3) {
+        // a zoom on its way counts with the FOV it is heading to, the framing would be off once it got there otherwise
+        let fovy = self.fov_tween.as_ref().map_or(self.camera.fovy, Tween::target);
+        let framing = camera::frame_bounds(min, max, self.eye_goal - self.target_goal, fovy, self.camera.aspect);
+        self.eye_tween = None;
+        self.eye_goal = framing.eye;
+        self.target_goal = framing.target;
+        (self.camera.znear, self.camera.zfar) = self.lens.clip_planes((framing.znear, framing.zfar));
+        self.dirty = true;
+        info!("Framed {:?}..{:?} from {:.2} away", min, max, framing.eye.distance(framing.target));
+    }
+
+    // step the FOV, projection and viewpoint transitions and glide the camera towards its goals, the camera only needs
+    // re-uploading while one of them is running
+    pub fn update(&mut self, dt: f32) {
+        if let Some(tween) = &mut self.ortho_tween {
+            self.camera.ortho = tween.advance(dt);
+            self.dirty = true;
+            if tween.is_finished() {
+                self.ortho_tween = None;
+            }
+        }
+        if let Some(tween) = &mut self.fov_tween {
+            self.camera.fovy = tween.advance(dt);
+            self.dirty = true;
+            if tween.is_finished() {
+                self.fov_tween = None;
+            }
+        }
+        if let Some(tween) = &mut self.eye_tween {
+            // a straight line between two viewpoints cuts towards the target, pushing it back out to the preset
+            // distance keeps the camera on a sphere around the cube for the whole move
+            let distance = tween.target().length();
+            self.eye_goal = self.target_goal + tween.advance(dt).normalize() * distance;
+            if tween.is_finished() {
+                self.eye_tween = None;
+            }
+        }
+
+        // done once close enough to see no difference, an exponential approach would otherwise never quite arrive
+        // and re-upload the camera every step forever
+        for (current, goal) in [(&mut self.camera.eye, self.eye_goal), (&mut self.camera.target, self.target_goal)] {
+            if *current == goal {
+                continue;
+            }
+            *current = damp(*current, goal, self.controls.half_life, dt);
+            if current.distance_squared(goal) < SETTLED_DISTANCE * SETTLED_DISTANCE {
+                *current = goal;
+            }
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rig() -> CameraRig {
+        let camera = Camera {
+            eye: Vec3::new(3.0, 3.0, 3.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            fovy: camera::DEFAULT_FOV,
+            aspect: 16.0 / 9.0,
+            znear: 0.1,
+            zfar: 100.0,
+            ortho: 0.0,
+            reverse_z: false,
+        };
+        CameraRig::new(camera, CameraControls::default(), Lens::default())
+    }
+
+    // steps a rig at `rate` steps per second for `frames` 60ths of a second and records the eye at the end of each
+    // `script` does the input of every step, it gets the 60th of a second and the step within it, and dt
+    fn trajectory(rate: u32, frames: u32, script: impl Fn(&mut CameraRig, u32, u32, f32)) -> Vec<Vec3> {
+        let mut rig = rig();
+        let steps = rate / 60;
+        let dt = 1.0 / rate as f32;
+        (0..frames)
+            .map(|frame| {
+                for step in 0..steps {
+                    script(&mut rig, frame, step, dt);
+                    rig.update(dt);
+                }
+                rig.camera.eye
+            })
+            .collect()
+    }
+
+    // the smoothing is exact for a goal that stands still, but a goal that moves every step (a preset's tween, a held
+    // key) jumps ahead at the start of each step and is chased for the whole of it, so the 60 Hz camera runs a fraction
+    // of a step ahead of the 240 Hz one while the goal moves: never by more than half of what it covers in a frame,
+    // and once the goal stops both settle on the same spot
+    fn assert_converge(slow: &[Vec3], fast: &[Vec3]) {
+        let per_frame = slow.windows(2).map(|pair| pair[0].distance(pair[1])).fold(0.0, f32::max);
+        for (frame, (a, b)) in slow.iter().zip(fast).enumerate() {
+            assert!(a.distance(*b) < per_frame / 2.0, "frame {}: {} at 60 Hz, {} at 240 Hz", frame, a, b);
+        }
+        assert!(slow.last().unwrap().distance(*fast.last().unwrap()) < 1e-3);
+    }
+
+    #[test]
+    fn damping_is_the_same_in_any_number_of_steps() {
+        let (start, goal) = (Vec3::new(4.0, -1.0, 2.0), Vec3::ZERO);
+        let mut coarse = start;
+        let mut fine = start;
+        for frame in 0..30 {
+            coarse = damp(coarse, goal, 0.08, 1.0 / 60.0);
+            for _ in 0..4 {
+                fine = damp(fine, goal, 0.08, 1.0 / 240.0);
+            }
+            assert!(coarse.distance(fine) < 1e-5, "frame {}: {} vs {}", frame, coarse, fine);
+        }
+        // a half-life in, half the distance is left
+        assert!((remaining(0.08, 0.08) - 0.5).abs() < 1e-6);
+        assert_eq!(remaining(0.0, 1.0 / 60.0), 0.0);
+    }
+
+    #[test]
+    fn a_preset_follows_the_same_path_at_60_and_240_hz() {
+        // a key press arrives once, on the very first step
+        let script = |rig: &mut CameraRig, frame: u32, step: u32, _dt: f32| {
+            if frame == 0 && step == 0 {
+                rig.act(Action::CameraFront);
+            }
+        };
+        let (slow, fast) = (trajectory(60, 90, script), trajectory(240, 90, script));
+        assert_converge(&slow, &fast);
+        // and both end up in front, at the starting distance
+        let end = *fast.last().unwrap();
+        assert!(end.normalize().abs_diff_eq(Vec3::Z, 1e-3), "{}", end);
+        assert!((end.length() - Vec3::splat(3.0).length()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn held_keys_cover_the_same_ground_at_60_and_240_hz() {
+        // a second of turning right while backing off, then let go
+        let script = |rig: &mut CameraRig, frame: u32, _step: u32, dt: f32| {
+            if frame < 60 {
+                rig.move_by(1.0, 0.5, dt);
+            }
+        };
+        let (slow, fast) = (trajectory(60, 120, script), trajectory(240, 120, script));
+        assert_converge(&slow, &fast);
+        let expected = (Vec3::splat(3.0).length() * (0.5 * DOLLY_SPEED).exp(), ORBIT_SPEED);
+        let end = *fast.last().unwrap();
+        assert!((end.length() - expected.0).abs() < 1e-3, "{} vs {}", end.length(), expected.0);
+        // turned around the vertical by ORBIT_SPEED radians, measured in the ground plane
+        let start = Vec3::new(3.0, 0.0, 3.0).normalize();
+        let angle = start.angle_between(Vec3::new(end.x, 0.0, end.z).normalize());
+        assert!((angle - expected.1).abs() < 1e-3, "{} vs {}", angle, expected.1);
+    }
+}
@@ -0,0 +1,207 @@
+// cube geometry, each face gets its own 4 vertices so every vertex can carry the normal of the face it belongs to
+// (a corner shared by 3 faces would otherwise need 3 different normals at once)
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use crate::mesh::MeshData;
+
+// guarantee struct memory layout matches C so the GPU reads position/color/normal at fixed byte offsets
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub normal: [f32; 3], // unit vector pointing out of the face, used for lighting and the normals debug view
+}
+
+impl Vertex {
+    // describes to the pipeline how to step through the vertex buffer, 9 floats * 4 bytes = 36 bytes per vertex
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// each corner keeps the color it had when the cube was 8 shared vertices so the gradients look the same
+fn corner_color(p: Vec3) -> [f32; 3] {
+    match (p.x > 0.0, p.y > 0.0, p.z > 0.0) {
+        (false, false, false) => [1.0, 0.0, 0.0],
+        (true, false, false) => [0.0, 1.0, 0.0],
+        (true, true, false) => [0.0, 0.0, 1.0],
+        (false, true, false) => [1.0, 1.0, 0.0],
+        (false, false, true) => [1.0, 0.0, 1.0],
+        (true, false, true) => [0.0, 1.0, 1.0],
+        (true, true, true) => [1.0, 1.0, 1.0],
+        (false, true, true) => [0.0, 0.0, 0.0],
+    }
+}
+
+// color anywhere on the cube, blended from the 8 corner colors by how close `p` is to each (trilinear weights)
+// on a face only its own 4 corners get any weight, which is exactly the gradient the GPU interpolates between them,
+// so a subdivided face looks the same as a single quad
+fn surface_color(p: Vec3) -> [f32; 3] {
+    let mut color = Vec3::ZERO;
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                let weight = (1.0 + x * p.x) * (1.0 + y * p.y) * (1.0 + z * p.z) / 8.0;
+                color += Vec3::from(corner_color(Vec3::new(x, y, z))) * weight;
+            }
+        }
+    }
+    color.to_array()
+}
+
+// every face is described by its normal n and two in-plane axes u, v chosen so u x v = n,
+// walking the corners (-u-v, +u-v, +u+v, -u+v) is then counter-clockwise when seen from outside the cube
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y, Vec3::Z),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+    (Vec3::Y, Vec3::Z, Vec3::X),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+];
+
+// build a cube spanning -1..1 on every axis with each face cut into a `subdivisions` x `subdivisions` grid of quads
+// (--subdivisions N, 1 is the plain 24 vertex / 36 index cube)
+// a face has (N+1)^2 vertices, all with the face's normal, and 6 indices per quad
+// the edge indices are the grid's lines, see edge_indices()
+// Mesh::new() uploads the indices as u16 when they fit, up to N = 103 (64896 vertices), N = 256 is already ~400k
+// vertices, far past what u16 can address
+pub fn make_cube(subdivisions: u32) -> MeshData {
+    let n = subdivisions.max(1);
+    let row = n + 1; // vertices along one edge of a face
+    let mut vertices = Vec::with_capacity((6 * row * row) as usize);
+    let mut indices = Vec::with_capacity((6 * n * n * 6) as usize);
+
+    for (normal, u, v) in FACES {
+        let base = vertices.len() as u32;
+        for j in 0..row {
+            for i in 0..row {
+                // -1..1 along u and v
+                let s = 2.0 * i as f32 / n as f32 - 1.0;
+                let t = 2.0 * j as f32 / n as f32 - 1.0;
+                let p = normal + u * s + v * t;
+                vertices.push(Vertex {
+                    position: p.to_array(),
+                    color: surface_color(p),
+                    normal: normal.to_array(),
+                });
+            }
+        }
+        for j in 0..n {
+            for i in 0..n {
+                // the quad's corners in the same counter-clockwise order as a whole face
+                let a = base + j * row + i;
+                let b = a + 1;
+                let c = a + row + 1;
+                let d = a + row;
+                // two triangles per quad sharing the diagonal a-c
+                indices.extend_from_slice(&[a, b, c, c, d, a]);
+            }
+        }
+    }
+
+    MeshData { vertices, indices, edge_indices: edge_indices(subdivisions) }
+}
+
+// index buffer for the wireframe draw mode: the outline of every quad from make_cube() as LineList pairs
+// each face is drawn as its N+1 lines along u and N+1 lines along v, split into one segment per quad edge,
+// the border lines are shared with the neighbouring face so those are drawn twice, the quad diagonals are left out
+fn edge_indices(subdivisions: u32) -> Vec<u32> {
+    let n = subdivisions.max(1);
+    let row = n + 1;
+    let mut indices = Vec::with_capacity((6 * 2 * row * n * 2) as usize);
+    for face in 0..6 {
+        let base = face * row * row;
+        for line in 0..row {
+            for step in 0..n {
+                // along u: (step, line) -> (step + 1, line), along v: (line, step) -> (line, step + 1)
+                let along_u = base + line * row + step;
+                let along_v = base + step * row + line;
+                indices.extend_from_slice(&[along_u, along_u + 1, along_v, along_v + row]);
+            }
+        }
+    }
+    indices
+}
+
+// the --lod stand-ins for a cube too far away to show its faces
+// a tetrahedron on four of the cube's corners (every other one, so no two share an edge of the cube): 4 triangles and
+// 12 indices instead of 12 and 36, still closed so back-face culling keeps working, and it has a face turned towards
+// the camera from every side like a billboard would without having to be turned to face it
+// each face has its own 3 vertices for a flat normal, like the cube's faces have their own 4
+pub fn make_tetrahedron() -> MeshData {
+    let corners = [Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0)];
+    let mut mesh = MeshData::default();
+    for left_out in 0..corners.len() {
+        let mut face: Vec<Vec3> = (0..corners.len()).filter(|&i| i != left_out).map(|i| corners[i]).collect();
+        // the centre is the origin, so a face whose normal points back towards it is wound clockwise, flip it
+        let mut normal = (face[1] - face[0]).cross(face[2] - face[0]);
+        if normal.dot(face[0]) < 0.0 {
+            face.swap(1, 2);
+            normal = -normal;
+        }
+        let base = mesh.vertices.len() as u32;
+        for corner in face {
+            mesh.vertices.push(Vertex {
+                position: corner.to_array(),
+                color: corner_color(corner),
+                normal: normal.normalize().to_array(),
+            });
+        }
+        mesh.indices.extend_from_slice(&[base, base + 1, base + 2]);
+        mesh.edge_indices.extend_from_slice(&[base, base + 1, base + 1, base + 2, base + 2, base]);
+    }
+    mesh
+}
+
+// the furthest stand-in: a single vertex at the centre, drawn as a point in the cube's average color
+// no triangles or edges, there is nothing to draw in the other modes
+pub fn make_point() -> MeshData {
+    MeshData {
+        vertices: vec![Vertex { position: [0.0; 3], color: surface_color(Vec3::ZERO), normal: [0.0, 1.0, 0.0] }],
+        ..MeshData::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_counts_grow_with_the_subdivisions() {
+        for n in [1, 2, 5] {
+            let data = make_cube(n);
+            let row = (n + 1) as usize;
+            let n = n as usize;
+            assert_eq!(data.vertices.len(), 6 * row * row, "{} subdivisions", n);
+            assert_eq!(data.indices.len(), 6 * n * n * 6, "{} subdivisions", n);
+            // every grid line of every face, one segment (2 indices) per quad edge
+            assert_eq!(data.edge_indices.len(), 6 * 2 * row * n * 2, "{} subdivisions", n);
+            assert!(data.indices.iter().chain(&data.edge_indices).all(|&index| (index as usize) < data.vertices.len()));
+        }
+        // 0 is taken as the plain cube rather than an empty mesh
+        assert_eq!(make_cube(0).indices.len(), 36);
+    }
+
+    #[test]
+    fn cube_spans_minus_one_to_one_and_winds_counter_clockwise() {
+        let data = make_cube(3);
+        assert_eq!(data.aabb(), Some((Vec3::NEG_ONE, Vec3::ONE)));
+        for triangle in data.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &data.vertices[triangle[i] as usize]);
+            let [pa, pb, pc] = [a, b, c].map(|vertex| Vec3::from(vertex.position));
+            // counter-clockwise seen from outside: the winding's normal is the face's, which back-face culling relies on
+            let winding = (pb - pa).cross(pc - pa).normalize();
+            assert!(winding.abs_diff_eq(Vec3::from(a.normal), 1e-5), "{:?} against {:?}", winding, a.normal);
+        }
+    }
+}
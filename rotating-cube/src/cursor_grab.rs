@@ -0,0 +1,60 @@
+// mouse capture for mouse-look (C by default): the cursor disappears and every movement of the mouse turns the camera,
+// no button needs to be held and the cursor can't run into the edge of the screen
+// while captured the camera is turned by DeviceEvent::MouseMotion, the raw movement of the mouse itself, rather than by
+// CursorMoved: a locked cursor doesn't move, so CursorMoved has nothing to report
+// Escape, or the window losing focus or being minimized, gives the cursor back, a captured cursor in a window the user
+// has left would be stuck
+use tracing::{info, warn};
+use winit::dpi::PhysicalPosition;
+use winit::window::{CursorGrabMode, Window};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorGrab {
+    #[default]
+    Free,
+    Locked,   // the OS holds the cursor in place (Wayland, macOS, the browser's pointer lock)
+    Confined, // the cursor can't leave the window but still moves (Windows, X11), recenter() puts it back every frame
+}
+
+impl CursorGrab {
+    // Locked where the platform has it, Confined where it doesn't, and Free if the window can't grab the cursor at all
+    // (the browser only allows it in response to a key press or click, which is when this is called)
+    pub fn capture(window: &Window) -> Self {
+        let grab = if window.set_cursor_grab(CursorGrabMode::Locked).is_ok() {
+            CursorGrab::Locked
+        } else if window.set_cursor_grab(CursorGrabMode::Confined).is_ok() {
+            CursorGrab::Confined
+        } else {
+            warn!("Couldn't capture the mouse, hold the mouse-look button instead");
+            return CursorGrab::Free;
+        };
+        window.set_cursor_visible(false);
+        info!("Mouse captured ({:?}), Escape releases it", grab);
+        grab
+    }
+
+    // safe to call in any state, every way out of the capture ends up here
+    pub fn release(&mut self, window: &Window) {
+        if *self == CursorGrab::Free {
+            return;
+        }
+        // failing to let go of a grab the OS already ended (alt-tab) changes nothing, so the error isn't interesting
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        *self = CursorGrab::Free;
+        info!("Mouse released");
+    }
+
+    pub fn is_captured(self) -> bool {
+        self != CursorGrab::Free
+    }
+
+    // a confined cursor stops at the window's edge and the mouse's movement past it would only show up as raw motion
+    // for as long as the OS keeps sending it, so it is moved back to the middle every frame
+    pub fn recenter(self, window: &Window) {
+        if self == CursorGrab::Confined {
+            let size = window.inner_size();
+            let _ = window.set_cursor_position(PhysicalPosition::new(size.width / 2, size.height / 2));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// immediate-mode debug lines: every frame the scene adds whatever segments it wants to see (bounding boxes, normals,
+// light direction, ...), they are uploaded in one go and drawn with a LineList pipeline after the main geometry
+// all positions are in world space, so anything attached to the cube has to be transformed before it is added
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+
+use crate::cube::Vertex;
+use crate::render_stats::{CountingPass, CountingQueue};
+
+// room for this many vertices before the first growth, enough for the cube's normals, box and axes
+const INITIAL_CAPACITY: usize = 128;
+
+// a line-list vertex only needs a position and color
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl LineVertex {
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct DebugLines {
+    vertices: Vec<LineVertex>, // segments added since the last clear, two vertices each
+    buffer: wgpu::Buffer,
+    capacity: usize,           // size of `buffer` in vertices, only ever grows
+    num_vertices: u32,         // vertices uploaded by the last upload(), what draw() renders
+}
+
+impl DebugLines {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            vertices: Vec::with_capacity(INITIAL_CAPACITY),
+            buffer: create_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            num_vertices: 0,
+        }
+    }
+
+    // start a new frame's worth of lines, the GPU buffer is kept so nothing is reallocated
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn add_line(&mut self, a: Vec3, b: Vec3, color: [f32; 3]) {
+        self.vertices.push(LineVertex { position: a.to_array(), color });
+        self.vertices.push(LineVertex { position: b.to_array(), color });
+    }
+
+    // the 12 edges of an axis-aligned box
+    pub fn add_aabb(&mut self, min: Vec3, max: Vec3, color: [f32; 3]) {
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(if x { max.x } else { min.x }, if y { max.y } else { min.y }, if z { max.z } else { min.z })
+        };
+        for a in [false, true] {
+            for b in [false, true] {
+                self.add_line(corner(false, a, b), corner(true, a, b), color); // edges along X
+                self.add_line(corner(a, false, b), corner(a, true, b), color); // edges along Y
+                self.add_line(corner(a, b, false), corner(a, b, true), color); // edges along Z
+            }
+        }
+    }
+
+    // the 12 edges of a frustum from lod::frustum_corners(): both ends' outlines and the four edges between them
+    pub fn add_frustum(&mut self, corners: &[Vec3; 8], color: [f32; 3]) {
+        for i in 0..4 {
+            let next = (i + 1) % 4;
+            self.add_line(corners[i], corners[next], color);
+            self.add_line(corners[i + 4], corners[next + 4], color);
+            self.add_line(corners[i], corners[i + 4], color);
+        }
+    }
+
+    // the local X (red), Y (green) and Z (blue) axes of `transform`, each `size` long
+    pub fn add_axes(&mut self, transform: Mat4, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [(Vec3::X, [1.0, 0.0, 0.0]), (Vec3::Y, [0.0, 1.0, 0.0]), (Vec3::Z, [0.0, 0.0, 1.0])] {
+            self.add_line(origin, transform.transform_point3(axis * size), color);
+        }
+    }
+
+    // one short segment per vertex along its normal, with the mesh placed by `transform`
+    // lines are colored by axis (|x|, |y|, |z| as RGB) so +/-X faces show red, +/-Y green and +/-Z blue
+    pub fn add_normals(&mut self, vertices: &[Vertex], transform: Mat4, length: f32) {
+        for v in vertices {
+            let (start, end, color) = normal_line(v, transform, length);
+            self.add_line(start, end, color);
+        }
+    }
+
+    // copy this frame's lines to the GPU, reallocating the buffer first if they no longer fit
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &CountingQueue) {
+        let needed = grown_capacity(self.capacity, self.vertices.len());
+        if needed != self.capacity {
+            self.capacity = needed;
+            self.buffer = create_buffer(device, self.capacity);
+        }
+        if !self.vertices.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+        self.num_vertices = self.vertices.len() as u32;
+    }
+
+    // expects the line pipeline and the camera bind group to be set already
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>) {
+        if self.num_vertices == 0 {
+            return;
+        }
+        pass.set_vertex_buffer(0, self.buffer.slice(..));
+        pass.draw(0..self.num_vertices, 0..1);
+    }
+}
+
+// buffer size needed for `len` vertices: double until they fit, never smaller than what we already have
+// so a frame with few lines doesn't throw away a buffer the next busy frame would need again
+pub fn grown_capacity(capacity: usize, len: usize) -> usize {
+    let mut capacity = capacity.max(1);
+    while capacity < len {
+        capacity *= 2;
+    }
+    capacity
+}
+
+// the segment add_normals() draws for one vertex: start, end and color
+pub fn normal_line(vertex: &Vertex, transform: Mat4, length: f32) -> (Vec3, Vec3, [f32; 3]) {
+    let normal = Vec3::from(vertex.normal);
+    let start = transform.transform_point3(Vec3::from(vertex.position));
+    let end = start + transform.transform_vector3(normal).normalize() * length;
+    (start, end, normal.abs().to_array())
+}
+
+// smallest world-space box around `vertices` once placed by `transform`
+pub fn transformed_bounds(vertices: &[Vertex], transform: Mat4) -> (Vec3, Vec3) {
+    vertices.iter().fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), v| {
+        let p = transform.transform_point3(Vec3::from(v.position));
+        (min.min(p), max.max(p))
+    })
+}
+
+fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Debug Lines Buffer"),
+        size: (capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::make_cube;
+
+    #[test]
+    fn capacity_doubles_until_the_lines_fit() {
+        assert_eq!(grown_capacity(128, 0), 128);
+        assert_eq!(grown_capacity(128, 128), 128);
+        assert_eq!(grown_capacity(128, 129), 256);
+        assert_eq!(grown_capacity(128, 1000), 1024);
+        // a buffer that was never given any room still grows
+        assert_eq!(grown_capacity(0, 3), 4);
+    }
+
+    #[test]
+    fn capacity_never_shrinks() {
+        let mut capacity = INITIAL_CAPACITY;
+        // a busy frame, then quiet ones: the buffer the busy one needed stays
+        for len in [5000, 10, 0, 300] {
+            let grown = grown_capacity(capacity, len);
+            assert!(grown >= capacity && grown >= len);
+            capacity = grown;
+        }
+        assert_eq!(capacity, 8192);
+    }
+
+    #[test]
+    fn normal_lines_start_at_the_vertices_and_point_out_of_the_faces() {
+        let cube = make_cube(1);
+        let transform = Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2) * Mat4::from_scale(Vec3::splat(2.0));
+        for vertex in &cube.vertices {
+            let (start, end, color) = normal_line(vertex, transform, 0.5);
+            assert!(start.abs_diff_eq(transform.transform_point3(Vec3::from(vertex.position)), 1e-5));
+            // the scale doesn't stretch the lines, only turns them with the cube
+            assert!(((end - start).length() - 0.5).abs() < 1e-5);
+            let normal = Vec3::from(vertex.normal);
+            assert!((end - start).normalize().abs_diff_eq(transform.transform_vector3(normal).normalize(), 1e-5));
+            // colored by the axis the face looks along in the mesh's own space, +X and -X both red
+            assert_eq!(color, normal.abs().to_array());
+        }
+    }
+
+    #[test]
+    fn a_face_gets_a_line_per_vertex() {
+        // the +Y face of a 2x2 subdivided cube has 3x3 vertices, every one a line going straight up from y = 1
+        let cube = make_cube(2);
+        let top: Vec<_> = cube.vertices.iter().filter(|vertex| vertex.normal == [0.0, 1.0, 0.0]).collect();
+        assert_eq!(top.len(), 9);
+        for vertex in top {
+            let (start, end, color) = normal_line(vertex, Mat4::IDENTITY, 0.2);
+            assert_eq!(start.y, 1.0);
+            assert!(end.abs_diff_eq(start + Vec3::Y * 0.2, 1e-6));
+            assert_eq!(color, [0.0, 1.0, 0.0]);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// debug views cycled with D: the normal image, the depth buffer as grayscale, world-space normals as colors, and the
+// cube's edges drawn over the shaded faces
+// the depth view is its own fullscreen pass that reads the depth texture the scene pass just filled in, the other two
+// only change how the scene pass itself draws
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::{Bindings, BindingsBuilder};
+use crate::camera::Camera;
+use crate::depth;
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::render_stats::{CountingPass, CountingQueue};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    Final,            // what the app normally draws
+    Depth,            // distance from the camera, bright = close, black where nothing was drawn
+    Normals,          // world-space normal per pixel, x/y/z mapped to red/green/blue
+    WireframeOverlay, // lit faces with their edges drawn on top
+}
+
+impl DebugView {
+    // order the D key cycles through
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Final => DebugView::Depth,
+            DebugView::Depth => DebugView::Normals,
+            DebugView::Normals => DebugView::WireframeOverlay,
+            DebugView::WireframeOverlay => DebugView::Final,
+        }
+    }
+
+    // HUD name, uppercase since the font only has capitals
+    pub fn label(self) -> &'static str {
+        match self {
+            DebugView::Final => "FINAL",
+            DebugView::Depth => "DEPTH",
+            DebugView::Normals => "NORMALS",
+            DebugView::WireframeOverlay => "WIREFRAME",
+        }
+    }
+}
+
+// matches Params in depth_view.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DepthViewUniform {
+    inv_proj: [[f32; 4]; 4], // undoes the camera's projection, see Camera::linear_depth()
+    near: f32,               // distance that shows as full white
+    falloff: f32,            // brightness halves roughly every 0.7 of this further away
+    clear_depth: f32,        // what the buffer was cleared to, pixels still at it are left black
+    _padding: f32,
+}
+
+impl DepthViewUniform {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            inv_proj: camera.proj().inverse().to_cols_array_2d(),
+            // the near plane is stored at the opposite end of the range from the clear value, whichever way round
+            near: camera.linear_depth(1.0 - depth::clear_value(camera.reverse_z)),
+            // the camera's distance to what it looks at, so the cube is mid-gray whatever the zoom
+            falloff: (camera.eye - camera.target).length(),
+            clear_depth: depth::clear_value(camera.reverse_z),
+            _padding: 0.0,
+        }
+    }
+}
+
+// pipeline that draws a depth texture as grayscale, shared by every window
+pub struct DepthView {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bindings: Bindings,
+    sampler: wgpu::Sampler,
+}
+
+impl DepthView {
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache, format: wgpu::TextureFormat) -> Self {
+        // depth can't be filtered (averaging two depths gives a surface that isn't there), so nearest sampling and a
+        // non-filtering sampler, and no compare function since we want the stored value and not a pass/fail test
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Depth View Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // the texture is bound as plain unfilterable floats rather than as a depth texture, the GL backend turns depth
+        // textures into shadow samplers that can only be read with a comparison
+        let unfilterable = wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        };
+        let non_filtering = wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering);
+        let bindings = BindingsBuilder::new("Depth View")
+            .uniform(0, wgpu::ShaderStages::FRAGMENT)
+            .entry(1, wgpu::ShaderStages::FRAGMENT, unfilterable)
+            .entry(2, wgpu::ShaderStages::FRAGMENT, non_filtering)
+            .build(device);
+
+        // no depth_compare in the key: the depth texture is being read, it can't be an attachment at the same time
+        let key = PipelineKey::new(ShaderId::new("depth_view.wgsl", "vs_main", "fs_main"), format, wgpu::BlendState::REPLACE);
+        let pipeline = cache.get_or_create(key, |key| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("depth_view.wgsl"));
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Depth View Pipeline Layout"),
+                bind_group_layouts: &[&bindings.layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Depth View Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[], //the fullscreen triangle's corners come from the vertex index
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &key.targets(),
+                }),
+                primitive: key.primitive(),
+                depth_stencil: key.depth_stencil(),
+                multisample: key.multisample(),
+                multiview: None,
+            })
+        });
+
+        Self { pipeline, bindings, sampler }
+    }
+
+    // one window's depth view settings, rewritten with write() whenever its camera changes
+    pub fn create_buffer(device: &wgpu::Device, camera: &Camera) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth View Buffer"),
+            contents: bytemuck::bytes_of(&DepthViewUniform::new(camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    pub fn write(queue: &CountingQueue, buffer: &wgpu::Buffer, camera: &Camera) {
+        queue.write_buffer(buffer, 0, bytemuck::bytes_of(&DepthViewUniform::new(camera)));
+    }
+
+    // the depth texture changes with every resize and is a different one for --record, so rather than keeping bind
+    // groups in sync with it this makes a fresh one each frame, only while the depth view is on
+    // `depth` must have been created with TEXTURE_BINDING usage
+    pub fn bind_group(&self, device: &wgpu::Device, buffer: &wgpu::Buffer, depth: &wgpu::TextureView) -> wgpu::BindGroup {
+        self.bindings.bind_group(
+            device,
+            &[
+                buffer.as_entire_binding(),
+                wgpu::BindingResource::TextureView(depth),
+                wgpu::BindingResource::Sampler(&self.sampler),
+            ],
+        )
+    }
+
+    // fills the current viewport, the bind group has to outlive the pass so it is made by bind_group() beforehand
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, bind_group: &'a wgpu::BindGroup) {
+        pass.set_pipeline(&self.pipeline, wgpu::PrimitiveTopology::TriangleList);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
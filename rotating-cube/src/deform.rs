@@ -0,0 +1,162 @@
+// --deform: a compute pass that ripples the cube's vertices along their normals every frame
+// the untouched mesh lives in a read-only storage buffer, the compute shader writes the displaced positions straight
+// into the vertex buffer the render pass draws from (which therefore needs STORAGE usage as well as VERTEX)
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::BindingsBuilder;
+use crate::cube::Vertex;
+use crate::render_stats::CountingQueue;
+
+// must match @workgroup_size in deform.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+// wave settings, amplitude is in model units (the cube is 2 wide)
+const AMPLITUDE: f32 = 0.15;
+const SPEED: f32 = 3.0;
+const FREQUENCY: f32 = 2.0;
+
+// matches Params in deform.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct DeformParams {
+    time: f32,
+    amplitude: f32,
+    speed: f32,
+    frequency: f32,
+}
+
+pub struct Deformer {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    workgroups: u32, // enough groups of WORKGROUP_SIZE to cover every vertex
+}
+
+impl Deformer {
+    // `vertex_buffer` must hold the same vertices as `base` and have been created with STORAGE usage
+    pub fn new(device: &wgpu::Device, base: &[Vertex], vertex_buffer: &wgpu::Buffer) -> Self {
+        let base_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Deform Base Vertices"),
+            contents: bytemuck::cast_slice(base),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Deform Params Buffer"),
+            contents: bytemuck::bytes_of(&params(0.0)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bindings = BindingsBuilder::new("Deform")
+            .uniform(0, wgpu::ShaderStages::COMPUTE)
+            .storage(1, wgpu::ShaderStages::COMPUTE, true)
+            .storage(2, wgpu::ShaderStages::COMPUTE, false)
+            .build(device);
+        let bind_group = bindings.bind_group(
+            device,
+            &[params_buffer.as_entire_binding(), base_buffer.as_entire_binding(), vertex_buffer.as_entire_binding()],
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("deform.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Deform Pipeline Layout"),
+            bind_group_layouts: &[&bindings.layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Deform Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            params_buffer,
+            workgroups: (base.len() as u32).div_ceil(WORKGROUP_SIZE),
+        }
+    }
+
+    // time in seconds, the same clock the rest of the animation uses
+    pub fn set_time(&self, queue: &CountingQueue, time: f32) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params(time)));
+    }
+
+    // record the compute pass, must come before the render pass that reads the vertex buffer
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Deform Pass") });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(self.workgroups, 1, 1);
+    }
+}
+
+fn params(time: f32) -> DeformParams {
+    DeformParams {
+        time,
+        amplitude: AMPLITUDE,
+        speed: SPEED,
+        frequency: FREQUENCY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::make_cube;
+    use crate::gpu;
+    use glam::Vec3;
+
+    // what cs_main does to one vertex, written out again on the CPU
+    fn reference(vertex: &Vertex, time: f32) -> Vec3 {
+        let position = Vec3::from(vertex.position);
+        let wave = (time * SPEED + position.dot(Vec3::ONE) * FREQUENCY).sin();
+        position + Vec3::from(vertex.normal) * AMPLITUDE * wave
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn one_dispatch_matches_the_cpu_reference() {
+        let context = pollster::block_on(gpu::Context::headless()).expect("no GPU adapter");
+        if !context.compute {
+            eprintln!("skipped: the adapter has no compute shaders");
+            return;
+        }
+        let (device, queue) = (&context.device, CountingQueue::new(context.queue));
+        // more vertices than one workgroup, and not a multiple of it, so the last group's bounds check runs too
+        let base = make_cube(4).vertices;
+        assert!(!(base.len() as u32).is_multiple_of(WORKGROUP_SIZE) && base.len() as u32 > WORKGROUP_SIZE);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Test Vertices"),
+            contents: bytemuck::cast_slice(&base),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let deformer = Deformer::new(device, &base, &vertex_buffer);
+        let time = 1.3;
+        deformer.set_time(&queue, time);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        deformer.dispatch(&mut encoder);
+        queue.submit(Some(encoder.finish()));
+
+        let bytes = gpu::read_buffer(device, &queue, &vertex_buffer);
+        let deformed: &[Vertex] = bytemuck::cast_slice(&bytes);
+        assert_eq!(deformed.len(), base.len());
+        let mut moved = 0;
+        for (before, after) in base.iter().zip(deformed) {
+            let expected = reference(before, time);
+            assert!(Vec3::from(after.position).abs_diff_eq(expected, 1e-4), "{:?} != {}", after.position, expected);
+            // only the positions are written
+            assert_eq!(after.color, before.color);
+            assert_eq!(after.normal, before.normal);
+            if !Vec3::from(after.position).abs_diff_eq(Vec3::from(before.position), 1e-3) {
+                moved += 1;
+            }
+        }
+        // and the wave really moved them, a dispatch that did nothing would match a reference at sin() = 0 too
+        assert!(moved > base.len() / 2, "only {} of {} vertices moved", moved, base.len());
+    }
+}
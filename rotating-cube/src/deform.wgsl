@@ -0,0 +1,39 @@
+// Deform compute shader: pushes every vertex in and out along its normal with a travelling sine wave
+// vertices are read as plain floats because a WGSL struct of vec3s would be padded to 16 bytes per field,
+// while Vertex in cube.rs is 9 tightly packed floats (position, color, normal)
+struct Params {
+    time: f32,      // seconds, moves the wave
+    amplitude: f32, // how far a vertex can move along its normal
+    speed: f32,     // radians per second
+    frequency: f32, // how quickly the phase changes across the mesh
+};
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var<storage, read> base: array<f32>;           // undeformed vertices, never written
+@group(0) @binding(2)
+var<storage, read_write> deformed: array<f32>; // the vertex buffer the render pass draws from
+
+const FLOATS_PER_VERTEX: u32 = 9u;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    // the last workgroup can run past the end of the mesh
+    let index = id.x;
+    if (index >= arrayLength(&base) / FLOATS_PER_VERTEX) {
+        return;
+    }
+    let o = index * FLOATS_PER_VERTEX;
+    let position = vec3<f32>(base[o], base[o + 1u], base[o + 2u]);
+    let normal = vec3<f32>(base[o + 6u], base[o + 7u], base[o + 8u]);
+
+    // phase depends on where the vertex is, so the wave runs diagonally across the cube instead of pulsing all at once
+    let wave = sin(params.time * params.speed + dot(position, vec3<f32>(1.0)) * params.frequency);
+    let displaced = position + normal * params.amplitude * wave;
+
+    // only the position changes, color and normal were copied in when the buffer was created
+    deformed[o] = displaced.x;
+    deformed[o + 1u] = displaced.y;
+    deformed[o + 2u] = displaced.z;
+}
@@ -0,0 +1,133 @@
+// depth buffer so overlapping cubes hide each other correctly no matter which one is drawn first
+// back-face culling alone is only enough for a single convex mesh
+
+// depth only, what every depth buffer starts out as and what the shadow map always is
+// a float format, which --reverse-z needs to gain anything (see below)
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// the selection outline (X key) needs 8 bits of stencil next to the depth: the cube marks the pixels it covers there so
+// the outline can be drawn only around it, see STENCIL_CUBE
+// a 32-bit float with stencil is an optional feature, gpu::Context asks for it wherever the adapter has it and then
+// every depth buffer uses it from the start. Without it the only stencil format is Depth24PlusStencil8, which may
+// store the depth as 24-bit integers, so the window's buffers only switch to it once the outline is first shown
+pub fn format(features: wgpu::Features, stencil: bool) -> wgpu::TextureFormat {
+    if features.contains(wgpu::Features::DEPTH32FLOAT_STENCIL8) {
+        wgpu::TextureFormat::Depth32FloatStencil8
+    } else if stencil {
+        wgpu::TextureFormat::Depth24PlusStencil8
+    } else {
+        DEPTH_FORMAT
+    }
+}
+
+// stencil value the cube's pipelines write wherever they draw, the buffer is cleared to 0 everywhere else
+pub const STENCIL_CUBE: u32 = 1;
+
+// --reverse-z: the projection maps the near plane to depth 1 and infinitely far away to 0, instead of near 0 / far 1
+// floats are densest close to 0, a regular projection spends that precision right in front of the camera and runs
+// out of it in the distance (where things z-fight), reversed it is spread much more evenly over distance
+// this needs a float depth format to pay off, which all of them here are except the outline's fallback: "24 plus" lets
+// the driver pick, most desktop GPUs store it as a 32-bit float but some use 24-bit integers, where --reverse-z still
+// works but doesn't gain any precision
+
+// depth the buffer is cleared to, the value of "nothing drawn here yet, as far away as it gets"
+pub fn clear_value(reverse_z: bool) -> f32 {
+    if reverse_z {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// depth test that keeps the fragment closest to the camera
+pub fn closer(reverse_z: bool) -> wgpu::CompareFunction {
+    if reverse_z {
+        wgpu::CompareFunction::GreaterEqual
+    } else {
+        wgpu::CompareFunction::Less
+    }
+}
+
+// for things drawn exactly on top of surfaces already in the depth buffer (the wireframe overlay's edges), which a
+// strict test would reject wherever they land at the very same depth
+pub fn closer_or_equal(reverse_z: bool) -> wgpu::CompareFunction {
+    if reverse_z {
+        wgpu::CompareFunction::GreaterEqual
+    } else {
+        wgpu::CompareFunction::LessEqual
+    }
+}
+
+pub struct DepthBuffer {
+    pub view: wgpu::TextureView,       // depth and stencil, what the render passes attach
+    pub depth_view: wgpu::TextureView, // only the depth half, a shader can't read a combined depth-stencil view
+    pub format: wgpu::TextureFormat,   // the pipelines drawing into it have to be built for the same one
+}
+
+impl DepthBuffer {
+    // a pass's stencil ops for this buffer, None when it has no stencil: wgpu would still act on a store differing
+    // from the depth's, clearing a stencil half that isn't there
+    pub fn stencil_ops(&self, ops: wgpu::Operations<u32>) -> Option<wgpu::Operations<u32>> {
+        self.format.has_stencil_aspect().then_some(ops)
+    }
+}
+
+// a depth texture has to match the size of the color target it is used with, so recreate it on resize
+pub fn create_depth_buffer(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat) -> DepthBuffer {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING, //TEXTURE_BINDING so the depth debug view can read it
+        view_formats: &[],
+    });
+    DepthBuffer {
+        view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        depth_view: texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        }),
+        format,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipelines::{DrawMode, PassKind, PipelineOptions, ShaderKind};
+
+    #[test]
+    fn float_depth_unless_only_the_outline_can_have_stencil() {
+        let with = wgpu::Features::DEPTH32FLOAT_STENCIL8;
+        let without = wgpu::Features::empty();
+        assert_eq!(format(with, false), wgpu::TextureFormat::Depth32FloatStencil8);
+        assert_eq!(format(with, true), wgpu::TextureFormat::Depth32FloatStencil8);
+        assert_eq!(format(without, false), wgpu::TextureFormat::Depth32Float);
+        assert_eq!(format(without, true), wgpu::TextureFormat::Depth24PlusStencil8);
+        assert!(format(without, true).has_stencil_aspect() && !format(without, false).has_stencil_aspect());
+    }
+
+    #[test]
+    fn pipelines_only_use_stencil_when_the_buffer_has_it() {
+        let outline = |depth_format| {
+            let options = PipelineOptions {
+                format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                depth_format,
+                reverse_z: true,
+                mode: DrawMode::Triangles,
+                kind: PassKind::Outline,
+            };
+            options.key(ShaderKind::Lit).depth_stencil().unwrap()
+        };
+        // a stencil test on a depth-only format would fail validation, the outline just has nothing to test against
+        let depth_only = outline(wgpu::TextureFormat::Depth32Float);
+        assert_eq!(depth_only.format, wgpu::TextureFormat::Depth32Float);
+        assert_eq!(depth_only.stencil, wgpu::StencilState::default());
+        let with_stencil = outline(wgpu::TextureFormat::Depth32FloatStencil8);
+        assert_eq!(with_stencil.format, wgpu::TextureFormat::Depth32FloatStencil8);
+        assert!(with_stencil.stencil.is_enabled());
+    }
+}
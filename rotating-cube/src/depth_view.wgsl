@@ -0,0 +1,43 @@
+// D key depth view: the depth buffer drawn as grayscale, close = white fading to black with distance
+// stored depth isn't linear in distance (perspective packs most of the range right in front of the camera), so it is
+// turned back into a view-space distance first, the same math as Camera::linear_depth()
+struct Params {
+    inv_proj: mat4x4<f32>, // inverse of the camera's projection, works for perspective, orthographic, reverse-Z and blends
+    near: f32,             // distance that shows as full white
+    falloff: f32,          // how quickly brightness drops with distance
+    clear_depth: f32,      // depth of pixels nothing was drawn to
+};
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var depth_texture: texture_2d<f32>; // the depth buffer, depth in the red channel
+@group(0) @binding(2)
+var depth_sampler: sampler; // plain non-filtering sampler, not a comparison one
+
+// one triangle big enough to cover the whole viewport, corners at (-1,-1), (3,-1) and (-1,3)
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// view-space distance in front of the camera for a depth buffer value
+// only the projection's z and w rows depend on depth, so x and y can be anything
+fn linear_depth(depth: f32) -> f32 {
+    let view = params.inv_proj * vec4<f32>(0.0, 0.0, depth, 1.0);
+    return -view.z / view.w;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // position is in framebuffer pixels, the same pixels the depth texture covers even with a letterboxed viewport
+    let uv = position.xy / vec2<f32>(textureDimensions(depth_texture));
+    let depth = textureSample(depth_texture, depth_sampler, uv).r;
+    if (depth == params.clear_depth) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let brightness = exp(-max(linear_depth(depth) - params.near, 0.0) / params.falloff);
+    return vec4<f32>(vec3<f32>(brightness), 1.0);
+}
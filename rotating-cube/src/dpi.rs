@@ -0,0 +1,58 @@
+// winit has two kinds of sizes: logical (what the OS lays windows out in, the same on every screen) and physical
+// (real pixels), physical = logical * scale factor, so a 800x600 window is 1200x900 pixels at 150% scaling
+// the surface, depth buffer and HUD must always work in physical pixels, a surface at the logical size gets
+// stretched over the window by the compositor and looks blurry
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+// size of the HUD's bitmap font pixels in physical pixels: 2 at 100%, 3 at 150%, 4 at 200%
+// whole pixels keep the glyph edges sharp, rounding down to 0 would make the text disappear
+pub fn hud_scale(scale_factor: f64) -> f32 {
+    (2.0 * scale_factor).round().max(1.0) as f32
+}
+
+// one line for the log, e.g. "800x600 logical, 1200x900 physical, scale factor 1.5"
+pub fn describe(physical: PhysicalSize<u32>, scale_factor: f64) -> String {
+    let logical: LogicalSize<f64> = physical.to_logical(scale_factor);
+    format!(
+        "{}x{} logical, {}x{} physical, scale factor {}",
+        logical.width.round(),
+        logical.height.round(),
+        physical.width,
+        physical.height,
+        scale_factor
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_and_physical_sizes_convert_by_the_scale_factor() {
+        let logical = LogicalSize::new(800.0, 600.0);
+        for (scale_factor, width, height) in [(1.0, 800, 600), (1.25, 1000, 750), (1.5, 1200, 900), (2.0, 1600, 1200)] {
+            let physical: PhysicalSize<u32> = logical.to_physical(scale_factor);
+            assert_eq!(physical, PhysicalSize::new(width, height), "scale factor {}", scale_factor);
+            // and back again to the same logical size
+            let back: LogicalSize<f64> = physical.to_logical(scale_factor);
+            assert_eq!(back, logical, "scale factor {}", scale_factor);
+        }
+    }
+
+    #[test]
+    fn describe_shows_both_sizes() {
+        assert_eq!(describe(PhysicalSize::new(1200, 900), 1.5), "800x600 logical, 1200x900 physical, scale factor 1.5");
+        assert_eq!(describe(PhysicalSize::new(800, 600), 1.0), "800x600 logical, 800x600 physical, scale factor 1");
+        // a physical size that doesn't divide evenly rounds to the nearest logical pixel
+        assert_eq!(describe(PhysicalSize::new(1001, 751), 1.25), "801x601 logical, 1001x751 physical, scale factor 1.25");
+    }
+
+    #[test]
+    fn hud_pixels_are_whole_and_never_zero() {
+        assert_eq!(hud_scale(1.0), 2.0);
+        assert_eq!(hud_scale(1.25), 3.0);
+        assert_eq!(hud_scale(1.5), 3.0);
+        assert_eq!(hud_scale(2.0), 4.0);
+        assert_eq!(hud_scale(0.2), 1.0);
+    }
+}
@@ -0,0 +1,225 @@
+// easing curves map linear progress t (0..1) to eased progress, every curve starts at 0 and ends at 1
+// only the "in" version of each curve is written out, "out" is the same curve played backwards and upside down,
+// "in-out" runs the in curve over the first half and the out curve over the second
+// a Tween uses one to move a value from A to B over a fixed time (FOV zoom, Home reset, camera presets)
+use crate::animation::Keyable;
+
+#[allow(dead_code)] // the full set is kept for experimenting, only a few are used by the key bindings
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn, // pulls back below 0 before setting off
+    BackOut, // overshoots past 1 and settles back
+    BackInOut,
+    ElasticIn, // wobbles around 0 with growing swings before snapping to 1
+    ElasticOut, // springs past 1 and wobbles down onto it
+    ElasticInOut,
+}
+
+impl Easing {
+    // eased progress for t, which is clamped to 0..1 first
+    // back and elastic leave 0..1 in between, every other curve only ever increases
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => quad(t),
+            Easing::QuadOut => out(quad, t),
+            Easing::QuadInOut => in_out(quad, t),
+            Easing::CubicIn => cubic(t),
+            Easing::CubicOut => out(cubic, t),
+            Easing::CubicInOut => in_out(cubic, t),
+            Easing::ExpoIn => expo(t),
+            Easing::ExpoOut => out(expo, t),
+            Easing::ExpoInOut => in_out(expo, t),
+            Easing::BackIn => back(t),
+            Easing::BackOut => out(back, t),
+            Easing::BackInOut => in_out(back, t),
+            Easing::ElasticIn => elastic(t),
+            Easing::ElasticOut => out(elastic, t),
+            Easing::ElasticInOut => in_out(elastic, t),
+        }
+    }
+}
+
+fn quad(t: f32) -> f32 {
+    t * t
+}
+
+fn cubic(t: f32) -> f32 {
+    t * t * t
+}
+
+// 2^(10(t-1)) is 1/1024 at t = 0 rather than 0, so the start is pinned explicitly
+fn expo(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else {
+        2f32.powf(10.0 * (t - 1.0))
+    }
+}
+
+// the usual constant, makes the curve dip about 10% below 0
+fn back(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.70158;
+    t * t * ((OVERSHOOT + 1.0) * t - OVERSHOOT)
+}
+
+// a sine wave with a period of 0.3 inside an exponentially growing envelope
+// the ends are pinned since sin() doesn't land on exactly 0 at them
+fn elastic(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    let period = 0.3;
+    -2f32.powf(10.0 * (t - 1.0)) * ((t - 1.0 - period / 4.0) * std::f32::consts::TAU / period).sin()
+}
+
+// mirror an in curve so it starts fast and slows down at the end
+fn out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    1.0 - ease_in(1.0 - t)
+}
+
+// in curve squashed into the first half, its mirror into the second, they meet at (0.5, 0.5)
+fn in_out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    if t < 0.5 {
+        ease_in(2.0 * t) / 2.0
+    } else {
+        1.0 - ease_in(2.0 - 2.0 * t) / 2.0
+    }
+}
+
+// moves a value from `from` to `to` over `duration` seconds, advanced by the fixed simulation step
+pub struct Tween<T: Keyable> {
+    from: T,
+    to: T,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl<T: Keyable> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            elapsed: 0.0,
+            easing,
+        }
+    }
+
+    // move forward by dt seconds and return the value there
+    pub fn advance(&mut self, dt: f32) -> T {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.value()
+    }
+
+    // a zero duration jumps straight to the end
+    pub fn value(&self) -> T {
+        let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        T::lerp(self.from, self.to, self.easing.apply(t))
+    }
+
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 16] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::ExpoIn,
+        Easing::ExpoOut,
+        Easing::ExpoInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+    ];
+
+    fn overshoots(easing: Easing) -> bool {
+        matches!(
+            easing,
+            Easing::BackIn | Easing::BackOut | Easing::BackInOut | Easing::ElasticIn | Easing::ElasticOut | Easing::ElasticInOut
+        )
+    }
+
+    #[test]
+    fn every_curve_starts_at_0_and_ends_at_1() {
+        for easing in ALL {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?} at 0 is {}", easing, easing.apply(0.0));
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?} at 1 is {}", easing, easing.apply(1.0));
+            // t outside 0..1 is clamped onto the ends
+            assert_eq!(easing.apply(-2.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(3.0), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn curves_without_overshoot_only_increase_and_stay_in_0_to_1() {
+        for easing in ALL.into_iter().filter(|&easing| !overshoots(easing)) {
+            let mut previous = easing.apply(0.0);
+            for i in 1..=1000 {
+                let value = easing.apply(i as f32 / 1000.0);
+                assert!(value >= previous, "{:?} goes down at t = {}", easing, i as f32 / 1000.0);
+                assert!((0.0..=1.0).contains(&value), "{:?} leaves 0..1 with {}", easing, value);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn in_out_curves_meet_in_the_middle() {
+        for easing in [Easing::QuadInOut, Easing::CubicInOut, Easing::ExpoInOut, Easing::BackInOut, Easing::ElasticInOut] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-6, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn back_and_elastic_do_leave_0_to_1() {
+        let samples = |easing: Easing| (1..100).map(move |i| easing.apply(i as f32 / 100.0));
+        assert!(samples(Easing::BackIn).any(|value| value < 0.0));
+        assert!(samples(Easing::BackOut).any(|value| value > 1.0));
+        assert!(samples(Easing::ElasticOut).any(|value| value > 1.0));
+    }
+
+    #[test]
+    fn tween_reaches_its_target_and_stays_there() {
+        let mut tween = Tween::new(10.0, 20.0, 0.5, Easing::QuadOut);
+        assert_eq!(tween.value(), 10.0);
+        assert!(!tween.is_finished());
+        for _ in 0..60 {
+            tween.advance(1.0 / 60.0);
+        }
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 20.0);
+
+        let instant = Tween::new(1.0, 2.0, 0.0, Easing::Linear);
+        assert_eq!(instant.value(), 2.0);
+    }
+}
@@ -0,0 +1,45 @@
+// procedural color effects cycled with E: the lit shader throws the vertex colors away and works a pattern out per
+// pixel from the time and where on the cube the pixel is (in the cube's own space, so the pattern turns with it)
+// the shader picks the pattern with a switch on GlobalsUniform::effect, this only decides which one that is
+// the unlit shader (U) keeps showing the vertex colors, like it ignores the lights
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Effect {
+    #[default]
+    Off,     // the vertex or hue colors as before
+    Plasma,  // flowing bands of color from sines of the position and time
+    Stripes, // diagonal stripes scrolling across the faces
+    Pulse,   // rings spreading out from the corner nearest the scene file's first light
+}
+
+impl Effect {
+    // order the E key cycles through, back to Off after the last one
+    pub fn next(self) -> Self {
+        match self {
+            Effect::Off => Effect::Plasma,
+            Effect::Plasma => Effect::Stripes,
+            Effect::Stripes => Effect::Pulse,
+            Effect::Pulse => Effect::Off,
+        }
+    }
+
+    // what shader.wgsl's switch in effect_color() goes by
+    pub fn index(self) -> u32 {
+        match self {
+            Effect::Off => 0,
+            Effect::Plasma => 1,
+            Effect::Stripes => 2,
+            Effect::Pulse => 3,
+        }
+    }
+
+    // HUD name, uppercase since the font only has capitals
+    pub fn label(self) -> &'static str {
+        match self {
+            Effect::Off => "OFF",
+            Effect::Plasma => "PLASMA",
+            Effect::Stripes => "STRIPES",
+            Effect::Pulse => "PULSE",
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// everything that can stop the renderer short of a panic, each message says what went wrong in terms of the
+// machine and the options rather than wgpu's, and what to try next where there is something
+// main.rs prints it and exits with a failure code instead of panicking
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::gpu::ScopeError;
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("No GPU adapter can render to this window, try --backend gl (--list-adapters shows what there is)")]
+    NoAdapter,
+    #[error("No adapter matching '{0}' can render to this window, see --list-adapters")]
+    NoMatchingAdapter(String),
+    #[error("The GPU adapter couldn't create a device, its driver may be too old: {0}")]
+    DeviceRequest(#[from] wgpu::RequestDeviceError),
+    #[error("Couldn't create a surface to draw into the window: {0}")]
+    SurfaceCreation(#[from] wgpu::CreateSurfaceError),
+    #[error("Couldn't get the next frame from the window's swapchain: {0}")]
+    SurfaceAcquire(#[from] wgpu::SurfaceError),
+    // the pipelines are built for one format, every window (and a replacement adapter) has to take it
+    #[error("The adapter can't draw to every window in the {0:?} format, try --windows 1 or another --adapter")]
+    UnsupportedFormat(wgpu::TextureFormat),
+    // a resource created inside gpu::scoped() didn't pass validation, most likely a bug rather than the machine
+    #[error("GPU validation error, {0}")]
+    Validation(#[from] ScopeError),
+    // more than one buffer can hold on this adapter, e.g. wgpu-test's --triangles
+    #[error("{what} would need a {} MB buffer, this device allows at most {} MB", .size >> 20, .max >> 20)]
+    BufferTooLarge { what: String, size: u64, max: u64 },
+    #[error("Couldn't open a window: {0}")]
+    Window(#[from] winit::error::OsError),
+    #[error("Couldn't write {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Couldn't encode the recording: {0}")]
+    Encoding(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_name_the_options_to_try() {
+        assert_eq!(
+            RenderError::NoAdapter.to_string(),
+            "No GPU adapter can render to this window, try --backend gl (--list-adapters shows what there is)"
+        );
+        assert_eq!(
+            RenderError::NoMatchingAdapter("radeon".to_string()).to_string(),
+            "No adapter matching 'radeon' can render to this window, see --list-adapters"
+        );
+        assert_eq!(
+            RenderError::UnsupportedFormat(wgpu::TextureFormat::Bgra8UnormSrgb).to_string(),
+            "The adapter can't draw to every window in the Bgra8UnormSrgb format, try --windows 1 or another --adapter"
+        );
+        assert_eq!(RenderError::Encoding("disk full".to_string()).to_string(), "Couldn't encode the recording: disk full");
+        let too_large = RenderError::BufferTooLarge { what: "1000000000 triangles".to_string(), size: 3 << 30, max: 256 << 20 };
+        assert_eq!(too_large.to_string(), "1000000000 triangles would need a 3072 MB buffer, this device allows at most 256 MB");
+    }
+
+    #[test]
+    fn wrapped_errors_keep_their_own_message() {
+        let io = RenderError::Io {
+            path: PathBuf::from("out/frames.csv"),
+            source: std::io::Error::new(std::io::ErrorKind::PermissionDenied, "permission denied"),
+        };
+        assert_eq!(io.to_string(), "Couldn't write out/frames.csv: permission denied");
+
+        // From lets `?` turn wgpu's error into ours, the message is ours followed by wgpu's
+        let acquire: RenderError = wgpu::SurfaceError::Timeout.into();
+        let message = acquire.to_string();
+        assert!(message.starts_with("Couldn't get the next frame from the window's swapchain: "), "{}", message);
+        assert!(message.ends_with(&wgpu::SurfaceError::Timeout.to_string()), "{}", message);
+    }
+
+    #[test]
+    fn validation_errors_name_what_was_being_created() {
+        let scope = ScopeError {
+            label: "Depth Buffer".to_string(),
+            error: wgpu::Error::OutOfMemory { source: Box::new(std::io::Error::other("too big")) },
+        };
+        let message = RenderError::from(scope).to_string();
+        assert!(message.starts_with("GPU validation error, creating Depth Buffer failed: "), "{}", message);
+    }
+}
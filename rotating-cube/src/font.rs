@@ -0,0 +1,198 @@
+// tiny 5x7 bitmap font used by the HUD, built into a single-channel texture atlas at startup
+// each glyph is 7 rows of 5 bits, the highest of the 5 bits is the leftmost pixel
+// lowercase letters are drawn with the uppercase glyphs, unknown characters show as '?'
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+
+// every glyph sits in a cell one pixel larger than itself so neighbouring glyphs never bleed into each other when sampled
+pub const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+// glyphs per atlas row
+const ATLAS_COLUMNS: u32 = 16;
+
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; 7])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('|', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    // fully lit cell, used for the HUD's translucent background panel
+    ('\u{2588}', [0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111]),
+];
+
+// character drawn as a solid block, sampling it gives full coverage
+pub const SOLID: char = '\u{2588}';
+
+// R8 pixel data for all glyphs plus where each glyph ended up
+pub struct FontAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>, // one byte per pixel, 255 = glyph pixel, 0 = empty
+}
+
+impl FontAtlas {
+    // rasterize every glyph into its cell of the atlas
+    pub fn build() -> Self {
+        let rows = (GLYPHS.len() as u32).div_ceil(ATLAS_COLUMNS);
+        let width = ATLAS_COLUMNS * CELL_WIDTH;
+        let height = rows * CELL_HEIGHT;
+        let mut pixels = vec![0u8; (width * height) as usize];
+
+        for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+            let (cell_x, cell_y) = cell_origin(index);
+            for (y, bits) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        let px = cell_x + x;
+                        let py = cell_y + y as u32;
+                        pixels[(py * width + px) as usize] = 255;
+                    }
+                }
+            }
+        }
+
+        Self { width, height, pixels }
+    }
+
+    // texture coordinates (u0, v0, u1, v1) of the glyph drawn for `c`, covering just the 5x7 glyph inside its cell
+    pub fn uv_rect(&self, c: char) -> [f32; 4] {
+        let (x, y) = cell_origin(glyph_index(c));
+        [
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            (x + GLYPH_WIDTH) as f32 / self.width as f32,
+            (y + GLYPH_HEIGHT) as f32 / self.height as f32,
+        ]
+    }
+}
+
+// position in the table of the glyph that should be drawn for `c`
+pub fn glyph_index(c: char) -> usize {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .position(|(glyph, _)| *glyph == c)
+        .unwrap_or_else(|| GLYPHS.iter().position(|(glyph, _)| *glyph == '?').unwrap())
+}
+
+// top-left pixel of a glyph's cell in the atlas
+fn cell_origin(index: usize) -> (u32, u32) {
+    let index = index as u32;
+    ((index % ATLAS_COLUMNS) * CELL_WIDTH, (index / ATLAS_COLUMNS) * CELL_HEIGHT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the atlas pixel `dx`, `dy` texels right of and below texture coordinates (u, v)
+    fn texel(atlas: &FontAtlas, u: f32, v: f32, dx: u32, dy: u32) -> u8 {
+        let x = (u * atlas.width as f32).round() as u32 + dx;
+        let y = (v * atlas.height as f32).round() as u32 + dy;
+        atlas.pixels[(y * atlas.width + x) as usize]
+    }
+
+    #[test]
+    fn atlas_holds_every_glyph_in_whole_cells() {
+        let atlas = FontAtlas::build();
+        assert_eq!(atlas.width, ATLAS_COLUMNS * CELL_WIDTH);
+        assert_eq!(atlas.height % CELL_HEIGHT, 0);
+        assert!(atlas.height / CELL_HEIGHT * ATLAS_COLUMNS >= GLYPHS.len() as u32);
+        assert_eq!(atlas.pixels.len(), (atlas.width * atlas.height) as usize);
+    }
+
+    #[test]
+    fn uv_rects_cover_exactly_their_glyph() {
+        let atlas = FontAtlas::build();
+        for (c, rows) in GLYPHS {
+            let [u0, v0, u1, v1] = atlas.uv_rect(*c);
+            assert!(0.0 <= u0 && u0 < u1 && u1 <= 1.0 && 0.0 <= v0 && v0 < v1 && v1 <= 1.0, "{:?}", c);
+            // 5x7 texels, not the padded cell
+            assert_eq!(((u1 - u0) * atlas.width as f32).round() as u32, GLYPH_WIDTH);
+            assert_eq!(((v1 - v0) * atlas.height as f32).round() as u32, GLYPH_HEIGHT);
+            for (y, bits) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    let lit = bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0;
+                    assert_eq!(texel(&atlas, u0, v0, x, y as u32) == 255, lit, "{:?} at {},{}", c, x, y);
+                }
+            }
+            // the padding column and row stay empty so linear sampling at the edge never picks up a neighbour
+            for y in 0..CELL_HEIGHT {
+                assert_eq!(texel(&atlas, u0, v0, GLYPH_WIDTH, y), 0, "{:?}", c);
+            }
+            for x in 0..CELL_WIDTH {
+                assert_eq!(texel(&atlas, u0, v0, x, GLYPH_HEIGHT), 0, "{:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn every_glyph_has_its_own_rect() {
+        let atlas = FontAtlas::build();
+        for (i, (a, _)) in GLYPHS.iter().enumerate() {
+            for (b, _) in &GLYPHS[i + 1..] {
+                assert_ne!(atlas.uv_rect(*a), atlas.uv_rect(*b), "{:?} and {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn lowercase_and_unknown_characters_fall_back() {
+        let atlas = FontAtlas::build();
+        assert_eq!(atlas.uv_rect('a'), atlas.uv_rect('A'));
+        assert_eq!(atlas.uv_rect('z'), atlas.uv_rect('Z'));
+        assert_eq!(atlas.uv_rect('~'), atlas.uv_rect('?'));
+        assert_eq!(atlas.uv_rect('é'), atlas.uv_rect('?'));
+        assert_eq!(glyph_index(' '), 0);
+    }
+}
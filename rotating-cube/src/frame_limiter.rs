@@ -0,0 +1,73 @@
+// caps the frame rate when the present mode doesn't block on vsync (Mailbox/Immediate),
+// otherwise ControlFlow::Poll renders as fast as possible and pins a whole CPU core
+use std::time::Duration;
+
+use instant::Instant; // the type winit's WaitUntil takes, std::time::Instant everywhere but the browser
+
+// thread::sleep and ControlFlow::WaitUntil can oversleep by a millisecond or more depending on the OS timer,
+// so we only sleep until this long before the deadline and busy-wait (spin) for the rest
+pub const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+pub struct FrameLimiter {
+    frame_duration: Option<Duration>, // target time per frame, None when no --max-fps was given
+    enabled: bool,                    // runtime switch (L key)
+    next_frame: Instant,              // deadline for the next frame to start
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<u32>) -> Self {
+        Self {
+            frame_duration: max_fps.map(|fps| Duration::from_secs_f64(1.0 / fps as f64)),
+            enabled: max_fps.is_some(),
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.frame_duration.is_some()
+    }
+
+    // flip the limiter on/off, returns the new state so it can be logged
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.next_frame = Instant::now();
+        self.is_active()
+    }
+
+    // when the next frame is due
+    pub fn deadline(&self) -> Instant {
+        self.next_frame
+    }
+
+    // true if the deadline is far enough away that the event loop should sleep via ControlFlow::WaitUntil
+    pub fn should_wait(&self, now: Instant) -> bool {
+        self.is_active() && now + SPIN_MARGIN < self.next_frame
+    }
+
+    // final precise wait right before rendering: coarse sleep for the bulk, then spin until the deadline
+    pub fn sleep_until_deadline(&self) {
+        if !self.is_active() {
+            return;
+        }
+        sleep_until(self.next_frame);
+    }
+
+    // schedule the next deadline one frame after the current one,
+    // if we've fallen behind (slow frame) restart from now instead of rendering a burst of frames to catch up
+    pub fn frame_started(&mut self, now: Instant) {
+        if let Some(frame_duration) = self.frame_duration {
+            self.next_frame = (self.next_frame + frame_duration).max(now);
+        }
+    }
+}
+
+// hybrid sleep: thread::sleep is cheap but imprecise, spinning is precise but burns CPU, so use both
+pub fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN_MARGIN {
+        std::thread::sleep(deadline - now - SPIN_MARGIN);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
@@ -0,0 +1,56 @@
+// --log-frames: one CSV row per frame with how far the cube has spun and how long the frame took, for plotting
+// a steady spin shows up as rotation_rad climbing in a straight line against time_ms whatever the fps does, which is
+// what stepping the simulation by real time is for
+//
+// the rows go through a BufWriter and are only flushed about once a second, a write per frame would be the kind of
+// I/O the program is trying to measure; finish() flushes the rest and closes the file when the program ends
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::RenderError;
+
+// milliseconds of frames collected before they are flushed to the file, so a crash loses at most about this much
+const FLUSH_INTERVAL_MS: f64 = 1000.0;
+
+pub struct FrameLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    frames: u64,      // rows written so far, the next row's frame_index
+    time_ms: f64,     // real time from the start of the first frame to the start of the latest one
+    flushed_ms: f64,  // time_ms when the rows were last flushed
+}
+
+impl FrameLog {
+    // an existing file is overwritten, starting with the header row
+    pub fn create(path: &Path) -> Result<Self, RenderError> {
+        let io_error = |source| RenderError::Io { path: path.to_path_buf(), source };
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        writeln!(writer, "frame_index,time_ms,rotation_rad,fps").map_err(io_error)?;
+        Ok(Self { path: path.to_path_buf(), writer, frames: 0, time_ms: 0.0, flushed_ms: 0.0 })
+    }
+
+    // one frame: `frame_time` is the real seconds since the previous frame started, `rotation` the radians spun so far
+    // fps is this frame's alone (1 / frame_time), not the HUD's half-second average, so single slow frames stand out
+    pub fn record(&mut self, frame_time: f32, rotation: f32) -> Result<(), RenderError> {
+        self.time_ms += frame_time as f64 * 1000.0;
+        let fps = if frame_time > 0.0 { 1.0 / frame_time } else { 0.0 };
+        writeln!(self.writer, "{},{:.3},{:.6},{:.2}", self.frames, self.time_ms, rotation, fps).map_err(|source| self.error(source))?;
+        self.frames += 1;
+
+        if self.time_ms - self.flushed_ms >= FLUSH_INTERVAL_MS {
+            self.writer.flush().map_err(|source| self.error(source))?;
+            self.flushed_ms = self.time_ms;
+        }
+        Ok(())
+    }
+
+    // write out what is still buffered and close the file, dropping the log would do both too but swallow an error
+    pub fn finish(mut self) -> Result<(), RenderError> {
+        self.writer.flush().map_err(|source| self.error(source))
+    }
+
+    fn error(&self, source: std::io::Error) -> RenderError {
+        RenderError::Io { path: self.path.clone(), source }
+    }
+}
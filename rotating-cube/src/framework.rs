@@ -0,0 +1,254 @@
+// the event loop every demo here needs, written once: winit's events go to an App, which only says what to do with
+// them, and run_app() does the rest, the fixed-timestep updates, drawing once per pass of the loop and exiting
+// a frame is drawn from RedrawRequested, which each pass of the loop asks for, except while a window is being
+// resized: Windows runs its own loop for as long as the border is dragged and only hands out a RedrawRequested when it
+// feels like it, so then the frame is drawn straight from MainEventsCleared, right after the Resized that caused it
+// a new demo implements App (input, resize, update, render) and hands it to run_app(), see examples/triangle.rs
+// the cube (main.rs) uses the optional methods as well, for its frame limiter, recording and benchmark, and wgpu-test
+// next to this crate is an App too, through a path dependency on it
+use instant::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceEvent, Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowId;
+
+use crate::error::RenderError;
+use crate::timestep::{FixedTimestep, FIXED_DT};
+
+pub trait App {
+    // every event of one of the app's windows, also the ones run_app() handles itself (close and resize)
+    fn input(&mut self, window: WindowId, event: &WindowEvent) -> Result<(), RenderError>;
+
+    // the window's new size in physical pixels, 0x0 while it is minimized
+    fn resize(&mut self, window: WindowId, size: PhysicalSize<u32>) -> Result<(), RenderError>;
+
+    // one simulation step, always FIXED_DT seconds long
+    fn update(&mut self, dt: f32);
+
+    // draw a frame, `alpha` (0..1) is how far real time has got from the last update towards the next one
+    fn render(&mut self, alpha: f32) -> Result<(), RenderError>;
+
+    // ask winit for a RedrawRequested, the frame is drawn when it comes
+    // an app with several windows asks for all of them (a minimized one may never get it), the first to come draws
+    fn request_redraw(&self);
+
+    // ----- the rest have defaults a simple app doesn't need to change -----
+
+    // a window's close button was pressed, returns true to end the program, which the default does straight away
+    fn close(&mut self, _window: WindowId) -> bool {
+        true
+    }
+
+    // input that isn't tied to a window, like the mouse's raw movement
+    fn device_event(&mut self, _event: &DeviceEvent) {}
+
+    // the OS took the windows' surfaces away (Android, iOS), and gave them back
+    fn suspend(&mut self) {}
+
+    fn resume(&mut self) -> Result<(), RenderError> {
+        Ok(())
+    }
+
+    // asked before every frame: Some(time) to sleep until then and be asked again, None to draw the frame now
+    fn wait_until(&mut self) -> Option<Instant> {
+        None
+    }
+
+    // a frame starts, `real_time` seconds after the previous one started
+    // returns the seconds the simulation advances this frame, which needn't be the real time (e.g. while recording)
+    fn begin_frame(&mut self, real_time: f32) -> f32 {
+        real_time
+    }
+
+    // the frame is drawn, `cpu_time` is how long that took since begin_frame()
+    // Some(result) ends the program after this frame, with a failure code if the result is an error
+    fn end_frame(&mut self, _cpu_time: Duration) -> Option<Result<(), RenderError>> {
+        None
+    }
+
+    // the event loop is ending, however that came about (a closed window, end_frame() or an error), the last chance
+    // to finish files before the process exits, which winit doesn't promise to drop the app for
+    fn exit(&mut self) {}
+}
+
+// runs until the app asks to exit or fails, never returns on the desktop since winit's loop exits the process
+pub fn run_app<A: App + 'static>(event_loop: EventLoop<()>, mut app: A) {
+    // real time is measured between frames and fed to the fixed-timestep accumulator
+    let mut timestep = FixedTimestep::new(FIXED_DT);
+    let mut last_frame = Instant::now();
+
+    // MainEventsCleared asked for a redraw and no RedrawRequested has drawn it yet
+    let mut redraw_pending = false;
+    // a window was resized since the last frame, its surface is already configured for the new size
+    let mut resizing = false;
+
+    // control_flow starts as Poll and is only changed below, resetting it on every event would undo WaitUntil
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent { window_id, event } => {
+                if let Err(err) = app.input(window_id, &event) {
+                    *control_flow = exit_code(err);
+                    return;
+                }
+                match event {
+                    WindowEvent::CloseRequested if app.close(window_id) => *control_flow = ControlFlow::Exit,
+                    // the surface is reconfigured here and now, so the next frame never presents the old size stretched
+                    // over the new one
+                    WindowEvent::Resized(size) => {
+                        if let Err(err) = app.resize(window_id, size) {
+                            *control_flow = exit_code(err);
+                        }
+                        resizing = true;
+                    }
+                    _ => {}
+                }
+            }
+            Event::DeviceEvent { event, .. } => app.device_event(&event),
+            Event::Suspended => app.suspend(),
+            Event::Resumed => {
+                if let Err(err) = app.resume() {
+                    *control_flow = exit_code(err);
+                }
+            }
+            Event::MainEventsCleared => {
+                // mid-drag every size gets a frame of its own straight away, the frame limiter doesn't hold it back
+                // and a RedrawRequested isn't waited for, so the cube keeps turning and never shows a black or
+                // stretched window
+                if resizing {
+                    resizing = false;
+                    *control_flow = ControlFlow::Poll;
+                    frame(&mut app, &mut timestep, &mut last_frame, control_flow);
+                    return;
+                }
+                // not time for a frame yet: let the event loop sleep instead of busy polling
+                if let Some(time) = app.wait_until() {
+                    *control_flow = ControlFlow::WaitUntil(time);
+                    return;
+                }
+                *control_flow = ControlFlow::Poll;
+                redraw_pending = true;
+                app.request_redraw();
+            }
+            // the OS asks for redraws of its own too (a window uncovered), those are left to the next frame
+            Event::RedrawRequested(_) if redraw_pending => {
+                redraw_pending = false;
+                frame(&mut app, &mut timestep, &mut last_frame, control_flow);
+            }
+            Event::LoopDestroyed => app.exit(),
+            _ => {}
+        }
+    });
+}
+
+// one frame: the fixed steps real time has covered since the last one, then the drawing in-between the last two
+fn frame<A: App>(app: &mut A, timestep: &mut FixedTimestep, last_frame: &mut Instant, control_flow: &mut ControlFlow) {
+    let now = Instant::now();
+    let frame_time = app.begin_frame((now - *last_frame).as_secs_f32());
+    *last_frame = now;
+
+    for _ in 0..timestep.advance(frame_time) {
+        app.update(timestep.dt());
+    }
+    if let Err(err) = app.render(timestep.alpha()) {
+        *control_flow = exit_code(err);
+        return;
+    }
+
+    match app.end_frame(now.elapsed()) {
+        Some(Ok(())) => *control_flow = ControlFlow::Exit,
+        Some(Err(err)) => *control_flow = exit_code(err),
+        None => {}
+    }
+}
+
+// print why the app can't go on, the event loop exits with a failure code after this event
+fn exit_code(err: RenderError) -> ControlFlow {
+    eprintln!("Error: {}", err);
+    ControlFlow::ExitWithCode(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the least an App has to be: the required methods and nothing else, counting what run_app() asks of it
+    // every frame pretends 3.5 fixed steps of time passed, so the counts don't depend on how fast the test runs
+    #[derive(Default)]
+    struct Counter {
+        updates: u32,
+        renders: Vec<f32>, // the alpha of each render
+        fail_render: bool,
+        done: bool,
+    }
+
+    impl App for Counter {
+        fn input(&mut self, _window: WindowId, _event: &WindowEvent) -> Result<(), RenderError> {
+            Ok(())
+        }
+
+        fn resize(&mut self, _window: WindowId, _size: PhysicalSize<u32>) -> Result<(), RenderError> {
+            Ok(())
+        }
+
+        fn update(&mut self, dt: f32) {
+            assert_eq!(dt, FIXED_DT);
+            self.updates += 1;
+        }
+
+        fn render(&mut self, alpha: f32) -> Result<(), RenderError> {
+            if self.fail_render {
+                return Err(RenderError::NoAdapter);
+            }
+            self.renders.push(alpha);
+            Ok(())
+        }
+
+        fn request_redraw(&self) {}
+
+        fn begin_frame(&mut self, _real_time: f32) -> f32 {
+            FIXED_DT * 3.5
+        }
+
+        fn end_frame(&mut self, _cpu_time: Duration) -> Option<Result<(), RenderError>> {
+            self.done.then_some(Ok(()))
+        }
+    }
+
+    fn run_frame(app: &mut Counter, timestep: &mut FixedTimestep) -> ControlFlow {
+        let mut control_flow = ControlFlow::Poll;
+        frame(app, timestep, &mut Instant::now(), &mut control_flow);
+        control_flow
+    }
+
+    #[test]
+    fn a_trivial_app_can_be_made_and_driven() {
+        let mut app = Counter::default();
+        // the defaults a simple app doesn't write itself, dummy() is a window id no real window has
+        assert!(app.close(unsafe { WindowId::dummy() }));
+        assert!(app.wait_until().is_none());
+        assert!(app.resume().is_ok());
+        app.exit();
+
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        assert_eq!(run_frame(&mut app, &mut timestep), ControlFlow::Poll);
+        // 3 whole steps, and the half left over is where the drawing sits between the last two
+        assert_eq!(app.updates, 3);
+        assert_eq!(app.renders.len(), 1);
+        assert!((app.renders[0] - 0.5).abs() < 1e-3, "{}", app.renders[0]);
+        // the next frame's half step completes a 4th
+        run_frame(&mut app, &mut timestep);
+        assert_eq!(app.updates, 7);
+        assert!(app.renders[1].abs() < 1e-3, "{}", app.renders[1]);
+    }
+
+    #[test]
+    fn end_frame_and_errors_end_the_loop() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        let mut finished = Counter { done: true, ..Default::default() };
+        assert_eq!(run_frame(&mut finished, &mut timestep), ControlFlow::Exit);
+        // a failed render exits with a failure code and never gets to end_frame()
+        let mut failing = Counter { fail_render: true, done: true, ..Default::default() };
+        assert_eq!(run_frame(&mut failing, &mut timestep), ControlFlow::ExitWithCode(1));
+        assert!(failing.renders.is_empty());
+    }
+}
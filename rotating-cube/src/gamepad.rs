@@ -0,0 +1,211 @@
+// gamepads, in a build with --features gamepad: gilrs reads every connected pad and its buttons and sticks are turned
+// into the keymap's Bindings (PadSouth, LeftStickUp, ...), so from there on they are handled like keys and both can
+// be used at the same time, see InputState::axis() and amount()
+// pads can be plugged in and out while the program runs, gilrs reports both and a pad that goes away lets go of
+// everything it was holding
+// the dead zone and response curve below don't need gilrs, the rest is only built with the feature
+use glam::Vec2;
+use winit::event::ElementState;
+
+use crate::input::{PadAxis, PadButton};
+#[cfg(feature = "gamepad")]
+use crate::input::{PAD_AXES, PAD_BUTTONS};
+
+// how a stick's or trigger's raw position becomes how far it counts as pushed, --gamepad-dead-zone and --gamepad-curve
+#[derive(Clone, Copy, Debug)]
+pub struct StickResponse {
+    // a stick at rest rarely reads exactly 0, everything up to this much of the way out counts as 0
+    pub dead_zone: f32,
+    // the rest of the way is raised to this power: above 1 gives fine control near the middle and still reaches
+    // full speed at the edge, 1 is linear
+    pub curve: f32,
+}
+
+impl Default for StickResponse {
+    fn default() -> Self {
+        Self { dead_zone: 0.15, curve: 2.0 }
+    }
+}
+
+impl StickResponse {
+    // one axis pushed `magnitude` (0..1) of the way, the result starts at 0 right at the dead zone's edge instead of
+    // jumping to dead_zone there, so small movements stay small
+    pub fn shape(&self, magnitude: f32) -> f32 {
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+        ((magnitude - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0).powf(self.curve)
+    }
+
+    // a stick's x and y together (-1..1 each), the dead zone is a circle around the middle rather than a cross along
+    // the two axes, so pushing diagonally isn't cut off near the middle and the direction is kept
+    pub fn shape_stick(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length();
+        if length <= self.dead_zone {
+            return Vec2::ZERO;
+        }
+        stick / length * self.shape(length)
+    }
+}
+
+// the four halves a stick is split into for the keymap, (left, right, up, down)
+pub fn split_stick(stick: Vec2) -> [f32; 4] {
+    [(-stick.x).max(0.0), stick.x.max(0.0), stick.y.max(0.0), (-stick.y).max(0.0)]
+}
+
+// what poll() reports
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadInput {
+    Button(PadButton, ElementState),
+    Axis(PadAxis, f32), // already through the dead zone and curve
+}
+
+#[cfg(feature = "gamepad")]
+pub struct Gamepads {
+    gilrs: gilrs::Gilrs,
+    response: StickResponse,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepads {
+    // None when the platform's gamepad API isn't there (e.g. no udev), the keyboard and mouse work regardless
+    pub fn new(response: StickResponse) -> Option<Self> {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(err) => {
+                tracing::warn!("Gamepads unavailable: {}", err);
+                return None;
+            }
+        };
+        for (_, pad) in gilrs.gamepads() {
+            tracing::info!("Gamepad connected: {}", pad.name());
+        }
+        Some(Self { gilrs, response })
+    }
+
+    // the button presses and releases since the last call, then where every axis is now
+    // the axes are read from gilrs' state rather than its events, a stick's x and y both count for the dead zone and
+    // more than one pad may be plugged in (the furthest pushed wins)
+    pub fn poll(&mut self) -> Vec<PadInput> {
+        let mut inputs = Vec::new();
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    inputs.extend(pad_button(button).map(|button| PadInput::Button(button, ElementState::Pressed)));
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    inputs.extend(pad_button(button).map(|button| PadInput::Button(button, ElementState::Released)));
+                }
+                gilrs::EventType::Connected => tracing::info!("Gamepad connected: {}", self.gilrs.gamepad(id).name()),
+                // its buttons will never report being released, let go of all of them now
+                gilrs::EventType::Disconnected => {
+                    tracing::info!("Gamepad disconnected: {}", self.gilrs.gamepad(id).name());
+                    inputs.extend(PAD_BUTTONS.iter().map(|button| PadInput::Button(*button, ElementState::Released)));
+                }
+                _ => {}
+            }
+        }
+
+        let mut axes = [0.0f32; PAD_AXES.len()];
+        for (_, pad) in self.gilrs.gamepads() {
+            let stick = |x, y| self.response.shape_stick(Vec2::new(pad.value(x), pad.value(y)));
+            let left = split_stick(stick(gilrs::Axis::LeftStickX, gilrs::Axis::LeftStickY));
+            let right = split_stick(stick(gilrs::Axis::RightStickX, gilrs::Axis::RightStickY));
+            // the triggers are analog buttons in gilrs, with the pressure as their value
+            let trigger = |button| self.response.shape(pad.button_data(button).map_or(0.0, |data| data.value()));
+            let values = left.into_iter().chain(right).chain([trigger(gilrs::Button::LeftTrigger2), trigger(gilrs::Button::RightTrigger2)]);
+            for (axis, value) in axes.iter_mut().zip(values) {
+                *axis = axis.max(value);
+            }
+        }
+        inputs.extend(PAD_AXES.iter().zip(axes).map(|(axis, value)| PadInput::Axis(*axis, value)));
+        inputs
+    }
+}
+
+// gilrs' names for the buttons there are bindings for, the triggers are read as axes instead
+#[cfg(feature = "gamepad")]
+fn pad_button(button: gilrs::Button) -> Option<PadButton> {
+    use gilrs::Button;
+    Some(match button {
+        Button::South => PadButton::South,
+        Button::East => PadButton::East,
+        Button::West => PadButton::West,
+        Button::North => PadButton::North,
+        Button::LeftTrigger => PadButton::LeftBumper, // gilrs calls the bumpers triggers and the triggers Trigger2
+        Button::RightTrigger => PadButton::RightBumper,
+        Button::Select => PadButton::Select,
+        Button::Start => PadButton::Start,
+        Button::DPadUp => PadButton::DPadUp,
+        Button::DPadDown => PadButton::DPadDown,
+        Button::DPadLeft => PadButton::DPadLeft,
+        Button::DPadRight => PadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+// without the feature there are never any gamepads, so the rest of the program needn't check which build it is
+#[cfg(not(feature = "gamepad"))]
+pub struct Gamepads;
+
+#[cfg(not(feature = "gamepad"))]
+impl Gamepads {
+    pub fn new(_response: StickResponse) -> Option<Self> {
+        None
+    }
+
+    pub fn poll(&mut self) -> Vec<PadInput> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: StickResponse = StickResponse { dead_zone: 0.2, curve: 2.0 };
+
+    #[test]
+    fn the_dead_zone_reads_as_rest() {
+        for magnitude in [0.0, 0.05, 0.19, 0.2] {
+            assert_eq!(RESPONSE.shape(magnitude), 0.0, "{}", magnitude);
+        }
+        // and just past it the output starts from 0 rather than jumping to 0.2
+        assert!(RESPONSE.shape(0.21) < 1e-3);
+        assert_eq!(RESPONSE.shape(1.0), 1.0);
+        // a stick that reads slightly over 1 is still full
+        assert_eq!(RESPONSE.shape(1.05), 1.0);
+    }
+
+    #[test]
+    fn the_curve_gives_fine_control_near_the_middle() {
+        // halfway through the live range: 0.5 squared
+        assert!((RESPONSE.shape(0.6) - 0.25).abs() < 1e-6);
+        let linear = StickResponse { curve: 1.0, ..RESPONSE };
+        assert!((linear.shape(0.6) - 0.5).abs() < 1e-6);
+        // every curve goes up all the way from the dead zone to the edge
+        for curve in [0.5, 1.0, 2.0, 3.0] {
+            let response = StickResponse { curve, ..RESPONSE };
+            let samples: Vec<f32> = (0..=100).map(|i| response.shape(i as f32 / 100.0)).collect();
+            assert!(samples.windows(2).all(|pair| pair[1] >= pair[0]), "curve {}", curve);
+        }
+    }
+
+    #[test]
+    fn the_stick_dead_zone_is_round_and_keeps_the_direction() {
+        // a diagonal push of 0.25 is past the circle, although each axis alone (0.18) would be inside it
+        let diagonal = Vec2::splat(0.25 / 2f32.sqrt());
+        let shaped = RESPONSE.shape_stick(diagonal);
+        assert!(shaped.length() > 0.0);
+        assert!(shaped.normalize().abs_diff_eq(diagonal.normalize(), 1e-6));
+        assert!((shaped.length() - RESPONSE.shape(0.25)).abs() < 1e-6);
+        assert_eq!(RESPONSE.shape_stick(Vec2::new(0.1, -0.1)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn split_stick_puts_each_direction_on_its_own_half() {
+        assert_eq!(split_stick(Vec2::new(-0.5, 0.25)), [0.5, 0.0, 0.25, 0.0]);
+        assert_eq!(split_stick(Vec2::new(0.75, -1.0)), [0.0, 0.75, 0.0, 1.0]);
+        assert_eq!(split_stick(Vec2::ZERO), [0.0; 4]);
+    }
+}
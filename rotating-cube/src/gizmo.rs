@@ -0,0 +1,73 @@
+// axis gizmo: a small X (red), Y (green), Z (blue) indicator in the bottom-left corner of every window, turning with
+// the camera so it always shows which way the world axes point from the current view
+// it is drawn with the debug line pipeline and lines.wgsl, just with its own camera matrix in a bind group of its own
+// and a viewport that confines it to its corner
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::camera::Camera;
+use crate::debug_lines::LineVertex;
+use crate::render_stats::CountingPass;
+use crate::viewport::Viewport;
+
+// edge of the square the gizmo is drawn in and its distance from the corner, in logical pixels
+const SIZE: f32 = 100.0;
+const MARGIN: f32 = 8.0;
+
+// half the width of the box the orthographic projection shows, a little over the axes' length of 1 so their tips
+// aren't cut off whichever way they point
+const EXTENT: f32 = 1.2;
+
+// the camera's orientation without its position, seen through an orthographic projection: the axes stay the same size
+// and centered however far the camera is from its target or how wide its field of view
+pub fn view_proj(camera: &Camera) -> Mat4 {
+    // look_at from the origin in the camera's viewing direction turns the world exactly like the real view does,
+    // only without moving it, so the axes' origin sits in the middle of the gizmo
+    let rotation = Mat4::look_at_rh(Vec3::ZERO, camera.target - camera.eye, camera.up);
+    // near and far are around the origin, the axes reach 1 towards and away from the camera
+    Mat4::orthographic_rh(-EXTENT, EXTENT, -EXTENT, EXTENT, -EXTENT, EXTENT) * rotation
+}
+
+// the corner of the scene's viewport the gizmo goes in, bottom-left since the HUD has the top-left
+// scaled like the HUD so it keeps its size on HiDPI screens, and never bigger than the viewport itself
+pub fn viewport(scene: Viewport, scale_factor: f64) -> Viewport {
+    let scale = scale_factor as f32;
+    let size = (SIZE * scale).min(scene.width).min(scene.height);
+    let margin = (MARGIN * scale).min(scene.width - size).min(scene.height - size);
+    Viewport {
+        x: scene.x + margin,
+        y: scene.y + scene.height - size - margin,
+        width: size,
+        height: size,
+    }
+}
+
+// the three axis lines, they never change, only the matrix they are drawn with does
+pub struct AxisGizmo {
+    vertex_buffer: wgpu::Buffer,
+}
+
+impl AxisGizmo {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let mut vertices = Vec::with_capacity(6);
+        for (axis, color) in [(Vec3::X, [1.0, 0.0, 0.0]), (Vec3::Y, [0.0, 1.0, 0.0]), (Vec3::Z, [0.0, 0.0, 1.0])] {
+            vertices.push(LineVertex { position: [0.0; 3], color });
+            vertices.push(LineVertex { position: axis.to_array(), color });
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Axis Gizmo Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        Self { vertex_buffer }
+    }
+
+    // expects the line pipeline to be set, `bind_group` carries view_proj() in place of the camera
+    // changes the pass's viewport, so this is the last thing drawn in it
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, bind_group: &'a wgpu::BindGroup, viewport: Viewport) {
+        viewport.apply(pass);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,200 @@
+// the device and queue every wgpu program starts with, requested the same way whatever it goes on to draw
+// nothing in here knows about the cube, so another program (wgpu-test) can ask for its device through it too
+// picking the adapter is up to the caller, adapter.rs has the --adapter/--power/--backend rules for that
+// bindings.rs below it is the other half every program needs, bind group layouts and bind groups without the boilerplate
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tracing::{error, info, warn};
+
+pub mod bindings;
+
+pub struct Context {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub info: wgpu::AdapterInfo,
+    pub timestamps: bool, // TIMESTAMP_QUERY was asked for and granted
+    pub compute: bool,    // the adapter runs compute shaders, missing on some downlevel backends (e.g. older GL)
+}
+
+impl Context {
+    // request a device from `adapter`, with timestamp queries if `timestamps` is set and the adapter has them
+    // with `trace_dir` wgpu records every call made on the device there, which needs the trace feature
+    pub async fn new(adapter: &wgpu::Adapter, timestamps: bool, trace_dir: Option<&Path>) -> Result<Self, wgpu::RequestDeviceError> {
+        let info = adapter.get_info();
+        info!("Using adapter: {} ({:?}, {:?})", info.name, info.device_type, info.backend);
+
+        // timestamp queries are optional, only ask for them when wanted and the adapter has them
+        let supported = adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        if timestamps && !supported {
+            warn!("Adapter doesn't support timestamp queries, GPU frame times won't be reported");
+        }
+        let timestamps = timestamps && supported;
+        // a float depth buffer with stencil, so the selection outline doesn't cost --reverse-z its precision, see depth.rs
+        let depth_stencil = adapter.features() & wgpu::Features::DEPTH32FLOAT_STENCIL8;
+
+        // WebGL2 (the browser build with --features webgl) can't meet wgpu's default limits, ask for what it has
+        let limits = if cfg!(target_arch = "wasm32") && info.backend == wgpu::Backend::Gl {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        } else {
+            wgpu::Limits::default()
+        };
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: depth_stencil | if timestamps { wgpu::Features::TIMESTAMP_QUERY } else { wgpu::Features::empty() },
+                    limits,
+                    ..Default::default()
+                },
+                trace_dir,
+            )
+            .await?;
+
+        let compute = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+
+        Ok(Self {
+            device,
+            queue,
+            info,
+            timestamps,
+            compute,
+        })
+    }
+
+    // a device with no window to draw into, only its own textures and buffers: the default adapter, picked without a
+    // surface it has to be able to present to. None when there is no adapter at all (or it has no device to give)
+    // the headless tests start from this, they don't need the --adapter rules
+    pub async fn headless() -> Option<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await?;
+        Self::new(&adapter, false, None).await.ok()
+    }
+
+    // wgpu 0.16 has no device-lost callback, a lost device shows up as errors from every call made on it
+    // those (and running out of memory) set `device_lost` for the program to recreate the device, anything else is a
+    // bug in the program and stays fatal like wgpu's default handler
+    pub fn watch_for_loss(&self, device_lost: &Arc<AtomicBool>) {
+        let lost = device_lost.clone();
+        self.device.on_uncaptured_error(Box::new(move |err| {
+            let is_lost = match &err {
+                wgpu::Error::OutOfMemory { .. } => true,
+                wgpu::Error::Validation { description, .. } => description.contains("device is lost"),
+            };
+            if !is_lost {
+                panic!("wgpu error: {}", err);
+            }
+            if !lost.swap(true, Ordering::SeqCst) {
+                error!("GPU device lost: {}", err);
+            }
+        }));
+    }
+}
+
+// copy `buffer` (made with COPY_SRC) back to the CPU once everything submitted so far is done with it
+// blocks until then, fine for tests and one-off reads, the browser can't wait like this at all
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Readback Encoder") });
+    encoder.copy_buffer_to_buffer(buffer, 0, &readback, 0, buffer.size());
+    queue.submit(Some(encoder.finish()));
+    map_read(device, &readback)
+}
+
+// the same for a 2D texture with 4 bytes per pixel (made with COPY_SRC), e.g. an offscreen render target
+// the copy pads every row to 256 bytes, the rows come back without it, top row first
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Vec<u8> {
+    let (width, height) = (texture.width(), texture.height());
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Readback Encoder") });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    map_read(device, &readback)
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..(width * 4) as usize])
+        .copied()
+        .collect()
+}
+
+// wait for `readback` (made with MAP_READ) to map and copy out what is in it
+#[cfg(not(target_arch = "wasm32"))]
+fn map_read(device: &wgpu::Device, readback: &wgpu::Buffer) -> Vec<u8> {
+    let slice = readback.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv().expect("map_async never answered").expect("Couldn't map the readback buffer");
+    let bytes = slice.get_mapped_range().to_vec();
+    readback.unmap();
+    bytes
+}
+
+// a validation error caught by scoped(), named after what was being created
+#[derive(Debug)]
+pub struct ScopeError {
+    pub label: String,
+    pub error: wgpu::Error,
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "creating {} failed: {}", self.label, self.error)
+    }
+}
+
+impl std::error::Error for ScopeError {}
+
+// run `create` inside a validation error scope, so a mistake in it comes back as an error naming `label` right away
+// instead of the uncaptured error handler panicking, often at the first use of the broken resource much later
+// the browser answers asynchronously and can't be waited for, there the error is only logged once it arrives
+pub fn scoped<T>(device: &wgpu::Device, label: &str, create: impl FnOnce() -> T) -> Result<T, ScopeError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    let scope = device.pop_error_scope();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(error) = pollster::block_on(scope) {
+        return Err(ScopeError { label: label.to_string(), error });
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let label = label.to_string();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(error) = scope.await {
+                error!("{}", ScopeError { label, error });
+            }
+        });
+    }
+    Ok(created)
+}
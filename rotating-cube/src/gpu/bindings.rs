@@ -0,0 +1,169 @@
+// bind group layouts without the boilerplate: a layout entry is six nested fields of which only the binding number,
+// the shader stages and the kind of resource ever change here, so BindingsBuilder takes just those
+// the layout it builds also remembers its binding numbers, so a bind group for it is the resources in the same order
+// wgpu labels both after the builder's name, which is what shows up in validation errors and GPU debuggers
+pub struct BindingsBuilder {
+    name: String,
+    entries: Vec<wgpu::BindGroupLayoutEntry>,
+}
+
+impl BindingsBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    // anything the helpers below don't cover
+    pub fn entry(mut self, binding: u32, stages: wgpu::ShaderStages, ty: wgpu::BindingType) -> Self {
+        self.entries.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: stages,
+            ty,
+            count: None,
+        });
+        self
+    }
+
+    // a uniform buffer, a whole struct the shader reads as var<uniform>
+    pub fn uniform(self, binding: u32, stages: wgpu::ShaderStages) -> Self {
+        self.buffer(binding, stages, wgpu::BufferBindingType::Uniform)
+    }
+
+    // a storage buffer, var<storage, read> or var<storage, read_write>
+    pub fn storage(self, binding: u32, stages: wgpu::ShaderStages, read_only: bool) -> Self {
+        self.buffer(binding, stages, wgpu::BufferBindingType::Storage { read_only })
+    }
+
+    // a color texture sampled with filtering, a texture_2d<f32> for D2
+    pub fn texture(self, binding: u32, stages: wgpu::ShaderStages, dimension: wgpu::TextureViewDimension) -> Self {
+        let ty = wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: dimension,
+            multisampled: false,
+        };
+        self.entry(binding, stages, ty)
+    }
+
+    // a depth texture, a texture_depth_2d, only ever read through a comparison sampler here (the shadow map)
+    pub fn depth_texture(self, binding: u32, stages: wgpu::ShaderStages) -> Self {
+        let ty = wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        };
+        self.entry(binding, stages, ty)
+    }
+
+    // a filtering sampler, or with `comparison` a sampler_comparison for shadow map lookups
+    pub fn sampler(self, binding: u32, stages: wgpu::ShaderStages, comparison: bool) -> Self {
+        let ty = if comparison { wgpu::SamplerBindingType::Comparison } else { wgpu::SamplerBindingType::Filtering };
+        self.entry(binding, stages, wgpu::BindingType::Sampler(ty))
+    }
+
+    fn buffer(self, binding: u32, stages: wgpu::ShaderStages, ty: wgpu::BufferBindingType) -> Self {
+        let ty = wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        self.entry(binding, stages, ty)
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> Bindings {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} Bind Group Layout", self.name)),
+            entries: &self.entries,
+        });
+        Bindings {
+            name: self.name,
+            layout,
+            bindings: self.entries.iter().map(|entry| entry.binding).collect(),
+        }
+    }
+}
+
+// a built layout, pipelines take `layout`, bind groups come from bind_group()
+pub struct Bindings {
+    name: String,
+    pub layout: wgpu::BindGroupLayout,
+    bindings: Vec<u32>, // binding numbers in the order the builder added them
+}
+
+impl Bindings {
+    // one bind group for this layout, `resources` in the order the builder's calls added their bindings
+    pub fn bind_group(&self, device: &wgpu::Device, resources: &[wgpu::BindingResource]) -> wgpu::BindGroup {
+        assert_eq!(resources.len(), self.bindings.len(), "{} bind group needs one resource per binding", self.name);
+        let entries: Vec<wgpu::BindGroupEntry> = self
+            .bindings
+            .iter()
+            .zip(resources)
+            .map(|(&binding, resource)| wgpu::BindGroupEntry {
+                binding,
+                resource: resource.clone(),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group", self.name)),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the builder's entries before build(), which is the only part that needs a device
+    #[test]
+    fn helpers_fill_in_the_same_entries_as_by_hand() {
+        let fragment = wgpu::ShaderStages::FRAGMENT;
+        let builder = BindingsBuilder::new("Test")
+            .uniform(0, wgpu::ShaderStages::VERTEX | fragment)
+            .storage(1, wgpu::ShaderStages::COMPUTE, true)
+            .storage(2, wgpu::ShaderStages::COMPUTE, false)
+            .texture(3, fragment, wgpu::TextureViewDimension::Cube)
+            .depth_texture(4, fragment)
+            .sampler(5, fragment, false)
+            .sampler(6, fragment, true);
+
+        let entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry { binding, visibility, ty, count: None };
+        let buffer = |ty| wgpu::BindingType::Buffer { ty, has_dynamic_offset: false, min_binding_size: None };
+        let by_hand = [
+            entry(0, wgpu::ShaderStages::VERTEX | fragment, buffer(wgpu::BufferBindingType::Uniform)),
+            entry(1, wgpu::ShaderStages::COMPUTE, buffer(wgpu::BufferBindingType::Storage { read_only: true })),
+            entry(2, wgpu::ShaderStages::COMPUTE, buffer(wgpu::BufferBindingType::Storage { read_only: false })),
+            entry(
+                3,
+                fragment,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+            ),
+            entry(
+                4,
+                fragment,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            ),
+            entry(5, fragment, wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)),
+            entry(6, fragment, wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)),
+        ];
+        assert_eq!(builder.entries, by_hand);
+    }
+
+    #[test]
+    fn entries_keep_the_order_they_were_added_in() {
+        // binding numbers needn't be in order or contiguous, bind_group() pairs resources with them by position
+        let builder = BindingsBuilder::new("Test").sampler(7, wgpu::ShaderStages::FRAGMENT, false).uniform(2, wgpu::ShaderStages::VERTEX);
+        let bindings: Vec<u32> = builder.entries.iter().map(|entry| entry.binding).collect();
+        assert_eq!(bindings, [7, 2]);
+    }
+}
@@ -0,0 +1,75 @@
+// measures how long the GPU spent on a frame using timestamp queries
+// only available when the adapter supports Features::TIMESTAMP_QUERY, so callers keep it in an Option
+
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,      // two timestamps: before and after the frame's commands
+    resolve_buffer: wgpu::Buffer,   // GPU-side destination the raw timestamps are resolved into
+    readback_buffer: wgpu::Buffer,  // CPU-mappable copy of the resolve buffer
+    period_ns: f32,                 // nanoseconds per timestamp tick, depends on the GPU
+}
+
+// two u64 timestamps
+const TIMESTAMPS_SIZE: wgpu::BufferAddress = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Frame Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period_ns: queue.get_timestamp_period(),
+        }
+    }
+
+    // record the start timestamp, call before any passes of the frame
+    pub fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 0);
+    }
+
+    // record the end timestamp and queue the copy that makes both readable on the CPU
+    pub fn end(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, TIMESTAMPS_SIZE);
+    }
+
+    // wait for the submitted frame to finish and return its GPU duration in milliseconds
+    // blocking here stalls CPU/GPU overlap, which is acceptable for benchmarking but not for normal rendering
+    pub fn read_ms(&self, device: &wgpu::Device) -> Option<f64> {
+        let slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+
+        let ms = {
+            let data = slice.get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            timestamps[1].wrapping_sub(timestamps[0]) as f64 * self.period_ns as f64 / 1_000_000.0
+        };
+        self.readback_buffer.unmap();
+        Some(ms)
+    }
+}
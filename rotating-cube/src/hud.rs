@@ -0,0 +1,272 @@
+// on-screen text overlay drawn on top of the scene with the bitmap font from font.rs
+// text is laid out in physical pixels so it stays sharp, and scaled up by whole pixels on HiDPI screens
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::BindingsBuilder;
+use crate::font::{FontAtlas, CELL_HEIGHT, CELL_WIDTH, GLYPH_HEIGHT, GLYPH_WIDTH, SOLID};
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::render_stats::{CountingPass, CountingQueue};
+
+// distance between the panel and the window's top-left corner, and between panel edge and text, in font pixels
+const MARGIN: f32 = 4.0;
+const PADDING: f32 = 3.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.55];
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct HudVertex {
+    position: [f32; 2], // pixels from the top-left corner
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+impl HudVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<HudVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct Hud {
+    pub visible: bool, // toggled with H
+    atlas: FontAtlas,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+    screen_buffer: wgpu::Buffer, // orthographic projection for the current surface size
+    vertex_buffer: wgpu::Buffer, // rebuilt whenever the text changes, grows if the text gets longer
+    vertex_capacity: usize,
+    num_vertices: u32,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, cache: &PipelineCache, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        // ----- Font atlas texture -----
+        let atlas = FontAtlas::build();
+        let size = wgpu::Extent3d {
+            width: atlas.width,
+            height: atlas.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HUD Font Atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm, // one byte per pixel is all a monochrome font needs
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &atlas.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(atlas.width),
+                rows_per_image: Some(atlas.height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // nearest filtering keeps the pixel font crisp when scaled by whole numbers
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("HUD Font Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("HUD Screen Buffer"),
+            contents: bytemuck::bytes_of(&screen_projection(width, height).to_cols_array_2d()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // ----- Bindings -----
+        let bindings = BindingsBuilder::new("HUD")
+            .uniform(0, wgpu::ShaderStages::VERTEX)
+            .texture(1, wgpu::ShaderStages::FRAGMENT, wgpu::TextureViewDimension::D2)
+            .sampler(2, wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let bind_group = bindings.bind_group(
+            device,
+            &[
+                screen_buffer.as_entire_binding(),
+                wgpu::BindingResource::TextureView(&view),
+                wgpu::BindingResource::Sampler(&sampler),
+            ],
+        );
+
+        // ----- Pipeline -----
+        // the same for every window, so only the first window's HUD builds it, the others get it from the cache
+        let key = PipelineKey::new(ShaderId::new("hud.wgsl", "vs_main", "fs_main"), format, wgpu::BlendState::ALPHA_BLENDING); //text and panel are see-through where alpha < 1
+        let pipeline = cache.get_or_create(key, |key| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("hud.wgsl"));
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("HUD Pipeline Layout"),
+                bind_group_layouts: &[&bindings.layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("HUD Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[HudVertex::layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &key.targets(),
+                }),
+                primitive: key.primitive(),
+                depth_stencil: key.depth_stencil(),
+                multisample: key.multisample(),
+                multiview: None,
+            })
+        });
+
+        let vertex_capacity = 1024;
+        let vertex_buffer = create_vertex_buffer(device, vertex_capacity);
+
+        Self {
+            visible: true,
+            atlas,
+            pipeline,
+            bind_group,
+            screen_buffer,
+            vertex_buffer,
+            vertex_capacity,
+            num_vertices: 0,
+        }
+    }
+
+    // keep the orthographic projection matched to the surface size in physical pixels
+    pub fn resize(&self, queue: &CountingQueue, width: u32, height: u32) {
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::bytes_of(&screen_projection(width, height).to_cols_array_2d()));
+    }
+
+    // lay out `lines` in the top-left corner, `scale` is how many physical pixels one font pixel covers
+    pub fn set_text(&mut self, device: &wgpu::Device, queue: &CountingQueue, lines: &[String], scale: f32) {
+        let vertices = self.layout(lines, scale);
+
+        if vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = create_vertex_buffer(device, self.vertex_capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>) {
+        if !self.visible || self.num_vertices == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline, wgpu::PrimitiveTopology::TriangleList);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.num_vertices, 0..1);
+    }
+
+    // two triangles per character, plus a background panel behind all the text
+    fn layout(&self, lines: &[String], scale: f32) -> Vec<HudVertex> {
+        let advance = CELL_WIDTH as f32 * scale;
+        let line_height = CELL_HEIGHT as f32 * scale;
+        let origin = (MARGIN + PADDING) * scale;
+
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        if longest == 0 {
+            return Vec::new();
+        }
+
+        let mut vertices = Vec::with_capacity((lines.len() * longest + 1) * 6);
+
+        // panel first so the text is blended on top of it
+        let panel_min = MARGIN * scale;
+        let panel_max_x = origin + longest as f32 * advance + (PADDING - 1.0) * scale;
+        let panel_max_y = origin + lines.len() as f32 * line_height + (PADDING - 1.0) * scale;
+        let solid = self.atlas.uv_rect(SOLID);
+        let center = [(solid[0] + solid[2]) * 0.5, (solid[1] + solid[3]) * 0.5];
+        push_quad(&mut vertices, [panel_min, panel_min, panel_max_x, panel_max_y], [center[0], center[1], center[0], center[1]], PANEL_COLOR);
+
+        for (row, line) in lines.iter().enumerate() {
+            let y = origin + row as f32 * line_height;
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let x = origin + column as f32 * advance;
+                let rect = [x, y, x + GLYPH_WIDTH as f32 * scale, y + GLYPH_HEIGHT as f32 * scale];
+                push_quad(&mut vertices, rect, self.atlas.uv_rect(c), TEXT_COLOR);
+            }
+        }
+        vertices
+    }
+}
+
+// maps pixel coordinates (0,0 top-left, y down) to clip space
+fn screen_projection(width: u32, height: u32) -> Mat4 {
+    Mat4::orthographic_rh(0.0, width as f32, height as f32, 0.0, -1.0, 1.0)
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("HUD Vertex Buffer"),
+        size: (capacity * std::mem::size_of::<HudVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// rect and uv are (min x, min y, max x, max y)
+fn push_quad(vertices: &mut Vec<HudVertex>, rect: [f32; 4], uv: [f32; 4], color: [f32; 4]) {
+    let [x0, y0, x1, y1] = rect;
+    let [u0, v0, u1, v1] = uv;
+    let corners = [
+        ([x0, y0], [u0, v0]),
+        ([x1, y0], [u1, v0]),
+        ([x1, y1], [u1, v1]),
+        ([x1, y1], [u1, v1]),
+        ([x0, y1], [u0, v1]),
+        ([x0, y0], [u0, v0]),
+    ];
+    vertices.extend(corners.iter().map(|&(position, uv)| HudVertex { position, uv, color }));
+}
+
+// frames-per-second averaged over half a second so the number is readable instead of flickering every frame
+#[derive(Default)]
+pub struct FpsCounter {
+    frames: u32,
+    elapsed: f32,
+    fps: f32,
+}
+
+impl FpsCounter {
+    pub fn tick(&mut self, frame_time: f32) {
+        self.frames += 1;
+        self.elapsed += frame_time;
+        if self.elapsed >= 0.5 {
+            self.fps = self.frames as f32 / self.elapsed;
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
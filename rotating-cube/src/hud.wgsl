@@ -0,0 +1,39 @@
+// HUD text shader: quads positioned in physical pixels, textured from the bitmap font atlas
+struct Screen {
+    proj: mat4x4<f32> // orthographic projection, pixel (0,0) = top-left corner of the surface
+};
+@group(0) @binding(0)
+var<uniform> screen: Screen;
+
+@group(0) @binding(1)
+var atlas: texture_2d<f32>; // single channel font atlas, red = glyph coverage
+@group(0) @binding(2)
+var atlas_sampler: sampler;
+
+struct HudInput {
+    @location(0) position: vec2<f32>, // corner position in pixels
+    @location(1) uv: vec2<f32>,       // corner position in the atlas
+    @location(2) color: vec4<f32>,    // text color, alpha fades the whole glyph
+};
+
+struct HudOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: HudInput) -> HudOutput {
+    var output: HudOutput;
+    output.clip_position = screen.proj * vec4<f32>(input.position, 0.0, 1.0);
+    output.uv = input.uv;
+    output.color = input.color;
+    return output;
+}
+
+@fragment
+fn fs_main(input: HudOutput) -> @location(0) vec4<f32> {
+    // glyph coverage becomes alpha so alpha blending lets the scene show through around the letters
+    let coverage = textureSample(atlas, atlas_sampler, input.uv).r;
+    return vec4<f32>(input.color.rgb, input.color.a * coverage);
+}
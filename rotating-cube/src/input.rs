@@ -0,0 +1,501 @@
+// keys, mouse buttons, the wheel and gamepads are turned into Actions here, the rest of the app only ever sees the Actions
+// which key does what is plain data: default_keymap.toml, built in, with --keymap FILE replacing the keys of any action
+// it lists, so rebinding doesn't touch the code that reacts to the action
+// every window has its own InputState since winit reports keys to the window that has focus
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use glam::{Quat, Vec3};
+use serde::de::{self, IntoDeserializer};
+use serde::{Deserialize, Deserializer};
+use winit::event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+pub const DEFAULT_KEYMAP: &str = include_str!("default_keymap.toml");
+
+// the names are what the keymap files use, e.g. CycleDrawMode is cycle-draw-mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    // the window's own camera and view
+    ToggleHud,
+    ZoomIn,
+    ZoomOut,
+    ToggleProjection,
+    Fullscreen,
+    CameraDiagonal,
+    CameraFront,
+    CameraSide,
+    CameraTop,
+    FrameAll, // the camera backs off or comes closer until every cube is in view
+    TogglePip, // an inset with the scene seen from above, see pip.rs
+    FreezeCamera, // a copy of the camera stays behind, its frustum drawn and what it would cull tinted red
+    CameraForward,
+    CameraBack,
+    CameraLeft,
+    CameraRight,
+    MouseLook,
+    ToggleMouseCapture,
+    ReleaseMouse,
+    // the shared scene
+    Pause,
+    ResetOrientation,
+    SpinAxisX,
+    SpinAxisY,
+    SpinAxisZ,
+    CycleDrawMode,
+    CycleDebugView,
+    CycleEffect,
+    CycleMaterial, // the opaque cubes in each of the demo materials in turn, then their own again
+    ToggleNormals,
+    ToggleBounds,
+    ToggleSelection,
+    ToggleShadows, // the scene file's first light casts shadows, see shadow.rs
+    ToggleCubeShader,
+    ToggleGlassShader,
+    ToggleDepthPrepass,
+    ShininessDown,
+    ShininessUp,
+    AddLight,
+    RemoveLight,
+    CubeTurnLeft, // these four are held, a stick turns the cube by hand as far as it is pushed
+    CubeTurnRight,
+    CubeTurnUp,
+    CubeTurnDown,
+    // the event loop's
+    ToggleFrameLimiter,
+}
+
+// the keymap file's name for it, CycleDrawMode is cycle-draw-mode
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_ascii_uppercase() && !name.is_empty() {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        f.write_str(&name)
+    }
+}
+
+// anything a keymap can put an action on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    WheelUp,
+    WheelDown,
+    Pad(PadButton),
+    Axis(PadAxis),
+}
+
+// gamepad buttons go by where they are rather than what is printed on them: South is A on an Xbox pad and the cross
+// on a PlayStation one, named PadSouth etc. in keymap files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+pub const PAD_BUTTONS: [PadButton; 12] = [
+    PadButton::South,
+    PadButton::East,
+    PadButton::West,
+    PadButton::North,
+    PadButton::LeftBumper,
+    PadButton::RightBumper,
+    PadButton::Select,
+    PadButton::Start,
+    PadButton::DPadUp,
+    PadButton::DPadDown,
+    PadButton::DPadLeft,
+    PadButton::DPadRight,
+];
+
+// a stick pushed one way, or a trigger, each from 0 at rest to 1 all the way, so a stick is four of these
+// they drive held actions by how far they are pushed, and act like a button press past halfway
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PadAxis {
+    LeftStickLeft,
+    LeftStickRight,
+    LeftStickUp,
+    LeftStickDown,
+    RightStickLeft,
+    RightStickRight,
+    RightStickUp,
+    RightStickDown,
+    LeftTrigger,
+    RightTrigger,
+}
+
+pub const PAD_AXES: [PadAxis; 10] = [
+    PadAxis::LeftStickLeft,
+    PadAxis::LeftStickRight,
+    PadAxis::LeftStickUp,
+    PadAxis::LeftStickDown,
+    PadAxis::RightStickLeft,
+    PadAxis::RightStickRight,
+    PadAxis::RightStickUp,
+    PadAxis::RightStickDown,
+    PadAxis::LeftTrigger,
+    PadAxis::RightTrigger,
+];
+
+// an axis counts as pressed from PRESS_LEVEL and released below RELEASE_LEVEL, the gap keeps a stick resting around
+// halfway from pressing over and over
+const PRESS_LEVEL: f32 = 0.5;
+const RELEASE_LEVEL: f32 = 0.4;
+
+impl Binding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "MouseLeft" => Binding::Mouse(MouseButton::Left),
+            "MouseRight" => Binding::Mouse(MouseButton::Right),
+            "MouseMiddle" => Binding::Mouse(MouseButton::Middle),
+            "WheelUp" => Binding::WheelUp,
+            "WheelDown" => Binding::WheelDown,
+            // the gamepad's names are this program's own, found by the same Display they are written with
+            _ if name.starts_with("Pad") || name.contains("Stick") || name.ends_with("Trigger") => {
+                let pad = PAD_BUTTONS.iter().map(|button| Binding::Pad(*button));
+                let axes = PAD_AXES.iter().map(|axis| Binding::Axis(*axis));
+                pad.chain(axes)
+                    .find(|binding| binding.to_string() == name)
+                    .ok_or_else(|| format!("unknown gamepad input \"{}\"", name))?
+            }
+            // winit's serde support knows every key by its variant name, no need for a second list here
+            _ => Binding::Key(
+                VirtualKeyCode::deserialize(name.into_deserializer())
+                    .map_err(|_: de::value::Error| format!("unknown key \"{}\"", name))?,
+            ),
+        })
+    }
+}
+
+// the same names parse() takes
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(MouseButton::Other(n)) => write!(f, "Mouse{}", n),
+            Binding::Mouse(button) => write!(f, "Mouse{:?}", button),
+            Binding::WheelUp => f.write_str("WheelUp"),
+            Binding::WheelDown => f.write_str("WheelDown"),
+            Binding::Pad(button) => write!(f, "Pad{:?}", button),
+            Binding::Axis(axis) => write!(f, "{:?}", axis),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Binding::parse(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+// `action = "Key"` and `action = ["Key", "Other"]` both work, most actions only have the one key
+// written out by hand since #[serde(untagged)] would replace the "unknown key" error with a vague one of its own
+struct Keys(Vec<Binding>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> de::Visitor<'de> for KeysVisitor {
+            type Value = Keys;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a key name or a list of key names")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Keys, E> {
+                Binding::parse(name).map(|key| Keys(vec![key])).map_err(E::custom)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Keys, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = seq.next_element()? {
+                    keys.push(key);
+                }
+                Ok(Keys(keys))
+            }
+        }
+
+        deserializer.deserialize_any(KeysVisitor)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    actions: HashMap<Binding, Action>,
+}
+
+impl Keymap {
+    // the built-in keys, with those of every action `overrides` (a --keymap file's text) lists replaced
+    pub fn with_overrides(overrides: Option<&str>) -> Result<Self, String> {
+        let mut bindings = parse_bindings(DEFAULT_KEYMAP).map_err(|err| format!("built-in keymap: {}", err))?;
+        if let Some(text) = overrides {
+            bindings.extend(parse_bindings(text)?);
+        }
+        Self::from_bindings(bindings)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("couldn't read {}: {}", path.display(), err))?;
+        Self::with_overrides(Some(&text)).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // a key on two actions is an error rather than one of them silently winning, with both named so the fix is obvious
+    // the actions go in name order (BTreeMap), so the same file always reports the same conflict
+    pub fn from_bindings(bindings: BTreeMap<Action, Vec<Binding>>) -> Result<Self, String> {
+        let mut actions = HashMap::new();
+        for (action, keys) in bindings {
+            for key in keys {
+                if let Some(other) = actions.insert(key, action) {
+                    if other != action {
+                        return Err(format!("{} is bound to both {} and {}, move one of them to another key", key, other, action));
+                    }
+                }
+            }
+        }
+        Ok(Self { actions })
+    }
+
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.actions.get(&binding).copied()
+    }
+}
+
+// an action missing from the file isn't in the map at all, so an override only replaces what it names
+fn parse_bindings(text: &str) -> Result<BTreeMap<Action, Vec<Binding>>, String> {
+    let file: BTreeMap<Action, Keys> = toml::from_str(text).map_err(|err| err.to_string())?;
+    Ok(file.into_iter().map(|(action, Keys(keys))| (action, keys)).collect())
+}
+
+// which bindings are down in one window, to tell a fresh press from the OS repeating a held key and to answer held()
+// the gamepads have one of their own in State, they aren't tied to a window
+#[derive(Default)]
+pub struct InputState {
+    down: HashSet<Binding>,
+    analog: HashMap<PadAxis, f32>, // how far each axis that isn't at rest is pushed, 0..1
+}
+
+impl InputState {
+    // the action a window event triggers, only on the press itself: releases, key repeats and unbound keys give None
+    pub fn event(&mut self, keymap: &Keymap, event: &WindowEvent) -> Option<Action> {
+        match event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), .. }, .. } => {
+                self.button(keymap, Binding::Key(*key), *state)
+            }
+            WindowEvent::MouseInput { state, button, .. } => self.button(keymap, Binding::Mouse(*button), *state),
+            // the wheel has no held state, every notch is a press of its own
+            WindowEvent::MouseWheel { delta, .. } => {
+                let y = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y,
+                };
+                match y {
+                    y if y > 0.0 => keymap.action(Binding::WheelUp),
+                    y if y < 0.0 => keymap.action(Binding::WheelDown),
+                    _ => None,
+                }
+            }
+            // keys released while another window (or program) has focus are never reported here, forget them all
+            // rather than have the camera keep moving after alt-tab
+            WindowEvent::Focused(false) => {
+                self.down.clear();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    // the edge detection itself, separate from the winit event so it can be driven directly
+    pub fn button(&mut self, keymap: &Keymap, binding: Binding, state: ElementState) -> Option<Action> {
+        match state {
+            ElementState::Pressed if self.down.insert(binding) => keymap.action(binding),
+            ElementState::Pressed => None, // already down, the OS repeating it
+            ElementState::Released => {
+                self.down.remove(&binding);
+                None
+            }
+        }
+    }
+
+    // a gamepad axis moved to `value` (0..1, dead zone and curve already applied)
+    // returns the action of its binding when that crosses PRESS_LEVEL, like a button being pressed
+    pub fn axis(&mut self, keymap: &Keymap, axis: PadAxis, value: f32) -> Option<Action> {
+        if value > 0.0 {
+            self.analog.insert(axis, value);
+        } else {
+            self.analog.remove(&axis);
+        }
+        match value {
+            _ if value >= PRESS_LEVEL => self.button(keymap, Binding::Axis(axis), ElementState::Pressed),
+            _ if value < RELEASE_LEVEL => self.button(keymap, Binding::Axis(axis), ElementState::Released),
+            _ => None,
+        }
+    }
+
+    // whether any key of `action` is down right now, for actions that last as long as the key is held
+    pub fn held(&self, keymap: &Keymap, action: Action) -> bool {
+        self.down.iter().any(|binding| keymap.action(*binding) == Some(action))
+    }
+
+    // how hard a held action is driven, 0..1: a key or button is all or nothing, an axis as far as it is pushed
+    pub fn amount(&self, keymap: &Keymap, action: Action) -> f32 {
+        let digital = self
+            .down
+            .iter()
+            .any(|binding| !matches!(binding, Binding::Axis(_)) && keymap.action(*binding) == Some(action));
+        if digital {
+            return 1.0;
+        }
+        self.analog
+            .iter()
+            .filter(|(axis, _)| keymap.action(Binding::Axis(**axis)) == Some(action))
+            .map(|(_, value)| *value)
+            .fold(0.0, f32::max)
+    }
+}
+
+// rotation axis presets, stored as the rotation that turns +Y into the wanted axis so they can be slerped
+pub fn axis_preset(action: Action) -> Option<Quat> {
+    match action {
+        Action::SpinAxisX => Some(Quat::from_rotation_arc(Vec3::Y, Vec3::X)),
+        Action::SpinAxisY => Some(Quat::IDENTITY),
+        Action::SpinAxisZ => Some(Quat::from_rotation_arc(Vec3::Y, Vec3::Z)),
+        _ => None,
+    }
+}
+
+// camera viewpoint presets as directions from the target, the camera keeps its distance when switching
+// with --windows each window starts at the next preset so they show the cube from different sides
+pub const CAMERA_PRESETS: [Vec3; 4] = [
+    Vec3::new(1.0, 1.0, 1.0),  // the starting diagonal view
+    Vec3::Z,                   // front
+    Vec3::X,                   // side
+    Vec3::new(0.0, 1.0, 0.01), // top, tipped slightly since looking straight along `up` has no defined view
+];
+
+pub fn camera_preset(action: Action) -> Option<Vec3> {
+    match action {
+        Action::CameraDiagonal => Some(CAMERA_PRESETS[0]),
+        Action::CameraFront => Some(CAMERA_PRESETS[1]),
+        Action::CameraSide => Some(CAMERA_PRESETS[2]),
+        Action::CameraTop => Some(CAMERA_PRESETS[3]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_keys_map_to_their_actions() {
+        let keymap = Keymap::with_overrides(None).unwrap();
+        assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::H)), Some(Action::ToggleHud));
+        // one action on several bindings, all of them lead to it
+        for binding in ["Equals", "NumpadAdd", "WheelUp", "RightTrigger"] {
+            assert_eq!(keymap.action(Binding::parse(binding).unwrap()), Some(Action::ZoomIn), "{}", binding);
+        }
+        assert_eq!(keymap.action(Binding::Mouse(MouseButton::Right)), Some(Action::MouseLook));
+    }
+
+    #[test]
+    fn an_override_replaces_only_the_actions_it_names() {
+        let keymap = Keymap::with_overrides(Some("toggle-hud = \"J\"")).unwrap();
+        assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::J)), Some(Action::ToggleHud));
+        assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::H)), None);
+        assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::O)), Some(Action::ToggleProjection));
+    }
+
+    #[test]
+    fn a_key_bound_to_two_actions_is_an_error_naming_both() {
+        let err = Keymap::with_overrides(Some("toggle-hud = \"O\"")).unwrap_err();
+        assert!(err.starts_with("O is bound to both "), "{}", err);
+        assert!(err.contains(&Action::ToggleHud.to_string()) && err.contains(&Action::ToggleProjection.to_string()), "{}", err);
+        // the same key twice on one action is fine
+        assert!(Keymap::with_overrides(Some("toggle-hud = [\"J\", \"J\"]")).is_ok());
+        // and taking the key from its old action first resolves it
+        assert!(Keymap::with_overrides(Some("toggle-hud = \"O\"\ntoggle-projection = \"F24\"")).is_ok());
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert!(Keymap::with_overrides(Some("toggle-hud = \"Hyper\"")).unwrap_err().contains("unknown key \"Hyper\""));
+        assert!(Keymap::with_overrides(Some("toggle-hud = \"PadTurbo\"")).unwrap_err().contains("unknown gamepad input"));
+        assert!(Keymap::with_overrides(Some("fly-away = \"J\"")).is_err());
+    }
+
+    #[test]
+    fn binding_names_read_back_as_written() {
+        for name in ["A", "F5", "Space", "MouseLeft", "MouseMiddle", "WheelDown", "PadSouth", "LeftStickUp", "RightTrigger"] {
+            assert_eq!(Binding::parse(name).unwrap().to_string(), name);
+        }
+    }
+
+    #[test]
+    fn only_the_press_itself_triggers_the_action() {
+        let keymap = Keymap::with_overrides(None).unwrap();
+        let mut input = InputState::default();
+        let h = Binding::Key(VirtualKeyCode::H);
+        assert_eq!(input.button(&keymap, h, ElementState::Pressed), Some(Action::ToggleHud));
+        // the OS repeating a held key
+        assert_eq!(input.button(&keymap, h, ElementState::Pressed), None);
+        assert_eq!(input.button(&keymap, h, ElementState::Released), None);
+        assert_eq!(input.button(&keymap, h, ElementState::Pressed), Some(Action::ToggleHud));
+        // unbound keys are tracked but lead nowhere
+        assert_eq!(input.button(&keymap, Binding::Key(VirtualKeyCode::F24), ElementState::Pressed), None);
+    }
+
+    #[test]
+    fn held_lasts_until_release_or_focus_loss() {
+        let keymap = Keymap::with_overrides(None).unwrap();
+        let mut input = InputState::default();
+        let right = Binding::Mouse(MouseButton::Right);
+        input.button(&keymap, right, ElementState::Pressed);
+        assert!(input.held(&keymap, Action::MouseLook));
+        assert_eq!(input.amount(&keymap, Action::MouseLook), 1.0);
+        input.button(&keymap, right, ElementState::Released);
+        assert!(!input.held(&keymap, Action::MouseLook));
+
+        input.button(&keymap, right, ElementState::Pressed);
+        assert_eq!(input.event(&keymap, &WindowEvent::Focused(false)), None);
+        assert!(!input.held(&keymap, Action::MouseLook));
+        // and after focus comes back the first press counts again
+        assert_eq!(input.button(&keymap, right, ElementState::Pressed), Some(Action::MouseLook));
+    }
+
+    #[test]
+    fn axes_press_and_release_with_a_gap_between() {
+        let keymap = Keymap::with_overrides(None).unwrap();
+        let mut input = InputState::default();
+        let trigger = PadAxis::RightTrigger;
+        assert_eq!(input.axis(&keymap, trigger, 0.3), None);
+        assert_eq!(input.amount(&keymap, Action::ZoomIn), 0.3);
+        assert_eq!(input.axis(&keymap, trigger, PRESS_LEVEL), Some(Action::ZoomIn));
+        // wobbling between the two levels neither releases nor presses again
+        assert_eq!(input.axis(&keymap, trigger, 0.45), None);
+        assert_eq!(input.axis(&keymap, trigger, 0.6), None);
+        assert!(input.held(&keymap, Action::ZoomIn));
+        assert_eq!(input.axis(&keymap, trigger, 0.1), None);
+        assert!(!input.held(&keymap, Action::ZoomIn));
+        assert_eq!(input.axis(&keymap, trigger, 0.9), Some(Action::ZoomIn));
+        // back at rest nothing is left driving it
+        input.axis(&keymap, trigger, 0.0);
+        assert_eq!(input.amount(&keymap, Action::ZoomIn), 0.0);
+    }
+}
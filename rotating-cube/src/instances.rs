@@ -0,0 +1,84 @@
+// per-instance data for drawing many copies of the cube with a single draw call (--grid N)
+// the vertex buffer still holds one cube, a second buffer stepped once per instance says where each copy goes
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec3};
+
+// distance between neighbouring cube centres, cubes are 2 units wide so this leaves a 1 unit gap
+pub const GRID_SPACING: f32 = 3.0;
+
+// largest --grid, 32 x 32 = 1024 cubes
+pub const MAX_GRID: u32 = 32;
+
+// hue offset (in turns of the color wheel) between a cube and the next ring of cubes around it
+const WAVE_STEP: f32 = 0.08;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Instance {
+    pub offset: [f32; 3], // world position of this cube's centre
+    pub phase: f32,       // how far behind the centre this cube's color animation runs, in turns
+    pub color: [f32; 3],  // multiplies the vertex colors, linear, white leaves them as they are
+    pub rotation: [f32; 4], // the object's own turn as a quaternion (x, y, z, w), applied before the offset
+    pub scale: [f32; 3],    // stretches the cube along its own axes, before the rotation
+}
+
+impl Instance {
+    // locations 0-2 are taken by Vertex, so the instance attributes continue at 3
+    // 5 is the emissive buffer's (material.rs), the color came later and goes after it, and the rotation and scale after that
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 5] =
+        wgpu::vertex_attr_array![3 => Float32x3, 4 => Float32, 6 => Float32x3, 7 => Float32x4, 8 => Float32x3];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance, // advance once per cube instead of once per vertex
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    // the scale and rotation as one matrix, what vs_main in shader.wgsl does to the cube before the model transform
+    // (the spin) and the offset
+    pub fn placement(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(Vec3::from(self.scale), Quat::from_array(self.rotation), Vec3::ZERO)
+    }
+
+    // how much further from its centre this cube reaches than a plain one, whichever way it is turned
+    pub fn reach(&self) -> f32 {
+        Vec3::from(self.scale).max_element()
+    }
+
+    // nothing stretched or turned, true of every --grid cube
+    pub fn is_plain(&self) -> bool {
+        self.rotation == Quat::IDENTITY.to_array() && self.scale == [1.0; 3]
+    }
+}
+
+// n x n cubes on the XZ plane centred on the origin
+// the phase grows with distance from the centre so the colors ripple outwards like a wave
+pub fn grid(n: u32) -> Vec<Instance> {
+    field(n * n)
+}
+
+// `count` cubes laid out like grid() in rows of ceil(sqrt(count)), the last row only as full as it needs to be, so any
+// count comes out close to square (--stress doubles it, which is only a square every other time)
+pub fn field(count: u32) -> Vec<Instance> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let (half_x, half_z) = ((columns as f32 - 1.0) * 0.5, (rows as f32 - 1.0) * 0.5);
+    (0..count)
+        .map(|i| {
+            let (row, column) = (i / columns, i % columns);
+            let offset = Vec3::new(column as f32 - half_x, 0.0, row as f32 - half_z) * GRID_SPACING;
+            Instance {
+                offset: offset.to_array(),
+                phase: offset.length() / GRID_SPACING * WAVE_STEP,
+                color: [1.0; 3],
+                rotation: Quat::IDENTITY.to_array(),
+                scale: [1.0; 3],
+            }
+        })
+        .collect()
+}
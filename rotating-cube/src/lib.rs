@@ -0,0 +1,68 @@
+// the rotating cube as a library: the renderer and everything it is built from, main.rs is the window and event
+// loop around it
+// framework.rs is the event loop, a new demo only implements its App trait (examples/triangle.rs)
+// gpu.rs doesn't depend on anything else in here, so other programs can create their device through it
+
+// in the browser stdout goes nowhere, so println!/eprintln! are redirected to the developer console
+// they have to be defined before the mod declarations for the other modules to pick them up instead of std's, and are
+// exported for main.rs to import the same way
+#[cfg(target_arch = "wasm32")]
+#[macro_export]
+macro_rules! println {
+    ($($arg:tt)*) => { web_sys::console::log_1(&format!($($arg)*).into()) };
+}
+#[cfg(target_arch = "wasm32")]
+#[macro_export]
+macro_rules! eprintln {
+    ($($arg:tt)*) => { web_sys::console::error_1(&format!($($arg)*).into()) };
+}
+
+pub mod adapter;
+pub mod animation;
+pub mod app;
+pub mod bench;
+pub mod camera;
+pub mod camera_control;
+pub mod cube;
+pub mod cursor_grab;
+pub mod debug_lines;
+pub mod debug_view;
+pub mod deform;
+pub mod depth;
+pub mod dpi;
+pub mod easing;
+pub mod effects;
+pub mod error;
+pub mod font;
+pub mod frame_limiter;
+pub mod frame_log;
+pub mod framework;
+pub mod gamepad;
+pub mod gizmo;
+pub mod gpu;
+pub mod gpu_timer;
+pub mod hud;
+pub mod input;
+pub mod instances;
+pub mod lights;
+pub mod lod;
+pub mod material;
+pub mod mesh;
+pub mod options;
+pub mod particles;
+pub mod picking;
+pub mod pip;
+pub mod pipeline_cache;
+pub mod pipelines;
+pub mod recorder;
+pub mod render_stats;
+pub mod renderer;
+pub mod scene;
+pub mod scene_file;
+pub mod scene_reload;
+pub mod shadow;
+pub mod sky;
+pub mod stress;
+pub mod timestep;
+pub mod transparency;
+pub mod viewport;
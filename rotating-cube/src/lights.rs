@@ -0,0 +1,87 @@
+// the lights the cubes are lit by: the scene file's [[lights]] and the built-in ones after them up to MAX_LIGHTS,
+// switched on one at a time with . and off again with , (the scene file's lights are the last to go off)
+// every light has a fixed slot in the light uniform's array and the shader loops over the first `count` of them, so
+// switching one on or off is a single number changing rather than the array being rebuilt
+// point lights get dimmer with distance, 1 / (constant + linear * d + quadratic * d^2) of their color at distance d,
+// each with its own coefficients, --attenuation's unless the scene file gives them; the first built-in one circles the
+// cube so that can be seen happening
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+// how many lights the uniform has room for, the array in shader.wgsl has the same length
+pub const MAX_LIGHTS: usize = 8;
+
+// LightSource::kind
+pub const DIRECTIONAL: u32 = 0;
+pub const POINT: u32 = 1;
+
+// matches LightSource in shader.wgsl, 48 bytes so the array's stride is a multiple of 16 as uniform arrays need
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct LightSource {
+    pub vector: [f32; 3], // DIRECTIONAL: unit vector towards the light, POINT: where it is in world space
+    pub kind: u32,
+    pub color: [f32; 3], // multiplies both its diffuse and specular light, white is the plain light
+    pub attenuation: [f32; 3], // POINT: constant, linear and quadratic falloff, three f32s in WGSL so no vec3 alignment
+    pub _padding: [f32; 2],
+}
+
+impl LightSource {
+    // nothing is far from the sun, a directional light doesn't fall off
+    pub fn directional(towards: Vec3, color: Vec3) -> Self {
+        Self {
+            vector: towards.normalize().to_array(),
+            kind: DIRECTIONAL,
+            color: color.to_array(),
+            attenuation: [1.0, 0.0, 0.0],
+            _padding: [0.0; 2],
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3, attenuation: [f32; 3]) -> Self {
+        Self { vector: position.to_array(), kind: POINT, color: color.to_array(), attenuation, _padding: [0.0; 2] }
+    }
+}
+
+// --attenuation's default: full brightness right at the light, about half 2 units away and a quarter at 4.5
+pub const DEFAULT_ATTENUATION: [f32; 3] = [1.0, 0.2, 0.1];
+
+// seconds for one trip around
+const ORBIT_PERIOD: f32 = 8.0;
+
+// where the orbiting light is `time` seconds into the animation
+// the orbit is an ellipse, 1.8 units from the middle at its closest and 4 at its furthest, so the cube's faces brighten
+// and dim with the distance as well as turning towards and away from it
+pub fn orbit_position(time: f32) -> Vec3 {
+    let angle = time / ORBIT_PERIOD * std::f32::consts::TAU;
+    Vec3::new(4.0 * angle.cos(), 1.0 + 0.5 * (angle * 2.0).sin(), 1.8 * angle.sin())
+}
+
+// the slot of the point light that circles the cube when the scene file has `file_lights` lights, it comes right
+// after them and is switched on from the start, None when the file's lights take every slot
+pub fn orbiting(file_lights: usize) -> Option<usize> {
+    (file_lights < MAX_LIGHTS).then_some(file_lights)
+}
+
+// how many lights are on to begin with: the scene file's and the orbiting one
+pub fn starting_count(file_lights: usize) -> usize {
+    (file_lights + 1).min(MAX_LIGHTS)
+}
+
+// the lights after the scene file's ones, in the order . switches them on, as many as there is room for
+// dim and colored, so each one that comes on is easy to tell apart and they don't wash the cubes out to white together
+// the first is the orbiting light, brighter since it is further away most of the time, and on from the start
+// `attenuation` is --attenuation, what the point lights among them fall off with
+pub fn extra_lights(attenuation: [f32; 3]) -> [LightSource; MAX_LIGHTS] {
+    [
+        LightSource::point(orbit_position(0.0), Vec3::new(1.0, 0.75, 0.45), attenuation),
+        LightSource::point(Vec3::new(-2.5, 1.0, 0.0), Vec3::new(0.1, 0.3, 0.8), attenuation),
+        LightSource::directional(Vec3::new(0.0, -1.0, 0.3), Vec3::new(0.15, 0.3, 0.15)), // from below
+        LightSource::point(Vec3::new(0.0, 2.5, -2.0), Vec3::new(0.6, 0.5, 0.1), attenuation),
+        LightSource::point(Vec3::new(0.0, -2.0, 2.5), Vec3::new(0.5, 0.1, 0.6), attenuation),
+        LightSource::directional(Vec3::new(-1.0, 0.2, -0.5), Vec3::new(0.2, 0.2, 0.25)),
+        LightSource::point(Vec3::new(2.0, -1.5, 2.0), Vec3::new(0.1, 0.6, 0.5), attenuation),
+        // only ever on when the scene file has no lights of its own
+        LightSource::directional(Vec3::new(0.5, 1.0, 0.75), Vec3::new(0.3, 0.3, 0.3)),
+    ]
+}
@@ -0,0 +1,30 @@
+// Debug line shader: same camera uniform as shader.wgsl, but vertices only carry position + color
+struct Camera {
+    view_proj: mat4x4<f32>
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct LineInput {
+    @location(0) position: vec3<f32>, // line end point in world space
+    @location(1) color: vec3<f32>,    // line color
+};
+
+struct LineOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(input: LineInput) -> LineOutput {
+    var output: LineOutput;
+    // no model matrix, DebugLines already placed everything in the world
+    output.clip_position = camera.view_proj * vec4<f32>(input.position, 1.0);
+    output.color = input.color;
+    return output;
+}
+
+@fragment
+fn fs_main(input: LineOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
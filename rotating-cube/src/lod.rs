@@ -0,0 +1,318 @@
+// --lod NEAR,FAR: the opaque cubes are drawn simpler the further they are from the camera, the full cube up to NEAR, a
+// tetrahedron on four of its corners up to FAR (cube::make_tetrahedron()) and a single point beyond that
+// every frame each window sorts the cubes it can see into the three levels by their distance from its own camera and
+// copies them into an instance buffer of its own, grouped by level, so each level is one instanced draw of its range
+// the points and lines draw modes ignore the levels and draw every cube as before, they are already cheap
+use std::ops::Range;
+
+use glam::{Mat4, Vec3};
+
+use crate::instances::Instance;
+use crate::render_stats::{CountingPass, CountingQueue};
+
+// how far past a threshold, as a fraction of it, a cube has to go before it changes level, so one sitting right on the
+// boundary doesn't flicker between two meshes while the camera wobbles around it
+pub const HYSTERESIS: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Full,   // the scene's cube, subdivided or not
+    Simple, // the tetrahedron, 12 indices
+    Point,  // one vertex, drawn with the points pipeline
+}
+
+impl Level {
+    // the order the levels' ranges follow each other in the instance buffer
+    pub const ALL: [Level; 3] = [Level::Full, Level::Simple, Level::Point];
+}
+
+// --lod's two distances from the camera in world units, near < far
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Thresholds {
+    pub near: f32, // full cubes up to here
+    pub far: f32,  // tetrahedra up to here, points beyond
+}
+
+impl Thresholds {
+    // the level for a cube `distance` away that was at `current` last frame
+    // each threshold is moved away from the side the cube is on: going out a tetrahedron only becomes a point beyond
+    // far * 1.1, coming back it only becomes a tetrahedron again inside far * 0.9
+    pub fn select(self, current: Level, distance: f32) -> Level {
+        let mut level = Level::Full;
+        for (threshold, beyond) in [(self.near, Level::Simple), (self.far, Level::Point)] {
+            let band = if current >= beyond { 1.0 - HYSTERESIS } else { 1.0 + HYSTERESIS };
+            if distance > threshold * band {
+                level = beyond;
+            }
+        }
+        level
+    }
+}
+
+// one window's sorting of the cubes, kept from frame to frame for the hysteresis
+#[derive(Clone, Debug, Default)]
+pub struct Buckets {
+    levels: Vec<Level>,   // every cube's level, one outside the view keeps the one it had when it left
+    pub order: Vec<u32>,  // the visible cubes' instance indices, all the full ones first, then the simple ones, then the points
+    pub counts: [u32; 3], // how many of `order` are at each level, in Level::ALL's order
+    pub culled: u32,      // cubes left out because they are outside the view
+}
+
+impl Buckets {
+    // `distances` has an entry per cube in instance order: its distance from the camera, None when it is out of view
+    pub fn update(&mut self, thresholds: Thresholds, distances: impl ExactSizeIterator<Item = Option<f32>>) {
+        // a reload can change the number of cubes, new ones start at full detail and move on from there in one step
+        self.levels.resize(distances.len(), Level::Full);
+        let mut groups: [Vec<u32>; 3] = Default::default();
+        self.culled = 0;
+        for (i, distance) in distances.enumerate() {
+            match distance {
+                Some(distance) => {
+                    let level = thresholds.select(self.levels[i], distance);
+                    self.levels[i] = level;
+                    groups[level as usize].push(i as u32);
+                }
+                None => self.culled += 1,
+            }
+        }
+        self.counts = groups.each_ref().map(|group| group.len() as u32);
+        self.order = groups.concat();
+    }
+
+    // where each level's cubes are in `order`, and so in the instance buffer LodBuffers::write() fills from it
+    pub fn ranges(&self) -> [Range<u32>; 3] {
+        let [full, simple, point] = self.counts;
+        [0..full, full..full + simple, full + simple..full + simple + point]
+    }
+}
+
+// whether a sphere is at least partly inside the view `view_proj` projects, from the planes of the clip volume
+// (Gribb and Hartmann): a point is in view when -w <= x <= w, -w <= y <= w and 0 <= z <= w after projection, and each of
+// those six is a plane in world space made of the matrix's rows
+pub fn in_view(view_proj: Mat4, center: Vec3, radius: f32) -> bool {
+    let (x, y, z, w) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+    let point = center.extend(1.0);
+    [w + x, w - x, w + y, w - y, z, w - z].iter().all(|plane| {
+        let length = plane.truncate().length();
+        // --reverse-z's infinite far plane comes out without a direction, nothing is beyond it
+        length < f32::EPSILON || plane.dot(point) >= -radius * length
+    })
+}
+
+// the corners of the view `view_proj` projects, in world space: the clip volume's corners (x and y at -1 and 1, depth
+// at 0 and 1) taken back through the inverse matrix and divided by w, the depth 0 four first, each four going
+// counter-clockwise from the bottom left as seen by the camera
+// depth 0 is the near plane unless the matrix is reverse-Z, whose perspective has no far plane to find the corners of,
+// its corners at depth 0 come out at infinity
+pub fn frustum_corners(view_proj: Mat4) -> [Vec3; 8] {
+    let inverse = view_proj.inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+        let depth = (i / 4) as f32;
+        *corner = inverse.project_point3(Vec3::new(x, y, depth)); //project_point3 does the divide by w
+    }
+    corners
+}
+
+// V: `instances` with the ones `culled` says the frozen camera can't see multiplied by CULLED_TINT
+pub const CULLED_TINT: [f32; 3] = [1.0, 0.15, 0.15];
+
+pub fn tint_culled(instances: &[Instance], culled: &[bool]) -> Vec<Instance> {
+    instances
+        .iter()
+        .zip(culled)
+        .map(|(instance, &culled)| {
+            if culled {
+                Instance { color: [0, 1, 2].map(|i| instance.color[i] * CULLED_TINT[i]), ..*instance }
+            } else {
+                *instance
+            }
+        })
+        .collect()
+}
+
+// a window's copy of the visible cubes grouped by level, with the hover glow in the same order beside it
+// Gpu::instance_buffer keeps every cube in scene order for the glass and the other draw modes
+pub struct LodBuffers {
+    instance_buffer: wgpu::Buffer,
+    emissive_buffer: wgpu::Buffer,
+    capacity: usize,    // cubes the buffers have room for
+    uploaded: Vec<u32>, // the order last written, nothing is uploaded again while it stays the same
+}
+
+impl LodBuffers {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        // at least one cube, a zero sized vertex buffer can't be bound
+        let cubes = capacity.max(1) as wgpu::BufferAddress;
+        let buffer = |label, stride: usize| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: cubes * stride as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            instance_buffer: buffer("LOD Instance Buffer", std::mem::size_of::<Instance>()),
+            emissive_buffer: buffer("LOD Emissive Buffer", std::mem::size_of::<f32>()),
+            capacity,
+            uploaded: Vec::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // copy the cubes `buckets` ordered out of `instances` and `glow` (both in scene order) unless the buffers already
+    // hold that order, `glow_changed` rewrites them anyway since the glow fades while the order stays
+    // returns how many buffers were written, for the HUD's upload counter
+    pub fn write(&mut self, queue: &CountingQueue, buckets: &Buckets, instances: &[Instance], glow: &[f32], glow_changed: bool) -> u32 {
+        if buckets.order == self.uploaded && !glow_changed {
+            return 0;
+        }
+        self.uploaded.clone_from(&buckets.order);
+        if buckets.order.is_empty() {
+            return 0;
+        }
+        let sorted: Vec<Instance> = buckets.order.iter().map(|&i| instances[i as usize]).collect();
+        let sorted_glow: Vec<f32> = buckets.order.iter().map(|&i| glow[i as usize]).collect();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&sorted));
+        queue.write_buffer(&self.emissive_buffer, 0, bytemuck::cast_slice(&sorted_glow));
+        2
+    }
+
+    // bind as the instance (slot 1) and emissive (slot 2) buffers in place of Gpu's
+    pub fn bind<'a>(&'a self, pass: &mut CountingPass<'_, 'a>) {
+        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        pass.set_vertex_buffer(2, self.emissive_buffer.slice(..));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+
+    const THRESHOLDS: Thresholds = Thresholds { near: 10.0, far: 20.0 };
+
+    #[test]
+    fn select_moves_each_threshold_away_from_the_current_level() {
+        // going out, 10% past each threshold
+        assert_eq!(THRESHOLDS.select(Level::Full, 10.5), Level::Full);
+        assert_eq!(THRESHOLDS.select(Level::Full, 11.5), Level::Simple);
+        assert_eq!(THRESHOLDS.select(Level::Simple, 21.5), Level::Simple);
+        assert_eq!(THRESHOLDS.select(Level::Simple, 22.5), Level::Point);
+        // coming back, 10% inside them
+        assert_eq!(THRESHOLDS.select(Level::Point, 18.5), Level::Point);
+        assert_eq!(THRESHOLDS.select(Level::Point, 17.5), Level::Simple);
+        assert_eq!(THRESHOLDS.select(Level::Simple, 9.5), Level::Simple);
+        assert_eq!(THRESHOLDS.select(Level::Simple, 8.5), Level::Full);
+        // a jump skips the level in between in either direction
+        assert_eq!(THRESHOLDS.select(Level::Full, 30.0), Level::Point);
+        assert_eq!(THRESHOLDS.select(Level::Point, 1.0), Level::Full);
+    }
+
+    #[test]
+    fn buckets_follow_an_approaching_camera_without_flickering() {
+        // three cubes on the x axis and a camera flying in along it from x = 40 to 0 and back out, wobbling by half a
+        // unit either way every frame like a hand-held orbit would
+        let cubes = [0.0f32, 5.0, 10.0];
+        let mut buckets = Buckets::default();
+        let mut history: Vec<[Level; 3]> = Vec::new();
+        let frames = 400;
+        for frame in 0..=frames {
+            let progress = frame as f32 / frames as f32;
+            let wobble = if frame % 2 == 0 { 0.5 } else { -0.5 };
+            let camera = 40.0 * (2.0 * progress - 1.0).abs() + wobble;
+            buckets.update(THRESHOLDS, cubes.iter().map(|&x| Some((camera - x).abs())));
+            history.push(std::array::from_fn(|i| buckets.levels[i]));
+        }
+        // everything starts out as points, is full detail at the closest and ends as points again
+        assert_eq!(history[0], [Level::Point; 3]);
+        assert_eq!(history[frames / 2], [Level::Full; 3]);
+        assert_eq!(history[frames], [Level::Point; 3]);
+        for cube in 0..cubes.len() {
+            let levels: Vec<Level> = history.iter().map(|levels| levels[cube]).collect();
+            let changes = levels.windows(2).filter(|pair| pair[0] != pair[1]).count();
+            // Point -> Simple -> Full on the way in and back on the way out, the wobble never flips one back and forth
+            assert_eq!(changes, 4, "cube {}: {:?}", cube, levels);
+            let (approach, retreat) = levels.split_at(frames / 2);
+            assert!(approach.windows(2).all(|pair| pair[1] <= pair[0]), "cube {}", cube);
+            assert!(retreat.windows(2).all(|pair| pair[1] >= pair[0]), "cube {}", cube);
+        }
+    }
+
+    #[test]
+    fn buckets_group_the_visible_cubes_by_level() {
+        let mut buckets = Buckets::default();
+        // a point, a full cube, one out of view, a tetrahedron and another full cube
+        let distances = [Some(50.0), Some(1.0), None, Some(15.0), Some(2.0)];
+        buckets.update(THRESHOLDS, distances.into_iter());
+        assert_eq!(buckets.order, [1, 4, 3, 0]);
+        assert_eq!(buckets.counts, [2, 1, 1]);
+        assert_eq!(buckets.culled, 1);
+        assert_eq!(buckets.ranges(), [0..2, 2..3, 3..4]);
+
+        // a reload down to two cubes keeps the first two's levels, one more starts at full detail
+        buckets.update(THRESHOLDS, [Some(19.0), Some(19.0)].into_iter());
+        assert_eq!(buckets.counts, [0, 1, 1]); // the point stays a point inside far * 1.1
+        buckets.update(THRESHOLDS, [Some(19.0), Some(19.0), Some(10.5)].into_iter());
+        assert_eq!(buckets.order, [2, 1, 0]);
+        assert_eq!(buckets.culled, 0);
+    }
+
+    // a camera looking from somewhere less tidy than down an axis, at a shape that isn't square
+    fn camera() -> Camera {
+        Camera {
+            eye: Vec3::new(3.0, 2.0, -4.0),
+            target: Vec3::new(0.5, 0.0, 0.5),
+            up: Vec3::Y,
+            fovy: 60.0,
+            aspect: 1.5,
+            znear: 0.5,
+            zfar: 40.0,
+            ortho: 0.0,
+            reverse_z: false,
+        }
+    }
+
+    // the four corners of the slice of the view `distance` in front of the camera, in frustum_corners()' order
+    fn slice(camera: &Camera, distance: f32) -> [Vec3; 4] {
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        let half_height = distance * (camera.fovy.to_radians() / 2.0).tan();
+        let half_width = half_height * camera.aspect;
+        let center = camera.eye + forward * distance;
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| center + right * half_width * x + up * half_height * y)
+    }
+
+    #[test]
+    fn frustum_corners_sit_on_the_clip_planes() {
+        let camera = camera();
+        let corners = frustum_corners(camera.view_proj());
+        // relative, the far plane is a long way out and the inverse matrix loses a little there
+        let close = |a: Vec3, b: Vec3| a.distance(b) < 1e-3 * b.distance(camera.eye);
+        for (i, expected) in slice(&camera, camera.znear).into_iter().chain(slice(&camera, camera.zfar)).enumerate() {
+            assert!(close(corners[i], expected), "corner {}: {} instead of {}", i, corners[i], expected);
+        }
+        // the near plane's size follows the field of view and the aspect: 2 * near * tan(fov / 2) high
+        let height = corners[3].distance(corners[0]);
+        assert!((height - 2.0 * camera.znear * 30f32.to_radians().tan()).abs() < 1e-4);
+        assert!((corners[1].distance(corners[0]) / height - camera.aspect).abs() < 1e-4);
+    }
+
+    #[test]
+    fn reverse_z_corners_start_at_the_near_plane_and_end_at_infinity() {
+        let camera = Camera { reverse_z: true, ..camera() };
+        let corners = frustum_corners(camera.view_proj());
+        // depth 1 is the near plane now, the second four
+        for (i, expected) in slice(&camera, camera.znear).into_iter().enumerate() {
+            assert!(corners[4 + i].distance(expected) < 1e-4, "corner {}: {} instead of {}", 4 + i, corners[4 + i], expected);
+        }
+        assert!(corners[..4].iter().all(|corner| !corner.is_finite() || corner.distance(camera.eye) > 1e5), "{:?}", &corners[..4]);
+    }
+}
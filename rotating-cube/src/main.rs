@@ -1,329 +1,359 @@
-// DeviceExt creates frame buffer which is dedicated block of memory that stores pixel data fed to GPU
-use wgpu::util::DeviceExt;
-
-// import Mat4 and Vec3 which are data types that store a 4x4 matrix and 3x1 vec
-// need 4x4 matrix to implement camera projection including rotation, translation, scaling and adding perspective
-// to view frustum
-use glam::{Mat4, Vec3};
+// in the browser the console version of eprintln! from lib.rs, see there
+#[cfg(target_arch = "wasm32")]
+use rotating_cube::eprintln;
 
 // window event loop imports
 use winit::{
-    dpi::PhysicalSize,
-    event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    dpi::{LogicalSize, PhysicalSize},
+    event::{DeviceEvent, WindowEvent},
+    event_loop::EventLoop,
+    window::{WindowBuilder, WindowId},
 };
 
-// bytemuck traits to safely copy uniforms to GPU
-use bytemuck::{Pod, Zeroable};
+use instant::{Duration, Instant};
+use tracing::{info, warn};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::prelude::*;
+
+use rotating_cube::app::State;
+use rotating_cube::bench::Bench;
+use rotating_cube::error::RenderError;
+use rotating_cube::frame_limiter::{FrameLimiter, SPIN_MARGIN};
+use rotating_cube::frame_log::FrameLog;
+use rotating_cube::framework::{run_app, App};
+use rotating_cube::input::Action;
+use rotating_cube::options::Options;
+use rotating_cube::stress::{FrameTiming, Step, Stress};
+
+// --list-adapters and the --backend check, the browser has no adapters to list
+#[cfg(not(target_arch = "wasm32"))]
+use rotating_cube::adapter;
+
+// windows are numbered in their titles once there is more than one
+fn window_title(index: u32, count: u32) -> String {
+    if count > 1 {
+        format!("Rotating Cube {}", index + 1)
+    } else {
+        "Rotating Cube".to_string()
+    }
+}
+
+fn main() {
+    // without this a panic in the browser only shows up as "unreachable executed"
+    #[cfg(target_arch = "wasm32")]
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    init_logging(options.log_level);
+    if options.print_scene {
+        print!("{}", options.scene.file.to_toml());
+        return;
+    }
+
+    // the instance only exposes the backends we ask for, so --backend gl really means "only try OpenGL"
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: options.gpu.backends,
+        ..Default::default()
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if options.list_adapters {
+            adapter::list_adapters(&instance, options.gpu.backends);
+            return;
+        }
+        if let Err(err) = adapter::check_backend_available(&instance, options.gpu.backends) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let event_loop = EventLoop::new();
+    let windows = (0..options.window.count)
+        .map(|i| {
+            let builder = WindowBuilder::new().with_title(window_title(i, options.window.count));
+            // --size is in logical pixels so the window looks the same size on a high-DPI screen
+            let builder = match options.window.size {
+                Some((width, height)) => builder.with_inner_size(LogicalSize::new(width, height)),
+                None => builder,
+            };
+            builder.build(&event_loop)
+        })
+        .collect::<Result<Vec<winit::window::Window>, _>>();
+    let windows = match windows {
+        Ok(windows) => windows,
+        Err(err) => return fail(err.into()),
+    };
+
+    // the browser's winit windows are <canvas> elements that only show up once they are part of the page
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
+        let body = web_sys::window()
+            .and_then(|page| page.document())
+            .and_then(|document| document.body())
+            .expect("The page has no <body> to put the canvas in");
+        for window in &windows {
+            body.append_child(&window.canvas()).expect("Couldn't add the canvas to the page");
+        }
+    }
 
-// guarantee struct memory layout matches C, needed for GPU buffer
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct CameraUniform {
-    view_proj: [[f32; 4]; 4],
+    // setting up the device is async, on the desktop we simply wait for it, but the browser's main thread must
+    // never block, so there it runs as a future on the page's event loop and run() starts the render loop when done
+    #[cfg(not(target_arch = "wasm32"))]
+    pollster::block_on(run(event_loop, instance, windows, options));
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(run(event_loop, instance, windows, options));
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct ModelUniform {
-    model: [[f32; 4]; 4],
+// RUST_LOG overrides --log-level with the full filter syntax, e.g. RUST_LOG=wgpu_core=debug,rotating_cube=trace
+// otherwise wgpu and the other crates only get through at warn, their info level is a flood on every frame
+// tracing-subscriber also picks up what wgpu logs through the log crate, so its warnings end up here too
+fn init_logging(level: LevelFilter) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("{},rotating_cube={}", level.min(LevelFilter::WARN), level)));
+    let registry = tracing_subscriber::registry().with(filter);
+    // stderr keeps stdout clean for --bench-json and --list-adapters
+    #[cfg(not(target_arch = "wasm32"))]
+    registry.with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr)).init();
+    // the browser has no stderr, the messages go to the developer console instead
+    #[cfg(target_arch = "wasm32")]
+    registry.with(tracing_wasm::WASMLayer::new(tracing_wasm::WASMLayerConfig::default())).init();
 }
 
-struct State {
-    surface: wgpu::Surface, // target for rendering, usually screen
-    device: wgpu::Device,   // handle to GPU
-    queue: wgpu::Queue,     // queue of GPU commands
-    config: wgpu::SurfaceConfiguration, // store surface settings (res, px format)
+// print why the renderer can't go on and end with a failure code, the browser has no process to exit so the page
+// just stays as it is
+fn fail(err: RenderError) {
+    eprintln!("Error: {}", err);
+    #[cfg(not(target_arch = "wasm32"))]
+    std::process::exit(1);
+}
 
-    render_pipeline: wgpu::RenderPipeline, // encapsulate GPU program (shaders, depth, blending)
+async fn run(event_loop: EventLoop<()>, instance: wgpu::Instance, windows: Vec<winit::window::Window>, options: Options) {
+    let mut state = match State::new(instance, windows, &options).await {
+        Ok(state) => state,
+        Err(err) => return fail(err),
+    };
+    // with --particles the title doubles as a readout of how many the compute shader is simulating
+    if let Some(particles) = &state.gpu.particles {
+        for (i, window) in state.windows.iter().enumerate() {
+            if let Some(window) = &window.window {
+                window.set_title(&format!("{} - {} particles", window_title(i as u32, options.window.count), particles.count()));
+            }
+        }
+    }
 
-    vertex_buffer: wgpu::Buffer, // store vertex data (positions, colors)
-    index_buffer: wgpu::Buffer,  // stores indices to reuse vertex
-    num_indices: u32,            // num indices in index_buffer
+    // opened before the first frame so a path that can't be written to stops the program before it starts
+    let frame_log = match options.log_frames.as_deref().map(FrameLog::create).transpose() {
+        Ok(frame_log) => frame_log,
+        Err(err) => return fail(err),
+    };
 
-    camera_buffer: wgpu::Buffer, // store view matrix
-    model_buffer: wgpu::Buffer,  // stores model matrix
-    bind_group: wgpu::BindGroup, // groups of resources for GPU
+    // --stress replaces the scene's cubes with its first count right away
+    let stress = options.stress.map(Stress::new);
+    if let Some(stress) = &stress {
+        state.set_cube_count(stress.cubes());
+    }
 
-    rotation: f32, // rotation value updated each frame
+    // Fifo already waits for vsync, stacking a second limiter on top would only add judder so it stays off
+    // every window shares the same present mode, so the first one speaks for all
+    let max_fps = if state.windows[0].config.present_mode == wgpu::PresentMode::Fifo {
+        if options.max_fps.is_some() {
+            warn!("--max-fps ignored: Fifo present mode is already capped by vsync");
+        }
+        None
+    } else {
+        options.max_fps
+    };
+
+    run_app(
+        event_loop,
+        Cube {
+            state,
+            limiter: FrameLimiter::new(max_fps),
+            // --bench collects per-frame timings and exits once enough frames have been measured
+            bench: options.bench.map(Bench::new),
+            bench_json: options.bench_json,
+            stress,
+            update_time: Duration::ZERO,
+            last_frame_end: None,
+            // while recording, every captured frame advances the simulation by exactly 1/record_fps regardless of how
+            // long it really took to render, so the output plays back smoothly at its own frame rate
+            record_dt: options.record.as_ref().map(|settings| 1.0 / settings.fps as f32),
+            frame_log,
+            frame_time: 0.0,
+            alpha: 0.0,
+        },
+    );
 }
 
-impl State {
-    async fn new(window: &winit::window::Window) -> Self {
-        // ----- Instance + Surface -----
-        let size = window.inner_size();
-        let instance = wgpu::Instance::default();
-        let surface = unsafe { instance.create_surface(window) }.unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
-            .await
-            .unwrap();
-
-        // ----- Swapchain config -----
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_capabilities(&adapter).formats[0],
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            view_formats: vec![],
-        };
-        surface.configure(&device, &config);
-
-        // ----- Cube vertices -----
-        #[rustfmt::skip]
-        let vertices: &[f32] = &[
-            // X     Y     Z     R   G   B
-            -1.0,-1.0,-1.0, 1.0,0.0,0.0,
-             1.0,-1.0,-1.0, 0.0,1.0,0.0,
-             1.0, 1.0,-1.0, 0.0,0.0,1.0,
-            -1.0, 1.0,-1.0, 1.0,1.0,0.0,
-            -1.0,-1.0, 1.0, 1.0,0.0,1.0,
-             1.0,-1.0, 1.0, 0.0,1.0,1.0,
-             1.0, 1.0, 1.0, 1.0,1.0,1.0,
-            -1.0, 1.0, 1.0, 0.0,0.0,0.0,
-        ];
-
-        let indices: &[u16] = &[
-            0,1,2, 2,3,0,
-            4,5,6, 6,7,4,
-            0,4,7, 7,3,0,
-            1,5,6, 6,2,1,
-            3,2,6, 6,7,3,
-            0,1,5, 5,4,0,
-        ];
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-
-        // ----- Camera (fixed) -----
-        //define view matrix and starting position
-        let view = Mat4::look_at_rh(
-            Vec3::new(3.0, 3.0, 3.0), // camera position
-            Vec3::ZERO,               // looks at origin
-            Vec3::Y,                  // up direction
-        );
-
-        //define projection matrix and starting field of view, along with near and far-clipping limits to encapsulate frustum 
-        let proj = Mat4::perspective_rh_gl(
-            45f32.to_radians(),
-            config.width as f32 / config.height as f32,
-            0.1,
-            100.0,
-        );
-
-        //define camera matrix as projection * view matrices and convert it to 2D array compatible with GPU func
-        let camera_uniform = CameraUniform {
-            view_proj: (proj * view).to_cols_array_2d(),
-        };
+// the renderer plus what only the binary has: the frame limiter, the end of --record, --bench and --stress, and the
+// --log-frames file
+struct Cube {
+    state: State,
+    limiter: FrameLimiter,
+    bench: Option<Bench>,
+    bench_json: bool,
+    stress: Option<Stress>,
+    update_time: Duration,           // this frame's simulation steps so far, --stress counts them as CPU work
+    last_frame_end: Option<Instant>, // --stress measures whole frames from one end_frame() to the next
+    record_dt: Option<f32>,
+    frame_log: Option<FrameLog>,
+    frame_time: f32, // this frame's real time from begin_frame(), for --log-frames
+    alpha: f32,      // and how far between two steps it was drawn, which is the spin it showed
+}
 
-        //create camera and model vertex buffers that will contain each vertex as [[x, y, z],[r,g,b]]
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::bytes_of(&camera_uniform),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
+impl App for Cube {
+    fn input(&mut self, window: WindowId, event: &WindowEvent) -> Result<(), RenderError> {
+        // keys, mouse buttons and the wheel turn into actions, the frame limiter is the one left to the event loop
+        if self.state.input(window, event) == Some(Action::ToggleFrameLimiter) {
+            let active = self.limiter.toggle();
+            info!("Frame limiter {}", if active { "on" } else { "off" });
+        }
+        match event {
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                self.state.rescale(window, *scale_factor, **new_inner_size)
+            }
+            _ => Ok(()),
+        }
+    }
 
-        // ----- Model (rotation updated each frame) -----
-        let model_uniform = ModelUniform {
-            model: Mat4::IDENTITY.to_cols_array_2d(),
-        };
+    fn resize(&mut self, window: WindowId, size: PhysicalSize<u32>) -> Result<(), RenderError> {
+        self.state.resize(window, size)
+    }
 
-        let model_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Model Buffer"),
-            contents: bytemuck::bytes_of(&model_uniform),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        //define bindings so GPU knows how to access each vertex correctly
-        // ----- Bind Group Layout -----
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                // camera
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0, //camera information for vertex shader
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                // model
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1, //model information for vertex shader
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: camera_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: model_buffer.as_entire_binding(),
-                },
-            ],
-        });
-
-        // ----- Shader -----
-        //reference the shader module
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-
-        // ----- Pipeline -----
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState { 
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: 6 * 4, //each vertex has 6 floating point values at 4 bytes each, hence each is 6*4=24 bytes 
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            shader_location: 0,
-                            offset: 0,
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                        wgpu::VertexAttribute {
-                            shader_location: 1,
-                            offset: 12, //recall the last three values are color, reference these directly in GPU to proc together by offset 12 (3 floats at 4 bytes each = 4*3=12 byte offset)
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                    ],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
-
-        Self {
-            surface,
-            device,
-            queue,
-            config,
-            render_pipeline,
-
-            vertex_buffer,
-            index_buffer,
-            num_indices: indices.len() as u32,
-
-            camera_buffer,
-            model_buffer,
-            bind_group,
-
-            rotation: 0.0,
-        }
+    fn update(&mut self, dt: f32) {
+        // only --stress wants to know, and reading the clock twice per step is nothing next to a step
+        let started = Instant::now();
+        self.state.update(dt);
+        self.update_time += started.elapsed();
     }
 
-    fn update(&mut self) {
-        // Rotate the cube every frame
-        self.rotation += 0.01;
-        let rot = Mat4::from_rotation_y(self.rotation) * Mat4::from_rotation_x(self.rotation * 0.5); //define rotation matrix along y and x-axes with fom_rotation_y/x func
+    fn render(&mut self, alpha: f32) -> Result<(), RenderError> {
+        self.alpha = alpha;
+        self.state.render(alpha)
+    }
 
-        let model = ModelUniform {
-            model: rot.to_cols_array_2d(), //convert to 2D array again for GPU to understand
-        };
+    fn request_redraw(&self) {
+        self.state.request_redraw();
+    }
 
-        self.queue.write_buffer(&self.model_buffer, 0, bytemuck::bytes_of(&model)); //load the model information to buffer after rotation changes applied
+    // closing one window leaves the others running, the program ends with the last one
+    fn close(&mut self, window: WindowId) -> bool {
+        self.state.close_window(window)
     }
 
-    fn render(&mut self) {
-        let frame = self.surface.get_current_texture().unwrap();
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default()); //get current texture and display it (vertices proc by shader)
-
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }); //write GPU commands and encode them 
-
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor { //render pass to black out view
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            pass.set_pipeline(&self.render_pipeline); //set up the pipeline and bindings, then fetch vertex information from buffer after shader has applied position and color transformations
-            pass.set_bind_group(0, &self.bind_group, &[]);
-            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            pass.draw_indexed(0..self.num_indices, 0, 0..1); //draw command 
+    // the mouse's own movement, only used while it is captured for mouse-look
+    fn device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.state.mouse_motion(*delta);
         }
+    }
 
-        self.queue.submit(Some(encoder.finish())); //send to encoder and call on GPU to present it
-        frame.present();
+    // the OS takes the native surfaces away while suspended and hands out new ones on resume
+    fn suspend(&mut self) {
+        self.state.suspend();
     }
-}
 
-fn main() {
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().with_title("Rotating Cube").build(&event_loop).unwrap();
+    fn resume(&mut self) -> Result<(), RenderError> {
+        self.state.resume()
+    }
 
-    let mut state = pollster::block_on(State::new(&window));
+    fn wait_until(&mut self) -> Option<Instant> {
+        // frame not due yet: WaitUntil wakes up with a couple of ms to spare
+        if self.limiter.should_wait(Instant::now()) {
+            return Some(self.limiter.deadline() - SPIN_MARGIN);
+        }
+        // and the rest is a precise sleep + spin
+        self.limiter.sleep_until_deadline();
+        self.limiter.frame_started(Instant::now());
+        None
+    }
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+    fn begin_frame(&mut self, real_time: f32) -> f32 {
+        self.state.fps.tick(real_time);
+        self.frame_time = real_time;
+        self.state.recenter_cursors();
+        // a saved --scene file shows up before this frame's steps
+        self.state.reload_scene();
+        self.record_dt.unwrap_or(real_time)
+    }
 
-        match event {
-            Event::MainEventsCleared => {
-                state.update();
-                state.render();
+    fn end_frame(&mut self, cpu_time: Duration) -> Option<Result<(), RenderError>> {
+        // every frame gets its row, the one that ends --record or --bench too
+        if let Some(frame_log) = &mut self.frame_log {
+            if let Err(err) = frame_log.record(self.frame_time, self.state.spin_angle(self.alpha)) {
+                return Some(Err(err));
             }
-            _ => {}
         }
-    });
+
+        if self.state.recording_done() {
+            return Some(self.state.finish_recording());
+        }
+
+        if self.stress.is_some() {
+            return self.stress_frame();
+        }
+
+        let bench = self.bench.as_mut()?;
+        // CPU time covers update + encoding + submit + present, measured from the start of this frame
+        bench.record(cpu_time, self.state.gpu_frame_ms(), self.state.render_stats());
+        if !bench.is_done() {
+            return None;
+        }
+        if self.bench_json {
+            bench.print_json();
+        } else {
+            bench.print_report();
+        }
+        Some(Ok(()))
+    }
+
+    // the log's last rows are still in its buffer, the exit code is already decided so a failure can only be reported
+    fn exit(&mut self) {
+        if let Some(Err(err)) = self.frame_log.take().map(FrameLog::finish) {
+            eprintln!("Error: {}", err);
+        }
+    }
+}
+
+impl Cube {
+    // feed this frame to --stress and change the cube count when it says so
+    // the frame time is the whole interval between two frames, waiting on vsync or the GPU included, that is what has
+    // to fit in the target, while the CPU time is only the work done on it, update() plus render() without the wait
+    // for the next swapchain image
+    fn stress_frame(&mut self) -> Option<Result<(), RenderError>> {
+        let now = Instant::now();
+        let cpu = std::mem::take(&mut self.update_time) + self.state.render_cpu_time();
+        // the first frame has nothing to be measured from
+        let previous = self.last_frame_end.replace(now)?;
+        let stress = self.stress.as_mut()?;
+        let timing = FrameTiming {
+            frame_ms: (now - previous).as_secs_f64() * 1000.0,
+            cpu_ms: cpu.as_secs_f64() * 1000.0,
+            gpu_ms: self.state.gpu_frame_ms(),
+        };
+        match stress.record(timing) {
+            Step::Continue => None,
+            Step::Grow(cubes) => {
+                if let Some(level) = stress.last_level() {
+                    eprintln!("{} cubes: {:.3} ms per frame, trying {}", level.cubes, level.average_ms(), cubes);
+                }
+                self.state.set_cube_count(cubes);
+                // rebuilding the buffers makes this a long frame, it belongs to neither count
+                self.last_frame_end = None;
+                None
+            }
+            Step::Done => {
+                stress.print_report();
+                Some(Ok(()))
+            }
+        }
+    }
 }
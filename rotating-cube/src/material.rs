@@ -0,0 +1,231 @@
+// per-cube material state kept on the CPU, for now just how much a cube glows while the cursor is over it
+// the values end up in the emissive buffer, one f32 per instance stepped alongside the instance buffer, rewritten
+// only while a cube is fading in or out
+// and the materials in bind group 1: color, metallic, roughness, emissive and reflectivity, one bind group per material in use,
+// each object's draw binds its own, see Materials below
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::{Bindings, BindingsBuilder};
+use crate::easing::Easing;
+
+// how long a cube takes to light up fully, and to fade out again after the cursor leaves
+pub const HOVER_FADE_TIME: f32 = 0.15;
+
+// how the glow ramps, QuadOut lights up quickly and settles gently
+const HOVER_EASING: Easing = Easing::QuadOut;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MaterialState {
+    pub emissive: f32, // linear 0..1 progress towards fully lit, eased by glow() before it reaches the shader
+    pub target: f32,   // 1 while hovered, 0 otherwise
+}
+
+impl MaterialState {
+    // move `emissive` towards `target` by the part of the fade that dt covers, returns true if it changed
+    // a constant rate rather than e.g. halving the distance every frame keeps it frame-rate independent: ten steps of
+    // 0.015 s land exactly where one step of 0.15 s does, and overshooting is clamped away
+    pub fn advance(&mut self, dt: f32) -> bool {
+        let before = self.emissive;
+        let step = dt / HOVER_FADE_TIME;
+        self.emissive = if self.target > self.emissive {
+            (self.emissive + step).min(self.target)
+        } else {
+            (self.emissive - step).max(self.target)
+        };
+        self.emissive != before
+    }
+
+    // the value the shader gets, the easing curve applied to the linear progress
+    pub fn glow(&self) -> f32 {
+        HOVER_EASING.apply(self.emissive)
+    }
+}
+
+// the emissive buffer's layout, vertex buffer slot 2 after the cube's vertices and the Instances
+// a separate buffer rather than a field in Instance so the instances stay untouched and only 4 bytes per cube are
+// rewritten while something fades
+pub const EMISSIVE_ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![5 => Float32]; // between Instance's 3-4 and 6
+
+pub fn emissive_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<f32>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &EMISSIVE_ATTRIBUTES,
+    }
+}
+
+// matches Material in shader.wgsl and shader_unlit.wgsl, the emissive vec3 starts on a 16-byte boundary in a uniform
+// buffer so `classic` and a padding f32 fill the gap before it, and reflectivity fills the one after it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct MaterialUniform {
+    pub base_color: [f32; 4], // linear color and opacity, only the glass pipelines blend so the opaque cubes ignore the alpha
+    pub metallic: f32,        // 0 for plastic, wood, stone, 1 for bare metal, which has no diffuse and a tinted highlight
+    pub roughness: f32,       // 0 mirror-smooth to 1 chalky, the shader clamps it above 0
+    pub classic: u32,         // 1 for the two defaults below, see there
+    pub _padding: f32,
+    pub emissive: [f32; 3],   // light of its own, added whatever the lights do
+    pub reflectivity: f32,    // 0..1, how much of the sky the surface mirrors in place of its lit color
+}
+
+// what an object without `material` gets: the cube's own vertex colors multiplied by base_color and the scene's Phong
+// highlight, whose shininess [ ] changes, the way every cube was lit before there were materials
+// metallic and roughness are unused while `classic` is 1
+pub const DEFAULT_CUBE: MaterialUniform = classic([1.0, 1.0, 1.0, 1.0]);
+// and a glass object, a cool tint so it reads as glass even unlit
+pub const DEFAULT_GLASS: MaterialUniform = classic([0.75, 0.9, 1.0, 0.4]);
+
+// where the two defaults are in every material table
+pub const CUBE: u32 = 0;
+pub const GLASS: u32 = 1;
+
+const fn classic(base_color: [f32; 4]) -> MaterialUniform {
+    MaterialUniform { base_color, metallic: 0.0, roughness: 1.0, classic: 1, _padding: 0.0, emissive: [0.0; 3], reflectivity: 0.0 }
+}
+
+const fn pbr(base_color: [f32; 3], metallic: f32, roughness: f32, emissive: [f32; 3], reflectivity: f32) -> MaterialUniform {
+    let [r, g, b] = base_color;
+    MaterialUniform { base_color: [r, g, b, 1.0], metallic, roughness, classic: 0, _padding: 0.0, emissive, reflectivity }
+}
+
+// built-in materials, K puts the opaque cubes in each one in turn, and a scene file's object can name them like its own
+// [materials] (which win over these when a name is in both)
+// the metals' colors are the usual measured reflectances of gold and chromium, linear like every color here
+// chrome is the one that mirrors the sky, the best way to see the reflections (with --day-length to watch them change)
+pub const DEMOS: [(&str, MaterialUniform); 5] = [
+    ("plastic", pbr([0.6, 0.05, 0.05], 0.0, 0.35, [0.0; 3], 0.0)),
+    ("gold", pbr([1.0, 0.77, 0.34], 1.0, 0.3, [0.0; 3], 0.2)),
+    ("chrome", pbr([0.55, 0.56, 0.55], 1.0, 0.05, [0.0; 3], 0.8)),
+    ("rubber", pbr([0.04, 0.04, 0.04], 0.0, 0.9, [0.0; 3], 0.0)),
+    ("lava", pbr([0.05, 0.01, 0.0], 0.0, 0.7, [1.0, 0.25, 0.02], 0.0)),
+];
+
+// the demos come right after the two defaults
+pub const FIRST_DEMO: u32 = 2;
+
+// index of the built-in material called `name`
+pub fn demo(name: &str) -> Option<u32> {
+    DEMOS.iter().position(|(demo, _)| *demo == name).map(|i| FIRST_DEMO + i as u32)
+}
+
+// group 1 of the cube pipelines, group 0 (camera, model, light, frame) is the same for every object in the frame
+// the vertex shader reads it too, a named material's color replaces the vertex colors there
+fn bindings(device: &wgpu::Device) -> Bindings {
+    BindingsBuilder::new("Material").uniform(0, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT).build(device)
+}
+
+// one object's material settings, fixed once created, so the buffer is only referenced through the bind group
+// (wgpu keeps it alive as long as the bind group is)
+fn create_bind_group(device: &wgpu::Device, bindings: &Bindings, uniform: MaterialUniform) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Material Buffer"),
+        contents: bytemuck::bytes_of(&uniform),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    bindings.bind_group(device, &[buffer.as_entire_binding()])
+}
+
+// the opaque instances cut into runs that share a material, in instance buffer order: each run is one draw with its
+// material's bind group, so a scene whose objects are listed material by material draws in as few calls as it can
+pub fn runs(slots: &[u32]) -> Vec<(u32, Range<u32>)> {
+    let mut runs: Vec<(u32, Range<u32>)> = Vec::new();
+    for (i, &slot) in slots.iter().enumerate() {
+        match runs.last_mut() {
+            Some((material, range)) if *material == slot => range.end = i as u32 + 1,
+            _ => runs.push((slot, i as u32..i as u32 + 1)),
+        }
+    }
+    runs
+}
+
+// the materials on the device: one bind group per entry of Scene::materials, uploaded once each however many objects
+// use it, and the opaque cubes' runs to draw them in
+pub struct Materials {
+    bindings: Bindings,
+    table: Vec<MaterialUniform>, // what the bind groups were made from
+    bind_groups: Vec<wgpu::BindGroup>,
+    pub runs: Vec<(u32, Range<u32>)>,
+}
+
+impl Materials {
+    // `slots` is the material of each opaque instance
+    pub fn new(device: &wgpu::Device, table: &[MaterialUniform], slots: &[u32]) -> Self {
+        let bindings = bindings(device);
+        let bind_groups = table.iter().map(|&uniform| create_bind_group(device, &bindings, uniform)).collect();
+        Self { bindings, table: table.to_vec(), bind_groups, runs: runs(slots) }
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bindings.layout
+    }
+
+    // the scene was reloaded or its cubes replaced, the bind groups are only made again when the table changed
+    pub fn update(&mut self, device: &wgpu::Device, table: &[MaterialUniform], slots: &[u32]) {
+        if table != self.table {
+            self.bind_groups = table.iter().map(|&uniform| create_bind_group(device, &self.bindings, uniform)).collect();
+            self.table = table.to_vec();
+        }
+        self.runs = runs(slots);
+    }
+
+    pub fn get(&self, material: u32) -> &wgpu::BindGroup {
+        &self.bind_groups[material as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `seconds` of hovering (or not, with target 0) in steps of `dt`
+    fn fade(mut state: MaterialState, seconds: f32, dt: f32) -> MaterialState {
+        for _ in 0..(seconds / dt).round() as u32 {
+            state.advance(dt);
+        }
+        state
+    }
+
+    #[test]
+    fn glow_fades_in_over_the_fade_time_and_back_out() {
+        let hovered = MaterialState { emissive: 0.0, target: 1.0 };
+        let halfway = fade(hovered, HOVER_FADE_TIME / 2.0, 0.001);
+        assert!((halfway.emissive - 0.5).abs() < 0.01, "{:?}", halfway);
+        // a little past it, so rounding in the sum of the steps can't leave it a hair short of fully lit
+        let lit = fade(hovered, HOVER_FADE_TIME + 0.01, 0.001);
+        assert_eq!(lit.emissive, 1.0);
+        assert_eq!(lit.glow(), 1.0);
+
+        let left = fade(MaterialState { target: 0.0, ..lit }, HOVER_FADE_TIME + 0.01, 0.001);
+        assert_eq!(left.emissive, 0.0);
+        assert_eq!(left.glow(), 0.0);
+    }
+
+    #[test]
+    fn the_same_time_at_any_frame_rate_glows_the_same() {
+        let hovered = MaterialState { emissive: 0.0, target: 1.0 };
+        // times both rates reach in whole frames
+        for seconds in [3.0 / 60.0, 6.0 / 60.0, 8.0 / 60.0] {
+            let at_60 = fade(hovered, seconds, 1.0 / 60.0);
+            let at_240 = fade(hovered, seconds, 1.0 / 240.0);
+            assert!((at_60.emissive - at_240.emissive).abs() < 1e-5, "{}: {:?} vs {:?}", seconds, at_60, at_240);
+        }
+        // and one long frame lands where many short ones do, rather than overshooting
+        let mut long = hovered;
+        long.advance(1.0);
+        assert_eq!(long, fade(hovered, 1.0, 1.0 / 240.0));
+    }
+
+    #[test]
+    fn advance_reports_whether_anything_moved() {
+        let mut settled = MaterialState::default();
+        assert!(!settled.advance(0.016));
+        let mut fading = MaterialState { emissive: 0.0, target: 1.0 };
+        assert!(fading.advance(0.016));
+        assert!(fading.emissive > 0.0 && fading.emissive < 1.0);
+        // the eased glow is ahead of the linear progress, QuadOut lights up quickly
+        assert!(fading.glow() > fading.emissive);
+    }
+}
@@ -0,0 +1,476 @@
+// geometry on the CPU (MeshData) and on the GPU (Mesh): the vertices, the triangle indices and the edge indices for the
+// lines draw mode
+// generators like cube::make_cube() build a MeshData, the helpers below fix up its normals or weld its vertices, and
+// Mesh::new() uploads it. State keeps a list of Meshes and render() draws each one, so adding another object is one
+// more MeshData and Mesh::new()
+// indices are 16 bit on the GPU when every vertex can be reached with one, halving the index buffers of the small
+// meshes, and 32 bit otherwise, see Indices::new()
+use std::collections::HashMap;
+use std::ops::Range;
+
+use glam::{IVec3, Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::cube::Vertex;
+use crate::pipelines::DrawMode;
+use crate::render_stats::CountingPass;
+
+pub struct Mesh {
+    pub vertex_buffer: wgpu::Buffer, // positions, colors and normals, --deform rewrites the positions in place
+    pub num_vertices: u32,           // every vertex is one point in the points draw mode
+    pub index_buffer: wgpu::Buffer,  // three indices per triangle so shared vertices are stored once
+    pub num_indices: u32,
+    pub edge_index_buffer: wgpu::Buffer, // pairs of indices, one per line segment
+    pub num_edge_indices: u32,
+    pub index_format: wgpu::IndexFormat, // used by both index buffers, they index the same vertices so they agree
+}
+
+// a mesh before it is uploaded, u32 indices whatever its size, Mesh::new() narrows them if it can
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,      // a triangle list, counter-clockwise seen from the front
+    pub edge_indices: Vec<u32>, // a line list for the lines draw mode, which edges to draw is up to whoever built it
+}
+
+impl MeshData {
+    // the corners of every triangle, as indices into `vertices`
+    fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|triangle| [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize])
+    }
+
+    fn position(&self, index: usize) -> Vec3 {
+        Vec3::from(self.vertices[index].position)
+    }
+
+    // the smallest box around every vertex as (min, max), None for a mesh without any
+    pub fn aabb(&self) -> Option<(Vec3, Vec3)> {
+        let first = Vec3::from(self.vertices.first()?.position);
+        Some(self.vertices.iter().fold((first, first), |(min, max), vertex| {
+            let position = Vec3::from(vertex.position);
+            (min.min(position), max.max(position))
+        }))
+    }
+
+    // every vertex's normal becomes the average of the triangles it is a corner of, weighted by their areas (the cross
+    // product's length is twice the area, so it is left unnormalized until the end)
+    // only vertices that are shared get rounded off: the cube's faces have vertices of their own, so its edges stay
+    // sharp however many times this runs. A vertex in no triangle at all is left with a zero normal
+    pub fn compute_smooth_normals(&mut self) {
+        let mut sums = vec![Vec3::ZERO; self.vertices.len()];
+        for [a, b, c] in self.triangles() {
+            let normal = (self.position(b) - self.position(a)).cross(self.position(c) - self.position(a));
+            for corner in [a, b, c] {
+                sums[corner] += normal;
+            }
+        }
+        for (vertex, sum) in self.vertices.iter_mut().zip(sums) {
+            vertex.normal = sum.normalize_or_zero().to_array();
+        }
+    }
+
+    // gives every triangle three vertices of its own with the triangle's normal, so every edge between triangles is
+    // sharp, the opposite of compute_smooth_normals()
+    // the vertices come out in triangle order (the indices are just 0, 1, 2, ...), any the triangles didn't use are
+    // dropped, and each edge index moves to the first copy of its vertex, which is in the same place
+    pub fn compute_flat_normals(&mut self) {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+        let mut first_copy = vec![None; self.vertices.len()];
+        for [a, b, c] in self.triangles() {
+            let normal = (self.position(b) - self.position(a)).cross(self.position(c) - self.position(a)).normalize_or_zero();
+            for corner in [a, b, c] {
+                first_copy[corner].get_or_insert(vertices.len() as u32);
+                vertices.push(Vertex { normal: normal.to_array(), ..self.vertices[corner] });
+            }
+        }
+        self.indices = (0..vertices.len() as u32).collect();
+        self.edge_indices = remap_edges(&self.edge_indices, &first_copy);
+        self.vertices = vertices;
+    }
+
+    // a tangent for every vertex, pointing the way `uvs` (one texture coordinate per vertex) increase their u, for
+    // normal mapping. Vertex has no texture coordinates of its own, so they have to be handed in
+    // the xyz is at right angles to the vertex's normal, w is 1 or -1 for whether the bitangent (normal x tangent)
+    // points the way v increases or the other way, which flips where the texture is mirrored
+    // like the normals, each triangle's tangent is spread over its corners weighted by its area
+    pub fn compute_tangents(&self, uvs: &[[f32; 2]]) -> Vec<[f32; 4]> {
+        assert_eq!(uvs.len(), self.vertices.len(), "one texture coordinate per vertex");
+        let mut tangents = vec![Vec3::ZERO; self.vertices.len()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertices.len()];
+        for [a, b, c] in self.triangles() {
+            let (edge1, edge2) = (self.position(b) - self.position(a), self.position(c) - self.position(a));
+            let (uv1, uv2) = (Vec2::from(uvs[b]) - Vec2::from(uvs[a]), Vec2::from(uvs[c]) - Vec2::from(uvs[a]));
+            // solving edge = du * tangent + dv * bitangent for both edges means dividing by the determinant, multiplying
+            // by its sign instead gives the same directions scaled by the triangle's area in the texture, which is the
+            // weighting wanted anyway and can't blow up on a triangle whose texture coordinates are all in a line
+            let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+            let sign = if determinant < 0.0 { -1.0 } else { 1.0 };
+            let tangent = (edge1 * uv2.y - edge2 * uv1.y) * sign;
+            let bitangent = (edge2 * uv1.x - edge1 * uv2.x) * sign;
+            for corner in [a, b, c] {
+                tangents[corner] += tangent;
+                bitangents[corner] += bitangent;
+            }
+        }
+        self.vertices
+            .iter()
+            .zip(tangents.into_iter().zip(bitangents))
+            .map(|(vertex, (tangent, bitangent))| {
+                // Gram-Schmidt: take out whatever part of the tangent is along the normal
+                let normal = Vec3::from(vertex.normal);
+                let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+                let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+                tangent.extend(handedness).to_array()
+            })
+            .collect()
+    }
+
+    // welds vertices whose position, color and normal are each within `epsilon` of an earlier vertex's into that
+    // vertex, then points the indices at the vertices that are left. Returns how many were removed
+    // vertices differing only in their normal, like the corners of the cube's faces, stay apart, that is how a hard edge
+    // is made. Triangles that end up with two corners in the same vertex are kept, they just cover no pixels
+    // positions are bucketed into cells `epsilon` wide, so only the vertices in a cell and its 26 neighbours have to be
+    // compared instead of all of them
+    pub fn dedup_vertices(&mut self, epsilon: f32) -> usize {
+        let epsilon = epsilon.max(f32::MIN_POSITIVE);
+        let cell = |position: [f32; 3]| (Vec3::from(position) / epsilon).floor().as_ivec3();
+        let mut cells: HashMap<IVec3, Vec<u32>> = HashMap::new();
+        let mut kept: Vec<Vertex> = Vec::with_capacity(self.vertices.len());
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        for vertex in &self.vertices {
+            let home = cell(vertex.position);
+            let mut neighbours = (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))));
+            let existing = neighbours.find_map(|offset| {
+                cells.get(&(home + offset))?.iter().copied().find(|&index| close(&kept[index as usize], vertex, epsilon))
+            });
+            let index = existing.unwrap_or_else(|| {
+                kept.push(*vertex);
+                let index = kept.len() as u32 - 1;
+                cells.entry(home).or_default().push(index);
+                index
+            });
+            remap.push(index);
+        }
+        let removed = self.vertices.len() - kept.len();
+        for index in self.indices.iter_mut().chain(self.edge_indices.iter_mut()) {
+            *index = remap[*index as usize];
+        }
+        self.vertices = kept;
+        removed
+    }
+}
+
+// every attribute of the two vertices within `epsilon` of each other
+fn close(a: &Vertex, b: &Vertex, epsilon: f32) -> bool {
+    let near = |x: [f32; 3], y: [f32; 3]| (Vec3::from(x) - Vec3::from(y)).abs().max_element() <= epsilon;
+    near(a.position, b.position) && near(a.color, b.color) && near(a.normal, b.normal)
+}
+
+// `edges` with each end point moved to its new index, pairs with an end point that has none are dropped
+fn remap_edges(edges: &[u32], new_index: &[Option<u32>]) -> Vec<u32> {
+    edges
+        .chunks_exact(2)
+        .filter_map(|pair| Some([new_index[pair[0] as usize]?, new_index[pair[1] as usize]?]))
+        .flatten()
+        .collect()
+}
+
+// a mesh's indices in the smallest type that can address all of its vertices
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    // `num_vertices` decides the type rather than the largest index, so a mesh's triangle and edge indices always come
+    // out the same type even when one of them doesn't happen to use the last vertex
+    // u16::MAX itself is left out: it is the value that restarts a strip, which isn't meant to ever come up in a list
+    // but some drivers are known to treat it specially anyway
+    pub fn new(indices: &[u32], num_vertices: usize) -> Self {
+        if num_vertices <= u16::MAX as usize {
+            Indices::U16(indices.iter().map(|&index| index as u16).collect())
+        } else {
+            Indices::U32(indices.to_vec())
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            Indices::U16(_) => wgpu::IndexFormat::Uint16,
+            Indices::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    // what goes into the index buffer, create_buffer_init() pads an odd number of u16s out to the 4 byte alignment
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+impl Mesh {
+    // `vertex_usage` is added on top of VERTEX, e.g. STORAGE when a compute pass writes into the vertices
+    pub fn new(device: &wgpu::Device, label: &str, data: &MeshData, vertex_usage: wgpu::BufferUsages) -> Self {
+        let vertices = &data.vertices;
+        let indices = Indices::new(&data.indices, vertices.len());
+        let edge_indices = Indices::new(&data.edge_indices, vertices.len());
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", label)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX | vertex_usage,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", label)),
+            contents: indices.bytes(),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let edge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Edge Index Buffer", label)),
+            contents: edge_indices.bytes(),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            num_vertices: vertices.len() as u32,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            edge_index_buffer,
+            num_edge_indices: edge_indices.len() as u32,
+            index_format: indices.format(),
+        }
+    }
+
+    // expects the pipeline for `mode`, the bind group, the instance buffer (slot 1) and the emissive buffer (slot 2) to be set already
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, mode: DrawMode, instances: Range<u32>) {
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        match mode {
+            // a mesh without triangles or edges (cube::make_point()) has an empty index buffer, which can't be bound
+            DrawMode::Triangles if self.num_indices == 0 => {}
+            DrawMode::Lines if self.num_edge_indices == 0 => {}
+            DrawMode::Triangles => {
+                pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+                pass.draw_indexed(0..self.num_indices, 0, instances);
+            }
+            DrawMode::Lines => {
+                pass.set_index_buffer(self.edge_index_buffer.slice(..), self.index_format);
+                pass.draw_indexed(0..self.num_edge_indices, 0, instances);
+            }
+            // every vertex once, no index buffer needed
+            DrawMode::Points => pass.draw(0..self.num_vertices, instances),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::State;
+    use crate::cube::make_cube;
+    use crate::gpu;
+    use crate::options::Options;
+    use winit::dpi::PhysicalSize;
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn cube_mesh_has_two_triangles_per_face() {
+        let context = pollster::block_on(gpu::Context::headless()).expect("no GPU adapter");
+        let data = make_cube(1);
+        let mesh = Mesh::new(&context.device, "Test Cube", &data, wgpu::BufferUsages::empty());
+        // 6 faces x 2 triangles x 3 corners, over 4 vertices of each face's own
+        assert_eq!(mesh.num_indices, 36);
+        assert_eq!(mesh.num_vertices, 24);
+        assert_eq!(mesh.num_edge_indices, data.edge_indices.len() as u32);
+        assert_eq!(mesh.index_format, wgpu::IndexFormat::Uint16);
+        // u16 indices, the index buffer is half the size u32 ones would need
+        assert_eq!(mesh.index_buffer.size(), 36 * 2);
+    }
+
+    #[test]
+    fn indices_narrow_to_u16_up_to_the_limit() {
+        // the last vertex a u16 can reach without using u16::MAX itself
+        let small = Indices::new(&[0, 1, 65534], 65535);
+        assert_eq!(small, Indices::U16(vec![0, 1, 65534]));
+        assert_eq!(small.format(), wgpu::IndexFormat::Uint16);
+        assert_eq!(small.bytes().len(), 3 * 2);
+
+        // one vertex more and every index is 32 bit, even where the indices themselves would fit
+        let large = Indices::new(&[0, 1, 2], 65536);
+        assert_eq!(large, Indices::U32(vec![0, 1, 2]));
+        assert_eq!(large.format(), wgpu::IndexFormat::Uint32);
+        assert_eq!(large.bytes().len(), 3 * 4);
+        assert_eq!(Indices::new(&[0, 65535, 70000], 70001).bytes(), bytemuck::cast_slice(&[0u32, 65535, 70000]));
+
+        assert!(Indices::new(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn a_mesh_of_over_65k_vertices_selects_u32() {
+        // a big OBJ-sized mesh: 6 * 129^2 = 99846 vertices, so indices past 65535 are really used
+        let data = make_cube(128);
+        assert!(data.vertices.len() > 65535);
+        let triangles = Indices::new(&data.indices, data.vertices.len());
+        let edges = Indices::new(&data.edge_indices, data.vertices.len());
+        // both buffers are drawn with the same format, and nothing is cut off to 16 bits on the way
+        assert_eq!(triangles.format(), wgpu::IndexFormat::Uint32);
+        assert_eq!(edges.format(), wgpu::IndexFormat::Uint32);
+        assert_eq!(triangles, Indices::U32(data.indices.clone()));
+        assert_eq!(edges, Indices::U32(data.edge_indices.clone()));
+        assert_eq!(data.indices.iter().max(), Some(&(data.vertices.len() as u32 - 1)));
+    }
+
+    // needs a GPU (or a software adapter like llvmpipe), run with cargo test -- --ignored
+    // --subdivisions 104 makes a cube of 6 * 105^2 = 66150 vertices, just past what u16 indices reach
+    #[test]
+    #[ignore]
+    fn a_mesh_past_the_u16_limit_draws_with_u32_indices() {
+        assert!(make_cube(103).vertices.len() <= u16::MAX as usize);
+        let data = make_cube(104);
+        assert_eq!(data.vertices.len(), 66150);
+
+        // dropped before the State below makes an instance of its own, wgpu's GL backend can't open a second EGL
+        // display while the first is still in use
+        {
+            let context = pollster::block_on(gpu::Context::headless()).expect("no GPU adapter");
+            let mesh = Mesh::new(&context.device, "Test Cube", &data, wgpu::BufferUsages::empty());
+            assert_eq!(mesh.index_format, wgpu::IndexFormat::Uint32);
+            assert_eq!(mesh.index_buffer.size(), data.indices.len() as u64 * 4);
+        }
+
+        // and the whole renderer draws it, in the triangles and the lines modes, where any validation error (an index
+        // buffer of the wrong format, indices past the vertex buffer) panics in the device's error handler
+        for mode in ["triangles", "lines"] {
+            let options = Options::parse_from(["--subdivisions", "104", "--draw-mode", mode].map(String::from)).unwrap();
+            let mut state = pollster::block_on(State::headless(&options, PhysicalSize::new(64, 64))).expect("no GPU adapter");
+            assert_eq!(state.gpu.meshes[0].index_format, wgpu::IndexFormat::Uint32);
+            state.render(1.0).unwrap();
+            assert!(state.read_frame().is_some());
+            if mode == "triangles" {
+                assert!(state.render_stats().triangles >= data.indices.len() as u64 / 3);
+            }
+        }
+    }
+
+    // a unit square in the z = 0 plane facing +z, two triangles sharing the diagonal 0-2, outlined by its four sides
+    fn quad() -> MeshData {
+        let corner = |x: f32, y: f32| Vertex { position: [x, y, 0.0], color: [1.0; 3], normal: [0.0; 3] };
+        MeshData {
+            vertices: vec![corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)],
+            indices: vec![0, 1, 2, 2, 3, 0],
+            edge_indices: vec![0, 1, 1, 2, 2, 3, 3, 0],
+        }
+    }
+
+    #[test]
+    fn aabb_of_a_quad_and_of_nothing() {
+        assert_eq!(quad().aabb(), Some((Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0))));
+        assert_eq!(MeshData::default().aabb(), None);
+    }
+
+    #[test]
+    fn smooth_normals_of_a_flat_quad_all_face_up() {
+        let mut data = quad();
+        // a fifth vertex no triangle uses
+        data.vertices.push(Vertex { normal: [1.0, 0.0, 0.0], ..data.vertices[0] });
+        data.compute_smooth_normals();
+        for vertex in &data.vertices[..4] {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        }
+        assert_eq!(data.vertices[4].normal, [0.0; 3]);
+    }
+
+    #[test]
+    fn smooth_normals_leave_the_cube_faces_alone() {
+        let cube = make_cube(2);
+        let mut smoothed = cube.clone();
+        smoothed.compute_smooth_normals();
+        for (before, after) in cube.vertices.iter().zip(&smoothed.vertices) {
+            assert!(Vec3::from(after.normal).abs_diff_eq(Vec3::from(before.normal), 1e-6));
+        }
+    }
+
+    #[test]
+    fn flat_normals_give_every_corner_its_own_vertex() {
+        let mut data = quad();
+        data.vertices.push(data.vertices[0]); // unused, dropped
+        data.compute_flat_normals();
+        assert_eq!(data.vertices.len(), 6);
+        assert_eq!(data.indices, [0, 1, 2, 3, 4, 5]);
+        assert!(data.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+        // the copies in the first triangle order: 0 1 2 then 2 3 0, so corner 3 is first copied to vertex 4
+        assert_eq!(data.edge_indices, [0, 1, 1, 2, 2, 4, 4, 0]);
+        let positions: Vec<[f32; 3]> = data.vertices.iter().map(|vertex| vertex.position).collect();
+        assert_eq!(positions[2], positions[3]);
+        assert_eq!(positions[0], positions[5]);
+
+        // the cube's triangles: 36 vertices, each with its face's normal
+        let mut cube = make_cube(1);
+        let faces: Vec<[f32; 3]> = cube.indices.iter().map(|&index| cube.vertices[index as usize].normal).collect();
+        cube.compute_flat_normals();
+        assert_eq!(cube.vertices.len(), 36);
+        for (vertex, face) in cube.vertices.iter().zip(faces) {
+            assert!(Vec3::from(vertex.normal).abs_diff_eq(Vec3::from(face), 1e-6));
+        }
+    }
+
+    #[test]
+    fn tangents_follow_u_and_flip_where_the_texture_is_mirrored() {
+        let mut data = quad();
+        data.compute_smooth_normals();
+        // u along +x and v along +y: the tangent is +x, and normal x tangent = +y is the way v goes
+        let uvs: Vec<[f32; 2]> = data.vertices.iter().map(|vertex| [vertex.position[0], vertex.position[1]]).collect();
+        for tangent in data.compute_tangents(&uvs) {
+            assert_eq!(tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+        // u mirrored: the tangent turns to -x, and +z x -x = -y is against v now
+        let mirrored: Vec<[f32; 2]> = uvs.iter().map(|&[u, v]| [1.0 - u, v]).collect();
+        for tangent in data.compute_tangents(&mirrored) {
+            assert_eq!(tangent, [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn dedup_welds_the_shared_corners() {
+        // the quad as two triangles with three vertices each, the diagonal's ends stored twice
+        let mut data = quad();
+        data.compute_flat_normals();
+        // a hair away is still the same place
+        data.vertices[5].position[0] += 1e-6;
+        assert_eq!(data.dedup_vertices(1e-5), 2);
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices, [0, 1, 2, 2, 3, 0]);
+        assert_eq!(data.edge_indices, [0, 1, 1, 2, 2, 3, 3, 0]);
+        // already welded, nothing more to do
+        assert_eq!(data.dedup_vertices(1e-5), 0);
+    }
+
+    #[test]
+    fn dedup_keeps_the_cube_edges_hard() {
+        // the corners of the faces share positions and colors but not normals
+        let mut cube = make_cube(1);
+        assert_eq!(cube.dedup_vertices(1e-5), 0);
+        assert_eq!(cube.vertices.len(), 24);
+        // with the normals all the same the 24 become the cube's 8 corners
+        for vertex in &mut cube.vertices {
+            vertex.normal = [0.0; 3];
+        }
+        assert_eq!(cube.dedup_vertices(1e-5), 16);
+        assert_eq!(cube.aabb(), Some((Vec3::NEG_ONE, Vec3::ONE)));
+        assert!(cube.indices.iter().chain(&cube.edge_indices).all(|&index| index < 8));
+    }
+}
@@ -0,0 +1,679 @@
+// command-line options, declared once on Args for clap to parse, validate and turn into --help
+// Args::into_options() then sorts them into the groups the rest of the program takes: GpuOptions for the device
+// and swapchain, SceneOptions for what is drawn, WindowOptions for the windows it is drawn into
+// rotating-cube --help lists every flag, EXAMPLES below ends up at the bottom of it
+
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use tracing::level_filters::LevelFilter;
+
+use crate::adapter::{parse_backend, parse_power_preference};
+use crate::animation::CLIP_NAMES;
+use crate::camera::Lens;
+use crate::camera_control::CameraControls;
+use crate::gamepad::StickResponse;
+use crate::input::Keymap;
+use crate::instances::MAX_GRID;
+use crate::lights::DEFAULT_ATTENUATION;
+use crate::lod::Thresholds;
+use crate::particles::MAX_PARTICLES;
+use crate::pipelines::DrawMode;
+use crate::recorder::RecordSettings;
+use crate::scene_file::{SceneFile, DEFAULT_SCENE};
+
+// wgpu's default max_texture_dimension_2d
+const MAX_RENDER_DIMENSION: u32 = 8192;
+
+// upper bound for --subdivisions, 6 * 257^2 = ~400k vertices and ~2.4M indices
+const MAX_SUBDIVISIONS: u32 = 256;
+
+// upper bound for --windows, each one costs a swapchain, depth buffer and a full scene draw per frame
+const MAX_WINDOWS: u32 = 8;
+
+const EXAMPLES: &str = "\
+Examples:
+  rotating-cube --grid 8 --draw-mode lines
+  rotating-cube --grid 32 --lod 40,80
+  rotating-cube --print-scene > my_scene.toml && rotating-cube --scene my_scene.toml
+  rotating-cube --size 1280x720 --clear-color 203040 --windows 2
+  rotating-cube --record spin.gif --duration 4s --record-fps 25 --render-size 640x480
+  rotating-cube --bench 600 --bench-json > bench.json
+  rotating-cube --max-fps 30 --present-mode immediate --log-frames frames.csv
+  rotating-cube --stress 8
+  rotating-cube --backend gl --power low --present-mode mailbox --max-fps 144
+
+In the browser the same options go in the page's query string: index.html?grid=4&deform&clear-color=203040
+
+RUST_LOG overrides --log-level with a full filter, e.g. RUST_LOG=wgpu_core=debug,rotating_cube=trace";
+
+// everything clap parses, field by field as it appears on the command line
+// the checks that span several flags or depend on the build are left to into_options()
+#[derive(Parser)]
+#[command(name = "rotating-cube", about = "A lit, spinning cube drawn with wgpu", after_help = EXAMPLES)]
+struct Args {
+    /// Adapter whose name contains this text, see --list-adapters
+    #[arg(long, value_name = "NAME", help_heading = "GPU")]
+    adapter: Option<String>,
+
+    /// Graphics API wgpu may use [default: all of them]
+    #[arg(long, value_name = "vulkan|dx12|metal|gl", value_parser = parse_backend, help_heading = "GPU")]
+    backend: Option<wgpu::Backends>,
+
+    /// Integrated (low) or discrete (high) GPU when no --adapter is given
+    #[arg(long, ignore_case = true, default_value = "high", value_parser = PossibleValuesParser::new(["low", "high"]), help_heading = "GPU")]
+    power: String,
+
+    /// How frames are queued for display, fifo waits for vsync [default: fifo, immediate with --bench]
+    #[arg(long, ignore_case = true, value_parser = PossibleValuesParser::new(["fifo", "mailbox", "immediate"]), help_heading = "GPU")]
+    present_mode: Option<String>,
+
+    /// Reversed depth with an infinite far plane, for precision far from the camera
+    #[arg(long, help_heading = "GPU")]
+    reverse_z: bool,
+
+    /// Record every wgpu call into DIR for a bug report (needs a build with --features trace)
+    #[arg(long, value_name = "DIR", help_heading = "GPU")]
+    trace_dir: Option<PathBuf>,
+
+    /// Print the available adapters and exit
+    #[arg(long, help_heading = "GPU")]
+    list_adapters: bool,
+
+    /// Objects, light, camera start and background from a TOML file, reloaded when it changes [default: a cube and a glass cube]
+    #[arg(long, value_name = "PATH", help_heading = "Scene")]
+    scene: Option<PathBuf>,
+
+    /// Print the scene (--scene or the default) as TOML and exit
+    #[arg(long, help_heading = "Scene")]
+    print_scene: bool,
+
+    /// Draw an N x N grid of hue-cycling cubes instead of the scene's opaque cubes
+    #[arg(long, value_name = "N", conflicts_with = "scene", value_parser = clap::value_parser!(u32).range(1..=MAX_GRID as i64), help_heading = "Scene")]
+    grid: Option<u32>,
+
+    /// Draw the opaque cubes as tetrahedra beyond NEAR and as points beyond FAR from the camera, in world units
+    #[arg(long, value_name = "NEAR,FAR", value_parser = parse_lod, help_heading = "Scene")]
+    lod: Option<Thresholds>,
+
+    /// Starting draw mode, M cycles through them at runtime
+    #[arg(long, ignore_case = true, default_value = "triangles", value_parser = PossibleValuesParser::new(["triangles", "points", "lines"]), help_heading = "Scene")]
+    draw_mode: String,
+
+    /// Quads along each edge of a cube face, 1 is the plain cube
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=MAX_SUBDIVISIONS as i64), help_heading = "Scene")]
+    subdivisions: u32,
+
+    /// Ripple the vertices along their normals with a compute shader
+    #[arg(long, help_heading = "Scene")]
+    deform: bool,
+
+    /// Simulate this many GPU particles spraying from the cube's corners
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=MAX_PARTICLES as i64), help_heading = "Scene")]
+    particles: Option<u32>,
+
+    /// Drive the cube from a keyframe clip instead of spinning it
+    #[arg(long, value_parser = PossibleValuesParser::new(CLIP_NAMES), help_heading = "Scene")]
+    anim: Option<String>,
+
+    /// Background color as hex RRGGBB or RGB, the # is optional [default: the scene's, or black]
+    #[arg(long, value_name = "COLOR", value_parser = parse_color, help_heading = "Scene")]
+    clear_color: Option<[f64; 3]>,
+
+    /// Replace the background with a sky going through dawn, noon, dusk and night once every TIME, e.g. 60 or 2m
+    #[arg(long, value_name = "TIME", value_parser = parse_duration, help_heading = "Scene")]
+    day_length: Option<f32>,
+
+    /// Point light falloff as CONSTANT,LINEAR,QUADRATIC: a light d away is 1 / (constant + linear*d + quadratic*d^2) as bright, for the scene file's lights without their own [default: 1,0.2,0.1]
+    #[arg(long, value_name = "C,L,Q", value_parser = parse_attenuation, help_heading = "Scene")]
+    attenuation: Option<[f32; 3]>,
+
+    /// Number of windows showing the scene, each with its own camera
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=MAX_WINDOWS as i64), help_heading = "Window")]
+    windows: u32,
+
+    /// Starting size of each window, in logical pixels [default: what the OS picks]
+    #[arg(long, value_name = "WxH", value_parser = parse_size, help_heading = "Window")]
+    size: Option<(u32, u32)>,
+
+    /// Draw the scene into a centered W x H viewport in pixels instead of the whole window, also the --record size
+    #[arg(long, value_name = "WxH", value_parser = parse_size, help_heading = "Window")]
+    render_size: Option<(u32, u32)>,
+
+    /// Draw each window as two halves side by side with a camera each, clicking a half gives it the camera controls
+    #[arg(long, help_heading = "Window")]
+    split_screen: bool,
+
+    /// Rebind controls from a TOML file of action = "Key" lines, unlisted actions keep their keys [default: src/default_keymap.toml]
+    #[arg(long, value_name = "PATH", help_heading = "Window")]
+    keymap: Option<PathBuf>,
+
+    /// Starting vertical field of view in degrees, +/- zoom from it [default: the scene's, or 45]
+    #[arg(long, value_name = "DEGREES", value_parser = parse_fov, help_heading = "Window")]
+    fov_deg: Option<f32>,
+
+    /// Distance to the near clipping plane, anything closer is cut off [default: fitted to the scene]
+    #[arg(long, value_name = "DISTANCE", value_parser = parse_clip_distance, help_heading = "Window")]
+    near: Option<f32>,
+
+    /// Distance to the far clipping plane, anything further is cut off, unused in perspective with --reverse-z [default: fitted to the scene]
+    #[arg(long, value_name = "DISTANCE", value_parser = parse_clip_distance, help_heading = "Window")]
+    far: Option<f32>,
+
+    /// How long the camera takes to glide half the way to where the keys or mouse put it, 0 turns the smoothing off
+    #[arg(long, value_name = "TIME", default_value = "80ms", value_parser = parse_half_life, help_heading = "Window")]
+    camera_smoothing: f32,
+
+    /// Degrees the camera turns per pixel the mouse moves while the right button is held
+    #[arg(long, value_name = "DEGREES", default_value_t = 0.3, value_parser = parse_sensitivity, help_heading = "Window")]
+    mouse_sensitivity: f32,
+
+    /// Smooth mouse-look like the keys instead of turning the camera with the mouse exactly
+    #[arg(long, help_heading = "Window")]
+    mouse_smoothing: bool,
+
+    /// How far a gamepad stick or trigger moves before it counts, as a fraction of the way (needs --features gamepad) [default: 0.15]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_dead_zone, help_heading = "Window")]
+    gamepad_dead_zone: Option<f32>,
+
+    /// Power the rest of a stick's travel is raised to, 1 is linear and higher gives finer control near the middle [default: 2]
+    #[arg(long, value_name = "EXPONENT", value_parser = parse_curve, help_heading = "Window")]
+    gamepad_curve: Option<f32>,
+
+    /// Frame limiter target, only used when the present mode isn't vsynced
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Window")]
+    max_fps: Option<u32>,
+
+    /// Render this many measured frames, print statistics and exit
+    #[arg(long, value_name = "FRAMES", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Benchmark and recording")]
+    bench: Option<u32>,
+
+    /// Print the benchmark report as JSON instead of text
+    #[arg(long, requires = "bench", help_heading = "Benchmark and recording")]
+    bench_json: bool,
+
+    /// Double the number of cubes from 1000 every 5 s until frames average over MS, then report how far it got [default MS: 16]
+    #[arg(long, value_name = "MS", num_args = 0..=1, default_missing_value = "16", value_parser = parse_frame_target, conflicts_with_all = ["bench", "record", "grid", "scene"], help_heading = "Benchmark and recording")]
+    stress: Option<f64>,
+
+    /// Capture the animation to a GIF (out.gif) or PNG sequence (frames.png), then exit
+    #[arg(long, value_name = "PATH", help_heading = "Benchmark and recording")]
+    record: Option<PathBuf>,
+
+    /// How much animation to record, e.g. 5, 2.5s, 800ms or 1m [default: 5s]
+    #[arg(long, value_parser = parse_duration, requires = "record", help_heading = "Benchmark and recording")]
+    duration: Option<f32>,
+
+    /// Frame rate of the recording [default: 30]
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..=100), requires = "record", help_heading = "Benchmark and recording")]
+    record_fps: Option<u32>,
+
+    /// Write every frame's index, time, spin angle and fps to a CSV file, e.g. frames.csv
+    #[arg(long, value_name = "PATH", help_heading = "Benchmark and recording")]
+    log_frames: Option<PathBuf>,
+
+    /// How much this program logs, wgpu only adds its warnings and errors
+    #[arg(long, ignore_case = true, default_value = "info", value_parser = PossibleValuesParser::new(["off", "error", "warn", "info", "debug", "trace"]))]
+    log_level: String,
+}
+
+// the device and the swapchains it presents to
+pub struct GpuOptions {
+    pub backends: wgpu::Backends,                // graphics APIs wgpu may use, all of them unless --backend is given
+    pub adapter: Option<String>,                 // pick the adapter whose name contains this text
+    pub power_preference: wgpu::PowerPreference, // integrated (low) vs discrete (high) GPU when no --adapter is given
+    pub present_mode: wgpu::PresentMode,         // how frames are queued for display, Fifo = vsync
+    pub reverse_z: bool,                         // reversed depth with an infinite far plane, for precision far from the camera
+    pub trace_dir: Option<PathBuf>,              // record wgpu's API calls here for a bug report, needs the trace feature
+}
+
+// what Scene::new() builds and how it is drawn at first
+pub struct SceneOptions {
+    pub file: SceneFile,               // --scene, or the built-in default_scene.toml
+    pub path: Option<PathBuf>,         // --scene, watched for changes while running
+    pub grid: Option<u32>,             // draw an N x N grid of hue-cycling cubes instead of a single cube
+    pub lod: Option<Thresholds>,       // distances from the camera where the cubes get simpler, None draws them all in full
+    pub draw_mode: DrawMode,           // starting draw mode, M cycles through them at runtime
+    pub subdivisions: u32,             // quads along each edge of a cube face, 1 = the plain cube
+    pub deform: bool,                  // ripple the vertices along their normals with a compute shader
+    pub particles: Option<u32>,        // simulate this many GPU particles spraying from the cube's corners
+    pub anim: Option<String>,          // drive the cube from this keyframe clip instead of spinning it
+    pub clear_color: Option<[f64; 3]>, // --clear-color as sRGB components 0-1, in place of the scene file's background
+    pub day_length: Option<f32>,       // seconds from one dawn to the next, None keeps the plain background
+    pub attenuation: [f32; 3],         // constant, linear and quadratic falloff with distance of the point lights that don't give their own
+}
+
+pub struct WindowOptions {
+    pub count: u32,                      // number of windows showing the scene, each with its own camera
+    pub size: Option<(u32, u32)>,        // starting logical size of each window, None leaves it to the OS
+    pub render_size: Option<(u32, u32)>, // draw the scene into a centered W x H viewport instead of the whole window
+    pub split_screen: bool,              // two cameras per window, side by side in the viewport's halves
+    pub keymap: Keymap,                  // the built-in controls with --keymap's changes
+    pub camera: CameraControls,          // camera smoothing and mouse-look settings
+    pub lens: Lens,                      // --fov-deg, --near and --far, each None when not given
+    pub gamepad: StickResponse,          // dead zone and response curve of the gamepad sticks and triggers
+}
+
+pub struct Options {
+    pub gpu: GpuOptions,
+    pub scene: SceneOptions,
+    pub window: WindowOptions,
+    pub max_fps: Option<u32>,           // frame limiter target, only used when the present mode isn't vsynced
+    pub bench: Option<u32>,             // render this many measured frames, print statistics and exit
+    pub bench_json: bool,               // print the benchmark report as JSON instead of text
+    pub stress: Option<f64>,            // grow the cube count until frames average over this many ms, then report
+    pub list_adapters: bool,            // print the available adapters and exit
+    pub print_scene: bool,              // print the scene as TOML and exit
+    pub record: Option<RecordSettings>, // capture the animation to a GIF or PNG sequence, then exit
+    pub log_frames: Option<PathBuf>,    // CSV file getting a row per frame, None (the default) writes nothing
+    pub log_level: LevelFilter,         // how much this program logs, wgpu only adds its warnings and errors (RUST_LOG overrides both)
+}
+
+impl Options {
+    // clap prints --help or the error itself and exits (0 for help, 2 for a bad option)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn parse() -> Result<Self, String> {
+        Args::parse().into_options()
+    }
+
+    // there is no command line in the browser, the page's query string stands in for it:
+    // index.html?grid=4&deform&draw-mode=lines is parsed as --grid 4 --deform --draw-mode lines
+    #[cfg(target_arch = "wasm32")]
+    pub fn parse() -> Result<Self, String> {
+        let search = web_sys::window().and_then(|window| window.location().search().ok()).unwrap_or_default();
+        let args: Vec<String> = search
+            .trim_start_matches('?')
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .flat_map(|pair| match pair.split_once('=') {
+                Some((name, value)) => vec![format!("--{}", name), value.to_string()],
+                None => vec![format!("--{}", pair)],
+            })
+            .collect();
+        let options = Self::parse_from(args)?;
+        // these need a filesystem or a list of adapters, neither of which a web page has
+        if options.record.is_some()
+            || options.log_frames.is_some()
+            || options.list_adapters
+            || options.gpu.adapter.is_some()
+            || options.gpu.trace_dir.is_some()
+        {
+            return Err("record, log-frames, list-adapters, adapter and trace-dir aren't available in the browser".into());
+        }
+        Ok(options)
+    }
+
+    // `args` without the program name, --help comes back as an Err holding the help text
+    pub fn parse_from<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let args = std::iter::once("rotating-cube".to_string()).chain(args);
+        Args::try_parse_from(args).map_err(|err| err.to_string())?.into_options()
+    }
+}
+
+impl Args {
+    fn into_options(self) -> Result<Options, String> {
+        // wgpu ignores the path without the feature, better to say so than to leave the directory empty
+        if self.trace_dir.is_some() && !cfg!(feature = "trace") {
+            return Err("--trace-dir needs a build with --features trace".into());
+        }
+        // the same for settings of gamepads that this build can't see
+        if (self.gamepad_dead_zone.is_some() || self.gamepad_curve.is_some()) && !cfg!(feature = "gamepad") {
+            return Err("--gamepad-dead-zone and --gamepad-curve need a build with --features gamepad".into());
+        }
+
+        // each is checked on its own by clap, but only together can they be the wrong way round
+        if let (Some(near), Some(far)) = (self.near, self.far) {
+            if near >= far {
+                return Err(format!("--near ({}) must be less than --far ({})", near, far));
+            }
+        }
+
+        let file = match &self.scene {
+            // the browser has no files to read, the query string can't point at one
+            Some(_) if cfg!(target_arch = "wasm32") => return Err("scene isn't available in the browser".into()),
+            Some(path) => SceneFile::load(path)?,
+            None => SceneFile::parse(DEFAULT_SCENE).map_err(|err| format!("built-in scene: {}", err))?,
+        };
+
+        let keymap = match &self.keymap {
+            Some(_) if cfg!(target_arch = "wasm32") => return Err("keymap isn't available in the browser".into()),
+            Some(path) => Keymap::load(path)?,
+            None => Keymap::with_overrides(None)?,
+        };
+
+        // benchmarks should measure how fast frames can be rendered, not the display's refresh rate
+        // so --bench and --stress switch the default present mode, but an explicit --present-mode wins
+        let present_mode = match self.present_mode.as_deref() {
+            Some(value) => parse_present_mode(value)?,
+            None if self.bench.is_some() || self.stress.is_some() => wgpu::PresentMode::Immediate,
+            None => wgpu::PresentMode::Fifo,
+        };
+
+        // clap already checked these against the same lists, the parse functions only map them onto the types
+        let gpu = GpuOptions {
+            backends: self.backend.unwrap_or(wgpu::Backends::all()),
+            adapter: self.adapter,
+            power_preference: parse_power_preference(&self.power)?,
+            present_mode,
+            reverse_z: self.reverse_z,
+            trace_dir: self.trace_dir,
+        };
+        let scene = SceneOptions {
+            grid: self.grid,
+            lod: self.lod,
+            draw_mode: parse_draw_mode(&self.draw_mode)?,
+            subdivisions: self.subdivisions,
+            deform: self.deform,
+            particles: self.particles,
+            anim: self.anim,
+            clear_color: self.clear_color,
+            day_length: self.day_length,
+            attenuation: self.attenuation.unwrap_or(DEFAULT_ATTENUATION),
+            file,
+            path: self.scene,
+        };
+        let window = WindowOptions {
+            count: self.windows,
+            size: self.size,
+            render_size: self.render_size,
+            split_screen: self.split_screen,
+            keymap,
+            camera: CameraControls {
+                half_life: self.camera_smoothing,
+                mouse_sensitivity: self.mouse_sensitivity.to_radians(),
+                mouse_smoothing: self.mouse_smoothing,
+            },
+            lens: Lens { fov_deg: self.fov_deg, near: self.near, far: self.far },
+            gamepad: StickResponse {
+                dead_zone: self.gamepad_dead_zone.unwrap_or(StickResponse::default().dead_zone),
+                curve: self.gamepad_curve.unwrap_or(StickResponse::default().curve),
+            },
+        };
+        let record = self.record.map(|path| RecordSettings {
+            path,
+            duration: self.duration.unwrap_or(5.0),
+            fps: self.record_fps.unwrap_or(30),
+        });
+
+        Ok(Options {
+            gpu,
+            scene,
+            window,
+            max_fps: self.max_fps,
+            bench: self.bench,
+            bench_json: self.bench_json,
+            stress: self.stress,
+            list_adapters: self.list_adapters,
+            print_scene: self.print_scene,
+            record,
+            log_frames: self.log_frames,
+            log_level: self.log_level.parse::<LevelFilter>().map_err(|err| err.to_string())?,
+        })
+    }
+}
+
+fn parse_present_mode(value: &str) -> Result<wgpu::PresentMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "fifo" => Ok(wgpu::PresentMode::Fifo),
+        "mailbox" => Ok(wgpu::PresentMode::Mailbox),
+        "immediate" => Ok(wgpu::PresentMode::Immediate),
+        _ => Err(format!("expected fifo, mailbox or immediate, got '{}'", value)),
+    }
+}
+
+fn parse_draw_mode(value: &str) -> Result<DrawMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "triangles" => Ok(DrawMode::Triangles),
+        "points" => Ok(DrawMode::Points),
+        "lines" => Ok(DrawMode::Lines),
+        _ => Err(format!("expected triangles, points or lines, got '{}'", value)),
+    }
+}
+
+// --size and --render-size, "1280x720": both sides at least 1 pixel and at most wgpu's default texture size limit
+// since a --record capture is made at the render size
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT like 1280x720, got '{}'", value))?;
+    let side = |text: &str| {
+        let pixels = text
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("expected WIDTHxHEIGHT like 1280x720, got '{}'", value))?;
+        if pixels == 0 || pixels > MAX_RENDER_DIMENSION {
+            return Err(format!("width and height must be between 1 and {}, got '{}'", MAX_RENDER_DIMENSION, value));
+        }
+        Ok(pixels)
+    };
+    Ok((side(width)?, side(height)?))
+}
+
+// --clear-color, "203040" or "#203040", or the short "234" which is the same as "223344" like in CSS
+// the components stay in sRGB, the way color pickers show them
+pub fn parse_color(value: &str) -> Result<[f64; 3], String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let digits: Vec<u32> = hex
+        .chars()
+        .map(|c| c.to_digit(16))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("expected a hex color like 203040, got '{}'", value))?;
+    let channels = match digits.len() {
+        3 => [digits[0] * 17, digits[1] * 17, digits[2] * 17],
+        6 => [digits[0] * 16 + digits[1], digits[2] * 16 + digits[3], digits[4] * 16 + digits[5]],
+        _ => return Err(format!("expected 3 or 6 hex digits, got '{}'", value)),
+    };
+    Ok(channels.map(|channel| channel as f64 / 255.0))
+}
+
+// --camera-smoothing, a time like --duration takes, or 0 / off for none
+fn parse_half_life(value: &str) -> Result<f32, String> {
+    match value.trim() {
+        "0" | "off" => Ok(0.0),
+        _ => parse_duration(value),
+    }
+}
+
+// --fov-deg, the same range the scene file's [camera] fov allows: 0 would show nothing and 180 everything squashed
+// into a point
+fn parse_fov(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(degrees) if degrees > 0.0 && degrees < 180.0 => Ok(degrees),
+        _ => Err(format!("expected degrees between 0 and 180 (exclusive), got '{}'", value)),
+    }
+}
+
+// --near and --far, a near plane at 0 would divide by zero in the projection
+fn parse_clip_distance(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(distance) if distance > 0.0 && distance.is_finite() => Ok(distance),
+        _ => Err(format!("expected a positive distance, got '{}'", value)),
+    }
+}
+
+// zero would make mouse-look do nothing, which unbinding it in --keymap says more clearly
+fn parse_sensitivity(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(degrees) if degrees > 0.0 && degrees.is_finite() => Ok(degrees),
+        _ => Err(format!("expected a positive number of degrees per pixel, got '{}'", value)),
+    }
+}
+
+// 1 would leave no travel outside the dead zone
+fn parse_dead_zone(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(fraction) if (0.0..1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("expected a fraction from 0 up to (but not) 1, got '{}'", value)),
+    }
+}
+
+fn parse_curve(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(exponent) if exponent > 0.0 && exponent.is_finite() => Ok(exponent),
+        _ => Err(format!("expected a positive exponent, got '{}'", value)),
+    }
+}
+
+// --attenuation, "1,0.2,0.1": none of them negative, and a constant term so a surface right at the light isn't lit
+// infinitely brightly
+fn parse_attenuation(value: &str) -> Result<[f32; 3], String> {
+    let expected = || format!("expected CONSTANT,LINEAR,QUADRATIC like 1,0.2,0.1, got '{}'", value);
+    let terms: Vec<f32> = value.split(',').map(|term| term.trim().parse::<f32>()).collect::<Result<_, _>>().map_err(|_| expected())?;
+    let [constant, linear, quadratic] = terms[..] else { return Err(expected()) };
+    if !(constant > 0.0 && constant.is_finite()) {
+        return Err(format!("the constant term must be above 0, got '{}'", value));
+    }
+    if !(linear >= 0.0 && quadratic >= 0.0 && linear.is_finite() && quadratic.is_finite()) {
+        return Err(format!("the linear and quadratic terms can't be negative, got '{}'", value));
+    }
+    Ok([constant, linear, quadratic])
+}
+
+// --stress's target, a frame can't take no time at all
+fn parse_frame_target(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ms) if ms > 0.0 && ms.is_finite() => Ok(ms),
+        _ => Err(format!("expected a positive number of milliseconds, got '{}'", value)),
+    }
+}
+
+// --lod, "30,90": both positive and the tetrahedra's range between them, otherwise it would be a level nothing is drawn at
+fn parse_lod(value: &str) -> Result<Thresholds, String> {
+    let expected = || format!("expected NEAR,FAR like 30,90, got '{}'", value);
+    let distances: Vec<f32> = value.split(',').map(|distance| distance.trim().parse::<f32>()).collect::<Result<_, _>>().map_err(|_| expected())?;
+    let [near, far] = distances[..] else { return Err(expected()) };
+    if !(near > 0.0 && far.is_finite()) {
+        return Err(format!("the distances must be positive, got '{}'", value));
+    }
+    if near >= far {
+        return Err(format!("NEAR must be less than FAR, got '{}'", value));
+    }
+    Ok(Thresholds { near, far })
+}
+
+// --duration, plain seconds or with a unit: "5", "2.5s", "800ms", "1m"
+fn parse_duration(value: &str) -> Result<f32, String> {
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = value.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (value, 1.0)
+    };
+    let seconds = number
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| format!("expected seconds like 5, 2.5s, 800ms or 1m, got '{}'", value))?
+        * scale;
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        return Err(format!("must be a positive amount of time, got '{}'", value));
+    }
+    Ok(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_take_6_or_3_digits_with_an_optional_hash() {
+        assert_eq!(parse_color("ff0080"), Ok([1.0, 0.0, 128.0 / 255.0]));
+        assert_eq!(parse_color("#FF0080"), parse_color("ff0080"));
+        // short form, each digit doubled like CSS
+        assert_eq!(parse_color("#f08"), parse_color("ff0088"));
+        assert_eq!(parse_color("000"), Ok([0.0; 3]));
+    }
+
+    #[test]
+    fn colors_reject_other_lengths_and_non_hex() {
+        assert!(parse_color("").is_err());
+        assert!(parse_color("#").is_err());
+        assert!(parse_color("ff00").is_err());
+        assert!(parse_color("ff008000").is_err());
+        assert!(parse_color("gg0000").is_err());
+        // a sign would get past a plain from_str_radix, not past the digit by digit parse
+        assert!(parse_color("+f0").is_err());
+        assert_eq!(parse_color("12345"), Err("expected 3 or 6 hex digits, got '12345'".to_string()));
+    }
+
+    #[test]
+    fn sizes_are_width_x_height_within_the_texture_limit() {
+        assert_eq!(parse_size("1280x720"), Ok((1280, 720)));
+        assert_eq!(parse_size("640X480"), Ok((640, 480)));
+        assert_eq!(parse_size(" 800 x 600 "), Ok((800, 600)));
+        assert_eq!(parse_size("1x8192"), Ok((1, MAX_RENDER_DIMENSION)));
+
+        assert!(parse_size("1280").is_err());
+        assert!(parse_size("1280x").is_err());
+        assert!(parse_size("x720").is_err());
+        assert!(parse_size("-1x720").is_err());
+        assert!(parse_size("1.5x720").is_err());
+        assert!(parse_size("0x720").is_err());
+        assert!(parse_size("8193x720").is_err());
+    }
+
+    #[test]
+    fn durations_take_seconds_or_a_unit() {
+        assert_eq!(parse_duration("5"), Ok(5.0));
+        assert_eq!(parse_duration("2.5s"), Ok(2.5));
+        assert_eq!(parse_duration("1m"), Ok(60.0));
+        let ms = parse_duration("800ms").unwrap();
+        assert!((ms - 0.8).abs() < 1e-6);
+
+        for bad in ["", "s", "ms", "five", "5h", "0", "0s", "-1", "inf", "NaN"] {
+            assert!(parse_duration(bad).is_err(), "'{}' parsed", bad);
+        }
+        // the smoothing half-life also takes 0 / off, where a plain duration can't be 0
+        assert_eq!(parse_half_life("off"), Ok(0.0));
+        assert_eq!(parse_half_life("0"), Ok(0.0));
+        assert_eq!(parse_half_life("250ms"), parse_duration("250ms"));
+        assert!(parse_half_life("-1s").is_err());
+    }
+
+    #[test]
+    fn ranges_of_the_single_number_options() {
+        assert_eq!(parse_fov("60"), Ok(60.0));
+        for bad in ["0", "180", "-10", "wide"] {
+            assert!(parse_fov(bad).is_err(), "fov '{}' parsed", bad);
+        }
+        assert_eq!(parse_clip_distance("0.1"), Ok(0.1));
+        assert!(parse_clip_distance("0").is_err());
+        assert!(parse_clip_distance("inf").is_err());
+        assert_eq!(parse_dead_zone("0"), Ok(0.0));
+        assert!(parse_dead_zone("1").is_err());
+        assert!(parse_dead_zone("-0.1").is_err());
+        assert!(parse_curve("0").is_err());
+        assert!(parse_sensitivity("-0.2").is_err());
+        assert!(parse_frame_target("0").is_err());
+        assert_eq!(parse_present_mode("Mailbox"), Ok(wgpu::PresentMode::Mailbox));
+        assert!(parse_present_mode("vsync").is_err());
+    }
+
+    #[test]
+    fn lists_need_every_term_in_range() {
+        assert_eq!(parse_attenuation("1,0.2,0.1"), Ok([1.0, 0.2, 0.1]));
+        assert_eq!(parse_attenuation("1, 0, 0"), Ok([1.0, 0.0, 0.0]));
+        assert!(parse_attenuation("1,0.2").is_err());
+        assert!(parse_attenuation("1,0.2,0.1,0").is_err());
+        assert!(parse_attenuation("0,0.2,0.1").is_err());
+        assert!(parse_attenuation("1,-0.2,0.1").is_err());
+
+        let lod = parse_lod("30,90").unwrap();
+        assert_eq!((lod.near, lod.far), (30.0, 90.0));
+        assert!(parse_lod("90,30").is_err());
+        assert!(parse_lod("30,30").is_err());
+        assert!(parse_lod("0,90").is_err());
+        assert!(parse_lod("30").is_err());
+    }
+
+    #[test]
+    fn bad_values_surface_from_the_command_line_parse() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(Options::parse_from(args(&["--clear-color", "#123"])).is_ok());
+        assert!(Options::parse_from(args(&["--clear-color", "blue"])).is_err());
+        assert!(Options::parse_from(args(&["--size", "0x0"])).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+// --particles N: GPU particle system spraying out of the cube's corners
+// positions and velocities never leave the GPU, a compute shader steps them every frame and the render pass draws
+// the same buffer as a point list, so the CPU cost stays the same whether there are 1k or 1M particles
+//
+// the state is double buffered (ping-pong): each frame reads buffer A and writes buffer B, the next frame the other way
+// round, so no particle ever reads a value another invocation is writing in the same dispatch
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::{Bindings, BindingsBuilder};
+use crate::depth;
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::render_stats::{CountingPass, CountingQueue};
+
+// must match @workgroup_size in particles_compute.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+// upper bound for --particles, keeps the buffers (32 bytes per particle) well inside default storage buffer limits
+pub const MAX_PARTICLES: u32 = 2_000_000;
+
+const GRAVITY: f32 = 2.0; // units per second squared, gentle so the sparks hang around the cube
+const DRAG: f32 = 0.4;    // fraction of velocity lost per second
+
+// matches Particle in particles_compute.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Particle {
+    position: [f32; 4], // xyz = world position, w = seconds of life left
+    velocity: [f32; 4],
+}
+
+// matches Sim in particles_compute.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct SimUniform {
+    model: [[f32; 4]; 4],
+    dt: f32,
+    seed: u32,
+    gravity: f32,
+    drag: f32,
+}
+
+pub struct Particles {
+    count: u32,
+    buffers: [wgpu::Buffer; 2],
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_groups: [wgpu::BindGroup; 2], // [0] reads buffers[0] and writes buffers[1], [1] the other way round
+    sim_buffer: wgpu::Buffer,
+    render_pipeline: Arc<wgpu::RenderPipeline>,
+    render_key: PipelineKey, // kept to build it again for another depth buffer
+    render_bindings: Bindings, // camera only, each window makes its own bind group with camera_bind_group()
+    frame: u32, // dispatches so far, its parity says which buffer holds the latest state
+}
+
+impl Particles {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        count: u32,
+        reverse_z: bool,
+    ) -> Self {
+        // ----- State buffers -----
+        // every particle starts dead with a staggered countdown, so they respawn gradually instead of all on frame one
+        // until then they sit at the origin inside the cube where the depth test hides them
+        let initial: Vec<Particle> = (0..count)
+            .map(|i| Particle {
+                position: [0.0, 0.0, 0.0, 3.0 * i as f32 / count as f32],
+                velocity: [0.0; 4],
+            })
+            .collect();
+        let buffers = [0, 1].map(|i| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("Particle Buffer {}", i)),
+                contents: bytemuck::cast_slice(&initial),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX, //written by compute, read as vertices
+            })
+        });
+
+        let sim_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Sim Buffer"),
+            size: std::mem::size_of::<SimUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // ----- Compute -----
+        let compute_bindings = BindingsBuilder::new("Particle Compute")
+            .uniform(0, wgpu::ShaderStages::COMPUTE)
+            .storage(1, wgpu::ShaderStages::COMPUTE, true)
+            .storage(2, wgpu::ShaderStages::COMPUTE, false)
+            .build(device);
+        let compute_bind_groups = [0, 1].map(|i| {
+            compute_bindings.bind_group(
+                device,
+                &[sim_buffer.as_entire_binding(), buffers[i].as_entire_binding(), buffers[1 - i].as_entire_binding()],
+            )
+        });
+
+        let compute_shader = device.create_shader_module(wgpu::include_wgsl!("particles_compute.wgsl"));
+        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Compute Pipeline Layout"),
+            bind_group_layouts: &[&compute_bindings.layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Compute Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: "cs_main",
+        });
+
+        // ----- Render -----
+        let render_bindings = BindingsBuilder::new("Particle Render").uniform(0, wgpu::ShaderStages::VERTEX).build(device);
+        // additive: overlapping particles add up, dense areas glow
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+        // hidden behind the cube, but they don't write depth since additive blending doesn't care about order
+        let key = PipelineKey {
+            topology: wgpu::PrimitiveTopology::PointList,
+            depth_compare: Some(depth::closer(reverse_z)),
+            depth_format,
+            ..PipelineKey::new(ShaderId::new("particles.wgsl", "vs_main", "fs_main"), format, additive)
+        };
+        let render_pipeline = render_pipeline(device, cache, &render_bindings, key.clone());
+
+        Self {
+            count,
+            buffers,
+            compute_pipeline,
+            compute_bind_groups,
+            sim_buffer,
+            render_pipeline,
+            render_key: key,
+            render_bindings,
+            frame: 0,
+        }
+    }
+
+    // drawn into depth buffers of another format from now on, the particles themselves carry on where they are
+    pub fn set_depth_format(&mut self, device: &wgpu::Device, cache: &PipelineCache, depth_format: wgpu::TextureFormat) {
+        self.render_key.depth_format = depth_format;
+        self.render_pipeline = render_pipeline(device, cache, &self.render_bindings, self.render_key.clone());
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    // upload this frame's step size and cube transform, then record the compute pass
+    // must be recorded before the render pass so draw() sees the updated positions
+    pub fn step(&mut self, queue: &CountingQueue, encoder: &mut wgpu::CommandEncoder, dt: f32, model: Mat4) {
+        let sim = SimUniform {
+            model: model.to_cols_array_2d(),
+            dt,
+            seed: self.frame.wrapping_mul(0x9E37_79B9), // spread consecutive frames far apart before hashing
+            gravity: GRAVITY,
+            drag: DRAG,
+        };
+        queue.write_buffer(&self.sim_buffer, 0, bytemuck::bytes_of(&sim));
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Particle Pass") });
+        pass.set_pipeline(&self.compute_pipeline);
+        pass.set_bind_group(0, &self.compute_bind_groups[(self.frame % 2) as usize], &[]);
+        pass.dispatch_workgroups(self.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        drop(pass);
+
+        self.frame += 1;
+    }
+
+    // bind group for draw() that reads the same camera buffer as a window's cube
+    pub fn camera_bind_group(&self, device: &wgpu::Device, camera_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        self.render_bindings.bind_group(device, &[camera_buffer.as_entire_binding()])
+    }
+
+    // draws whichever buffer the last step() wrote, seen through the camera in `camera` (from camera_bind_group())
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, camera: &'a wgpu::BindGroup) {
+        let latest = &self.buffers[(self.frame % 2) as usize];
+        pass.set_pipeline(&self.render_pipeline, wgpu::PrimitiveTopology::PointList);
+        pass.set_bind_group(0, camera, &[]);
+        pass.set_vertex_buffer(0, latest.slice(..));
+        pass.draw(0..self.count, 0..1);
+    }
+}
+
+// the pipeline draw() uses, only this one goes through the cache: the compute pipeline has no color target or depth
+// test a PipelineKey could describe, and there is only ever one of it
+fn render_pipeline(device: &wgpu::Device, cache: &PipelineCache, bindings: &Bindings, key: PipelineKey) -> Arc<wgpu::RenderPipeline> {
+    cache.get_or_create(key, |key| {
+        let render_shader = device.create_shader_module(wgpu::include_wgsl!("particles.wgsl"));
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Render Pipeline Layout"),
+            bind_group_layouts: &[&bindings.layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &render_shader,
+                entry_point: "vs_main",
+                // read straight from the state buffer, only the first vec4 (position + life) of each particle
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &render_shader,
+                entry_point: "fs_main",
+                targets: &key.targets(),
+            }),
+            primitive: key.primitive(),
+            depth_stencil: key.depth_stencil(),
+            multisample: key.multisample(),
+            multiview: None,
+        })
+    })
+}
@@ -0,0 +1,30 @@
+// Particle render shader: every particle is one point, colors add up where many overlap (additive blending)
+struct Camera {
+    view_proj: mat4x4<f32>
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct ParticleInput {
+    @location(0) position: vec4<f32>, // xyz = world position, w = seconds of life left
+};
+
+struct ParticleOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(input: ParticleInput) -> ParticleOutput {
+    var output: ParticleOutput;
+    output.clip_position = camera.view_proj * vec4<f32>(input.position.xyz, 1.0);
+    // young particles are hot yellow, fading through orange to nothing as their life runs out
+    let heat = clamp(input.position.w / 3.0, 0.0, 1.0);
+    output.color = vec3<f32>(1.0, 0.3 + 0.6 * heat, 0.1 * heat) * heat * 0.6;
+    return output;
+}
+
+@fragment
+fn fs_main(input: ParticleOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
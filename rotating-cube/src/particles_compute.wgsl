@@ -0,0 +1,72 @@
+// Particle simulation compute shader: one invocation per particle, reads last frame's state and writes this frame's
+// reading and writing different buffers (ping-pong) means no invocation can see a half-updated neighbour
+struct Particle {
+    position: vec4<f32>, // xyz = world position, w = seconds of life left
+    velocity: vec4<f32>, // xyz = world velocity, w unused
+};
+
+struct Sim {
+    model: mat4x4<f32>, // cube transform this frame, particles respawn at its transformed corners
+    dt: f32,            // seconds since the previous dispatch
+    seed: u32,          // changes every frame so respawned particles don't repeat the same random numbers
+    gravity: f32,
+    drag: f32,          // fraction of velocity lost per second
+};
+@group(0) @binding(0)
+var<uniform> sim: Sim;
+
+@group(0) @binding(1)
+var<storage, read> source: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> destination: array<Particle>;
+
+const MIN_LIFE: f32 = 1.0;
+const MAX_LIFE: f32 = 3.0;
+const EMIT_SPEED: f32 = 1.5;
+
+// integer hash (PCG), good enough randomness for scattering particles without a random number buffer
+fn hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// 0..1 from a hash
+fn random(seed: u32) -> f32 {
+    return f32(hash(seed)) / 4294967295.0;
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= arrayLength(&source)) {
+        return;
+    }
+    var particle = source[index];
+    particle.position.w -= sim.dt;
+
+    if (particle.position.w <= 0.0) {
+        // respawn at one of the 8 corners, bits of the corner number pick -1 or +1 per axis
+        let seed = hash(index ^ sim.seed);
+        let corner_id = seed % 8u;
+        let corner = vec3<f32>(
+            select(-1.0, 1.0, (corner_id & 1u) != 0u),
+            select(-1.0, 1.0, (corner_id & 2u) != 0u),
+            select(-1.0, 1.0, (corner_id & 4u) != 0u),
+        );
+        let jitter = vec3<f32>(random(seed + 1u), random(seed + 2u), random(seed + 3u)) - 0.5;
+        // shoot out away from the cube's centre, rotated along with the cube
+        let direction = (sim.model * vec4<f32>(normalize(corner) + jitter, 0.0)).xyz;
+        particle.position = vec4<f32>((sim.model * vec4<f32>(corner, 1.0)).xyz, mix(MIN_LIFE, MAX_LIFE, random(seed + 4u)));
+        particle.velocity = vec4<f32>(direction * EMIT_SPEED, 0.0);
+    } else {
+        // semi-implicit Euler: update velocity first, then move with the new velocity
+        var velocity = particle.velocity.xyz;
+        velocity.y -= sim.gravity * sim.dt;
+        velocity *= max(1.0 - sim.drag * sim.dt, 0.0);
+        particle.velocity = vec4<f32>(velocity, 0.0);
+        particle.position = vec4<f32>(particle.position.xyz + velocity * sim.dt, particle.position.w);
+    }
+
+    destination[index] = particle;
+}
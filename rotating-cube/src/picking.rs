@@ -0,0 +1,85 @@
+// which cube is under the mouse cursor: the cursor becomes a ray from the camera through that pixel, and the ray is
+// tested against every instance's box, the nearest hit wins
+// done on the CPU with plain math, the cubes are boxes so there is no need to read anything back from the GPU
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::instances::Instance;
+use crate::viewport::Viewport;
+
+// the cube spans -1..1 on every axis before the model transform, see cube.rs
+const HALF_SIZE: f32 = 1.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3, // unit length, so a hit's t is its distance from the origin
+}
+
+// the ray through `cursor` (physical pixels from the window's top-left corner, what CursorMoved reports), None when the
+// cursor is over the bars around a --render-size viewport
+pub fn cursor_ray(camera: &Camera, viewport: Viewport, cursor: Vec2) -> Option<Ray> {
+    // pixels to normalized device coordinates: -1..1 across the viewport, y flipped since pixels count downwards
+    let x = (cursor.x - viewport.x) / viewport.width * 2.0 - 1.0;
+    let y = 1.0 - (cursor.y - viewport.y) / viewport.height * 2.0;
+    if !(-1.0..=1.0).contains(&x) || !(-1.0..=1.0).contains(&y) {
+        return None;
+    }
+
+    // undo the camera for two points on that pixel at different depths, the ray goes from one through the other
+    // the near plane sits at the opposite end of the depth range from the clear value, 0.5 is somewhere behind it;
+    // the far end isn't used since with reverse-Z it is infinitely far away
+    // going through the inverse view-projection covers perspective, orthographic and the blend between them, in
+    // orthographic every ray has the same direction and only the origin moves with the cursor
+    let inverse = camera.view_proj().inverse();
+    let near_depth = 1.0 - crate::depth::clear_value(camera.reverse_z);
+    let near = inverse.project_point3(Vec3::new(x, y, near_depth));
+    let behind = inverse.project_point3(Vec3::new(x, y, 0.5));
+    Some(Ray {
+        origin: near,
+        direction: (behind - near).normalize(),
+    })
+}
+
+// index of the nearest instance the ray hits, `model` is the transform every cube gets after its own placement and
+// before its offset is added, the same as vs_main in shader.wgsl
+pub fn pick<'a>(ray: Ray, model: Mat4, instances: impl IntoIterator<Item = &'a Instance>) -> Option<usize> {
+    // rather than moving every box into the world, the ray is moved into the cube's own space where the box is simply
+    // -1..1, an affine transform keeps points along the ray at the same t so the distances still compare
+    // the plain cubes share one inverse, a turned or stretched one needs its own
+    let inverse = model.inverse();
+    let direction = inverse.transform_vector3(ray.direction);
+    instances
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, instance)| {
+            let origin = ray.origin - Vec3::from(instance.offset);
+            let t = if instance.is_plain() {
+                hit_box(inverse.transform_point3(origin), direction)
+            } else {
+                let inverse = (model * instance.placement()).inverse();
+                hit_box(inverse.transform_point3(origin), inverse.transform_vector3(ray.direction))
+            };
+            t.map(|t| (i, t))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+// slab test against the -HALF_SIZE..HALF_SIZE box: the ray is inside the box where it is between both planes of every
+// axis at once, so take the latest entry and the earliest exit across the three axes, it hit if it entered before it
+// left, returns the distance to the entry point (0 when the ray starts inside the box)
+fn hit_box(origin: Vec3, direction: Vec3) -> Option<f32> {
+    // dividing by a zero component gives +-infinity, which works out: a ray parallel to a slab is either always
+    // between its planes (entry -inf, exit +inf) or never (both the same infinity, so it never enters)
+    let inv = direction.recip();
+    let t1 = (Vec3::splat(-HALF_SIZE) - origin) * inv;
+    let t2 = (Vec3::splat(HALF_SIZE) - origin) * inv;
+    let enter = t1.min(t2).max_element();
+    let exit = t1.max(t2).min_element();
+    if enter <= exit && exit >= 0.0 {
+        Some(enter.max(0.0))
+    } else {
+        None
+    }
+}
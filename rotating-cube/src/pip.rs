@@ -0,0 +1,288 @@
+// picture-in-picture (P): an inset in the bottom-right corner of a window showing the scene from a fixed camera
+// straight above it, while the window's own camera goes wherever it is steered
+// the inset is its own small scene pass into a texture of the inset's size (so it clears its own background and has a
+// depth buffer to itself), which pip.wgsl then copies into the corner of the window's frame with a thin border around it
+// only the scene itself is in it: sky, cubes, particles, glass and the debug lines, the overlays (outline, wireframe,
+// depth view, gizmo, HUD) are about the main camera
+use std::sync::Arc;
+
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::{Bindings, BindingsBuilder};
+use crate::camera::{self, Camera};
+use crate::depth::{self, DepthBuffer};
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::render_stats::{CountingPass, CountingQueue};
+use crate::renderer::{CameraUniform, LightUniform, SharedBindings};
+use crate::viewport::Viewport;
+
+// how much of the scene's viewport the inset covers, the same fraction of the width and the height so it has the same
+// shape as the view around it
+const FRACTION: f32 = 0.3;
+
+// gap between the inset and the corner, in logical pixels like the gizmo's
+const MARGIN: f32 = 8.0;
+
+// the overhead camera looks down from here, tipped a little towards +Z for the same reason as the top camera preset:
+// looking straight along `up` has no defined view
+const OVERHEAD: Vec3 = Vec3::new(0.0, 1.0, 0.01);
+
+// where the inset goes inside the scene's viewport, in the same physical pixels, whole ones so the copy stays sharp
+// it keeps FRACTION of the viewport whatever size the window is resized to
+pub fn viewport(scene: Viewport, scale_factor: f64) -> Viewport {
+    let width = (scene.width * FRACTION).round().max(1.0);
+    let height = (scene.height * FRACTION).round().max(1.0);
+    let margin = (MARGIN * scale_factor as f32).min(scene.width - width).min(scene.height - height).max(0.0).round();
+    Viewport {
+        x: scene.x + scene.width - width - margin,
+        y: scene.y + scene.height - height - margin,
+        width,
+        height,
+    }
+}
+
+// the scissor rectangle for `inset` in a `width` x `height` target, wgpu rejects one that reaches outside the target
+// so it is clipped to it, None when nothing of it is left to draw
+pub fn scissor(inset: Viewport, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let x = (inset.x.max(0.0) as u32).min(width);
+    let y = (inset.y.max(0.0) as u32).min(height);
+    let right = ((inset.x + inset.width).max(0.0) as u32).min(width);
+    let bottom = ((inset.y + inset.height).max(0.0) as u32).min(height);
+    (right > x && bottom > y).then(|| (x, y, right - x, bottom - y))
+}
+
+// the fixed camera over the box min..max, far enough up to see all of it in an inset of `aspect`
+// perspective whatever the main camera is doing, with the planes fitted to the scene like F does
+pub fn camera(min: Vec3, max: Vec3, aspect: f32, reverse_z: bool) -> Camera {
+    let framing = camera::frame_bounds(min, max, OVERHEAD, camera::DEFAULT_FOV, aspect);
+    Camera {
+        eye: framing.eye,
+        target: framing.target,
+        up: Vec3::Y,
+        fovy: camera::DEFAULT_FOV,
+        aspect,
+        znear: framing.znear,
+        zfar: framing.zfar,
+        ortho: 0.0,
+        reverse_z,
+    }
+}
+
+// one window's inset: the texture it is drawn into with its depth buffer, and a frame bind group like the window's
+// own but with the overhead camera in it, made when the inset is first shown and again whenever its size changes
+pub struct PipTarget {
+    pub size: (u32, u32),
+    pub camera: Camera, // what is in camera_buffer, the glass is sorted by its view
+    pub view: wgpu::TextureView,
+    pub depth: DepthBuffer,
+    pub camera_buffer: wgpu::Buffer,
+    pub light_buffer: wgpu::Buffer, // the window's lights with this camera's eye, for the specular highlights
+    pub bind_group: wgpu::BindGroup,
+    pub particle_bind_group: Option<wgpu::BindGroup>,
+    pub composite_bind_group: wgpu::BindGroup, // the texture for pip.wgsl to read
+}
+
+impl PipTarget {
+    // `globals_buffer` is the window's, the inset has the same shape so the sky's sun comes out just as round
+    pub fn new(
+        device: &wgpu::Device,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        camera: Camera,
+        shared: &SharedBindings,
+        globals_buffer: &wgpu::Buffer,
+        compositor: &Compositor,
+    ) -> Self {
+        let (width, height) = size;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Picture-in-Picture Texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format, //the window's, so the cube pipelines can draw into it as they are
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = depth::create_depth_buffer(device, width, height, shared.depth_format);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picture-in-Picture Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform { view_proj: camera.view_proj().to_cols_array_2d() }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picture-in-Picture Light Buffer"),
+            contents: bytemuck::bytes_of(&LightUniform { eye_position: camera.eye.to_array(), ..*shared.light }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = shared.frame_bind_group(device, &camera_buffer, &light_buffer, globals_buffer);
+        let particle_bind_group = shared.particles.map(|particles| particles.camera_bind_group(device, &camera_buffer));
+        let composite_bind_group = compositor.bind_group(device, &view);
+
+        Self { size, camera, view, depth, camera_buffer, light_buffer, bind_group, particle_bind_group, composite_bind_group }
+    }
+
+    // the overhead camera was framed again, on a scene that changed or an inset that changed shape
+    pub fn write_camera(&mut self, queue: &CountingQueue, camera: Camera) {
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&CameraUniform { view_proj: camera.view_proj().to_cols_array_2d() }));
+        self.camera = camera;
+    }
+
+    // `light` is the shared settings, the eye is this camera's
+    pub fn write_light(&self, queue: &CountingQueue, light: &LightUniform) {
+        queue.write_buffer(&self.light_buffer, 0, bytemuck::bytes_of(&LightUniform { eye_position: self.camera.eye.to_array(), ..*light }));
+    }
+}
+
+// copies an inset's texture into a window's frame, shared by every window like the depth view's pipeline
+pub struct Compositor {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bindings: Bindings,
+    sampler: wgpu::Sampler,
+}
+
+impl Compositor {
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache, format: wgpu::TextureFormat) -> Self {
+        // linear, --record's capture can be a different size than the window the inset was drawn for
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Picture-in-Picture Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bindings = BindingsBuilder::new("Picture-in-Picture")
+            .texture(0, wgpu::ShaderStages::FRAGMENT, wgpu::TextureViewDimension::D2)
+            .sampler(1, wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+
+        // drawn in a pass of its own without a depth buffer, so no depth_compare in the key
+        let key = PipelineKey::new(ShaderId::new("pip.wgsl", "vs_main", "fs_main"), format, wgpu::BlendState::REPLACE);
+        let pipeline = cache.get_or_create(key, |key| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("pip.wgsl"));
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Picture-in-Picture Pipeline Layout"),
+                bind_group_layouts: &[&bindings.layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Picture-in-Picture Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[], //one triangle over the whole viewport, made from the vertex index
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &key.targets(),
+                }),
+                primitive: key.primitive(),
+                depth_stencil: key.depth_stencil(),
+                multisample: key.multisample(),
+                multiview: None,
+            })
+        });
+
+        Self { pipeline, bindings, sampler }
+    }
+
+    fn bind_group(&self, device: &wgpu::Device, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        self.bindings.bind_group(device, &[wgpu::BindingResource::TextureView(view), wgpu::BindingResource::Sampler(&self.sampler)])
+    }
+
+    // the inset's texture stretched over `inset`, nothing outside `scissor` is touched even where the viewport's
+    // rounding would reach a pixel further
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, bind_group: &'a wgpu::BindGroup, inset: Viewport, scissor: (u32, u32, u32, u32)) {
+        let (x, y, width, height) = scissor;
+        inset.apply(pass);
+        pass.set_scissor_rect(x, y, width, height);
+        pass.set_pipeline(&self.pipeline, wgpu::PrimitiveTopology::TriangleList);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport;
+
+    // the scissor a frame would use: bounded by the scene's viewport, as render() does
+    fn scissor_in(inset: Viewport, scene: Viewport) -> Option<(u32, u32, u32, u32)> {
+        scissor(inset, (scene.x + scene.width) as u32, (scene.y + scene.height) as u32)
+    }
+
+    #[test]
+    fn inset_sits_in_the_bottom_right_corner() {
+        let inset = viewport(viewport::fit(800, 600, None), 1.0);
+        assert_eq!(inset, Viewport { x: 552.0, y: 412.0, width: 240.0, height: 180.0 });
+        // the margin is in logical pixels, twice as many physical ones at a scale factor of 2
+        let inset = viewport(viewport::fit(1920, 1080, None), 2.0);
+        assert_eq!(inset, Viewport { x: 1328.0, y: 740.0, width: 576.0, height: 324.0 });
+        // inside a --render-size viewport rather than the window's corner
+        let scene = viewport::fit(1000, 600, Some((640, 480)));
+        let inset = viewport(scene, 1.0);
+        assert_eq!((inset.x + inset.width, inset.y + inset.height), (scene.x + 640.0 - 8.0, scene.y + 480.0 - 8.0));
+    }
+
+    #[test]
+    fn inset_keeps_its_share_and_shape_at_every_size() {
+        for (width, height, scale_factor) in [(800, 600, 1.0), (1920, 1080, 2.0), (30, 20, 2.0), (1, 1, 1.0), (333, 777, 1.5)] {
+            let scene = viewport::fit(width, height, None);
+            let inset = viewport(scene, scale_factor);
+            let case = format!("{}x{} at {}", width, height, scale_factor);
+            assert_eq!(inset.width, (scene.width * FRACTION).round().max(1.0), "{}", case);
+            assert_eq!(inset.height, (scene.height * FRACTION).round().max(1.0), "{}", case);
+            // the same shape give or take the rounding to whole pixels
+            assert!((inset.aspect() - scene.aspect()).abs() <= scene.aspect() * 0.05, "{}", case);
+            // a tiny window squeezes the margin rather than pushing the inset out of it
+            assert!(inset.x >= scene.x && inset.y >= scene.y, "{}: {:?}", case, inset);
+            // so the scissor is the inset itself
+            let whole = (inset.x as u32, inset.y as u32, inset.width as u32, inset.height as u32);
+            assert_eq!(scissor_in(inset, scene), Some(whole), "{}", case);
+        }
+    }
+
+    #[test]
+    fn scissor_is_clipped_to_the_target() {
+        // hanging over the left and bottom edges
+        assert_eq!(scissor(Viewport { x: -5.0, y: 590.0, width: 20.0, height: 20.0 }, 800, 600), Some((0, 590, 15, 10)));
+        // entirely outside
+        assert_eq!(scissor(Viewport { x: 900.0, y: 10.0, width: 20.0, height: 20.0 }, 800, 600), None);
+        assert_eq!(scissor(Viewport { x: -30.0, y: 10.0, width: 20.0, height: 20.0 }, 800, 600), None);
+    }
+
+    #[test]
+    fn scissor_stays_inside_after_a_resize() {
+        // an inset worked out for the old size, used for one frame against the window's new, smaller one
+        let old = viewport(viewport::fit(1920, 1080, None), 1.0);
+        for (width, height) in [(1600, 900), (800, 600), (1000, 300), (200, 200)] {
+            let scene = viewport::fit(width, height, None);
+            match scissor_in(old, scene) {
+                Some((x, y, w, h)) => assert!(x + w <= width && y + h <= height, "{}x{}: {:?}", width, height, (x, y, w, h)),
+                None => assert!(old.x >= width as f32 || old.y >= height as f32, "{}x{}", width, height),
+            }
+            // and the one worked out for the new size fits it exactly
+            let inset = viewport(scene, 1.0);
+            let (x, y, w, h) = scissor_in(inset, scene).unwrap();
+            assert_eq!((x + w, y + h), (width - MARGIN as u32, height - MARGIN as u32));
+        }
+    }
+
+    #[test]
+    fn overhead_camera_sees_the_whole_scene_from_above() {
+        let (min, max) = (Vec3::splat(-1.0), Vec3::splat(1.0));
+        let camera = camera(min, max, 4.0 / 3.0, false);
+        assert_eq!(camera.target, Vec3::ZERO);
+        assert!((camera.eye - camera.target).normalize().dot(Vec3::Y) > 0.99);
+        let view_proj = camera.view_proj();
+        for corner in [min, max, Vec3::new(-1.0, 1.0, 1.0), Vec3::new(1.0, -1.0, -1.0)] {
+            let ndc = view_proj.project_point3(corner);
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z), "{} at {}", corner, ndc);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// P picture-in-picture: the inset's texture copied into the corner of the window, see pip.rs
+// the viewport is set to the inset's rectangle, so the triangle below only has to cover the viewport
+@group(0) @binding(0)
+var inset_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var inset_sampler: sampler;
+
+// width of the frame around the inset in texels of the inset's texture
+const BORDER: f32 = 2.0;
+const BORDER_COLOR: vec4<f32> = vec4<f32>(0.85, 0.85, 0.85, 1.0);
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// one triangle big enough to cover the whole viewport, corners at (-1,-1), (3,-1) and (-1,3)
+// texture coordinates run down from the top-left while clip space y runs up, hence the flip
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // sampled first, textureSample has to be reached by every fragment of a quad alike
+    let color = textureSample(inset_texture, inset_sampler, in.uv);
+    let size = vec2<f32>(textureDimensions(inset_texture));
+    let texel = in.uv * size;
+    if (any(texel < vec2<f32>(BORDER)) || any(texel > size - vec2<f32>(BORDER))) {
+        return BORDER_COLOR;
+    }
+    return color;
+}
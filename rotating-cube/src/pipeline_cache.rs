@@ -0,0 +1,218 @@
+// every render pipeline the app builds goes through one cache, keyed by everything that makes two pipelines differ
+// building a pipeline compiles its shaders for the GPU, which is slow enough to stutter a frame, so each combination
+// is built once, the first time something asks for it, and handed out again (as another Arc of the same pipeline)
+// after that. every build is printed, so a pipeline built twice, or rebuilt every frame, shows up in the output
+// the cache belongs to the device, State::recreate_device() drops it along with every pipeline in it
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::depth;
+
+// which shader program a pipeline runs: WGSL file and entry point of each stage
+// the file also stands for the vertex buffer and bind group layouts, every file is only ever used with one of each
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderId {
+    pub vertex: (&'static str, &'static str),
+    pub fragment: Option<(&'static str, &'static str)>, // None for a depth-only pass
+}
+
+impl ShaderId {
+    // both stages from the same file, the usual case
+    pub fn new(file: &'static str, vertex: &'static str, fragment: &'static str) -> Self {
+        Self {
+            vertex: (file, vertex),
+            fragment: Some((file, fragment)),
+        }
+    }
+}
+
+// everything about a pipeline that isn't the shader's code, the render pass it goes into decides most of it
+// the descriptor is filled in from the key's own helpers below so the two can't disagree
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader_id: ShaderId,
+    pub topology: wgpu::PrimitiveTopology,
+    pub cull: Option<wgpu::Face>,
+    pub format: wgpu::TextureFormat,
+    pub blend: Option<wgpu::BlendState>,
+    pub depth_write: bool,
+    pub depth_compare: Option<wgpu::CompareFunction>, // None: the pass has no depth attachment
+    pub depth_format: wgpu::TextureFormat,            // the depth attachment's, see depth::format()
+    pub stencil: wgpu::StencilState,                  // only used when depth_format has stencil
+    pub sample_count: u32, // 1 until there is MSAA, but a multisampled target needs its own pipeline
+}
+
+impl PipelineKey {
+    // the usual key for a pass without depth or stencil, filled triangles, nothing culled, one sample
+    pub fn new(shader_id: ShaderId, format: wgpu::TextureFormat, blend: wgpu::BlendState) -> Self {
+        Self {
+            shader_id,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull: None,
+            format,
+            blend: Some(blend),
+            depth_write: false,
+            depth_compare: None,
+            depth_format: depth::DEPTH_FORMAT,
+            stencil: wgpu::StencilState::default(),
+            sample_count: 1,
+        }
+    }
+
+    pub fn primitive(&self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: self.topology,
+            front_face: wgpu::FrontFace::Ccw, //every mesh here is wound counter-clockwise seen from outside
+            cull_mode: self.cull,
+            ..Default::default()
+        }
+    }
+
+    // the color target, used only when the shader has a fragment stage
+    pub fn targets(&self) -> [Option<wgpu::ColorTargetState>; 1] {
+        [Some(wgpu::ColorTargetState {
+            format: self.format,
+            blend: self.blend,
+            write_mask: wgpu::ColorWrites::ALL,
+        })]
+    }
+
+    // a stencil state on a format without stencil fails validation, a pass that can't have it draws without
+    pub fn depth_stencil(&self) -> Option<wgpu::DepthStencilState> {
+        self.depth_compare.map(|depth_compare| wgpu::DepthStencilState {
+            format: self.depth_format,
+            depth_write_enabled: self.depth_write,
+            depth_compare,
+            stencil: if self.depth_format.has_stencil_aspect() { self.stencil.clone() } else { wgpu::StencilState::default() },
+            bias: wgpu::DepthBiasState::default(),
+        })
+    }
+
+    pub fn multisample(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            ..Default::default()
+        }
+    }
+}
+
+// the short form printed when a pipeline is built, enough to tell two builds apart without the whole {:?}
+// (the stencil state and the exact blend factors are left out)
+impl fmt::Display for PipelineKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (file, vertex) = self.shader_id.vertex;
+        write!(f, "{}:{}", file, vertex)?;
+        if let Some((file, fragment)) = self.shader_id.fragment {
+            write!(f, " + {}:{}", file, fragment)?;
+        }
+        write!(f, ", {:?}", self.topology)?;
+        if let Some(cull) = self.cull {
+            write!(f, ", cull {:?}", cull)?;
+        }
+        if self.blend != Some(wgpu::BlendState::REPLACE) {
+            write!(f, ", blended")?;
+        }
+        if let Some(compare) = self.depth_compare {
+            write!(f, ", depth {:?}{}", compare, if self.depth_write { " + write" } else { "" })?;
+            if self.depth_format != depth::DEPTH_FORMAT {
+                write!(f, " into {:?}", self.depth_format)?;
+            }
+        }
+        if self.sample_count > 1 {
+            write!(f, ", {}x MSAA", self.sample_count)?;
+        }
+        Ok(())
+    }
+}
+
+// generic over what it holds so the keying can be checked without a GPU, the app only ever stores RenderPipelines
+// interior mutability since the windows build their HUDs while the rest of Gpu is borrowed for their bind groups
+pub struct PipelineCache<T = wgpu::RenderPipeline> {
+    pipelines: RefCell<HashMap<PipelineKey, Arc<T>>>,
+}
+
+impl<T> Default for PipelineCache<T> {
+    fn default() -> Self {
+        Self {
+            pipelines: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> PipelineCache<T> {
+    // the pipeline for `key`, built by `create` if this is the first time it's asked for
+    // `create` must build exactly what the key describes, or a later caller with the same key gets the wrong pipeline
+    pub fn get_or_create(&self, key: PipelineKey, create: impl FnOnce(&PipelineKey) -> T) -> Arc<T> {
+        if let Some(pipeline) = self.pipelines.borrow().get(&key) {
+            return Arc::clone(pipeline);
+        }
+        let pipeline = Arc::new(create(&key));
+        let mut pipelines = self.pipelines.borrow_mut();
+        info!("Building pipeline #{}: {}", pipelines.len() + 1, key);
+        pipelines.insert(key, Arc::clone(&pipeline));
+        pipeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn key(fragment: &'static str) -> PipelineKey {
+        PipelineKey::new(ShaderId::new("test.wgsl", "vs_main", fragment), wgpu::TextureFormat::Bgra8UnormSrgb, wgpu::BlendState::REPLACE)
+    }
+
+    // no GPU needed: the cache holds a count of how many times create ran instead of a pipeline
+    #[test]
+    fn the_same_key_builds_once() {
+        let cache = PipelineCache::<u32>::default();
+        let builds = Cell::new(0);
+        let build = |_: &PipelineKey| {
+            builds.set(builds.get() + 1);
+            builds.get()
+        };
+        let first = cache.get_or_create(key("fs_main"), build);
+        let again = cache.get_or_create(key("fs_main"), build);
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(builds.get(), 1);
+    }
+
+    #[test]
+    fn distinct_keys_build_their_own() {
+        let cache = PipelineCache::<&'static str>::default();
+        let lit = cache.get_or_create(key("fs_main"), |_| "lit");
+        let points = cache.get_or_create(key("fs_points"), |_| "points");
+        // only one field differs from a key already there
+        let lines = cache.get_or_create(PipelineKey { topology: wgpu::PrimitiveTopology::LineList, ..key("fs_main") }, |_| "lines");
+        let depth = cache.get_or_create(PipelineKey { depth_compare: Some(wgpu::CompareFunction::Less), ..key("fs_main") }, |_| "depth");
+        let stencil = cache.get_or_create(
+            PipelineKey {
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                depth_format: wgpu::TextureFormat::Depth24PlusStencil8,
+                ..key("fs_main")
+            },
+            |_| "stencil",
+        );
+        let all = [&lit, &points, &lines, &depth, &stencil];
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                assert!(!Arc::ptr_eq(a, b), "{} and {} share a pipeline", a, b);
+            }
+        }
+        // and each was built by its own create
+        assert_eq!([*lit, *points, *lines, *depth, *stencil], ["lit", "points", "lines", "depth", "stencil"]);
+    }
+
+    #[test]
+    fn the_printed_key_tells_builds_apart() {
+        let depth = PipelineKey { depth_write: true, depth_compare: Some(wgpu::CompareFunction::Less), ..key("fs_main") };
+        assert_eq!(depth.to_string(), "test.wgsl:vs_main + test.wgsl:fs_main, TriangleList, depth Less + write");
+        let stencil = PipelineKey { depth_format: wgpu::TextureFormat::Depth24PlusStencil8, ..depth };
+        assert_eq!(stencil.to_string(), "test.wgsl:vs_main + test.wgsl:fs_main, TriangleList, depth Less + write into Depth24PlusStencil8");
+    }
+}
@@ -0,0 +1,292 @@
+// the cube can be drawn as filled triangles, as a point cloud or as a wireframe (--draw-mode, M key)
+// the topology is baked into a render pipeline, so each mode needs its own pipeline built from the same shader and layout
+// they are only built the first time a mode is used and kept afterwards, so switching back and forth is free
+// the depth prepass (Z key) needs two more variants per mode: depth-only, and color that only passes on equal depth
+// the wireframe debug view (D key) adds one more: edges drawn over the faces that are already there
+// and the selection outline (X key) another: a slightly bigger cube drawn only where the stencil says the cube isn't
+// the glass cube has two of its own, blended over what's behind it: one for its back faces and then one for its front
+// and the faces can be shaded by either of two shader files, each object picks one (ShaderKind)
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::cube::Vertex;
+use crate::depth;
+use crate::gpu::{self, ScopeError};
+use crate::instances::Instance;
+use crate::material;
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawMode {
+    Triangles, // filled and lit faces
+    Points,    // one point per vertex, shaded by distance from the camera
+    Lines,     // face outlines from the edge index buffer
+}
+
+impl DrawMode {
+    // order the M key cycles through
+    pub fn next(self) -> Self {
+        match self {
+            DrawMode::Triangles => DrawMode::Points,
+            DrawMode::Points => DrawMode::Lines,
+            DrawMode::Lines => DrawMode::Triangles,
+        }
+    }
+
+    pub fn topology(self) -> wgpu::PrimitiveTopology {
+        match self {
+            DrawMode::Triangles => wgpu::PrimitiveTopology::TriangleList,
+            DrawMode::Points => wgpu::PrimitiveTopology::PointList,
+            DrawMode::Lines => wgpu::PrimitiveTopology::LineList,
+        }
+    }
+}
+
+// which part of the frame a pipeline variant is used for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PassKind {
+    Single,            // no prepass: depth test and shading in one go
+    DepthOnly,         // prepass: fill the depth buffer, no fragment shader at all
+    ColorAfterPrepass, // shade only the fragment whose depth the prepass kept, so each pixel is shaded once
+    Overlay,           // drawn after the scene in a flat color, passes where it is at least as close as what's there
+    Outline,           // grown cube in a flat color, no depth test, only outside the pixels the cube marked in the stencil
+    GlassBack,         // the far side of a see-through cube, alpha blended, depth tested but not written
+    GlassFront,        // its near side, the same but drawn after the far side so that shows through it
+}
+
+// which WGSL file shades the faces of an object, switched per object at runtime (U for the cubes, G for the glass)
+// only the fragment stage changes: every variant takes its vertex stage from shader.wgsl, so a color pass still computes
+// the same depths as a depth-only prepass drawn with the other shader
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderKind {
+    Lit,   // shader.wgsl: diffuse and specular lighting
+    Unlit, // shader_unlit.wgsl: the vertex colors as they are
+}
+
+impl ShaderKind {
+    pub fn toggle(self) -> Self {
+        match self {
+            ShaderKind::Lit => ShaderKind::Unlit,
+            ShaderKind::Unlit => ShaderKind::Lit,
+        }
+    }
+
+    // HUD name, uppercase since the font only has capitals
+    pub fn label(self) -> &'static str {
+        match self {
+            ShaderKind::Lit => "LIT",
+            ShaderKind::Unlit => "UNLIT",
+        }
+    }
+
+    // the flat-color passes (outline, wireframe, points) look the same whatever the object's shader is, they always
+    // use the lit file's version so switching doesn't build duplicates of them
+    fn for_pass(self, mode: DrawMode, kind: PassKind) -> Self {
+        match (mode, kind) {
+            (DrawMode::Points, _) => ShaderKind::Lit,
+            (_, PassKind::Single | PassKind::ColorAfterPrepass | PassKind::GlassBack | PassKind::GlassFront) => self,
+            _ => ShaderKind::Lit,
+        }
+    }
+}
+
+// what a cube pipeline variant is for, PipelineOptions::key() turns it into the cache key that spells out the rest
+#[derive(Copy, Clone, Debug)]
+pub struct PipelineOptions {
+    pub format: wgpu::TextureFormat,
+    pub depth_format: wgpu::TextureFormat, // with stencil for the outline to work, see depth::format()
+    pub reverse_z: bool, // flips the depth test, see depth.rs
+    pub mode: DrawMode,
+    pub kind: PassKind,
+}
+
+impl PipelineOptions {
+    // every setting the variant gets, `shader` only picks the file its fragment stage comes from
+    pub fn key(self, shader: ShaderKind) -> PipelineKey {
+        let PipelineOptions { format, depth_format, reverse_z, mode, kind } = self;
+        let file = match shader {
+            ShaderKind::Lit => "shader.wgsl",
+            ShaderKind::Unlit => "shader_unlit.wgsl",
+        };
+        // points have no faces to light, they get their own fragment shader that shades by distance instead
+        let fragment_entry = match (mode, kind) {
+            (_, PassKind::Overlay) => "fs_wireframe",
+            (_, PassKind::Outline) => "fs_outline",
+            (_, PassKind::GlassBack | PassKind::GlassFront) => "fs_glass",
+            (DrawMode::Points, _) => "fs_points",
+            _ => "fs_main",
+        };
+        // same vertex shader in every variant, so both passes compute bit-identical depths and Equal works
+        let shader_id = ShaderId {
+            vertex: ("shader.wgsl", if kind == PassKind::Outline { "vs_outline" } else { "vs_main" }),
+            fragment: (kind != PassKind::DepthOnly).then_some((file, fragment_entry)),
+        };
+        // culling only applies to triangles, points and lines have no front or back
+        // glass shows both sides, but with blending the order matters, so instead of turning culling off (which would
+        // draw the faces in index order) it is drawn twice, culling the front faces the first time and the back faces after
+        let cull = match (mode, kind) {
+            (DrawMode::Triangles, PassKind::GlassBack) => Some(wgpu::Face::Front),
+            (DrawMode::Triangles, _) => Some(wgpu::Face::Back), //skip faces pointing away so back faces never draw over lit front faces
+            _ => None,
+        };
+
+        // the color pass after a prepass must not write depth again, and only matches the exact depth already stored
+        let (depth_write, depth_compare) = match kind {
+            PassKind::Single | PassKind::DepthOnly => (true, depth::closer(reverse_z)), //keep the fragment closest to the camera
+            PassKind::ColorAfterPrepass => (false, wgpu::CompareFunction::Equal),
+            PassKind::Overlay => (false, depth::closer_or_equal(reverse_z)), //edges lie on the faces, so equal has to pass
+            PassKind::Outline => (false, wgpu::CompareFunction::Always), //the rim shows even where something is in front of it
+            PassKind::GlassBack | PassKind::GlassFront => (false, depth::closer(reverse_z)), //hidden by opaque things in front, see transparency.rs
+        };
+        // color = src * alpha + dst * (1 - alpha), the glass tints what is already there instead of replacing it
+        let blend = match kind {
+            PassKind::GlassBack | PassKind::GlassFront => wgpu::BlendState::ALPHA_BLENDING,
+            _ => wgpu::BlendState::REPLACE,
+        };
+
+        PipelineKey {
+            topology: mode.topology(),
+            cull,
+            depth_write,
+            depth_compare: Some(depth_compare),
+            depth_format,
+            stencil: stencil_state(kind),
+            ..PipelineKey::new(shader_id, format, blend)
+        }
+    }
+}
+
+pub struct PipelineVariants {
+    lit: wgpu::ShaderModule,   // shader.wgsl, also every variant's vertex stage
+    unlit: wgpu::ShaderModule, // shader_unlit.wgsl, fragment stages only
+    layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    reverse_z: bool,
+    // the pipelines themselves live in the PipelineCache, these are the ones prepared so far, kept here since a render
+    // pass holds on to its pipeline for as long as the pass lives and get() can't hand out a reference into the cache
+    pipelines: HashMap<(ShaderKind, wgpu::PrimitiveTopology, PassKind), Arc<wgpu::RenderPipeline>>,
+}
+
+impl PipelineVariants {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        reverse_z: bool,
+    ) -> Self {
+        Self {
+            lit: device.create_shader_module(wgpu::include_wgsl!("shader.wgsl")),
+            unlit: device.create_shader_module(wgpu::include_wgsl!("shader_unlit.wgsl")),
+            layout,
+            format,
+            depth_format,
+            reverse_z,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    // the depth buffers were swapped for ones in `depth_format`: forget the variants prepared for the old one, the next
+    // prepare() fetches them again (the cache still has the old ones, so switching back costs nothing)
+    pub fn set_depth_format(&mut self, depth_format: wgpu::TextureFormat) {
+        self.depth_format = depth_format;
+        self.pipelines.clear();
+    }
+
+    // build the pipelines `mode` needs unless they are already cached, call before get() since drawing only borrows self
+    pub fn prepare(&mut self, device: &wgpu::Device, cache: &PipelineCache, mode: DrawMode, prepass: bool, shader: ShaderKind) -> Result<(), ScopeError> {
+        let kinds: &[PassKind] = if prepass { &[PassKind::DepthOnly, PassKind::ColorAfterPrepass] } else { &[PassKind::Single] };
+        for &kind in kinds {
+            self.prepare_variant(device, cache, mode, kind, shader)?;
+        }
+        Ok(())
+    }
+
+    // a single extra variant, for the passes that are only drawn in some modes (wireframe overlay, outline, glass)
+    // built the first time a key press asks for it, so a bad combination of options only shows up then
+    pub fn prepare_variant(&mut self, device: &wgpu::Device, cache: &PipelineCache, mode: DrawMode, kind: PassKind, shader: ShaderKind) -> Result<(), ScopeError> {
+        let shader = shader.for_pass(mode, kind);
+        let variant = (shader, mode.topology(), kind);
+        if !self.pipelines.contains_key(&variant) {
+            let fragment = match shader {
+                ShaderKind::Lit => &self.lit,
+                ShaderKind::Unlit => &self.unlit,
+            };
+            let options = PipelineOptions {
+                format: self.format,
+                depth_format: self.depth_format,
+                reverse_z: self.reverse_z,
+                mode,
+                kind,
+            };
+            let key = options.key(shader);
+            let label = format!("pipeline {}", key);
+            let pipeline = gpu::scoped(device, &label, || {
+                cache.get_or_create(key, |key| create_pipeline(device, &self.lit, fragment, &self.layout, key))
+            })?;
+            self.pipelines.insert(variant, pipeline);
+        }
+        Ok(())
+    }
+
+    // panics if prepare() was never called for this mode, pass and shader
+    // switching an object's shader only changes which of these is bound, nothing is rebuilt once both exist
+    pub fn get(&self, mode: DrawMode, kind: PassKind, shader: ShaderKind) -> &wgpu::RenderPipeline {
+        &self.pipelines[&(shader.for_pass(mode, kind), mode.topology(), kind)]
+    }
+}
+
+// one cube pipeline as `key` describes it: vertex stage from `vertex`, fragment stage (if the pass has one) from
+// `fragment`, both have to follow the vertex buffers and VertexOutput of shader.wgsl
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    vertex: &wgpu::ShaderModule,
+    fragment: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    key: &PipelineKey,
+) -> wgpu::RenderPipeline {
+    let targets = key.targets();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(&format!("Cube Pipeline ({})", key)),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: vertex,
+            entry_point: key.shader_id.vertex.1,
+            buffers: &[Vertex::layout(), Instance::layout(), material::emissive_layout()], //per vertex: position, color, normal, per instance: offset, phase, color, rotation, scale and hover glow
+        },
+        fragment: key.shader_id.fragment.map(|(_, entry_point)| wgpu::FragmentState {
+            module: fragment,
+            entry_point,
+            targets: &targets,
+        }),
+        primitive: key.primitive(),
+        depth_stencil: key.depth_stencil(),
+        multisample: key.multisample(),
+        multiview: None,
+    })
+}
+
+// the cube's own passes write STENCIL_CUBE wherever they draw (set as the pass's stencil reference), the outline is
+// only drawn where the value is anything else, the wireframe overlay and the glass leave the stencil alone
+fn stencil_state(kind: PassKind) -> wgpu::StencilState {
+    let (compare, pass_op, write_mask) = match kind {
+        PassKind::Single | PassKind::DepthOnly | PassKind::ColorAfterPrepass => {
+            (wgpu::CompareFunction::Always, wgpu::StencilOperation::Replace, 0xff)
+        }
+        PassKind::Outline => (wgpu::CompareFunction::NotEqual, wgpu::StencilOperation::Keep, 0),
+        PassKind::Overlay | PassKind::GlassBack | PassKind::GlassFront => return wgpu::StencilState::default(),
+    };
+    let face = wgpu::StencilFaceState {
+        compare,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op,
+    };
+    wgpu::StencilState {
+        front: face,
+        back: face,
+        read_mask: 0xff,
+        write_mask,
+    }
+}
@@ -0,0 +1,282 @@
+// --record: capture rendered frames into an animated GIF or a numbered PNG sequence
+//
+// frames are rendered into an offscreen texture, copied into one of a small ring of CPU-readable buffers and mapped
+// once the GPU is done with them, so reading frame N back doesn't wait on frame N+1 being drawn
+// encoding happens on a worker thread fed by a bounded channel, if the encoder falls behind `send` blocks (backpressure)
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use tracing::{info, warn};
+
+use crate::error::RenderError;
+
+// number of readback buffers in flight, 3 lets the GPU run a couple of frames ahead of the CPU reading them back
+const RING_SIZE: usize = 3;
+
+// frames waiting for the encoder thread before the render loop blocks
+const ENCODE_QUEUE_DEPTH: usize = 4;
+
+pub struct RecordSettings {
+    pub path: PathBuf,   // .gif for an animated GIF, anything else becomes a numbered PNG sequence
+    pub duration: f32,   // seconds of animation to capture
+    pub fps: u32,        // frames per second of the output, also the simulation step per captured frame
+}
+
+// map state of a ring slot, written by the map_async callback
+const MAP_WAITING: u8 = 0;
+const MAP_READY: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+// one readback buffer and the state its map_async callback reports
+struct Slot {
+    buffer: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+}
+
+pub struct Recorder {
+    texture: wgpu::Texture, // offscreen color target with COPY_SRC so it can be read back
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32, // rows in a buffer copy must be padded to 256 bytes
+    slots: Vec<Slot>,
+    pending: VecDeque<usize>, // slots copied into but not yet read, oldest first
+    frames_total: u32,
+    frames_captured: u32,     // frames whose copy has been submitted
+    sender: Option<SyncSender<Vec<u8>>>,
+    worker: Option<JoinHandle<Result<(), RenderError>>>,
+}
+
+impl Recorder {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32, settings: &RecordSettings) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format, // same format as the surface so the existing pipelines can draw into it
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let slots = (0..RING_SIZE)
+            .map(|i| Slot {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("Capture Readback Buffer {}", i)),
+                    size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(MAP_WAITING)),
+            })
+            .collect();
+
+        // the surface is often BGRA, image files want RGBA, the worker swaps channels if needed
+        let bgra = matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+        let (sender, receiver) = sync_channel::<Vec<u8>>(ENCODE_QUEUE_DEPTH);
+        let path = settings.path.clone();
+        let fps = settings.fps;
+        let worker = std::thread::spawn(move || encode_frames(&path, width, height, fps, bgra, receiver.into_iter()));
+
+        info!(
+            "Recording {}x{} at {} fps for {} s to {}",
+            width,
+            height,
+            settings.fps,
+            settings.duration,
+            settings.path.display()
+        );
+
+        Self {
+            texture,
+            view,
+            width,
+            height,
+            padded_bytes_per_row,
+            slots,
+            pending: VecDeque::new(),
+            frames_total: (settings.duration * settings.fps as f32).round().max(1.0) as u32,
+            frames_captured: 0,
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+
+    // where the scene should be drawn for the frame being captured
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frames_captured >= self.frames_total
+    }
+
+    // copy the capture texture into the next ring slot, waiting for that slot to be read back if it is still in use
+    // returns the slot index, pass it to after_submit once the encoder has been submitted
+    pub fn copy_frame(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) -> usize {
+        let slot = self.frames_captured as usize % RING_SIZE;
+        while self.pending.contains(&slot) {
+            device.poll(wgpu::Maintain::Wait);
+            self.drain_ready();
+        }
+
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.slots[slot].buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+        );
+        slot
+    }
+
+    // map_async may only be called after the copy has been submitted
+    pub fn after_submit(&mut self, device: &wgpu::Device, slot: usize) {
+        let state = self.slots[slot].state.clone();
+        state.store(MAP_WAITING, Ordering::Release);
+        self.slots[slot].buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            state.store(if result.is_ok() { MAP_READY } else { MAP_FAILED }, Ordering::Release);
+        });
+        self.pending.push_back(slot);
+        self.frames_captured += 1;
+
+        // non-blocking poll lets finished copies complete their map callbacks
+        device.poll(wgpu::Maintain::Poll);
+        self.drain_ready();
+    }
+
+    // hand every mapped frame at the front of the queue to the encoder thread, in capture order
+    fn drain_ready(&mut self) {
+        while let Some(&slot) = self.pending.front() {
+            match self.slots[slot].state.load(Ordering::Acquire) {
+                MAP_WAITING => break,
+                MAP_FAILED => {
+                    // nothing to read, drop the frame rather than stalling the ring forever
+                    warn!("Recording: failed to map a readback buffer, frame skipped");
+                    self.pending.pop_front();
+                    continue;
+                }
+                _ => {}
+            }
+            self.pending.pop_front();
+
+            let buffer = &self.slots[slot].buffer;
+            let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+            {
+                // strip the per-row padding while copying out of the mapped range
+                let data = buffer.slice(..).get_mapped_range();
+                for row in data.chunks(self.padded_bytes_per_row as usize) {
+                    pixels.extend_from_slice(&row[..(self.width * 4) as usize]);
+                }
+            }
+            buffer.unmap();
+
+            if let Some(sender) = &self.sender {
+                // blocks if the encoder is ENCODE_QUEUE_DEPTH frames behind, an error means the worker died
+                if sender.send(pixels).is_err() {
+                    self.sender = None;
+                }
+            }
+        }
+    }
+
+    // wait for the outstanding readbacks, close the channel and let the encoder finish writing
+    // an error means the output is missing or cut short
+    pub fn finish(mut self, device: &wgpu::Device) -> Result<(), RenderError> {
+        while !self.pending.is_empty() {
+            device.poll(wgpu::Maintain::Wait);
+            self.drain_ready();
+        }
+        drop(self.sender.take());
+
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .unwrap_or_else(|_| Err(RenderError::Encoding("the encoder thread panicked".to_string())))?;
+            info!("Recording finished: {} frames", self.frames_captured);
+        }
+        Ok(())
+    }
+}
+
+// worker thread body: write every received frame to the output until the channel closes
+fn encode_frames(
+    path: &Path,
+    width: u32,
+    height: u32,
+    fps: u32,
+    bgra: bool,
+    frames: impl Iterator<Item = Vec<u8>>,
+) -> Result<(), RenderError> {
+    let is_gif = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+
+    if is_gif {
+        let file = create_file(path)?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(encoding)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(encoding)?;
+
+        for mut pixels in frames {
+            to_rgba(&mut pixels, bgra);
+            // speed 10 is gif's suggested quality/speed trade-off for palette quantization
+            let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut pixels, 10);
+            frame.delay = (100 / fps.max(1)) as u16; // GIF delays are in hundredths of a second
+            encoder.write_frame(&frame).map_err(encoding)?;
+        }
+    } else {
+        for (index, mut pixels) in frames.enumerate() {
+            to_rgba(&mut pixels, bgra);
+            let frame_path = numbered_path(path, index);
+            let file = create_file(&frame_path)?;
+            let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(encoding)?;
+            writer.write_image_data(&pixels).map_err(encoding)?;
+        }
+    }
+    Ok(())
+}
+
+fn create_file(path: &Path) -> Result<File, RenderError> {
+    File::create(path).map_err(|source| RenderError::Io { path: path.to_path_buf(), source })
+}
+
+// gif and png report write errors through their own error types, the message is all that is kept
+fn encoding(err: impl std::fmt::Display) -> RenderError {
+    RenderError::Encoding(err.to_string())
+}
+
+// frames.png -> frames_00000.png, frames_00001.png, ...
+pub fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+    path.with_file_name(format!("{}_{:05}.png", stem, index))
+}
+
+fn to_rgba(pixels: &mut [u8], bgra: bool) {
+    if bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+}
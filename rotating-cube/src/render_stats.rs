@@ -0,0 +1,174 @@
+// what one frame asked of the GPU: draw calls, the instances and triangles they drew, the bytes written into buffers
+// and how often the pipeline changed, shown in the HUD and added up by --bench
+// these are counts of commands, not of work: a triangle that is culled or behind another still counts, the GPU
+// timestamps are what says how long they took
+//
+// the counting is done by the two wrappers below rather than at each call site, every draw helper takes a
+// CountingPass and every per-frame upload goes through the CountingQueue in Gpu, so a new pass or buffer is counted
+// without anyone having to remember to
+use std::cell::Cell;
+use std::ops::{Deref, Range};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub instances: u64,         // summed over the draw calls, a call drawing 100 instanced cubes adds 100
+    pub triangles: u64,         // from triangle lists only, the points and lines modes draw none
+    pub uploaded_bytes: u64,    // queue.write_buffer()s since the last frame, buffers made with their contents aren't counted
+    pub pipeline_switches: u32, // set_pipeline() with a different pipeline than the one already set in that pass
+}
+
+impl RenderStats {
+    // for --bench's running totals
+    pub fn add(&mut self, other: &RenderStats) {
+        self.draw_calls += other.draw_calls;
+        self.instances += other.instances;
+        self.triangles += other.triangles;
+        self.uploaded_bytes += other.uploaded_bytes;
+        self.pipeline_switches += other.pipeline_switches;
+    }
+
+    // one of the HUD's lines
+    pub fn label(&self) -> String {
+        format!(
+            "DRAWS: {}  INSTANCES: {}  TRIANGLES: {}  UPLOADED: {:.1} KB  PIPELINES: {}",
+            self.draw_calls,
+            self.instances,
+            self.triangles,
+            self.uploaded_bytes as f64 / 1024.0,
+            self.pipeline_switches
+        )
+    }
+}
+
+// a render pass that adds every draw to `stats`
+// only the calls the renderer makes are passed on, and there is no way to get at the wgpu pass inside, so nothing can
+// be drawn around the counting
+pub struct CountingPass<'s, 'a> {
+    pass: wgpu::RenderPass<'a>,
+    stats: &'s mut RenderStats,
+    pipeline: Option<&'a wgpu::RenderPipeline>,
+    // a pipeline can't be asked what it draws, so set_pipeline() is told along with it
+    topology: wgpu::PrimitiveTopology,
+}
+
+impl<'s, 'a> CountingPass<'s, 'a> {
+    pub fn new(pass: wgpu::RenderPass<'a>, stats: &'s mut RenderStats) -> Self {
+        Self { pass, stats, pipeline: None, topology: wgpu::PrimitiveTopology::TriangleList }
+    }
+
+    // `topology` is the one `pipeline` was made with
+    pub fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline, topology: wgpu::PrimitiveTopology) {
+        // setting the same one again costs the driver nothing, only a real change is counted
+        if !self.pipeline.is_some_and(|current| std::ptr::eq(current, pipeline)) {
+            self.stats.pipeline_switches += 1;
+        }
+        self.pipeline = Some(pipeline);
+        self.topology = topology;
+        self.pass.set_pipeline(pipeline);
+    }
+
+    pub fn set_bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup, offsets: &[wgpu::DynamicOffset]) {
+        self.pass.set_bind_group(index, bind_group, offsets);
+    }
+
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer: wgpu::BufferSlice<'a>) {
+        self.pass.set_vertex_buffer(slot, buffer);
+    }
+
+    pub fn set_index_buffer(&mut self, buffer: wgpu::BufferSlice<'a>, format: wgpu::IndexFormat) {
+        self.pass.set_index_buffer(buffer, format);
+    }
+
+    pub fn set_viewport(&mut self, x: f32, y: f32, width: f32, height: f32, min_depth: f32, max_depth: f32) {
+        self.pass.set_viewport(x, y, width, height, min_depth, max_depth);
+    }
+
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.pass.set_scissor_rect(x, y, width, height);
+    }
+
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.pass.set_stencil_reference(reference);
+    }
+
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.count(vertices.len() as u32, instances.len() as u32);
+        self.pass.draw(vertices, instances);
+    }
+
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.count(indices.len() as u32, instances.len() as u32);
+        self.pass.draw_indexed(indices, base_vertex, instances);
+    }
+
+    fn count(&mut self, vertices: u32, instances: u32) {
+        self.stats.draw_calls += 1;
+        self.stats.instances += instances as u64;
+        self.stats.triangles += triangles(self.topology, vertices) as u64 * instances as u64;
+    }
+}
+
+// triangles in one instance of a draw of `vertices` vertices (or indices)
+pub fn triangles(topology: wgpu::PrimitiveTopology, vertices: u32) -> u32 {
+    match topology {
+        wgpu::PrimitiveTopology::TriangleList => vertices / 3,
+        wgpu::PrimitiveTopology::TriangleStrip => vertices.saturating_sub(2),
+        _ => 0,
+    }
+}
+
+// the device's queue with the bytes written through write_buffer() added up until the frame takes them
+// write_buffer() is this type's own, so it is the one called even where the rest (submit, write_texture) is reached
+// through Deref; the helpers that upload every frame take this type rather than a wgpu::Queue so theirs are counted too
+pub struct CountingQueue {
+    queue: wgpu::Queue,
+    uploaded: Cell<u64>, // only the thread that renders writes, the Cell lets that happen through the &Gpu everyone has
+}
+
+impl CountingQueue {
+    pub fn new(queue: wgpu::Queue) -> Self {
+        Self { queue, uploaded: Cell::new(0) }
+    }
+
+    pub fn write_buffer(&self, buffer: &wgpu::Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        self.uploaded.set(self.uploaded.get() + data.len() as u64);
+        self.queue.write_buffer(buffer, offset, data);
+    }
+
+    // bytes written since the last call
+    pub fn take_uploaded(&self) -> u64 {
+        self.uploaded.replace(0)
+    }
+}
+
+impl Deref for CountingQueue {
+    type Target = wgpu::Queue;
+
+    fn deref(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangles_by_topology() {
+        assert_eq!(triangles(wgpu::PrimitiveTopology::TriangleList, 36), 12);
+        assert_eq!(triangles(wgpu::PrimitiveTopology::TriangleStrip, 4), 2);
+        assert_eq!(triangles(wgpu::PrimitiveTopology::TriangleStrip, 1), 0);
+        assert_eq!(triangles(wgpu::PrimitiveTopology::LineList, 36), 0);
+        assert_eq!(triangles(wgpu::PrimitiveTopology::PointList, 36), 0);
+    }
+
+    #[test]
+    fn add_sums_every_count() {
+        let frame = RenderStats { draw_calls: 3, instances: 10, triangles: 120, uploaded_bytes: 64, pipeline_switches: 2 };
+        let mut total = RenderStats::default();
+        total.add(&frame);
+        total.add(&frame);
+        assert_eq!(total, RenderStats { draw_calls: 6, instances: 20, triangles: 240, uploaded_bytes: 128, pipeline_switches: 4 });
+    }
+}
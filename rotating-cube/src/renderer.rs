@@ -0,0 +1,599 @@
+// everything the cube scene puts on the GPU: the uniform layouts, the device's shared buffers and pipelines (Gpu),
+// each window's own (WindowGpu) and each camera's in it (CameraGpu)
+// app.rs decides what changes and when, this only creates the resources those changes are written into
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use tracing::warn;
+
+// DeviceExt creates frame buffer which is dedicated block of memory that stores pixel data fed to GPU
+use wgpu::util::DeviceExt;
+
+use glam::Mat4;
+
+// bytemuck traits to safely copy uniforms to GPU
+use bytemuck::{Pod, Zeroable};
+
+use crate::gpu::bindings::{Bindings, BindingsBuilder};
+use crate::camera::Camera;
+use crate::cube;
+use crate::debug_lines::{DebugLines, LineVertex};
+use crate::debug_view::DepthView;
+use crate::deform::Deformer;
+use crate::effects::Effect;
+use crate::depth::{self, DepthBuffer};
+use crate::error::RenderError;
+use crate::gizmo::{self, AxisGizmo};
+use crate::gpu::{self, ScopeError};
+use crate::gpu_timer::GpuTimer;
+use crate::hud::Hud;
+use crate::instances;
+use crate::lights::{LightSource, MAX_LIGHTS};
+use crate::lod::LodBuffers;
+use crate::material::Materials;
+use crate::mesh::Mesh;
+use crate::particles::Particles;
+use crate::pip::{Compositor, PipTarget};
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::pipelines::PipelineVariants;
+use crate::render_stats::CountingQueue;
+use crate::scene::Scene;
+use crate::shadow::ShadowMap;
+use crate::sky::{GlobalsUniform, Sky};
+
+// guarantee struct memory layout matches C, needed for GPU buffer
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub view_proj: [[f32; 4]; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ModelUniform {
+    pub model: [[f32; 4]; 4],
+}
+
+// matches the Light struct in shader.wgsl, the padding-style f32s fill the 16-byte alignment after each vec3
+// the settings every light shares come first, then the lights themselves, see lights.rs
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct LightUniform {
+    pub eye_position: [f32; 3],
+    pub shininess: f32,
+    pub specular_color: [f32; 3],
+    pub ambient: f32,
+    pub count: u32,         // how many of `sources` are switched on, the shader ignores the rest
+    pub _padding: [u32; 3], // the array starts on 16 bytes
+    pub sources: [LightSource; MAX_LIGHTS],
+}
+
+impl LightUniform {
+    // the lights that are switched on
+    pub fn active(&self) -> &[LightSource] {
+        &self.sources[..self.count as usize]
+    }
+}
+
+// matches the Frame struct in shader.wgsl, uniform buffers are padded to 16 bytes
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct FrameUniform {
+    pub time: f32,
+    pub hue_mix: f32,
+    pub normal_colors: f32, // 1 while the D key's normals view is on
+    pub _padding: f32,
+}
+
+// the per-window resources created from the device
+pub struct WindowGpu {
+    pub depth: DepthBuffer, // depth and stencil buffer matching the surface size
+    pub globals_buffer: wgpu::Buffer, // time, time of day and this window's resolution, see sky.rs
+    pub cameras: Vec<CameraGpu>, // one per camera, the window's only one or its two halves' with --split-screen
+    pub hud: Hud,            // text overlay in the top-left corner, toggled with H
+    pub lod: Option<LodBuffers>, // --lod's cubes sorted by level for the first camera, made on the first frame that sorts them
+    pub pip: Option<PipTarget>,  // the picture-in-picture's texture and camera (P), made when it is first shown
+}
+
+// the buffers one camera is drawn with, they all follow the camera in the app's WindowState::write_uniforms()
+pub struct CameraGpu {
+    pub camera_buffer: wgpu::Buffer, // store view matrix
+    pub light_buffer: wgpu::Buffer,  // the shared light settings, but with this camera's eye position for the specular term
+    pub bind_group: wgpu::BindGroup, // groups of resources for GPU, this camera, its light and the window's globals with the shared model and frame
+    pub particle_bind_group: Option<wgpu::BindGroup>, // this camera for the particle pipeline, only with --particles
+    pub depth_debug_buffer: wgpu::Buffer, // how the depth view turns this camera's depth back into distances
+    pub gizmo_camera_buffer: wgpu::Buffer, // the axis gizmo's view_proj, this camera's rotation only
+    pub gizmo_bind_group: wgpu::BindGroup, // same as bind_group but with the gizmo's matrix as the camera
+}
+
+// shared resources each window's bind groups point at, only needed while the windows are being set up
+pub struct SharedBindings<'a> {
+    pub bindings: &'a Bindings,
+    pub model_buffer: &'a wgpu::Buffer,
+    pub frame_buffer: &'a wgpu::Buffer,
+    pub light: &'a LightUniform,
+    pub particles: Option<&'a Particles>,
+    pub pipeline_cache: &'a PipelineCache, // for the HUD, the same pipeline in every window
+    pub shadow: &'a ShadowMap,
+    pub depth_format: wgpu::TextureFormat, // every window's depth buffers, the pipelines are built for it
+}
+
+impl SharedBindings<'_> {
+    // a frame bind group (group 0) for one camera: its own camera and light buffers, the window's globals and the
+    // shared model, frame and shadow map
+    pub fn frame_bind_group(
+        &self,
+        device: &wgpu::Device,
+        camera_buffer: &wgpu::Buffer,
+        light_buffer: &wgpu::Buffer,
+        globals_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.bindings.bind_group(
+            device,
+            &[
+                camera_buffer.as_entire_binding(),
+                self.model_buffer.as_entire_binding(),
+                light_buffer.as_entire_binding(),
+                self.frame_buffer.as_entire_binding(),
+                globals_buffer.as_entire_binding(),
+                self.shadow.buffer.as_entire_binding(),
+                wgpu::BindingResource::TextureView(&self.shadow.depth.depth_view),
+                wgpu::BindingResource::Sampler(&self.shadow.sampler),
+            ],
+        )
+    }
+}
+
+// everything created from the device, State::recreate_device() throws all of it away and builds it again from the Scene
+pub struct Gpu {
+    pub device: wgpu::Device,   // handle to GPU
+    pub queue: CountingQueue,    // queue of GPU commands, counting the bytes written for RenderStats
+    pub frame_bindings: Bindings, // per frame (group 0): camera, model, light, frame, globals and the shadow map, every window's bind group follows it
+    pub materials: Materials, // per material (group 1): one bind group for each of Scene::materials
+
+    pub depth_format: wgpu::TextureFormat, // of the windows' depth buffers, gains stencil for the outline, see depth::format()
+    pub pipelines: PipelineVariants, // encapsulate GPU program (shaders, depth, blending), one per draw mode
+    pub pipeline_cache: PipelineCache, // owns every render pipeline, the fields here only hold on to the ones they use
+
+    pub meshes: Vec<Mesh>,           // vertex and index buffers of everything drawn with the main pipelines, just the cube for now
+    pub lod_meshes: [Mesh; 2],       // --lod's stand-ins for the cube further away, the tetrahedron and the point
+    pub deformer: Option<Deformer>,  // --deform compute pass that rewrites the cube mesh's positions every frame
+    pub particles: Option<Particles>, // --particles compute-driven sparks from the cube's corners
+    pub instance_buffer: wgpu::Buffer, // one Instance per cube, a single cube at the origin without --grid, then the glass cubes
+    pub num_instances: u32,            // the opaque cubes at the start of instance_buffer
+    pub num_glass: u32,                // the glass cubes after them
+    pub emissive_buffer: wgpu::Buffer, // one hover glow f32 per instance, written from State::materials
+
+    pub model_buffer: wgpu::Buffer,  // stores model matrix
+    pub frame_buffer: wgpu::Buffer,  // time and hue mix for the grid's color animation
+
+    pub line_pipeline: Arc<wgpu::RenderPipeline>, // LineList pipeline used to draw the debug lines
+    pub debug_lines: DebugLines,             // rebuilt every frame from the toggles in State
+    pub depth_debug: DepthView,              // fullscreen pass for the depth view (D key)
+    pub sky: Option<Sky>,                    // --day-length background, drawn instead of the clear color
+    pub axis_gizmo: AxisGizmo,               // XYZ indicator in each window's corner, drawn with line_pipeline
+    pub shadow: ShadowMap,                   // the scene file's light's view of the cubes, drawn and read while S has shadows on
+    pub pip_compositor: Compositor,          // copies a window's picture-in-picture into its corner (P)
+
+    pub gpu_timer: Option<GpuTimer>, // GPU frame timing, only in --bench mode on adapters with timestamp queries
+}
+
+impl WindowGpu {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, cameras: &[&Camera], shared: &SharedBindings) -> Self {
+        let depth = depth::create_depth_buffer(device, config.width, config.height, shared.depth_format);
+
+        // written every frame before anything is drawn, see State::write_uniforms()
+        let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Globals Buffer"),
+            contents: bytemuck::bytes_of(&GlobalsUniform::new(0.0, None, Effect::Off)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let cameras = cameras.iter().map(|camera| CameraGpu::new(device, camera, shared, &globals_buffer)).collect();
+        let hud = Hud::new(device, queue, shared.pipeline_cache, config.format, config.width, config.height);
+
+        Self {
+            depth,
+            globals_buffer,
+            cameras,
+            hud,
+            lod: None,
+            pip: None,
+        }
+    }
+}
+
+impl CameraGpu {
+    // `globals_buffer` is the window's, every camera in it draws at the same resolution
+    fn new(device: &wgpu::Device, camera: &Camera, shared: &SharedBindings, globals_buffer: &wgpu::Buffer) -> Self {
+        //define camera matrix as projection * view matrices and convert it to 2D array compatible with GPU func
+        let camera_uniform = CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+        };
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&camera_uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, //COPY_DST so it can be rewritten when the camera moves
+        });
+
+        let light = LightUniform {
+            eye_position: camera.eye.to_array(),
+            ..*shared.light
+        };
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = shared.frame_bind_group(device, &camera_buffer, &light_buffer, globals_buffer);
+
+        let gizmo_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Axis Gizmo Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform { view_proj: gizmo::view_proj(camera).to_cols_array_2d() }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // the gizmo's bind group only differs in the camera buffer
+        let gizmo_bind_group = shared.frame_bind_group(device, &gizmo_camera_buffer, &light_buffer, globals_buffer);
+
+        let particle_bind_group = shared.particles.map(|particles| particles.camera_bind_group(device, &camera_buffer));
+        let depth_debug_buffer = DepthView::create_buffer(device, camera);
+
+        Self {
+            camera_buffer,
+            light_buffer,
+            bind_group,
+            particle_bind_group,
+            depth_debug_buffer,
+            gizmo_camera_buffer,
+            gizmo_bind_group,
+        }
+    }
+}
+
+impl Gpu {
+    // request a device from `adapter` and upload the scene's static buffers to it
+    // `device_lost` is set by the device's error handler once it stops working
+    pub async fn new(
+        adapter: wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        scene: &Scene,
+        bench: bool,
+        reverse_z: bool,
+        device_lost: &Arc<AtomicBool>,
+        trace_dir: Option<&Path>,
+    ) -> Result<Self, RenderError> {
+        // --bench asks for timestamp queries, the device only has them if the adapter does too
+        let context = gpu::Context::new(&adapter, bench, trace_dir).await?;
+        context.watch_for_loss(device_lost);
+        let gpu::Context { device, queue, timestamps, compute, .. } = context;
+
+        let gpu_timer = timestamps.then(|| GpuTimer::new(&device, &queue));
+
+        // without compute shaders draw the plain cube instead
+        let deform = scene.deform && compute;
+        if scene.deform && !deform {
+            warn!("Adapter doesn't support compute shaders, --deform is ignored");
+        }
+        if scene.particles.is_some() && !compute {
+            warn!("Adapter doesn't support compute shaders, --particles is ignored");
+        }
+
+        // ----- Cube vertices -----
+        let cube = Mesh::new(
+            &device,
+            "Cube",
+            &scene.cube,
+            // --deform's compute pass writes into the vertex buffer, which needs STORAGE on top of VERTEX
+            if deform { wgpu::BufferUsages::STORAGE } else { wgpu::BufferUsages::empty() },
+        );
+        let deformer = if deform {
+            Some(gpu::scoped(&device, "Deformer", || Deformer::new(&device, &scene.cube.vertices, &cube.vertex_buffer))?)
+        } else {
+            None
+        };
+
+        // a few vertices each, made whether or not --lod is given so nothing here has to know about it
+        let lod_meshes = [
+            Mesh::new(&device, "LOD Tetrahedron", &cube::make_tetrahedron(), wgpu::BufferUsages::empty()),
+            Mesh::new(&device, "LOD Point", &cube::make_point(), wgpu::BufferUsages::empty()),
+        ];
+
+        // ----- Instances -----
+        let (instance_buffer, emissive_buffer) = instance_buffers(&device, scene);
+
+        // ----- Model (rotation updated each frame) -----
+        let model_uniform = ModelUniform {
+            model: Mat4::IDENTITY.to_cols_array_2d(),
+        };
+
+        let model_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Model Buffer"),
+            contents: bytemuck::bytes_of(&model_uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // ----- Frame (time for the grid's hue animation) -----
+        let frame_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Frame Buffer"),
+            contents: bytemuck::bytes_of(&FrameUniform { time: 0.0, hue_mix: scene.hue_mix, normal_colors: 0.0, _padding: 0.0 }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        //define bindings so GPU knows how to access each vertex correctly
+        // ----- Bind Group Layout -----
+        let frame_bindings = BindingsBuilder::new("Frame")
+            .uniform(0, wgpu::ShaderStages::VERTEX) //camera information for vertex shader
+            // model information for vertex shader, the fragment shader turns the light into the cube's own space with it
+            .uniform(1, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+            .uniform(2, wgpu::ShaderStages::FRAGMENT) //the lights, eye position and specular settings for fragment shader
+            // time and hue mix, the vertex shader picks each instance's color from them, the fragment shader checks for
+            // the normals view
+            .uniform(3, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+            // time, resolution and time of day, per window, for whichever stage wants them
+            .uniform(4, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+            // the shadow map with the light's view_proj and the sampler that compares against it, see shadow.rs
+            .uniform(5, wgpu::ShaderStages::FRAGMENT)
+            .depth_texture(6, wgpu::ShaderStages::FRAGMENT)
+            .sampler(7, wgpu::ShaderStages::FRAGMENT, true)
+            .build(&device);
+
+        // ----- Materials -----
+        // group 1, switched between draws while group 0 stays bound: each object binds its own before drawing
+        let materials = Materials::new(&device, &scene.materials, &scene.instance_materials);
+
+        // ----- Pipeline -----
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&frame_bindings.layout, materials.layout()],
+            push_constant_ranges: &[],
+        });
+
+        // nothing is built yet, render() builds the current draw mode's pipeline on first use and the others when M
+        // first switches to them, the shader files (lit and unlit) are compiled here
+        // no stencil until the outline asks for it, unless the device has a float format with stencil to start with
+        let depth_format = depth::format(device.features(), false);
+        let pipelines = PipelineVariants::new(&device, pipeline_layout, format, depth_format, reverse_z);
+        // every render pipeline is built through this, each only once however many things ask for it
+        let pipeline_cache = PipelineCache::default();
+
+        // ----- Particles -----
+        // the compute and render pipelines and the buffers sized by --particles
+        let particles = match scene.particles.filter(|_| compute) {
+            Some(count) => Some(gpu::scoped(&device, "Particles", || Particles::new(&device, &pipeline_cache, format, depth_format, count, reverse_z))?),
+            None => None,
+        };
+
+        // ----- Debug lines -----
+        // line segments rebuilt each frame, drawn with a LineList topology instead of triangles
+        let debug_lines = DebugLines::new(&device);
+        let depth_debug = gpu::scoped(&device, "Depth View", || DepthView::new(&device, &pipeline_cache, format))?;
+        let axis_gizmo = AxisGizmo::new(&device);
+        let shadow = gpu::scoped(&device, "Shadow Map", || ShadowMap::new(&device, &pipeline_cache, &model_buffer, format))?;
+        let pip_compositor = gpu::scoped(&device, "Picture-in-Picture", || Compositor::new(&device, &pipeline_cache, format))?;
+        let sky = match scene.day_length {
+            Some(_) => Some(gpu::scoped(&device, "Sky", || Sky::new(&device, &pipeline_cache, &frame_bindings, format, depth_format))?),
+            None => None,
+        };
+
+        let line_pipeline = gpu::scoped(&device, "Line Pipeline", || line_pipeline(&device, &pipeline_cache, &frame_bindings, format, depth_format))?;
+
+        Ok(Self {
+            device,
+            queue: CountingQueue::new(queue),
+            frame_bindings,
+            materials,
+            depth_format,
+            pipelines,
+            pipeline_cache,
+
+            meshes: vec![cube],
+            lod_meshes,
+            deformer,
+            particles,
+            instance_buffer,
+            num_instances: scene.instances.len() as u32,
+            num_glass: scene.glass.len() as u32,
+            emissive_buffer,
+
+            model_buffer,
+            frame_buffer,
+
+            line_pipeline,
+            debug_lines,
+            depth_debug,
+            sky,
+            axis_gizmo,
+            shadow,
+            pip_compositor,
+
+            gpu_timer,
+        })
+    }
+
+    // a reloaded scene with as many opaque and glass cubes as before: overwrite them where they are
+    // V's frozen camera passes the opaque ones with the culled cubes tinted rather than the scene's own
+    pub fn write_instances(&self, opaque: &[instances::Instance], glass: &[instances::Instance]) {
+        let all: Vec<instances::Instance> = opaque.iter().chain(glass).copied().collect();
+        self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&all));
+    }
+
+    // a reloaded scene with cubes added, removed or moved between opaque and glass: the buffers are sized for the old
+    // count, so both are replaced, everything else on the device stays as it is
+    pub fn set_instances(&mut self, scene: &Scene) {
+        (self.instance_buffer, self.emissive_buffer) = instance_buffers(&self.device, scene);
+        self.num_instances = scene.instances.len() as u32;
+        self.num_glass = scene.glass.len() as u32;
+    }
+
+    // the scene's materials changed or its objects were given other ones
+    pub fn set_materials(&mut self, scene: &Scene) {
+        self.materials.update(&self.device, &scene.materials, &scene.instance_materials);
+    }
+
+    // the outline was turned on and the depth buffers are about to be made again with stencil: build everything drawn
+    // into them for the new format. `format` is the windows' color format, the pipelines are keyed by both
+    pub fn set_depth_format(&mut self, format: wgpu::TextureFormat, depth_format: wgpu::TextureFormat) -> Result<(), ScopeError> {
+        self.depth_format = depth_format;
+        self.pipelines.set_depth_format(depth_format);
+        let (device, cache) = (&self.device, &self.pipeline_cache);
+        self.line_pipeline = gpu::scoped(device, "Line Pipeline", || line_pipeline(device, cache, &self.frame_bindings, format, depth_format))?;
+        if let Some(particles) = &mut self.particles {
+            gpu::scoped(device, "Particles", || particles.set_depth_format(device, cache, depth_format))?;
+        }
+        if self.sky.is_some() {
+            self.sky = Some(gpu::scoped(device, "Sky", || Sky::new(device, cache, &self.frame_bindings, format, depth_format))?);
+        }
+        Ok(())
+    }
+
+    // what every window's bind groups point at, `light` is the starting point for each window's own light buffer
+    pub fn shared_bindings<'a>(&'a self, light: &'a LightUniform) -> SharedBindings<'a> {
+        SharedBindings {
+            bindings: &self.frame_bindings,
+            model_buffer: &self.model_buffer,
+            frame_buffer: &self.frame_buffer,
+            light,
+            particles: self.particles.as_ref(),
+            pipeline_cache: &self.pipeline_cache,
+            shadow: &self.shadow,
+            depth_format: self.depth_format,
+        }
+    }
+}
+
+// the glass cubes go in the same buffer after the opaque ones, the draws pick them out by instance range
+fn all_instances(scene: &Scene) -> Vec<instances::Instance> {
+    scene.instances.iter().chain(&scene.glass).copied().collect()
+}
+
+// the instances, writable for a scene reload, and their hover glow
+fn instance_buffers(device: &wgpu::Device, scene: &Scene) -> (wgpu::Buffer, wgpu::Buffer) {
+    let all_instances = all_instances(scene);
+    let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Instance Buffer"),
+        contents: bytemuck::cast_slice(&all_instances),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    });
+    // nothing hovered yet, State uploads the glow values whenever they change
+    let emissive_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Emissive Buffer"),
+        contents: bytemuck::cast_slice(&vec![0.0f32; all_instances.len()]),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    });
+    (instance_buffer, emissive_buffer)
+}
+
+// LineList pipeline for the debug lines and the axis gizmo, drawn in passes with a depth buffer of `depth_format`
+// the pass has a depth buffer so the pipeline must name its format, but lines ignore it and stay on top
+fn line_pipeline(
+    device: &wgpu::Device,
+    cache: &PipelineCache,
+    frame_bindings: &Bindings,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+) -> Arc<wgpu::RenderPipeline> {
+    let key = PipelineKey {
+        topology: wgpu::PrimitiveTopology::LineList, //every 2 vertices form an independent line segment
+        depth_compare: Some(wgpu::CompareFunction::Always),
+        depth_format,
+        ..PipelineKey::new(ShaderId::new("lines.wgsl", "vs_main", "fs_main"), format, wgpu::BlendState::REPLACE)
+    };
+    cache.get_or_create(key, |key| {
+        let line_shader = device.create_shader_module(wgpu::include_wgsl!("lines.wgsl"));
+        // only the per-frame group, lines have no material
+        let line_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Line Pipeline Layout"),
+            bind_group_layouts: &[&frame_bindings.layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Line Pipeline"),
+            layout: Some(&line_layout), //same bind group as the cube, the line shader only reads the camera
+            vertex: wgpu::VertexState {
+                module: &line_shader,
+                entry_point: "vs_main",
+                buffers: &[LineVertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &line_shader,
+                entry_point: "fs_main",
+                targets: &key.targets(),
+            }),
+            primitive: key.primitive(),
+            depth_stencil: key.depth_stencil(),
+            multisample: key.multisample(),
+            multiview: None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::{offset_of, size_of};
+
+    use super::*;
+    use crate::material::MaterialUniform;
+    use crate::shadow::ShadowUniform;
+
+    // the offsets WGSL gives each member of the matching struct in shader.wgsl and sky.wgsl: a vec3 or a struct starts
+    // on 16 bytes, a vec2 on 8, and the struct's size is rounded up to its largest alignment
+    // a Rust field out of step with them shifts everything after it and the shader reads garbage, with no error anywhere
+
+    #[test]
+    fn matrices_are_64_bytes() {
+        assert_eq!(size_of::<CameraUniform>(), 64);
+        assert_eq!(size_of::<ModelUniform>(), 64);
+    }
+
+    #[test]
+    fn light_matches_the_wgsl_layout() {
+        assert_eq!(offset_of!(LightUniform, eye_position), 0);
+        assert_eq!(offset_of!(LightUniform, shininess), 12); // in the vec3's last 4 bytes
+        assert_eq!(offset_of!(LightUniform, specular_color), 16);
+        assert_eq!(offset_of!(LightUniform, ambient), 28);
+        assert_eq!(offset_of!(LightUniform, count), 32);
+        assert_eq!(offset_of!(LightUniform, sources), 48); // an array of structs starts on 16
+        // a source is a vec3 and a u32, then a vec3 and three f32s rounded up to 16
+        assert_eq!(offset_of!(LightSource, kind), 12);
+        assert_eq!(offset_of!(LightSource, color), 16);
+        assert_eq!(offset_of!(LightSource, attenuation), 28); // in the vec3's last 4 bytes and on, no vec3 alignment
+        assert_eq!(size_of::<LightSource>(), 48);
+        assert_eq!(size_of::<LightUniform>(), 48 + 48 * MAX_LIGHTS);
+    }
+
+    #[test]
+    fn frame_is_padded_to_16_bytes() {
+        // three f32s in WGSL, the padding only rounds the buffer up
+        assert_eq!(offset_of!(FrameUniform, normal_colors), 8);
+        assert_eq!(size_of::<FrameUniform>(), 16);
+    }
+
+    #[test]
+    fn globals_match_the_wgsl_layout() {
+        assert_eq!(offset_of!(GlobalsUniform, resolution), 8);
+        assert_eq!(offset_of!(GlobalsUniform, ambient_tint), 16);
+        assert_eq!(offset_of!(GlobalsUniform, effect), 28);
+        assert_eq!(size_of::<GlobalsUniform>(), 32);
+    }
+
+    #[test]
+    fn shadow_matches_the_wgsl_layout() {
+        assert_eq!(offset_of!(ShadowUniform, enabled), 64);
+        assert_eq!(offset_of!(ShadowUniform, bias), 68);
+        // 72 bytes of members, rounded up to the matrix's 16
+        assert_eq!(size_of::<ShadowUniform>(), 80);
+    }
+
+    #[test]
+    fn material_matches_the_wgsl_layout() {
+        assert_eq!(offset_of!(MaterialUniform, metallic), 16);
+        assert_eq!(offset_of!(MaterialUniform, roughness), 20);
+        assert_eq!(offset_of!(MaterialUniform, classic), 24);
+        // WGSL skips 28..32 to put the vec3 on 16, the Rust side fills it with _padding
+        assert_eq!(offset_of!(MaterialUniform, emissive), 32);
+        assert_eq!(offset_of!(MaterialUniform, reflectivity), 44);
+        assert_eq!(size_of::<MaterialUniform>(), 48);
+    }
+}
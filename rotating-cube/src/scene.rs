@@ -0,0 +1,292 @@
+// CPU-side description of everything the static GPU buffers are built from
+// the buffers themselves die with the device (driver update, GPU reset, eGPU unplugged), State::recreate_device()
+// uploads this again into a fresh one so the app keeps running instead of panicking
+use glam::{DQuat, DVec3, EulerRot, Mat4, Vec3};
+
+use crate::cube;
+use crate::debug_lines;
+use crate::instances::{self, Instance};
+use crate::lights::{self, LightSource, MAX_LIGHTS};
+use crate::material::{self, MaterialUniform};
+use crate::mesh::MeshData;
+use crate::options::SceneOptions;
+use crate::renderer::LightUniform;
+use crate::scene_file::{self, Object, SceneFile};
+
+pub struct Scene {
+    pub cube: MeshData,           // --subdivisions N cuts every face into N x N quads, see cube.rs
+    pub instances: Vec<Instance>, // one per opaque cube in the scene file, or the --grid
+    pub glass: Vec<Instance>,     // see-through cubes drawn after the opaque ones, see transparency.rs
+    pub materials: Vec<MaterialUniform>, // every material there is, each once: the two defaults, the demos, then the file's
+    pub instance_materials: Vec<u32>, // which of them each of `instances` is drawn with
+    pub glass_materials: Vec<u32>,    // and each of `glass`
+    pub light: LightUniform,      // shininess changes with [ ], lights go on and off with . and , each window fills in its own eye position
+    pub file_lights: usize,       // how many of the light's slots the scene file's [[lights]] take, the orbiting one is next
+    pub attenuation: [f32; 3],    // --attenuation, what the point lights without their own fall off with
+    pub hue_mix: f32,             // 1 with --grid so the cubes cycle through hues, 0 keeps the vertex colors
+    pub deform: bool,             // --deform was asked for, only honored on adapters with compute shaders
+    pub particles: Option<u32>,   // --particles count, same condition
+    pub day_length: Option<f32>,  // --day-length in seconds, the sky's pipeline is only built with it
+}
+
+impl Scene {
+    pub fn new(options: &SceneOptions) -> Self {
+        let file = &options.file;
+        let (opaque, glass) = objects(file);
+        let (materials, opaque_materials, glass_materials) = materials(file);
+        // --grid N draws N x N copies of the cube in one draw call, in place of the scene's opaque cubes
+        let (instances, instance_materials) = match options.grid {
+            Some(n) => {
+                let grid = instances::grid(n);
+                let slots = vec![material::CUBE; grid.len()];
+                (grid, slots)
+            }
+            None => (opaque, opaque_materials),
+        };
+
+        Self {
+            cube: cube::make_cube(options.subdivisions),
+            instances,
+            glass,
+            materials,
+            instance_materials,
+            glass_materials,
+            light: light(file, options.attenuation),
+            file_lights: file.lights.len(),
+            attenuation: options.attenuation,
+            hue_mix: if options.grid.is_some() { 1.0 } else { 0.0 },
+            deform: options.deform,
+            particles: options.particles,
+            day_length: options.day_length,
+        }
+    }
+
+    // the box around every cube, opaque and glass, with its placement and `model` (the spin) applied to the mesh like
+    // the vertex shader does before moving it into place, None when there is nothing to see
+    // a plain cube's box is worked out once for all of them, only a turned or stretched one goes over the mesh itself
+    pub fn bounds(&self, model: Mat4) -> Option<(Vec3, Vec3)> {
+        if self.cube.vertices.is_empty() {
+            return None;
+        }
+        let plain = debug_lines::transformed_bounds(&self.cube.vertices, model);
+        self.instances.iter().chain(&self.glass).fold(None, |bounds, instance| {
+            let (mesh_min, mesh_max) = if instance.is_plain() {
+                plain
+            } else {
+                debug_lines::transformed_bounds(&self.cube.vertices, model * instance.placement())
+            };
+            let offset = Vec3::from(instance.offset);
+            let (min, max) = bounds.unwrap_or((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)));
+            Some((min.min(mesh_min + offset), max.max(mesh_max + offset)))
+        })
+    }
+}
+
+// the file's opaque and glass objects in the order they are listed, the order of the instance buffer
+pub fn objects(file: &SceneFile) -> (Vec<Instance>, Vec<Instance>) {
+    let (glass, opaque): (Vec<&Object>, Vec<&Object>) = file.objects.iter().partition(|object| object.glass);
+    (opaque.into_iter().map(instance).collect(), glass.into_iter().map(instance).collect())
+}
+
+// the material table and which entry each opaque and glass object uses, in the order objects() lists them
+// a name is looked up in the file's [materials] first and then among the demos, and gets its entry the first time an
+// object uses it, the demos are always there for K
+pub fn materials(file: &SceneFile) -> (Vec<MaterialUniform>, Vec<u32>, Vec<u32>) {
+    let mut table = vec![material::DEFAULT_CUBE, material::DEFAULT_GLASS];
+    table.extend(material::DEMOS.iter().map(|(_, demo)| *demo));
+    let mut named: Vec<String> = Vec::new(); // the file's materials that have an entry, after the demos
+    let mut slot = |object: &Object| -> u32 {
+        let default = if object.glass { material::GLASS } else { material::CUBE };
+        let Some(name) = object.material.as_deref() else { return default };
+        if let Some(i) = named.iter().position(|other| *other == name) {
+            return (table.len() - named.len() + i) as u32;
+        }
+        match file.materials.get(name) {
+            Some(settings) => {
+                named.push(name.to_string());
+                table.push(uniform(settings));
+                (table.len() - 1) as u32
+            }
+            // SceneFile::validate() has already turned away names that are neither
+            None => material::demo(name).unwrap_or(default),
+        }
+    };
+    let (glass, opaque): (Vec<&Object>, Vec<&Object>) = file.objects.iter().partition(|object| object.glass);
+    let opaque = opaque.into_iter().map(&mut slot).collect();
+    let glass = glass.into_iter().map(&mut slot).collect();
+    (table, opaque, glass)
+}
+
+fn uniform(settings: &scene_file::Material) -> MaterialUniform {
+    let [r, g, b] = settings.base_color.linear();
+    MaterialUniform {
+        base_color: [r, g, b, settings.alpha as f32],
+        metallic: settings.metallic as f32,
+        roughness: settings.roughness as f32,
+        classic: 0,
+        _padding: 0.0,
+        emissive: settings.emissive.linear(),
+        reflectivity: settings.reflectivity as f32,
+    }
+}
+
+// the file's [[lights]] in the first slots and the orbiting light after them, all switched on, the built-in others
+// after that ready for . to switch on, `attenuation` is --attenuation for the point lights that don't give their own
+// fixed directions, adjustable shininess
+pub fn light(file: &SceneFile, attenuation: [f32; 3]) -> LightUniform {
+    let mut sources = lights::extra_lights(attenuation);
+    let count = file.lights.len();
+    sources.copy_within(..MAX_LIGHTS - count, count);
+    for (slot, light) in sources.iter_mut().zip(&file.lights) {
+        *slot = source(light, attenuation);
+    }
+    LightUniform {
+        eye_position: [0.0; 3], //every window uploads its own camera's eye here
+        shininess: file.shininess as f32,
+        specular_color: file.specular.linear(),
+        ambient: file.ambient as f32,
+        count: lights::starting_count(count) as u32,
+        _padding: [0; 3],
+        sources,
+    }
+}
+
+fn source(light: &scene_file::Light, attenuation: [f32; 3]) -> LightSource {
+    match light {
+        scene_file::Light::Directional { direction, color } => {
+            LightSource::directional(DVec3::from(*direction).as_vec3(), Vec3::from(color.linear()))
+        }
+        scene_file::Light::Point { position, color, attenuation: own } => LightSource::point(
+            DVec3::from(*position).as_vec3(),
+            Vec3::from(color.linear()),
+            own.map_or(attenuation, |own| own.map(|term| term as f32)),
+        ),
+    }
+}
+
+// every object is a cube for now, so all it takes is where it goes, how it is turned and stretched, and its color
+fn instance(object: &Object) -> Instance {
+    let [x, y, z] = object.rotation.map(f64::to_radians);
+    Instance {
+        offset: DVec3::from(object.position).as_vec3().to_array(),
+        phase: 0.0,
+        color: object.color.linear(),
+        rotation: DQuat::from_euler(EulerRot::XYZ, x, y, z).as_quat().to_array(),
+        scale: DVec3::from(object.scale).as_vec3().to_array(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+
+    // where the file's own materials start in the table, after the defaults and the demos
+    const FIRST_NAMED: u32 = material::FIRST_DEMO + material::DEMOS.len() as u32;
+
+    fn table(text: &str) -> (Vec<MaterialUniform>, Vec<u32>, Vec<u32>) {
+        materials(&SceneFile::parse(text).unwrap())
+    }
+
+    #[test]
+    fn objects_without_a_material_fall_back_to_the_defaults() {
+        let (table, opaque, glass) = table("[[objects]]\n[[objects]]\nglass = true\n[[objects]]");
+        assert_eq!(opaque, [material::CUBE, material::CUBE]);
+        assert_eq!(glass, [material::GLASS]);
+        assert_eq!(table[material::CUBE as usize], material::DEFAULT_CUBE);
+        assert_eq!(table[material::GLASS as usize], material::DEFAULT_GLASS);
+        // the demos are there for K even when nothing names them
+        assert_eq!(table.len() as u32, FIRST_NAMED);
+    }
+
+    #[test]
+    fn each_named_material_is_uploaded_once() {
+        let (table, opaque, glass) = table(
+            "[materials.brass]\nmetallic = 1.0\n[materials.unused]\n\
+             [[objects]]\nmaterial = \"brass\"\n[[objects]]\nmaterial = \"chrome\"\n\
+             [[objects]]\nmaterial = \"brass\"\nglass = true\n[[objects]]\nmaterial = \"brass\"",
+        );
+        // brass gets one entry after the demos however many objects use it, chrome is the built-in one
+        assert_eq!(opaque, [FIRST_NAMED, material::demo("chrome").unwrap(), FIRST_NAMED]);
+        assert_eq!(glass, [FIRST_NAMED]);
+        assert_eq!(table.len() as u32, FIRST_NAMED + 1);
+        assert_eq!(table[FIRST_NAMED as usize].metallic, 1.0);
+    }
+
+    #[test]
+    fn a_file_material_wins_over_a_demo_of_the_same_name() {
+        let (table, opaque, _) = table("[materials.gold]\nroughness = 0.9\n[[objects]]\nmaterial = \"gold\"");
+        assert_eq!(opaque, [FIRST_NAMED]);
+        assert_eq!(table[FIRST_NAMED as usize].roughness, 0.9);
+        // the demo itself is still in the table for K
+        assert_eq!(table[material::demo("gold").unwrap() as usize], material::DEMOS[1].1);
+    }
+
+    #[test]
+    fn file_materials_become_linear_uniforms() {
+        let settings = scene_file::Material {
+            base_color: scene_file::Color([1.0, 0.5, 0.0]),
+            alpha: 0.25,
+            emissive: scene_file::Color([0.0, 0.0, 1.0]),
+            ..Default::default()
+        };
+        let uniform = uniform(&settings);
+        let half = scene_file::srgb_to_linear(0.5) as f32;
+        assert_eq!(uniform.base_color, [1.0, half, 0.0, 0.25]);
+        assert_eq!(uniform.emissive, [0.0, 0.0, 1.0]);
+        assert_eq!((uniform.metallic, uniform.roughness, uniform.reflectivity), (0.0, 0.5, 0.0));
+        // lit the metallic/roughness way, not the vertex colors' classic way
+        assert_eq!(uniform.classic, 0);
+    }
+
+    #[test]
+    fn the_files_lights_come_first_and_the_orbiting_one_after_them() {
+        let file = SceneFile::parse(
+            "[[lights]]\nkind = \"directional\"\ndirection = [0.0, 2.0, 0.0]\ncolor = \"ff0000\"\n\
+             [[lights]]\nkind = \"point\"\nposition = [1.0, 2.0, 3.0]\nattenuation = [2.0, 0.0, 0.5]\n\
+             [[lights]]\nkind = \"point\"\nposition = [0.0, 0.0, 5.0]\n[[objects]]",
+        )
+        .unwrap();
+        let uniform = light(&file, lights::DEFAULT_ATTENUATION);
+        assert_eq!(uniform.sources[0], LightSource::directional(Vec3::Y, Vec3::X));
+        // its own attenuation, or --attenuation's without one
+        assert_eq!(uniform.sources[1], LightSource::point(Vec3::new(1.0, 2.0, 3.0), Vec3::ONE, [2.0, 0.0, 0.5]));
+        assert_eq!(uniform.sources[2].attenuation, lights::DEFAULT_ATTENUATION);
+        // the built-in ones move up behind them, the orbiting one on with the file's
+        assert_eq!(lights::orbiting(3), Some(3));
+        assert_eq!(uniform.sources[3..], lights::extra_lights(lights::DEFAULT_ATTENUATION)[..MAX_LIGHTS - 3]);
+        assert_eq!(uniform.count, 4);
+
+        // a full house leaves no room for the orbiting light
+        let full = SceneFile::parse(&format!("{}[[objects]]", "[[lights]]\nkind = \"point\"\nposition = [0.0, 1.0, 0.0]\n".repeat(MAX_LIGHTS))).unwrap();
+        assert_eq!(lights::orbiting(MAX_LIGHTS), None);
+        assert_eq!(light_count(&full), MAX_LIGHTS);
+        assert!(light(&full, lights::DEFAULT_ATTENUATION).sources.iter().all(|source| source.kind == lights::POINT));
+        // and none at all starts with only the orbiting one
+        assert_eq!(light_count(&SceneFile::parse("lights = []\n[[objects]]").unwrap()), 1);
+    }
+
+    fn light_count(file: &SceneFile) -> usize {
+        light(file, lights::DEFAULT_ATTENUATION).count as usize
+    }
+
+    #[test]
+    fn objects_are_turned_and_stretched_as_the_file_says() {
+        let file = SceneFile::parse("[[objects]]\nposition = [1.0, 0.0, 0.0]\nrotation = [0.0, 90.0, 0.0]\nscale = [2.0, 1.0, 1.0]").unwrap();
+        let (opaque, _) = objects(&file);
+        let instance = opaque[0];
+        assert!(!instance.is_plain());
+        assert_eq!(instance.reach(), 2.0);
+        // stretched along its own x first, which a quarter turn around y then points along -z
+        let corner = instance.placement().transform_point3(Vec3::X);
+        assert!(corner.abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-6), "{}", corner);
+        // the box around it is as long along z as the stretch, and sits at the offset
+        let mut options = Options::parse_from(Vec::new()).unwrap();
+        options.scene.file = file;
+        let (min, max) = Scene::new(&options.scene).bounds(Mat4::IDENTITY).unwrap();
+        assert!(min.abs_diff_eq(Vec3::new(0.0, -1.0, -2.0), 1e-5), "{}", min);
+        assert!(max.abs_diff_eq(Vec3::new(2.0, 1.0, 2.0), 1e-5), "{}", max);
+        // the --grid's cubes are all plain
+        assert!(instances::grid(2).iter().all(Instance::is_plain));
+    }
+}
@@ -0,0 +1,591 @@
+// --scene scene.toml: the objects, lights, camera start and background as a file instead of code
+// without --scene the built-in default_scene.toml is used, which is the scene the program always had
+// serde turns the TOML into the structs below and the toml crate reports mistakes with the line and column they are on,
+// the range checks run during deserializing for the same reason, so a bad value is pointed at rather than just named
+// Scene::new() builds the GPU-side description from this, see scene.rs
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::lights::MAX_LIGHTS;
+use crate::material;
+use crate::options::parse_color;
+
+pub const DEFAULT_SCENE: &str = include_str!("default_scene.toml");
+
+// background behind the scene when neither --clear-color nor the scene file give one
+const DEFAULT_BACKGROUND: [f64; 3] = [0.0, 0.0, 0.0];
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)] // a misspelled key is an error instead of being silently ignored
+pub struct SceneFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+    // what every light shares: the light that reaches faces turned away from all of them, and the Phong highlight
+    #[serde(default = "default_ambient", deserialize_with = "ambient")]
+    pub ambient: f64,
+    #[serde(default = "default_shininess", deserialize_with = "shininess")]
+    pub shininess: f64,
+    #[serde(default = "white")]
+    pub specular: Color,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraStart>,
+    // [[lights]] in the order they take the first slots of the light uniform, see lights.rs
+    // without any the scene has the one directional light it always had, `lights = []` leaves only the ambient light
+    #[serde(default = "default_lights")]
+    pub lights: Vec<Light>,
+    // [materials.gold] and so on, sorted by name when written back out
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, Material>,
+    pub objects: Vec<Object>,
+}
+
+// where the first window's camera starts
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraStart {
+    pub eye: [f64; 3],
+    #[serde(default)]
+    pub target: [f64; 3],
+    #[serde(default = "default_fov", deserialize_with = "fov")]
+    pub fov: f64, // vertical, in degrees
+}
+
+// one [[lights]] entry, `kind = "directional"` or `kind = "point"` says which of the two it is
+// only the first light casts a shadow (S), and only when it is directional
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Light {
+    // as far away as the sun, the same direction and brightness everywhere
+    Directional {
+        #[serde(deserialize_with = "direction")]
+        direction: [f64; 3], // towards the light, normalized when the scene is built
+        #[serde(default = "white")]
+        color: Color,
+    },
+    // a bulb at `position`, dimmer the further away a surface is
+    Point {
+        position: [f64; 3],
+        #[serde(default = "white")]
+        color: Color,
+        // constant, linear and quadratic falloff with distance, --attenuation's when not given
+        #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "attenuation")]
+        attenuation: Option<[f64; 3]>,
+    },
+}
+
+// a material objects can name, see material.rs for how each value is used
+// colors are hex sRGB like everywhere else in the file, the rest 0 to 1
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Material {
+    pub base_color: Color, // in place of the cube's vertex colors
+    #[serde(deserialize_with = "alpha")]
+    pub alpha: f64, // opacity, only glass objects are drawn see-through
+    #[serde(deserialize_with = "metallic")]
+    pub metallic: f64,
+    #[serde(deserialize_with = "roughness")]
+    pub roughness: f64,
+    pub emissive: Color,
+    #[serde(deserialize_with = "reflectivity")]
+    pub reflectivity: f64, // how much of the sky it mirrors
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: white(),
+            alpha: 1.0,
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: Color([0.0; 3]),
+            reflectivity: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Object {
+    // what a reload matches the object by, see scene_reload.rs, unnamed objects go by their place in the list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub shape: Shape,
+    #[serde(default)]
+    pub position: [f64; 3],
+    // degrees around x, then y, then z, applied before it is moved to `position`
+    #[serde(default)]
+    pub rotation: [f64; 3],
+    // along the object's own axes before it is rotated, 1 is the cube's size of 2 units
+    #[serde(default = "unit_scale", deserialize_with = "scale")]
+    pub scale: [f64; 3],
+    #[serde(default = "white")]
+    pub color: Color,
+    #[serde(default)]
+    pub glass: bool,
+    // one of [materials] or a built-in one (material::DEMOS), without it the cube keeps its vertex colors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    // a mesh and an image to put on it, relative to the scene file's folder; load() checks they are there, but there
+    // is no loader for either yet, so the object is still drawn as `shape` with its vertex or material colors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shape {
+    #[default]
+    Cube, // cube.rs, cut up by --subdivisions
+}
+
+// a hex color like --clear-color takes, kept as the sRGB components 0-1 it stands for
+// written back out as hex, so a scene survives --print-scene unchanged
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [f64; 3]);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        parse_color(&value).map(Color)
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> String {
+        let [r, g, b] = color.0.map(|channel| (channel * 255.0).round() as u8);
+        format!("{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl Color {
+    // the shaders work in linear light, what an sRGB surface expects and converts back when storing
+    pub fn linear(self) -> [f32; 3] {
+        self.0.map(|channel| srgb_to_linear(channel) as f32)
+    }
+}
+
+// the standard sRGB transfer curve, inverted
+pub fn srgb_to_linear(channel: f64) -> f64 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl Object {
+    // `index` is the object's place in [[objects]], which stands in for a missing name
+    pub fn key(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("objects[{}]", index))
+    }
+}
+
+fn white() -> Color {
+    Color([1.0; 3])
+}
+
+fn default_fov() -> f64 {
+    45.0
+}
+
+fn default_ambient() -> f64 {
+    0.15
+}
+
+fn default_shininess() -> f64 {
+    32.0
+}
+
+fn unit_scale() -> [f64; 3] {
+    [1.0; 3]
+}
+
+// the light the scene had before there could be more than one in the file
+pub fn default_lights() -> Vec<Light> {
+    vec![Light::Directional { direction: [0.5, 1.0, 0.75], color: white() }]
+}
+
+// range checks, an error from in here comes out with the position of the offending value
+fn fov<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let degrees = f64::deserialize(deserializer)?;
+    if !(degrees > 0.0 && degrees < 180.0) {
+        return Err(D::Error::custom(format!("fov must be between 0 and 180 degrees, got {}", degrees)));
+    }
+    Ok(degrees)
+}
+
+fn ambient<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let ambient = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&ambient) {
+        return Err(D::Error::custom(format!("ambient must be between 0 and 1, got {}", ambient)));
+    }
+    Ok(ambient)
+}
+
+fn shininess<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let shininess = f64::deserialize(deserializer)?;
+    if !(shininess >= 1.0 && shininess.is_finite()) {
+        return Err(D::Error::custom(format!("shininess must be at least 1, got {}", shininess)));
+    }
+    Ok(shininess)
+}
+
+fn alpha<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    fraction(deserializer, "alpha")
+}
+
+fn metallic<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    fraction(deserializer, "metallic")
+}
+
+// 0 is allowed, the shader keeps the specular term finite itself
+fn roughness<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    fraction(deserializer, "roughness")
+}
+
+fn reflectivity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    fraction(deserializer, "reflectivity")
+}
+
+fn fraction<'de, D: Deserializer<'de>>(deserializer: D, name: &str) -> Result<f64, D::Error> {
+    let value = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(D::Error::custom(format!("{} must be between 0 and 1, got {}", name, value)));
+    }
+    Ok(value)
+}
+
+// a zero (or negative) scale would turn the cube inside out or flatten it to nothing, and its normals with it
+fn scale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 3], D::Error> {
+    let scale = <[f64; 3]>::deserialize(deserializer)?;
+    if !scale.iter().all(|axis| *axis > 0.0 && axis.is_finite()) {
+        return Err(D::Error::custom(format!("scale must be above 0 on every axis, got {:?}", scale)));
+    }
+    Ok(scale)
+}
+
+// the same rules as --attenuation: none of them negative, and a constant term so a surface right at the light isn't lit
+// infinitely bright
+fn attenuation<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[f64; 3]>, D::Error> {
+    let attenuation = <[f64; 3]>::deserialize(deserializer)?;
+    if !(attenuation[0] > 0.0 && attenuation.iter().all(|term| *term >= 0.0 && term.is_finite())) {
+        return Err(D::Error::custom(format!(
+            "attenuation needs a constant term above 0 and no negative ones, got {:?}",
+            attenuation
+        )));
+    }
+    Ok(Some(attenuation))
+}
+
+// a zero vector has no direction to normalize to
+fn direction<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 3], D::Error> {
+    let direction = <[f64; 3]>::deserialize(deserializer)?;
+    if direction == [0.0; 3] {
+        return Err(D::Error::custom("direction must not be [0, 0, 0]"));
+    }
+    Ok(direction)
+}
+
+impl SceneFile {
+    // models and textures are looked for relative to the working directory, load() looks next to the file instead
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_in(text, Path::new(""))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("couldn't read {}: {}", path.display(), err))?;
+        let folder = path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&text, folder).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn parse_in(text: &str, folder: &Path) -> Result<Self, String> {
+        let scene: SceneFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        scene.validate(folder)?;
+        Ok(scene)
+    }
+
+    // sRGB components 0-1, --clear-color still wins over this
+    pub fn background(&self) -> [f64; 3] {
+        self.background.map_or(DEFAULT_BACKGROUND, |color| color.0)
+    }
+
+    // the scene as TOML again, what --print-scene shows
+    pub fn to_toml(&self) -> String {
+        // only fails for types TOML can't hold (e.g. a map with non-string keys), none of which appear above
+        toml::to_string(self).expect("scene fits in TOML")
+    }
+
+    // what can only be checked once the whole file is read, `folder` is where relative model and texture paths start
+    fn validate(&self, folder: &Path) -> Result<(), String> {
+        // the opaque cubes drive the camera distance and picking, and a scene of nothing but glass has nothing to see
+        if !self.objects.iter().any(|object| !object.glass) {
+            return Err("the scene needs at least one [[objects]] entry that isn't glass".into());
+        }
+        // a reload couldn't tell which of two objects with the same name changed
+        let mut names = std::collections::HashSet::new();
+        if let Some(name) = self.objects.iter().filter_map(|object| object.name.as_deref()).find(|name| !names.insert(*name)) {
+            return Err(format!("more than one object is named \"{}\"", name));
+        }
+        // a misspelled material would otherwise quietly draw the cube with its vertex colors
+        let unknown = |name: &&str| !self.materials.contains_key(*name) && material::demo(name).is_none();
+        if let Some(name) = self.objects.iter().filter_map(|object| object.material.as_deref()).find(unknown) {
+            let demos: Vec<&str> = material::DEMOS.iter().map(|(demo, _)| *demo).collect();
+            return Err(format!("no material named \"{}\", add [materials.{}] or use one of {}", name, name, demos.join(", ")));
+        }
+        if let Some(camera) = &self.camera {
+            if camera.eye == camera.target {
+                return Err("[camera] eye and target are the same point, there is no direction to look in".into());
+            }
+        }
+        // the light uniform's array has a fixed length, see lights.rs
+        if self.lights.len() > MAX_LIGHTS {
+            return Err(format!("{} [[lights]] entries, there is only room for {}", self.lights.len(), MAX_LIGHTS));
+        }
+        // a typo in a path would otherwise only show once something tries to load it
+        for (i, object) in self.objects.iter().enumerate() {
+            for (what, path) in [("model", &object.model), ("texture", &object.texture)] {
+                let Some(path) = path else { continue };
+                let full = folder.join(path);
+                if !full.is_file() {
+                    return Err(format!("the {} of {} isn't there, no file {}", what, object.key(i), full.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: &str = r#"
+background = "102030"
+ambient = 0.3
+shininess = 16.0
+specular = "ff8000"
+
+[camera]
+eye = [4.0, 2.0, 6.0]
+target = [0.0, 0.5, 0.0]
+fov = 60.0
+
+[[lights]]
+kind = "directional"
+direction = [0.0, 1.0, 0.0]
+
+[[lights]]
+kind = "point"
+position = [1.0, 2.0, 3.0]
+color = "00ff00"
+attenuation = [1.0, 0.5, 0.25]
+
+[[lights]]
+kind = "point"
+position = [-1.0, 0.0, 0.0]
+
+[materials.brass]
+base_color = "e1c16e"
+metallic = 1.0
+roughness = 0.25
+
+[[objects]]
+name = "middle"
+material = "brass"
+rotation = [0.0, 45.0, 10.0]
+scale = [1.0, 0.5, 2.0]
+
+[[objects]]
+position = [2.0, 0.0, 0.0]
+color = "f00"
+glass = true
+material = "gold"
+"#;
+
+    fn error(text: &str) -> String {
+        SceneFile::parse(text).expect_err("scene parsed")
+    }
+
+    fn assert_same(a: &SceneFile, b: &SceneFile) {
+        assert_eq!(a.background, b.background);
+        assert_eq!((a.ambient, a.shininess, a.specular), (b.ambient, b.shininess, b.specular));
+        assert_eq!(a.camera, b.camera);
+        assert_eq!(a.lights, b.lights);
+        assert_eq!(a.materials, b.materials);
+        assert_eq!(a.objects, b.objects);
+    }
+
+    #[test]
+    fn print_scene_output_reads_back_the_same() {
+        for text in [FULL, DEFAULT_SCENE] {
+            let scene = SceneFile::parse(text).unwrap();
+            let printed = scene.to_toml();
+            let again = SceneFile::parse(&printed).unwrap_or_else(|err| panic!("{}\n{}", err, printed));
+            assert_same(&scene, &again);
+            // and printing that is a fixed point
+            assert_eq!(again.to_toml(), printed);
+        }
+    }
+
+    #[test]
+    fn missing_values_take_their_defaults() {
+        let scene = SceneFile::parse("[[objects]]").unwrap();
+        assert_eq!(scene.background(), DEFAULT_BACKGROUND);
+        assert_eq!((scene.ambient, scene.shininess, scene.specular), (0.15, 32.0, white()));
+        assert_eq!(scene.lights, default_lights());
+        assert!(scene.camera.is_none());
+        let object = Object {
+            name: None,
+            shape: Shape::Cube,
+            position: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+            color: white(),
+            glass: false,
+            material: None,
+            model: None,
+            texture: None,
+        };
+        assert_eq!(scene.objects[0], object);
+        // a point light's color and attenuation are optional, a directional light's color too
+        let scene = SceneFile::parse("[[lights]]\nkind = \"point\"\nposition = [1.0, 0.0, 0.0]\n[[objects]]").unwrap();
+        assert_eq!(scene.lights, [Light::Point { position: [1.0, 0.0, 0.0], color: white(), attenuation: None }]);
+        assert!(SceneFile::parse("lights = []\n[[objects]]").unwrap().lights.is_empty());
+        let camera = SceneFile::parse("[camera]\neye = [1.0, 1.0, 1.0]\n[[objects]]").unwrap().camera.unwrap();
+        assert_eq!((camera.target, camera.fov), ([0.0; 3], 45.0));
+    }
+
+    #[test]
+    fn colors_print_as_the_hex_they_were_read_from() {
+        let scene = SceneFile::parse(FULL).unwrap();
+        assert_eq!(String::from(scene.background.unwrap()), "102030");
+        assert_eq!(String::from(scene.objects[1].color), "ff0000");
+    }
+
+    #[test]
+    fn lights_read_every_value() {
+        let scene = SceneFile::parse(FULL).unwrap();
+        assert_eq!(scene.lights[0], Light::Directional { direction: [0.0, 1.0, 0.0], color: white() });
+        assert_eq!(
+            scene.lights[1],
+            Light::Point { position: [1.0, 2.0, 3.0], color: Color([0.0, 1.0, 0.0]), attenuation: Some([1.0, 0.5, 0.25]) }
+        );
+        assert_eq!((scene.ambient, scene.shininess, scene.specular), (0.3, 16.0, Color([1.0, 128.0 / 255.0, 0.0])));
+        assert_eq!((scene.objects[0].rotation, scene.objects[0].scale), ([0.0, 45.0, 10.0], [1.0, 0.5, 2.0]));
+    }
+
+    #[test]
+    fn range_checks_point_at_the_value() {
+        let message = error("background = \"000000\"\nambient = 2.0\n[[objects]]");
+        assert!(message.contains("ambient must be between 0 and 1, got 2"), "{}", message);
+        // toml's errors carry the line the value is on
+        assert!(message.contains("line 2"), "{}", message);
+        assert!(error("[camera]\neye = [1.0, 1.0, 1.0]\nfov = 180.0\n[[objects]]").contains("fov must be between 0 and 180"));
+        assert!(error("shininess = 0.5\n[[objects]]").contains("shininess must be at least 1"));
+        let directional = "[[lights]]\nkind = \"directional\"\n";
+        assert!(error(&format!("{}direction = [0.0, 0.0, 0.0]\n[[objects]]", directional)).contains("direction must not be [0, 0, 0]"));
+        let point = "[[lights]]\nkind = \"point\"\nposition = [0.0, 1.0, 0.0]\n";
+        for attenuation in ["[0.0, 0.2, 0.1]", "[1.0, -0.2, 0.1]"] {
+            let message = error(&format!("{}attenuation = {}\n[[objects]]", point, attenuation));
+            assert!(message.contains("attenuation needs a constant term above 0"), "{}", message);
+        }
+        // each kind has its own keys, and a kind there isn't
+        assert!(error("[[lights]]\nkind = \"point\"\n[[objects]]").contains("missing field `position`"));
+        assert!(error(&format!("{}position = [0.0, 1.0, 0.0]\n[[objects]]", directional)).contains("position"));
+        assert!(error("[[lights]]\nkind = \"spot\"\n[[objects]]").contains("spot"));
+        assert!(error("[[lights]]\ndirection = [0.0, 1.0, 0.0]\n[[objects]]").contains("kind"));
+        for scale in ["[1.0, 0.0, 1.0]", "[-1.0, 1.0, 1.0]"] {
+            let message = error(&format!("[[objects]]\nscale = {}", scale));
+            assert!(message.contains("scale must be above 0 on every axis"), "{}", message);
+        }
+        assert!(error("[[objects]]\ncolor = \"purple\"").contains("expected a hex color"));
+        assert!(error("[[objects]]\nshape = \"sphere\"").contains("sphere"));
+        // deny_unknown_fields catches a misspelled key
+        assert!(error("[[objects]]\npositon = [1.0, 0.0, 0.0]").contains("positon"));
+    }
+
+    #[test]
+    fn validation_after_the_whole_file_is_read() {
+        assert!(error("objects = []").contains("at least one [[objects]] entry that isn't glass"));
+        assert!(error("[[objects]]\nglass = true").contains("at least one [[objects]] entry that isn't glass"));
+        assert_eq!(error("[[objects]]\nname = \"a\"\n[[objects]]\nname = \"a\""), "more than one object is named \"a\"");
+        assert!(error("[[objects]]\nmaterial = \"brass\"").starts_with("no material named \"brass\", add [materials.brass]"));
+        assert!(error("[camera]\neye = [1.0, 2.0, 3.0]\ntarget = [1.0, 2.0, 3.0]\n[[objects]]").contains("eye and target are the same point"));
+        let nine_lights = "[[lights]]\nkind = \"point\"\nposition = [0.0, 1.0, 0.0]\n".repeat(MAX_LIGHTS + 1);
+        assert_eq!(error(&format!("{}[[objects]]", nine_lights)), "9 [[lights]] entries, there is only room for 8");
+        assert_eq!(error("[[objects]]\nmodel = \"no-such-model.obj\""), "the model of objects[0] isn't there, no file no-such-model.obj");
+        // unnamed objects never clash, and built-in materials need no [materials] entry
+        assert!(SceneFile::parse("[[objects]]\nmaterial = \"chrome\"\n[[objects]]").is_ok());
+    }
+
+    #[test]
+    fn objects_without_a_name_go_by_their_index() {
+        let scene = SceneFile::parse(FULL).unwrap();
+        assert_eq!(scene.objects[0].key(0), "middle");
+        assert_eq!(scene.objects[1].key(1), "objects[1]");
+    }
+
+    #[test]
+    fn materials_read_every_value_and_default_the_rest() {
+        let scene = SceneFile::parse(
+            "[materials.full]\nbase_color = \"ff0000\"\nalpha = 0.5\nmetallic = 1.0\nroughness = 0.0\nemissive = \"00ff00\"\nreflectivity = 0.75\n\
+             [materials.bare]\n[[objects]]",
+        )
+        .unwrap();
+        let full = &scene.materials["full"];
+        assert_eq!(full.base_color, Color([1.0, 0.0, 0.0]));
+        assert_eq!((full.alpha, full.metallic, full.roughness, full.reflectivity), (0.5, 1.0, 0.0, 0.75));
+        assert_eq!(full.emissive, Color([0.0, 1.0, 0.0]));
+        // an empty table is the default material: white, opaque, a dielectric halfway rough, no glow or mirroring
+        assert_eq!(scene.materials["bare"], Material::default());
+        // an unused material is fine, only a missing one is an error
+        assert!(scene.objects[0].material.is_none());
+    }
+
+    #[test]
+    fn material_values_are_range_checked() {
+        for (key, value) in [("alpha", "1.5"), ("metallic", "-0.1"), ("roughness", "2.0"), ("reflectivity", "-1.0")] {
+            let message = error(&format!("[materials.bad]\n{} = {}\n[[objects]]", key, value));
+            assert!(message.contains(&format!("{} must be between 0 and 1", key)), "{}", message);
+        }
+        assert!(error("[materials.bad]\nemissive = \"glow\"\n[[objects]]").contains("expected a hex color"));
+        assert!(error("[materials.bad]\nshininess = 10.0\n[[objects]]").contains("shininess"));
+    }
+
+    // a folder of its own under the temp directory with the files the scene names in it
+    fn folder_with(files: &[&str]) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("scene-file-{}-{}", std::process::id(), files.len()));
+        std::fs::create_dir_all(&folder).unwrap();
+        for file in files {
+            std::fs::write(folder.join(file), "").unwrap();
+        }
+        folder
+    }
+
+    #[test]
+    fn models_and_textures_are_looked_for_next_to_the_scene_file() {
+        let folder = folder_with(&["box.obj", "box.png"]);
+        let path = folder.join("scene.toml");
+        std::fs::write(&path, "[[objects]]\nname = \"box\"\nmodel = \"box.obj\"\ntexture = \"box.png\"").unwrap();
+        let scene = SceneFile::load(&path).unwrap();
+        assert_eq!(scene.objects[0].model.as_deref(), Some(Path::new("box.obj")));
+        assert_eq!(scene.objects[0].texture.as_deref(), Some(Path::new("box.png")));
+        // and they print and read back like everything else
+        let printed = scene.to_toml();
+        let again = SceneFile::parse_in(&printed, &folder).unwrap_or_else(|err| panic!("{}\n{}", err, printed));
+        assert_same(&scene, &again);
+
+        std::fs::write(&path, "[[objects]]\nname = \"box\"\ntexture = \"missing.png\"").unwrap();
+        let message = SceneFile::load(&path).expect_err("scene loaded");
+        assert!(message.ends_with(&format!("the texture of box isn't there, no file {}", folder.join("missing.png").display())), "{}", message);
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+// --scene is watched while the app runs: save the file and the running scene follows without a restart
+// the file's modification time is checked a few times a second, cheap enough that no file watcher crate is needed,
+// and when it changed the file is parsed again and compared with the scene that is running
+// only what differs is touched on the GPU, see State::reload_scene(): the lights and background are uniforms/clear
+// values, moved or recolored objects are rewritten in the instance buffer where they are, and only added, removed or
+// reordered objects need a new instance buffer since every cube lives in the same one
+// a file that doesn't parse (or is caught half-saved) is reported and the previous scene stays up
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use instant::Instant;
+use tracing::warn;
+
+use crate::scene_file::{Object, SceneFile};
+
+// how often the modification time is looked at, quick enough to feel immediate after saving
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// what changed between two versions of a scene file, objects are matched by Object::key()
+#[derive(Debug, Default, PartialEq)]
+pub struct SceneDiff {
+    pub added: Vec<String>,    // keys only in the new file, in its order
+    pub removed: Vec<String>,  // keys only in the old file, in its order
+    pub modified: Vec<String>, // in both, but placed, colored or drawn differently
+    pub slots_changed: bool,   // the objects no longer line up with the old instance buffer one to one
+    pub lights: bool,          // a [[lights]] entry or the ambient, shininess or specular they share
+    pub materials: bool,       // a [materials] entry was added, removed or changed
+    pub background: bool,
+    pub camera: bool,          // only read at startup, a change is reported but can't move the running cameras
+}
+
+// pure, so the same two files always give the same diff no matter what the app did in between
+pub fn diff(old: &SceneFile, new: &SceneFile) -> SceneDiff {
+    let old_objects = keyed(old);
+    let new_objects = keyed(new);
+
+    let mut diff = SceneDiff::default();
+    for (key, object) in &new_objects {
+        match find(&old_objects, key) {
+            None => diff.added.push(key.clone()),
+            Some(old_object) if old_object != *object => diff.modified.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old_objects.iter().filter(|(key, _)| find(&new_objects, key).is_none()).map(|(key, _)| key.clone()).collect();
+
+    // opaque cubes first and then the glass, each in file order, so a slot only stays put when the keys come in the
+    // same order and no object switched between opaque and glass
+    let slots = |objects: &[(String, &Object)]| -> Vec<(String, bool)> {
+        objects.iter().map(|(key, object)| (key.clone(), object.glass)).collect()
+    };
+    diff.slots_changed = slots(&old_objects) != slots(&new_objects);
+    diff.lights = old.lights != new.lights || (old.ambient, old.shininess, old.specular) != (new.ambient, new.shininess, new.specular);
+    diff.materials = old.materials != new.materials;
+    diff.background = old.background != new.background;
+    diff.camera = old.camera != new.camera;
+    diff
+}
+
+fn keyed(file: &SceneFile) -> Vec<(String, &Object)> {
+    file.objects.iter().enumerate().map(|(i, object)| (object.key(i), object)).collect()
+}
+
+// a linear search, scenes have a handful of objects and keep them in file order this way
+fn find<'a>(objects: &[(String, &'a Object)], key: &str) -> Option<&'a Object> {
+    objects.iter().find(|(other, _)| other == key).map(|(_, object)| *object)
+}
+
+impl SceneDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == SceneDiff::default()
+    }
+}
+
+// for the log line, e.g. "added box-2, modified objects[0], lights"
+impl fmt::Display for SceneDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        for (label, keys) in [("added", &self.added), ("removed", &self.removed), ("modified", &self.modified)] {
+            if !keys.is_empty() {
+                parts.push(format!("{} {}", label, keys.join(", ")));
+            }
+        }
+        // same objects, only listed in a different order, which draws the same
+        if parts.is_empty() && self.slots_changed {
+            parts.push("objects reordered".to_string());
+        }
+        for (label, changed) in [("lights", self.lights), ("materials", self.materials), ("background", self.background), ("camera", self.camera)] {
+            if changed {
+                parts.push(label.to_string());
+            }
+        }
+        if parts.is_empty() {
+            return f.write_str("nothing changed");
+        }
+        f.write_str(&parts.join("; "))
+    }
+}
+
+pub struct SceneWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>, // when the file was last changed, None while it can't be read
+    next_check: Instant,
+    current: SceneFile,           // the scene that is running, what the next version is compared with
+}
+
+impl SceneWatcher {
+    pub fn new(path: PathBuf, current: SceneFile) -> Self {
+        Self {
+            modified: modified_time(&path),
+            path,
+            next_check: Instant::now() + CHECK_INTERVAL,
+            current,
+        }
+    }
+
+    // the new scene and what changed, once the file was saved with something different in it
+    // called every frame, returns None straight away until the next check is due
+    pub fn poll(&mut self) -> Option<(SceneFile, SceneDiff)> {
+        let now = Instant::now();
+        if now < self.next_check {
+            return None;
+        }
+        self.next_check = now + CHECK_INTERVAL;
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let file = match SceneFile::load(&self.path) {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("Scene not reloaded, keeping the previous one: {}", err);
+                return None;
+            }
+        };
+        let diff = diff(&self.current, &file);
+        if diff.is_empty() {
+            return None;
+        }
+        self.current = file.clone();
+        Some((file, diff))
+    }
+}
+
+// editors often save by writing a new file and renaming it over the old one, so the time is looked up by path
+// every time instead of holding on to the file
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+[[objects]]
+name = "a"
+
+[[objects]]
+name = "b"
+position = [2.0, 0.0, 0.0]
+
+[[objects]]
+position = [-2.0, 0.0, 0.0]
+"#;
+
+    fn scene(text: &str) -> SceneFile {
+        SceneFile::parse(text).unwrap()
+    }
+
+    // BASE with `from` replaced by `to`
+    fn edited(from: &str, to: &str) -> SceneFile {
+        assert!(BASE.contains(from));
+        scene(&BASE.replacen(from, to, 1))
+    }
+
+    #[test]
+    fn the_same_file_is_no_change() {
+        let diff = diff(&scene(BASE), &scene(BASE));
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "nothing changed");
+    }
+
+    #[test]
+    fn an_added_object_is_listed_by_its_key() {
+        let new = scene(&format!("{}\n[[objects]]\nname = \"c\"\n", BASE));
+        let diff = diff(&scene(BASE), &new);
+        assert_eq!(diff.added, ["c"]);
+        assert!(diff.removed.is_empty() && diff.modified.is_empty());
+        assert!(diff.slots_changed);
+        assert_eq!(diff.to_string(), "added c");
+    }
+
+    #[test]
+    fn a_removed_object_is_listed_by_its_key() {
+        let new = edited("[[objects]]\nname = \"b\"\nposition = [2.0, 0.0, 0.0]\n", "");
+        let diff = diff(&scene(BASE), &new);
+        // the unnamed object moved up to index 1, so it is a different key now
+        assert_eq!(diff.removed, ["b", "objects[2]"]);
+        assert_eq!(diff.added, ["objects[1]"]);
+        assert!(diff.slots_changed);
+    }
+
+    #[test]
+    fn a_changed_object_is_modified_in_its_slot() {
+        let moved = diff(&scene(BASE), &edited("[2.0, 0.0, 0.0]", "[3.0, 0.0, 0.0]"));
+        assert_eq!(moved.modified, ["b"]);
+        assert!(moved.added.is_empty() && moved.removed.is_empty());
+        // rewritten where it is, no new instance buffer
+        assert!(!moved.slots_changed);
+        assert_eq!(moved.to_string(), "modified b");
+
+        let recolored = diff(&scene(BASE), &edited("name = \"a\"", "name = \"a\"\ncolor = \"00ff00\""));
+        assert_eq!(recolored.modified, ["a"]);
+        assert!(!recolored.slots_changed);
+
+        let turned = diff(&scene(BASE), &edited("name = \"a\"", "name = \"a\"\nrotation = [0.0, 45.0, 0.0]\nscale = [2.0, 1.0, 1.0]"));
+        assert_eq!(turned.modified, ["a"]);
+        assert!(!turned.slots_changed);
+
+        // turning to glass moves it to the end of the instance buffer
+        let glass = diff(&scene(BASE), &edited("name = \"a\"", "name = \"a\"\nglass = true"));
+        assert_eq!(glass.modified, ["a"]);
+        assert!(glass.slots_changed);
+    }
+
+    #[test]
+    fn reordering_named_objects_only_changes_the_slots() {
+        let swapped = "[[objects]]\nname = \"b\"\nposition = [2.0, 0.0, 0.0]\n\n[[objects]]\nname = \"a\"\n\n[[objects]]\nposition = [-2.0, 0.0, 0.0]\n";
+        let diff = diff(&scene(BASE), &scene(swapped));
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty());
+        assert!(diff.slots_changed);
+        assert_eq!(diff.to_string(), "objects reordered");
+    }
+
+    #[test]
+    fn the_other_sections_are_flagged_on_their_own() {
+        let new = scene(&format!("background = \"202020\"\nambient = 0.5\n[camera]\neye = [1.0, 1.0, 1.0]\n[materials.x]\nmetallic = 1.0\n{}", BASE));
+        let diff = diff(&scene(BASE), &new);
+        assert!(diff.lights && diff.materials && diff.background && diff.camera);
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty() && !diff.slots_changed);
+        assert_eq!(diff.to_string(), "lights; materials; background; camera");
+        // a light of its own, the shared values staying as they were
+        let new = scene(&format!("[[lights]]\nkind = \"point\"\nposition = [0.0, 2.0, 0.0]\n{}", BASE));
+        assert_eq!(super::diff(&scene(BASE), &new).to_string(), "lights");
+    }
+}
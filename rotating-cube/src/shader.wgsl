@@ -12,30 +12,414 @@ struct Model {
 @group(0) @binding(1)
 var<uniform> model: Model;
 
+// Light uniform (up to MAX_LIGHTS directional or point lights + Phong specular settings), see lights.rs
+// vec3 fields are 16-byte aligned in uniform buffers, so each one is paired with an f32 to fill the gap
+const MAX_LIGHTS: u32 = 8u;
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+
+struct LightSource {
+    vector: vec3<f32>, // directional: unit vector pointing from the surface towards the light, point: its position
+    kind: u32,         // LIGHT_DIRECTIONAL or LIGHT_POINT
+    color: vec3<f32>,  // multiplies its diffuse and specular light
+    // point lights are divided by constant + linear * d + quadratic * d^2 at distance d
+    attenuation_constant: f32,
+    attenuation_linear: f32,
+    attenuation_quadratic: f32,
+};
+
+struct Light {
+    eye_position: vec3<f32>,   // camera position in world space, needed for the view direction
+    shininess: f32,            // specular exponent, higher = smaller and sharper highlight
+    specular_color: vec3<f32>, // color of the highlight
+    ambient: f32,              // constant light so faces pointing away aren't pitch black
+    count: u32,                // how many of `sources` are switched on
+    sources: array<LightSource, MAX_LIGHTS>,
+};
+@group(0) @binding(2)
+var<uniform> light: Light;
+
+// Per-frame values shared by every instance
+struct Frame {
+    time: f32,    // simulation time in seconds, drives the hue animation
+    hue_mix: f32, // 0 = vertex colors, 1 = animated hue (--grid)
+    normal_colors: f32, // 1 in the normals debug view (D key), faces show their world-space normal instead of being lit
+};
+@group(0) @binding(3)
+var<uniform> frame: Frame;
+
+// Per-window values, see sky.rs
+struct Globals {
+    time: f32,               // the same as frame.time
+    day_phase: f32,
+    resolution: vec2<f32>,
+    ambient_tint: vec3<f32>, // the time of day's color for the ambient light, white without --day-length
+    effect: u32,             // the E key's color effect, see effect_color()
+};
+@group(0) @binding(4)
+var<uniform> globals: Globals;
+
+// The shadow map of the scene file's first light (S key) and how to look things up in it, see shadow.rs
+struct Shadow {
+    light_view_proj: mat4x4<f32>, // world space to the shadow map's clip space
+    enabled: u32,                 // 0 while shadows are off, nothing is shadowed
+    bias: f32,                    // how far a fragment is moved towards the light before comparing, in the map's depth
+};
+@group(0) @binding(5)
+var<uniform> shadow: Shadow;
+@group(0) @binding(6)
+var shadow_map: texture_depth_2d;
+@group(0) @binding(7)
+var shadow_sampler: sampler_comparison;
+
+// Per-material values, group 1 so each object binds its own while group 0 stays bound for the whole pass
+// shader_unlit.wgsl declares the same struct, see material.rs
+struct Material {
+    base_color: vec4<f32>, // classic: multiplies the vertex or hue color, otherwise the color itself, alpha is the glass's opacity
+    metallic: f32,         // 0..1, metals reflect their own color and have no diffuse light
+    roughness: f32,        // 0..1, how far the highlight spreads, clamped to MIN_ROUGHNESS before use
+    classic: u32,          // 1 for the defaults: vertex colors and the Phong highlight with the scene's shininess
+    emissive: vec3<f32>,   // added on top of the lighting
+    reflectivity: f32,     // 0..1, blends the lit color towards the sky reflected in the surface, see environment()
+};
+@group(1) @binding(0)
+var<uniform> material: Material;
+
+// how many times per second the hue goes all the way around the color wheel
+const HUE_SPEED: f32 = 0.2;
+
+// how much of its own color a fully hovered cube adds on top of the lighting
+const HOVER_BRIGHTNESS: f32 = 0.6;
+
+// at a roughness of 0 the GGX distribution below is 0/0 where the highlight's centre is, NaN that then spreads to the
+// whole pixel, this keeps it a very small but finite highlight instead
+const MIN_ROUGHNESS: f32 = 0.045;
+
+const PI: f32 = 3.14159265;
+
 // 3. Vertex input
 struct VertexInput {
     @location(0) position: vec3<f32>, // vertex position
     @location(1) color: vec3<f32>,    // vertex color
+    @location(2) normal: vec3<f32>,   // face normal, also drawn as a line in the normals debug view (N key)
 };
 
+// 3b. Instance input, one per cube in the grid
+struct InstanceInput {
+    @location(3) offset: vec3<f32>, // where this cube sits in the world
+    @location(4) phase: f32,        // color animation delay, larger further from the centre
+    @location(5) emissive: f32,     // 0..1 glow while the mouse cursor is over this cube, from the emissive buffer
+    @location(6) color: vec3<f32>,  // the object's color from the scene file, white leaves the vertex colors as they are
+    @location(7) rotation: vec4<f32>, // the object's own turn as a unit quaternion, (0, 0, 0, 1) for none
+    @location(8) scale: vec3<f32>,    // stretch along the object's own axes, before the rotation
+};
+
+// a quaternion rotation without building a matrix: v + 2 q.xyz x (q.xyz x v + w v)
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
 // 4. Vertex output to fragment shader
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>, // where GPU draws vertex in clip-space
     @location(0) frag_color: vec3<f32>,          // pass color to fragment shader
+    @location(1) world_position: vec3<f32>,      // position after the model transform, for the view direction
+    @location(2) world_normal: vec3<f32>,        // normal after the model transform, for lighting
+    @location(3) @interpolate(flat) emissive: f32, // the same for the whole cube, nothing to interpolate
+    @location(4) object_position: vec3<f32>,     // position before the model transform, the effects' patterns stick to the cube
 };
 
+// hue (0..1 around the color wheel) to a fully saturated RGB color
+fn hue_to_rgb(hue: f32) -> vec3<f32> {
+    let k = vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0);
+    return clamp(abs(fract(vec3<f32>(hue) + k) * 6.0 - 3.0) - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
 // 5. Vertex shader
 @vertex
-fn vs_main(input: VertexInput) -> VertexOutput {
+fn vs_main(input: VertexInput, instance: InstanceInput) -> VertexOutput {
     var output: VertexOutput;
-    // Transform vertex: model -> world -> camera -> clip
-    output.clip_position = camera.view_proj * model.model * vec4<f32>(input.position, 1.0);
-    output.frag_color = input.color; // pass color to fragment shader
+    // Transform vertex: model -> world -> camera -> clip, every cube is stretched and turned as the scene file places it,
+    // then spins around its own centre before being moved into place
+    let placed = rotate(instance.rotation, input.position * instance.scale);
+    let world_position = model.model * vec4<f32>(placed, 1.0) + vec4<f32>(instance.offset, 0.0);
+    output.clip_position = camera.view_proj * world_position;
+    // cubes further out lag behind the centre, so the hues travel outwards as a wave
+    let hue = hue_to_rgb(fract(frame.time * HUE_SPEED - instance.phase));
+    // a named material's own color stands in for the vertex colors and the grid's hues
+    var color = mix(input.color, hue, frame.hue_mix);
+    if (material.classic == 0u) {
+        color = material.base_color.rgb;
+    }
+    output.frag_color = color * instance.color;
+    output.world_position = world_position.xyz;
+    // w = 0 so translation doesn't affect the direction, fine for normals while the model is only rotated
+    // a stretched cube's faces tilt the other way from its vertices, so the normal is divided by the scale instead
+    let placed_normal = rotate(instance.rotation, input.normal / instance.scale);
+    output.world_normal = (model.model * vec4<f32>(placed_normal, 0.0)).xyz;
+    output.emissive = instance.emissive;
+    output.object_position = input.position;
     return output;
 }
 
 // 6. Fragment shader
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(input.frag_color, 1.0); // final pixel color
+    return vec4<f32>(shade(input), 1.0); // final pixel color
+}
+
+// lit color of a point on a face, shared by the opaque cubes and the glass
+fn shade(input: VertexOutput) -> vec3<f32> {
+    // interpolation between vertices shortens normals, renormalize per pixel
+    let normal = normalize(input.world_normal);
+    // -1..1 per axis squeezed into 0..1 per color channel, so +X is red, +Y green, +Z blue and negative axes darker
+    if (frame.normal_colors > 0.5) {
+        return normal * 0.5 + 0.5;
+    }
+    let view_dir = normalize(light.eye_position - input.world_position);
+    // emissive light doesn't depend on the light's direction, so a hovered cube brightens on its shadowed faces too
+    let hover = input.emissive * HOVER_BRIGHTNESS;
+    if (material.classic == 0u) {
+        let base = effect_color(input);
+        return shade_pbr(input.world_position, normal, view_dir, base) + material.emissive + base * hover;
+    }
+
+    // every light adds its own diffuse and specular on top of the others
+    var diffuse = vec3<f32>(0.0);
+    var specular = vec3<f32>(0.0);
+    for (var i = 0u; i < min(light.count, MAX_LIGHTS); i += 1u) {
+        let sample = light_towards(light.sources[i], input.world_position);
+        let light_dir = sample.direction;
+        let color = sample.color * visibility(i, input.world_position, normal);
+
+        // diffuse: surfaces facing the light are brightest, falling off with the angle
+        diffuse += color * max(dot(normal, light_dir), 0.0);
+
+        // specular: reflect the incoming light about the normal, highlight is strongest when that points at the eye
+        let reflect_dir = reflect(-light_dir, normal);
+        specular += color * pow(max(dot(reflect_dir, view_dir), 0.0), light.shininess);
+    }
+
+    let base = effect_color(input) * material.base_color.rgb;
+    return base * (light.ambient * globals.ambient_tint + diffuse) + light.specular_color * specular + base * hover;
+}
+
+// where the light comes from at `position` and how strong it is there
+// a point light's direction changes across the surface and it gets dimmer further away, a directional light's is the
+// same everywhere
+fn light_towards(source: LightSource, position: vec3<f32>) -> LightSample {
+    if (source.kind != LIGHT_POINT) {
+        return LightSample(source.vector, source.color);
+    }
+    let to_light = source.vector - position;
+    let d = length(to_light);
+    let falloff = source.attenuation_constant + source.attenuation_linear * d + source.attenuation_quadratic * d * d;
+    return LightSample(to_light / d, source.color / falloff);
+}
+
+struct LightSample {
+    direction: vec3<f32>,
+    color: vec3<f32>,
+};
+
+// how much of light `i` reaches `position`, 0 in a shadow, 1 in the open and in between along a shadow's edge
+// only the scene file's first light has a shadow map, and only when it is directional, every other light reaches everything
+// the map is compared 3x3 texels around the point and averaged (percentage-closer filtering), on top of the
+// sampler's own blend of four, so the edges are soft instead of stairs of texels
+// textureSampleCompareLevel() rather than textureSampleCompare() since this runs inside the lights loop, where WGSL
+// doesn't allow the kind of sampling that picks a mip level from its neighbouring pixels
+fn visibility(i: u32, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (i != 0u || shadow.enabled == 0u || light.sources[0].kind != LIGHT_DIRECTIONAL) {
+        return 1.0;
+    }
+    let clip = shadow.light_view_proj * vec4<f32>(position, 1.0); // orthographic, w is 1
+    // clip space has +Y up, the texture's rows go down
+    let uv = clip.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || clip.z > 1.0) {
+        return 1.0; // outside the light's box, nothing there to cast a shadow
+    }
+    // a face the light only grazes spreads over more of the map's depth per texel, it gets up to 4 times the bias
+    let n_l = max(dot(normal, light.sources[0].vector), 0.0);
+    let depth = clip.z - shadow.bias * (1.0 + 3.0 * (1.0 - n_l));
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y += 1) {
+        for (var x = -1; x <= 1; x += 1) {
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + vec2<f32>(f32(x), f32(y)) * texel, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+// a named material, lit the metallic/roughness way: the highlight's size comes from the roughness instead of the
+// scene's shininess and its color from the metalness, non-metals reflect about 4% of the light whatever their color
+// GGX distribution, Schlick's Fresnel and the Schlick-GGX shadowing, the usual real-time choices
+fn shade_pbr(position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, base: vec3<f32>) -> vec3<f32> {
+    let roughness = clamp(material.roughness, MIN_ROUGHNESS, 1.0);
+    let a2 = pow(roughness, 4.0); // the distribution takes roughness squared, and that squared again
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let f0 = mix(vec3<f32>(0.04), base, material.metallic);
+    let n_v = max(dot(normal, view_dir), 0.0001);
+
+    var diffuse = vec3<f32>(0.0);
+    var specular = vec3<f32>(0.0);
+    for (var i = 0u; i < min(light.count, MAX_LIGHTS); i += 1u) {
+        let sample = light_towards(light.sources[i], position);
+        let color = sample.color * visibility(i, position, normal);
+        let n_l = max(dot(normal, sample.direction), 0.0);
+        let half_dir = normalize(sample.direction + view_dir);
+        let n_h = max(dot(normal, half_dir), 0.0);
+        let d = n_h * n_h * (a2 - 1.0) + 1.0;
+        let distribution = a2 / (PI * d * d);
+        let shadowing = n_l / (n_l * (1.0 - k) + k) * n_v / (n_v * (1.0 - k) + k);
+        let fresnel = f0 + (1.0 - f0) * pow(1.0 - max(dot(half_dir, view_dir), 0.0), 5.0);
+        // what the highlight reflects isn't there for the diffuse, and metals have none at all
+        diffuse += (1.0 - fresnel) * (1.0 - material.metallic) * color * n_l;
+        specular += distribution * shadowing * fresnel / max(4.0 * n_l * n_v, 0.0001) * color * n_l;
+    }
+    // the ambient light reflects off metals too, or they would be black wherever no highlight is
+    let ambient = light.ambient * globals.ambient_tint * mix(base, f0, material.metallic);
+    let lit = ambient + base * diffuse + light.specular_color * specular;
+
+    // a mirror-like surface shows whatever the view bounces off it towards, a metal tints it with its own color
+    let mirrored = environment(reflect(-view_dir, normal)) * mix(vec3<f32>(1.0), base, material.metallic);
+    return mix(lit, mirrored, material.reflectivity);
+}
+
+// 6a. The sky around the scene, for reflections
+// there is no environment map, the sky is sky.wgsl's gradient drawn in screen space, so this lays the same gradient
+// over the directions instead: the horizon's color straight out sideways, the zenith's straight up, and below the
+// horizon a ground in a darker shade of it. Without --day-length it stays the noon sky
+// keyframe() and palette() are copies of sky.wgsl's (and sky.rs'), a change to one needs the same change to the others,
+// and sky.rs has a copy of environment() for its tests
+
+// how much darker the ground is than the horizon above it
+const GROUND_BRIGHTNESS: f32 = 0.3;
+
+fn environment(direction: vec3<f32>) -> vec3<f32> {
+    let colors = palette(globals.day_phase);
+    if (direction.y >= 0.0) {
+        return mix(colors.horizon, colors.zenith, pow(direction.y, 0.6));
+    }
+    // a short fade so the horizon is a soft line in a mirror rather than a hard one
+    return colors.horizon * mix(1.0, GROUND_BRIGHTNESS, smoothstep(0.0, 0.2, -direction.y));
+}
+
+struct SkyColors {
+    zenith: vec3<f32>,
+    horizon: vec3<f32>,
+};
+
+fn keyframe(index: u32) -> SkyColors {
+    switch index {
+        case 0u: {
+            return SkyColors(vec3<f32>(0.16, 0.22, 0.45), vec3<f32>(0.95, 0.45, 0.2));
+        }
+        case 1u: {
+            return SkyColors(vec3<f32>(0.12, 0.3, 0.8), vec3<f32>(0.55, 0.75, 0.95));
+        }
+        case 2u: {
+            return SkyColors(vec3<f32>(0.2, 0.1, 0.35), vec3<f32>(0.9, 0.3, 0.15));
+        }
+        default: {
+            return SkyColors(vec3<f32>(0.005, 0.008, 0.03), vec3<f32>(0.03, 0.05, 0.12));
+        }
+    }
+}
+
+fn palette(phase: f32) -> SkyColors {
+    let scaled = fract(phase) * 4.0;
+    let index = u32(floor(scaled)) % 4u;
+    let t = smoothstep(0.0, 1.0, fract(scaled));
+    let start = keyframe(index);
+    let end = keyframe((index + 1u) % 4u);
+    return SkyColors(mix(start.zenith, end.zenith, t), mix(start.horizon, end.horizon, t));
+}
+
+// 6b. Procedural color effects (E key), in place of the vertex colors, see effects.rs
+// the cases are Effect::index(), written out since this naga only takes literals as case values
+
+fn effect_color(input: VertexOutput) -> vec3<f32> {
+    let p = input.object_position; // -1..1 across the cube
+    let t = globals.time;
+    switch globals.effect {
+        // sines of the position running at different speeds and angles, added up and used as a hue
+        case 1u: { // plasma
+            let v = sin(p.x * 3.0 + t) + sin(p.y * 4.0 - t * 1.3) + sin((p.x + p.z) * 2.5 + t * 0.7) + sin(length(p) * 5.0 - t * 2.0);
+            return hue_to_rgb(v * 0.125 + 0.5);
+        }
+        // bands along the cube's diagonal that scroll one way, so they wrap around the faces like a barber's pole
+        case 2u: { // stripes
+            let band = fract(dot(p, vec3<f32>(1.0)) * 1.5 - t * 0.5);
+            let edge = smoothstep(0.45, 0.5, band) - smoothstep(0.95, 1.0, band);
+            return mix(vec3<f32>(0.1, 0.1, 0.15), input.frag_color, edge);
+        }
+        // the scene file's first light turned into the cube's own space (the model matrix is a rotation, its transpose
+        // undoes it) says which corner faces it, rings travel out from there across the faces every 2 seconds
+        case 3u: { // pulse
+            let m = model.model;
+            let towards_light = transpose(mat3x3<f32>(m[0].xyz, m[1].xyz, m[2].xyz)) * light.sources[0].vector;
+            let corner = select(vec3<f32>(-1.0), vec3<f32>(1.0), towards_light >= vec3<f32>(0.0));
+            let radius = fract(t * 0.5) * 3.5; // the far corner is 2 * sqrt(3) away
+            let ring = exp(-pow((distance(p, corner) - radius) * 4.0, 2.0));
+            return mix(input.frag_color * 0.25, vec3<f32>(1.0, 0.8, 0.4), ring);
+        }
+        default: {
+            return input.frag_color;
+        }
+    }
+}
+
+// 7. Fragment shader for the points draw mode
+// WebGPU points are always exactly one pixel (there is no point size), so depth is shown through brightness instead:
+// points close to the camera are bright, points further back fade out
+@fragment
+fn fs_points(input: VertexOutput) -> @location(0) vec4<f32> {
+    // distance relative to the camera's distance from the origin, ~1 around the middle of the scene
+    let relative = distance(light.eye_position, input.world_position) / length(light.eye_position);
+    let fade = mix(1.0, 0.25, smoothstep(0.5, 1.5, relative));
+    return vec4<f32>(input.frag_color * fade, 1.0);
+}
+
+// 8. Fragment shader for the wireframe debug view (D key), the edges are drawn in one flat color over the shaded faces
+const WIREFRAME_COLOR: vec3<f32> = vec3<f32>(1.0, 1.0, 1.0);
+
+@fragment
+fn fs_wireframe(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(WIREFRAME_COLOR, 1.0);
+}
+
+// 9. Selection outline (X key): the cube drawn again a little bigger in one flat color, the stencil test throws away
+// every pixel the cube itself covered so only a rim around it is left
+const CUBE_HALF_SIZE: f32 = 1.0; // the cube spans -1..1, see cube.rs
+const OUTLINE_WIDTH: f32 = 0.06; // in world units
+const OUTLINE_COLOR: vec3<f32> = vec3<f32>(1.0, 0.6, 0.1);
+
+@vertex
+fn vs_outline(input: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    // every face has its own copy of each corner, moved out along only its own normal the faces would come apart at
+    // the edges and the rim would have notches at the corners, so each vertex is moved out along the normals of every
+    // face it lies on (sign() of each axis where it touches the surface), the grown faces then still meet and the rim is
+    // equally thick everywhere, corners included
+    let on_surface = step(vec3<f32>(CUBE_HALF_SIZE - 0.0001), abs(input.position)) * sign(input.position);
+    // --deform pushes vertices off the flat faces, those only have their own normal to go by
+    let push = select(on_surface, input.normal, all(on_surface == vec3<f32>(0.0)));
+    let position = input.position + push * OUTLINE_WIDTH;
+
+    // only the position is passed on, the fragment shader is a flat color
+    let placed = rotate(instance.rotation, position * instance.scale);
+    let world_position = model.model * vec4<f32>(placed, 1.0) + vec4<f32>(instance.offset, 0.0);
+    return camera.view_proj * world_position;
+}
+
+@fragment
+fn fs_outline() -> @location(0) vec4<f32> {
+    return vec4<f32>(OUTLINE_COLOR, 1.0);
+}
+
+// 10. See-through glass cube, lit like the others but only partly covering what's behind it
+// the pipeline blends with the material's alpha, see transparency.rs for the draw order that needs
+@fragment
+fn fs_glass(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(input), material.base_color.a);
 }
@@ -0,0 +1,59 @@
+// Unlit fragment shaders: the cube's colors exactly as the vertex shader passes them on, no light direction or highlight
+// only fragment stages live here, the pipelines take their vertex stage from shader.wgsl (see pipelines.rs), so the
+// structs below have to match the ones there
+
+// Per-frame values, only the normals view flag is read here
+struct Frame {
+    time: f32,
+    hue_mix: f32,
+    normal_colors: f32, // 1 in the normals debug view (D key)
+};
+@group(0) @binding(3)
+var<uniform> frame: Frame;
+
+struct Material {
+    base_color: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    classic: u32,
+    emissive: vec3<f32>,
+    reflectivity: f32,
+};
+@group(1) @binding(0)
+var<uniform> material: Material;
+
+// what vs_main in shader.wgsl outputs, every location has to be declared even if it isn't used
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) frag_color: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+    @location(3) @interpolate(flat) emissive: f32,
+    @location(4) object_position: vec3<f32>,
+};
+
+// hover glow added on top, the same amount as the lit shader adds
+const HOVER_BRIGHTNESS: f32 = 0.6;
+
+fn shade(input: VertexOutput) -> vec3<f32> {
+    // the debug views work the same whichever shader an object uses
+    if (frame.normal_colors > 0.5) {
+        return normalize(input.world_normal) * 0.5 + 0.5;
+    }
+    // a named material's color is already in frag_color, the vertex shader put it there in place of the vertex colors
+    if (material.classic == 0u) {
+        return input.frag_color * (1.0 + input.emissive * HOVER_BRIGHTNESS) + material.emissive;
+    }
+    let base = input.frag_color * material.base_color.rgb;
+    return base * (1.0 + input.emissive * HOVER_BRIGHTNESS);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(input), 1.0);
+}
+
+@fragment
+fn fs_glass(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(input), material.base_color.a);
+}
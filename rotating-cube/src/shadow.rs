@@ -0,0 +1,182 @@
+// shadows from the scene file's first light (S key): every frame the opaque cubes are first drawn depth-only as the light
+// sees them, into a texture of their distances from it (the shadow map), then the main passes look up each fragment in
+// it, a fragment further from the light than what the map has in front of it is in the shadow of that
+// the light has to be directional (a point light first in the file casts none), its rays are parallel, so the view
+// from it is an orthographic box rather than a frustum, fitted around a sphere that holds every cube however it is
+// turned, so the box doesn't jitter as the cube spins
+//
+// only the first light casts shadows, the others still light everything they face, and only the opaque cubes cast them
+// (the glass lets its light through), though the glass has them falling on it like everything else
+// the main pass samples the map through the frame group (bindings 5 to 7), the shadow pass has a group of its own
+// since the map can't be read in the same pass that writes it
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::gpu::bindings::BindingsBuilder;
+use crate::cube::Vertex;
+use crate::depth::{self, DepthBuffer};
+use crate::instances::Instance;
+use crate::mesh::Mesh;
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::pipelines::DrawMode;
+use crate::render_stats::{CountingPass, CountingQueue, RenderStats};
+
+// width and height of the shadow map in texels, one texel covers the scene's diameter / SIZE
+pub const SIZE: u32 = 2048;
+
+// the least the fragment is moved towards the light before the comparison, in world units, so a surface doesn't
+// shadow itself where the map's texels don't quite line up with it (shadow acne); kept this small the shadows still
+// start right where the cube touches what they fall on
+const MIN_BIAS: f32 = 0.01;
+
+// matches Shadow in shader.wgsl and shadow.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct ShadowUniform {
+    pub light_view_proj: [[f32; 4]; 4], // world space to the shadow map's clip space
+    pub enabled: u32,                   // 0 while S has them off (zeroed()), every fragment is then lit and the rest unused
+    pub bias: f32,                      // MIN_BIAS or a texel and a half if that is more, in the map's 0..1 depth
+    pub _padding: [f32; 2],
+}
+
+impl ShadowUniform {
+    // `towards` is the light's direction (towards it, like LightSource::vector), the box is fitted around the sphere
+    // at `center` with `radius`
+    pub fn new(towards: Vec3, center: Vec3, radius: f32) -> Self {
+        let radius = radius.max(f32::EPSILON);
+        // the texels are 2 * radius / SIZE wide, a slope can put a surface half of one away from the texel's centre
+        // either way, so the bias grows with them for a big scene
+        let bias = MIN_BIAS.max(1.5 * 2.0 * radius / SIZE as f32);
+        Self {
+            light_view_proj: light_view_proj(towards, center, radius).to_cols_array_2d(),
+            enabled: 1,
+            bias: bias / (2.0 * radius), //the box is 2 * radius deep
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+// the light's view of the sphere: the eye on its surface towards the light, looking at its centre, and a box just big
+// enough around it, so depth 0 is the side the light comes from and 1 the far side
+// glam's orthographic_rh() already gives wgpu's 0..1 depth, and the map is never reversed whatever --reverse-z says
+pub fn light_view_proj(towards: Vec3, center: Vec3, radius: f32) -> Mat4 {
+    let towards = towards.normalize_or_zero();
+    // look_at needs an up that isn't along the view direction, a light from straight above gets Z instead
+    let up = if towards.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+    let view = Mat4::look_at_rh(center + towards * radius, center, up);
+    Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, 2.0 * radius) * view
+}
+
+// the sphere around every cube: the box around their offsets (moved by `shift`, the model's translation) and `radius`,
+// one cube's, on top of the box's half diagonal. None without any cubes
+pub fn bounding_sphere(offsets: impl Iterator<Item = Vec3>, shift: Vec3, radius: f32) -> Option<(Vec3, f32)> {
+    let (min, max) = offsets.fold(None, |bounds: Option<(Vec3, Vec3)>, offset| {
+        let (min, max) = bounds.unwrap_or((offset, offset));
+        Some((min.min(offset), max.max(offset)))
+    })?;
+    Some(((min + max) / 2.0 + shift, (max - min).length() / 2.0 + radius))
+}
+
+pub struct ShadowMap {
+    pub depth: DepthBuffer,          // the map itself, depth_view is what the main pass samples
+    pub sampler: wgpu::Sampler,      // comparison sampler, answers "is the fragment in front" instead of returning depth
+    pub buffer: wgpu::Buffer,        // the ShadowUniform, read by both passes
+    bind_group: wgpu::BindGroup,     // the shadow pass's: the uniform and the cube's model matrix
+    pipeline: Arc<wgpu::RenderPipeline>,
+}
+
+impl ShadowMap {
+    // `model_buffer` is the one the cubes spin with, the shadow pass needs the same transform
+    // `format` is only there to key the pipeline, it has no color target
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache, model_buffer: &wgpu::Buffer, format: wgpu::TextureFormat) -> Self {
+        // the map only needs depth, the outline's stencil is the window's business
+        let depth = depth::create_depth_buffer(device, SIZE, SIZE, depth::DEPTH_FORMAT);
+
+        // linear filtering compares the four nearest texels and blends the answers, softening the edge a little for free
+        // outside the map counts as lit, the shader already leaves those fragments alone
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual), //lit where the fragment is no further from the light than the map says
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow Buffer"),
+            contents: bytemuck::bytes_of(&ShadowUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bindings = BindingsBuilder::new("Shadow Pass")
+            .uniform(0, wgpu::ShaderStages::VERTEX) //the light's view_proj
+            .uniform(1, wgpu::ShaderStages::VERTEX) //the cube's model matrix
+            .build(device);
+        let bind_group = bindings.bind_group(device, &[buffer.as_entire_binding(), model_buffer.as_entire_binding()]);
+
+        // the cube's front faces (seen from the light) are culled, so the map holds the far sides' depths: a lit face is
+        // then well in front of what the map has there and never shadows itself, only faces turned away from the light
+        // are close to their own depth in it, and those get no light from it anyway
+        let key = PipelineKey {
+            cull: Some(wgpu::Face::Front),
+            depth_write: true,
+            depth_compare: Some(wgpu::CompareFunction::Less),
+            ..PipelineKey::new(ShaderId { vertex: ("shadow.wgsl", "vs_main"), fragment: None }, format, wgpu::BlendState::REPLACE)
+        };
+        let pipeline = cache.get_or_create(key, |key| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("shadow.wgsl"));
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shadow Pipeline Layout"),
+                bind_group_layouts: &[&bindings.layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Shadow Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::layout(), Instance::layout()], //only the positions and placements are read
+                },
+                fragment: None, //depth is all the map needs
+                primitive: key.primitive(),
+                depth_stencil: key.depth_stencil(),
+                multisample: key.multisample(),
+                multiview: None,
+            })
+        });
+
+        Self { depth, sampler, buffer, bind_group, pipeline }
+    }
+
+    pub fn write(&self, queue: &CountingQueue, uniform: &ShadowUniform) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(uniform));
+    }
+
+    // draw the first `count` instances (the opaque cubes) of every mesh into the map, before the passes that read it
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, meshes: &[Mesh], instance_buffer: &wgpu::Buffer, count: u32, stats: &mut RenderStats) {
+        let mut pass = CountingPass::new(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0), //as far from the light as the box goes, nothing in the way
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        }), stats);
+        pass.set_pipeline(&self.pipeline, wgpu::PrimitiveTopology::TriangleList);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        for mesh in meshes {
+            mesh.draw(&mut pass, DrawMode::Triangles, 0..count);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+// Shadow pass (S key), see shadow.rs: the opaque cubes' depth as the scene file's first light sees them, nothing else
+// the cube is placed exactly like shader.wgsl's vs_main does, only seen through the light's box instead of the camera
+struct Shadow {
+    light_view_proj: mat4x4<f32>, // world space to the shadow map's clip space
+    enabled: u32,
+    bias: f32,
+};
+@group(0) @binding(0)
+var<uniform> shadow: Shadow;
+
+struct Model {
+    model: mat4x4<f32>
+};
+@group(0) @binding(1)
+var<uniform> model: Model;
+
+// the same as shader.wgsl's
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+// only the position of the vertex and where the instance is placed matter, the buffers' other attributes are skipped
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(3) offset: vec3<f32>,
+    @location(7) rotation: vec4<f32>,
+    @location(8) scale: vec3<f32>,
+) -> @builtin(position) vec4<f32> {
+    let placed = rotate(rotation, position * scale);
+    let world_position = model.model * vec4<f32>(placed, 1.0) + vec4<f32>(offset, 0.0);
+    return shadow.light_view_proj * world_position;
+}
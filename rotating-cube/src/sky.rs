@@ -0,0 +1,282 @@
+// --day-length: the background becomes a sky going from dawn to noon to dusk to night and round again, and the scene's
+// ambient light takes on a little of the sky's color as it goes
+// palette() is the whole day as colors, sky.wgsl has the same function for the gradient (and shader.wgsl for the
+// reflections) so they have to be kept in step, the ambient tint is worked out here and handed to the shaders in the GlobalsUniform
+// the sky is drawn over the viewport first thing in the scene pass, in screen space, so it doesn't turn with the camera
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use crate::gpu::bindings::Bindings;
+use crate::effects::Effect;
+use crate::pipeline_cache::{PipelineCache, PipelineKey, ShaderId};
+use crate::render_stats::CountingPass;
+
+// matches Globals in shader.wgsl and sky.wgsl, bound to both stages as binding 4 of the frame group
+// every window has its own since the resolution is its own
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct GlobalsUniform {
+    pub time: f32,              // simulation time in seconds, stops with the spin while paused
+    pub day_phase: f32,         // 0..1 through the day: 0 dawn, 0.25 noon, 0.5 dusk, 0.75 night
+    pub resolution: [f32; 2],   // the viewport the scene is drawn into, in physical pixels
+    pub ambient_tint: [f32; 3], // multiplies the light's ambient term, white without --day-length
+    pub effect: u32,            // Effect::index() of the E key's color effect, see effects.rs
+}
+
+impl GlobalsUniform {
+    // `day_length` is --day-length in seconds, None leaves the phase at noon and the ambient light white
+    // the resolution is each window's to fill in
+    pub fn new(time: f32, day_length: Option<f32>, effect: Effect) -> Self {
+        let (day_phase, ambient_tint) = match day_length {
+            Some(length) => {
+                let phase = (time / length).rem_euclid(1.0);
+                (phase, palette(phase).ambient.to_array())
+            }
+            None => (NOON, [1.0; 3]),
+        };
+        Self { time, day_phase, resolution: [0.0; 2], ambient_tint, effect: effect.index() }
+    }
+}
+
+const NOON: f32 = 0.25;
+
+// one moment's colors, linear RGB like everything the shaders write
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkyColors {
+    pub zenith: Vec3,  // straight up, the top of the screen
+    pub horizon: Vec3, // the bottom
+    pub ambient: Vec3, // tint of the scene's ambient light, close to white so the cubes keep their own colors
+}
+
+// the four times of day a quarter of the day apart, starting at dawn (phase 0), sky.wgsl's keyframe() has the same
+const KEYFRAMES: [SkyColors; 4] = [
+    // dawn: pale blue above a warm orange horizon
+    SkyColors { zenith: Vec3::new(0.16, 0.22, 0.45), horizon: Vec3::new(0.95, 0.45, 0.2), ambient: Vec3::new(1.05, 0.9, 0.8) },
+    // noon: deep blue fading to a hazy light blue
+    SkyColors { zenith: Vec3::new(0.12, 0.3, 0.8), horizon: Vec3::new(0.55, 0.75, 0.95), ambient: Vec3::new(1.0, 1.0, 1.0) },
+    // dusk: purple above a red horizon
+    SkyColors { zenith: Vec3::new(0.2, 0.1, 0.35), horizon: Vec3::new(0.9, 0.3, 0.15), ambient: Vec3::new(1.1, 0.8, 0.7) },
+    // night: nearly black with a little blue, moonlight for the ambient
+    SkyColors { zenith: Vec3::new(0.005, 0.008, 0.03), horizon: Vec3::new(0.03, 0.05, 0.12), ambient: Vec3::new(0.55, 0.6, 0.85) },
+];
+
+// the sky's colors `phase` (0..1, wraps) of the way through the day
+// each quarter blends from one keyframe to the next, smoothstepped so the colors linger around each time of day and
+// don't change speed with a jolt when they pass it
+pub fn palette(phase: f32) -> SkyColors {
+    let scaled = phase.rem_euclid(1.0) * KEYFRAMES.len() as f32;
+    // rem_euclid() can round up to exactly 1.0 for a tiny negative phase, the % takes that back to dawn
+    let index = scaled.floor() as usize % KEYFRAMES.len();
+    let t = smoothstep(scaled.fract());
+    let (from, to) = (KEYFRAMES[index], KEYFRAMES[(index + 1) % KEYFRAMES.len()]);
+    SkyColors {
+        zenith: from.zenith.lerp(to.zenith, t),
+        horizon: from.horizon.lerp(to.horizon, t),
+        ambient: from.ambient.lerp(to.ambient, t),
+    }
+}
+
+// WGSL's smoothstep(0, 1, t)
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// how much darker the ground below the horizon is than the horizon, shader.wgsl's has the same
+const GROUND_BRIGHTNESS: f32 = 0.3;
+
+// WGSL's reflect(): `incident` bounced off a surface facing `normal` (which has to be normalized), the angle to the
+// normal the same on the way out as on the way in
+pub fn reflect(incident: Vec3, normal: Vec3) -> Vec3 {
+    incident - 2.0 * normal.dot(incident) * normal
+}
+
+// the sky a reflection shows in `direction` (normalized) `phase` of the way through the day, a copy of shader.wgsl's
+// environment() so the math can be checked without a GPU: the horizon's color straight out sideways, the zenith's
+// straight up, and a darker ground below the horizon that fades in over a short way so a mirror shows a soft line
+pub fn environment(direction: Vec3, phase: f32) -> Vec3 {
+    let colors = palette(phase);
+    if direction.y >= 0.0 {
+        return colors.horizon.lerp(colors.zenith, direction.y.powf(0.6));
+    }
+    colors.horizon * (1.0 + (GROUND_BRIGHTNESS - 1.0) * smoothstep((-direction.y / 0.2).min(1.0)))
+}
+
+// the background pipeline, only built with --day-length
+pub struct Sky {
+    pipeline: Arc<wgpu::RenderPipeline>,
+}
+
+impl Sky {
+    // `frame_bindings` is the layout the scene's bind group follows, the sky only reads its globals
+    // `depth_format` is the scene pass's depth buffer's, made again with the new one if that changes
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        frame_bindings: &Bindings,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        // drawn inside the scene pass, which has a depth buffer, but it must neither be hidden by nor hide anything
+        let key = PipelineKey {
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            depth_format,
+            ..PipelineKey::new(ShaderId::new("sky.wgsl", "vs_main", "fs_main"), format, wgpu::BlendState::REPLACE)
+        };
+        let pipeline = cache.get_or_create(key, |key| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("sky.wgsl"));
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sky Pipeline Layout"),
+                bind_group_layouts: &[&frame_bindings.layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Sky Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[], //a fullscreen triangle from the vertex index, like the depth view's
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &key.targets(),
+                }),
+                primitive: key.primitive(),
+                depth_stencil: key.depth_stencil(),
+                multisample: key.multisample(),
+                multiview: None,
+            })
+        });
+        Self { pipeline }
+    }
+
+    // fills the current viewport, before anything else is drawn into it
+    pub fn draw<'a>(&'a self, pass: &mut CountingPass<'_, 'a>, bind_group: &'a wgpu::BindGroup) {
+        pass.set_pipeline(&self.pipeline, wgpu::PrimitiveTopology::TriangleList);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: SkyColors, b: SkyColors) -> bool {
+        a.zenith.abs_diff_eq(b.zenith, 1e-5) && a.horizon.abs_diff_eq(b.horizon, 1e-5) && a.ambient.abs_diff_eq(b.ambient, 1e-5)
+    }
+
+    #[test]
+    fn each_quarter_starts_on_its_keyframe() {
+        for (i, keyframe) in KEYFRAMES.into_iter().enumerate() {
+            assert_eq!(palette(i as f32 * 0.25), keyframe, "keyframe {}", i);
+        }
+        assert_eq!(palette(NOON), KEYFRAMES[1]);
+    }
+
+    #[test]
+    fn the_day_wraps_around() {
+        for phase in [0.1, 0.3, 0.6, 0.9] {
+            assert!(close(palette(phase + 1.0), palette(phase)), "{}", phase);
+            assert!(close(palette(phase - 2.0), palette(phase)), "{}", phase);
+        }
+        // night blends back into dawn: 1 is dawn again, and so is a tiny negative phase that rem_euclid() rounds up to 1
+        assert!(close(palette(1.0), KEYFRAMES[0]));
+        assert!(close(palette(-1e-9), KEYFRAMES[0]));
+        assert!(palette(0.999).zenith.distance(KEYFRAMES[0].zenith) < 1e-3);
+    }
+
+    #[test]
+    fn halfway_between_keyframes_is_their_average() {
+        // smoothstep(0.5) is 0.5
+        let between = palette(0.125);
+        assert!(between.zenith.abs_diff_eq((KEYFRAMES[0].zenith + KEYFRAMES[1].zenith) / 2.0, 1e-6));
+        assert!(between.horizon.abs_diff_eq((KEYFRAMES[0].horizon + KEYFRAMES[1].horizon) / 2.0, 1e-6));
+    }
+
+    #[test]
+    fn colors_change_smoothly_through_the_day() {
+        // no jump anywhere, and the smoothstep slows down to a stop at each keyframe
+        let step = 1.0 / 4000.0;
+        let change = |phase: f32| palette(phase + step).zenith.distance(palette(phase).zenith);
+        let largest = (0..4000).map(|i| change(i as f32 * step)).fold(0.0, f32::max);
+        assert!(largest < 1e-3, "{}", largest);
+        assert!(change(0.25) < largest / 100.0);
+    }
+
+    #[test]
+    fn globals_follow_the_day_length() {
+        let fixed = GlobalsUniform::new(123.0, None, Effect::Off);
+        assert_eq!((fixed.day_phase, fixed.ambient_tint), (NOON, [1.0; 3]));
+        // 75 seconds into a 60 second day is a quarter of the way through the second one
+        let day = GlobalsUniform::new(75.0, Some(60.0), Effect::Off);
+        assert!((day.day_phase - 0.25).abs() < 1e-6);
+        assert_eq!(day.ambient_tint, palette(day.day_phase).ambient.to_array());
+    }
+
+    #[test]
+    fn the_shaders_have_the_same_keyframes() {
+        // written out the way the WGSL has them, {:?} keeps the .0 on whole numbers
+        let wgsl = |color: Vec3| format!("vec3<f32>({:?}, {:?}, {:?})", color.x, color.y, color.z);
+        for (name, source) in [("sky.wgsl", include_str!("sky.wgsl")), ("shader.wgsl", include_str!("shader.wgsl"))] {
+            for keyframe in KEYFRAMES {
+                let colors = format!("SkyColors({}, {})", wgsl(keyframe.zenith), wgsl(keyframe.horizon));
+                assert!(source.contains(&colors), "{} has no {}", name, colors);
+            }
+        }
+    }
+
+    #[test]
+    fn reflect_keeps_the_angle_to_the_normal() {
+        // straight down onto a floor comes straight back up, at 45 degrees it leaves at 45 degrees the other way
+        assert_eq!(reflect(Vec3::NEG_Y, Vec3::Y), Vec3::Y);
+        assert_eq!(reflect(Vec3::new(1.0, -1.0, 0.0), Vec3::Y), Vec3::new(1.0, 1.0, 0.0));
+        // along the surface it is untouched
+        assert_eq!(reflect(Vec3::X, Vec3::Y), Vec3::X);
+        for (incident, normal) in [(Vec3::new(0.3, -0.8, 0.5), Vec3::Z), (Vec3::new(-2.0, 1.0, 0.5), Vec3::new(1.0, 1.0, 0.0).normalize())] {
+            let reflected = reflect(incident, normal);
+            assert!((reflected.length() - incident.length()).abs() < 1e-5);
+            assert!((reflected.dot(normal) + incident.dot(normal)).abs() < 1e-5);
+            // and bouncing it again brings it back
+            assert!(reflect(reflected, normal).abs_diff_eq(incident, 1e-5));
+        }
+    }
+
+    #[test]
+    fn environment_is_the_sky_above_and_ground_below() {
+        let noon = palette(NOON);
+        assert!(environment(Vec3::Y, NOON).abs_diff_eq(noon.zenith, 1e-6));
+        assert!(environment(Vec3::X, NOON).abs_diff_eq(noon.horizon, 1e-6));
+        assert!(environment(Vec3::NEG_Y, NOON).abs_diff_eq(noon.horizon * GROUND_BRIGHTNESS, 1e-6));
+        // no seam at the horizon, just below it is still nearly the horizon's color
+        let below = Vec3::new(1.0, -0.001, 0.0).normalize();
+        assert!(environment(below, NOON).abs_diff_eq(noon.horizon, 1e-3));
+        // and it follows the time of day
+        assert!(environment(Vec3::Y, 0.75).abs_diff_eq(KEYFRAMES[3].zenith, 1e-6));
+    }
+
+    #[test]
+    fn a_mirror_shows_the_sky_the_view_bounces_towards() {
+        // the default camera at (3, 3, 3) looking at the top of the cube sees the sky in it, at the side it sees
+        // the horizon and ground behind the camera's side of the cube
+        let eye = Vec3::new(3.0, 3.0, 3.0);
+        let top = (Vec3::new(0.0, 1.0, 0.0), Vec3::Y);
+        let view = (top.0 - eye).normalize();
+        let bounced = reflect(view, top.1);
+        assert!(bounced.y > 0.0);
+        // partway up, so partway from the horizon's color to the zenith's
+        let (sky, noon) = (environment(bounced, NOON), palette(NOON));
+        assert!(sky.z < noon.horizon.z && sky.z > noon.zenith.z, "{}", sky);
+        let side = (Vec3::new(1.0, 0.0, 0.0), Vec3::X);
+        let bounced = reflect((side.0 - eye).normalize(), side.1);
+        assert!(bounced.x > 0.0 && bounced.y < 0.0, "{}", bounced);
+    }
+
+    #[test]
+    fn the_shader_has_the_same_ground() {
+        assert!(include_str!("shader.wgsl").contains(&format!("const GROUND_BRIGHTNESS: f32 = {:?};", GROUND_BRIGHTNESS)));
+    }
+}
@@ -0,0 +1,87 @@
+// --day-length background: a gradient from the horizon (bottom of the viewport) up to the zenith (top) in the colors
+// of the time of day, and the sun crossing it from left to right between dawn and dusk
+// keyframe() and palette() are sky.rs' KEYFRAMES and palette(), and shader.wgsl has them again for the reflections,
+// a change to one needs the same change to the others
+
+// matches GlobalsUniform in sky.rs
+struct Globals {
+    time: f32,
+    day_phase: f32,            // 0 dawn, 0.25 noon, 0.5 dusk, 0.75 night
+    resolution: vec2<f32>,     // viewport size in pixels, keeps the sun round whatever its shape
+    ambient_tint: vec3<f32>,   // for the lit shaders, the sky works its colors out itself
+    effect: u32,
+};
+@group(0) @binding(4)
+var<uniform> globals: Globals;
+
+struct SkyColors {
+    zenith: vec3<f32>,
+    horizon: vec3<f32>,
+};
+
+const PI: f32 = 3.14159265;
+const SUN_COLOR: vec3<f32> = vec3<f32>(1.0, 0.85, 0.6);
+const SUN_RADIUS: f32 = 0.04; // as a fraction of the viewport's height
+
+// dawn, noon, dusk and night, the ambient tints are left out since sky.rs uploads the blended one
+fn keyframe(index: u32) -> SkyColors {
+    switch index {
+        case 0u: {
+            return SkyColors(vec3<f32>(0.16, 0.22, 0.45), vec3<f32>(0.95, 0.45, 0.2));
+        }
+        case 1u: {
+            return SkyColors(vec3<f32>(0.12, 0.3, 0.8), vec3<f32>(0.55, 0.75, 0.95));
+        }
+        case 2u: {
+            return SkyColors(vec3<f32>(0.2, 0.1, 0.35), vec3<f32>(0.9, 0.3, 0.15));
+        }
+        default: {
+            return SkyColors(vec3<f32>(0.005, 0.008, 0.03), vec3<f32>(0.03, 0.05, 0.12));
+        }
+    }
+}
+
+fn palette(phase: f32) -> SkyColors {
+    let scaled = fract(phase) * 4.0;
+    let index = u32(floor(scaled)) % 4u;
+    let t = smoothstep(0.0, 1.0, fract(scaled));
+    let start = keyframe(index);
+    let end = keyframe((index + 1u) % 4u);
+    return SkyColors(mix(start.zenith, end.zenith, t), mix(start.horizon, end.horizon, t));
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>, // 0..1 across the viewport, y = 1 at the top
+};
+
+// one triangle covering the viewport, corners at (-1,-1), (3,-1) and (-1,3)
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    var output: VertexOutput;
+    // the depth doesn't matter, the pipeline's depth test always passes and writes nothing
+    output.position = vec4<f32>(x, y, 0.0, 1.0);
+    output.uv = vec2<f32>(x, y) * 0.5 + 0.5;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let colors = palette(globals.day_phase);
+    // most of the change happens near the horizon, like a real sky
+    var color = mix(colors.horizon, colors.zenith, pow(input.uv.y, 0.6));
+
+    // the sun rises at the left at dawn, is highest at noon and sets at the right at dusk, it is below the horizon
+    // the rest of the time and fades out as it gets there
+    let height = sin(globals.day_phase * 2.0 * PI);
+    let sun = vec2<f32>(mix(0.1, 0.9, clamp(globals.day_phase * 2.0, 0.0, 1.0)), height * 0.7);
+    let aspect = globals.resolution.x / max(globals.resolution.y, 1.0);
+    let to_sun = length((input.uv - sun) * vec2<f32>(aspect, 1.0));
+    let disc = 1.0 - smoothstep(SUN_RADIUS * 0.8, SUN_RADIUS, to_sun);
+    let glow = exp(-to_sun * 8.0) * 0.3;
+    color += SUN_COLOR * (disc + glow) * smoothstep(-0.1, 0.05, height);
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,264 @@
+// --stress [MS]: how many cubes this machine can draw in time
+// starts with START_CUBES instanced cubes and doubles them every LEVEL_SECONDS until a level's average frame time goes
+// over the target (16 ms, a 60 Hz frame, unless given), then prints every level's frame times, the most cubes that
+// stayed within the target and whether the CPU or the GPU was the one holding it back
+// this is only the bookkeeping, fed one FrameTiming per frame by main.rs, which changes the cube count when told to
+use crate::bench::{self, WARMUP_FRAMES};
+
+pub const START_CUBES: u32 = 1000;
+pub const DEFAULT_TARGET_MS: f64 = 16.0;
+// how long each cube count is measured for, after its warm-up frames
+const LEVEL_SECONDS: f64 = 5.0;
+// 1000 doubled 12 times, 4M cubes are ~230 MB of instances, stop there rather than run out of buffer size
+const MAX_CUBES: u32 = START_CUBES << 12;
+
+// what one frame cost, all in milliseconds
+#[derive(Copy, Clone, Debug)]
+pub struct FrameTiming {
+    pub frame_ms: f64,       // from the end of the previous frame to the end of this one, what the target is about
+    pub cpu_ms: f64,         // the CPU's own work in it: simulation steps, uploads and encoding, not waiting on the GPU
+    pub gpu_ms: Option<f64>, // the GPU's time, from timestamp queries when the adapter has them
+}
+
+// what main.rs has to do after a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Continue,  // keep drawing as before
+    Grow(u32), // switch to this many cubes
+    Done,      // print the report and exit
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bottleneck {
+    Cpu,
+    Gpu,
+}
+
+// one cube count's measurements
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub cubes: u32,
+    frame_ms: Vec<f64>,
+    cpu_ms: Vec<f64>,
+    gpu_ms: Vec<f64>, // empty without timestamp queries
+}
+
+impl Level {
+    fn new(cubes: u32) -> Self {
+        Self { cubes, frame_ms: Vec::new(), cpu_ms: Vec::new(), gpu_ms: Vec::new() }
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        average(&self.frame_ms)
+    }
+
+    // with GPU timings the larger of the two is the one the other waits for, without them the CPU's share of the frame
+    // has to do: if its own work isn't even half the frame, the rest was spent waiting for the GPU
+    pub fn bottleneck(&self) -> Bottleneck {
+        let cpu = average(&self.cpu_ms);
+        let gpu_busier = if self.gpu_ms.is_empty() { cpu < self.average_ms() * 0.5 } else { average(&self.gpu_ms) > cpu };
+        if gpu_busier {
+            Bottleneck::Gpu
+        } else {
+            Bottleneck::Cpu
+        }
+    }
+}
+
+pub struct Stress {
+    target_ms: f64,
+    level: Level,       // the cube count being measured
+    warmup_left: u32,   // frames still to skip at this count, the first ones after a change pay for the new buffers
+    levels: Vec<Level>, // the counts measured so far, the last one is the one that ended the test once it is done
+    done: bool,
+}
+
+impl Stress {
+    pub fn new(target_ms: f64) -> Self {
+        Self { target_ms, level: Level::new(START_CUBES), warmup_left: WARMUP_FRAMES, levels: Vec::new(), done: false }
+    }
+
+    // the cube count to draw right now
+    pub fn cubes(&self) -> u32 {
+        self.level.cubes
+    }
+
+    // the level that finished last, for a progress line after each Grow
+    pub fn last_level(&self) -> Option<&Level> {
+        self.levels.last()
+    }
+
+    // count one frame, time only moves on as far as the frames say it did so a test can feed in any timings it likes
+    pub fn record(&mut self, timing: FrameTiming) -> Step {
+        if self.done {
+            return Step::Done;
+        }
+        if self.warmup_left > 0 {
+            self.warmup_left -= 1;
+            return Step::Continue;
+        }
+        self.level.frame_ms.push(timing.frame_ms);
+        self.level.cpu_ms.push(timing.cpu_ms);
+        if let Some(gpu_ms) = timing.gpu_ms {
+            self.level.gpu_ms.push(gpu_ms);
+        }
+        if self.level.frame_ms.iter().sum::<f64>() < LEVEL_SECONDS * 1000.0 {
+            return Step::Continue;
+        }
+
+        // this count is done, the next one is twice as many
+        let cubes = self.level.cubes * 2;
+        let finished = std::mem::replace(&mut self.level, Level::new(cubes));
+        let over = finished.average_ms() > self.target_ms;
+        self.levels.push(finished);
+        if over || cubes > MAX_CUBES {
+            self.done = true;
+            return Step::Done;
+        }
+        self.warmup_left = WARMUP_FRAMES;
+        Step::Grow(cubes)
+    }
+
+    // the largest count whose average stayed within the target, None if even the first didn't
+    // the levels only grow, and the first one over the target ends the test, so it is the one before it
+    pub fn max_sustained(&self) -> Option<u32> {
+        self.levels.iter().filter(|level| level.average_ms() <= self.target_ms).map(|level| level.cubes).next_back()
+    }
+
+    pub fn print_report(&self) {
+        println!("Stress test: doubling from {} cubes until frames average over {:.1} ms", START_CUBES, self.target_ms);
+        println!("  {:>8}  {:>6}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}", "cubes", "frames", "min ms", "avg ms", "p95 ms", "p99 ms", "max ms", "cpu avg", "gpu avg");
+        for level in &self.levels {
+            let Some(frames) = bench::summarize(&level.frame_ms) else { continue };
+            let gpu = if level.gpu_ms.is_empty() { format!("{:>8}", "-") } else { format!("{:8.3}", average(&level.gpu_ms)) };
+            println!(
+                "  {:>8}  {:>6}  {:8.3}  {:8.3}  {:8.3}  {:8.3}  {:8.3}  {:8.3}  {}",
+                level.cubes,
+                level.frame_ms.len(),
+                frames.min,
+                frames.avg,
+                frames.p95,
+                frames.p99,
+                frames.max,
+                average(&level.cpu_ms),
+                gpu
+            );
+        }
+
+        match self.max_sustained() {
+            Some(cubes) => println!("Maximum sustained: {} cubes", cubes),
+            None => println!("Maximum sustained: none, {} cubes were already over the target", START_CUBES),
+        }
+        let Some(last) = self.levels.last() else { return };
+        if last.average_ms() <= self.target_ms {
+            println!("Stopped at the {} cube limit without going over the target", MAX_CUBES);
+        }
+        // judged where it got too slow (or as far as it went), at small counts neither side is working hard
+        let cpu = average(&last.cpu_ms);
+        match (last.bottleneck(), last.gpu_ms.is_empty()) {
+            (Bottleneck::Gpu, false) => println!("Bottleneck: GPU, {:.3} ms of GPU time per frame against {:.3} ms of CPU work", average(&last.gpu_ms), cpu),
+            (Bottleneck::Cpu, false) => println!("Bottleneck: CPU, {:.3} ms of CPU work per frame against {:.3} ms of GPU time", cpu, average(&last.gpu_ms)),
+            (bottleneck, true) => println!(
+                "Bottleneck: probably {}, the CPU's own work is {:.3} of {:.3} ms per frame (no GPU timestamps on this adapter)",
+                if bottleneck == Bottleneck::Gpu { "GPU" } else { "CPU" },
+                cpu,
+                last.average_ms()
+            ),
+        }
+    }
+}
+
+fn average(samples: &[f64]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(frame_ms: f64) -> FrameTiming {
+        FrameTiming { frame_ms, cpu_ms: frame_ms / 4.0, gpu_ms: None }
+    }
+
+    // drives a whole test with a machine whose frames take `frame_ms(cubes)`, returning every Grow it was told to do
+    fn run(stress: &mut Stress, frame_ms: impl Fn(u32) -> f64) -> Vec<u32> {
+        let mut grown = Vec::new();
+        // far more frames than any of the tests below need, a test that never ends fails instead of hanging
+        for _ in 0..1_000_000 {
+            match stress.record(timing(frame_ms(stress.cubes()))) {
+                Step::Continue => {}
+                Step::Grow(cubes) => {
+                    assert_eq!(stress.cubes(), cubes);
+                    grown.push(cubes);
+                }
+                Step::Done => return grown,
+            }
+        }
+        panic!("never finished, at {} cubes", stress.cubes());
+    }
+
+    #[test]
+    fn doubles_until_a_level_goes_over_the_target() {
+        // 4 ms per thousand cubes: 4, 8 and 16 ms are within 16, 32 ms at 8000 cubes isn't
+        let mut stress = Stress::new(DEFAULT_TARGET_MS);
+        assert_eq!(stress.cubes(), START_CUBES);
+        let grown = run(&mut stress, |cubes| cubes as f64 * 0.004);
+        assert_eq!(grown, [2000, 4000, 8000]);
+        assert_eq!(stress.levels.iter().map(|level| level.cubes).collect::<Vec<_>>(), [1000, 2000, 4000, 8000]);
+        assert_eq!(stress.max_sustained(), Some(4000));
+        // each level ran for LEVEL_SECONDS of its own frames
+        assert_eq!(stress.levels[0].frame_ms.len(), 1250);
+        assert_eq!(stress.levels[3].frame_ms.len(), 157); // 5000 / 32 rounded up
+        // and once done it stays done
+        assert_eq!(stress.record(timing(1.0)), Step::Done);
+        assert_eq!(stress.levels.len(), 4);
+    }
+
+    #[test]
+    fn warm_up_frames_are_left_out() {
+        let mut stress = Stress::new(DEFAULT_TARGET_MS);
+        // the frames right after a change are slow, but don't count
+        for _ in 0..WARMUP_FRAMES {
+            assert_eq!(stress.record(timing(1000.0)), Step::Continue);
+        }
+        assert!(stress.level.frame_ms.is_empty());
+        assert_eq!(stress.record(timing(5.0)), Step::Continue);
+        assert_eq!(stress.level.frame_ms, [5.0]);
+    }
+
+    #[test]
+    fn over_the_target_from_the_start() {
+        let mut stress = Stress::new(10.0);
+        assert!(run(&mut stress, |_| 20.0).is_empty());
+        assert_eq!(stress.max_sustained(), None);
+        assert_eq!(stress.last_level().map(|level| level.cubes), Some(START_CUBES));
+    }
+
+    #[test]
+    fn stops_at_the_cube_limit() {
+        // a machine that never slows down is still only taken up to MAX_CUBES
+        let mut stress = Stress::new(DEFAULT_TARGET_MS);
+        let grown = run(&mut stress, |_| 8.0);
+        assert_eq!(grown.len(), 12);
+        assert_eq!(grown.last(), Some(&MAX_CUBES));
+        assert_eq!(stress.max_sustained(), Some(MAX_CUBES));
+    }
+
+    #[test]
+    fn bottleneck_from_gpu_timings_or_the_cpu_share() {
+        let level = |cpu_ms: f64, gpu_ms: Option<f64>| Level {
+            cubes: START_CUBES,
+            frame_ms: vec![20.0],
+            cpu_ms: vec![cpu_ms],
+            gpu_ms: gpu_ms.into_iter().collect(),
+        };
+        assert_eq!(level(5.0, Some(15.0)).bottleneck(), Bottleneck::Gpu);
+        assert_eq!(level(15.0, Some(5.0)).bottleneck(), Bottleneck::Cpu);
+        // without GPU timings: under half of the frame spent working means the rest went waiting on the GPU
+        assert_eq!(level(5.0, None).bottleneck(), Bottleneck::Gpu);
+        assert_eq!(level(15.0, None).bottleneck(), Bottleneck::Cpu);
+    }
+}
@@ -0,0 +1,108 @@
+// fixed-timestep accumulator: the simulation always advances in equal steps of `dt` no matter how fast frames are drawn
+// real frame time is poured into an accumulator and drained one `dt` at a time, whatever is left over (less than one step)
+// becomes the interpolation factor between the previous and current simulation states when rendering
+
+// simulation rate, 120 updates per second
+pub const FIXED_DT: f32 = 1.0 / 120.0;
+
+// longest frame time we are willing to catch up on, a longer stall (e.g. window drag blocking the loop on Windows)
+// would otherwise queue hundreds of updates which make the next frame slow, which queues more updates... (spiral of death)
+pub const MAX_FRAME_TIME: f32 = 0.25;
+
+pub struct FixedTimestep {
+    dt: f32,
+    accumulator: f32,
+    max_frame_time: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(dt: f32) -> Self {
+        Self {
+            dt,
+            accumulator: 0.0,
+            max_frame_time: MAX_FRAME_TIME,
+        }
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    // add the real time that passed since the last frame and return how many fixed updates should run now (may be 0)
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.clamp(0.0, self.max_frame_time);
+
+        let mut steps = 0;
+        while self.accumulator >= self.dt {
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+        steps
+    }
+
+    // how far (0..1) we are between the last simulated state and the next one, used to blend the two when rendering
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // frame times between 1 and 40 ms in no particular order, from a small LCG so the run is the same every time
+    fn irregular_frames(count: usize) -> Vec<f32> {
+        let mut seed: u32 = 12345;
+        (0..count)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                0.001 + (seed >> 8) as f32 / (1 << 24) as f32 * 0.039
+            })
+            .collect()
+    }
+
+    #[test]
+    fn irregular_frames_add_up_to_the_same_steps() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        let frames = irregular_frames(1000);
+        let mut steps = 0;
+        for &frame_time in &frames {
+            steps += timestep.advance(frame_time);
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha {} after a {} s frame", alpha, frame_time);
+        }
+        // whatever frames the time came in, the steps cover it all but the leftover alpha
+        let total: f64 = frames.iter().map(|&t| t as f64).sum();
+        let simulated = (steps as f64 + timestep.alpha() as f64) * FIXED_DT as f64;
+        assert!((simulated - total).abs() < 1e-3, "simulated {} s of {} s", simulated, total);
+        assert_eq!(steps, (total / FIXED_DT as f64) as u32);
+    }
+
+    #[test]
+    fn short_frames_run_no_steps_until_one_is_due() {
+        let mut timestep = FixedTimestep::new(0.01);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn a_long_stall_is_clamped() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        // two seconds of a dragged window only catch up MAX_FRAME_TIME worth of steps
+        let steps = timestep.advance(2.0);
+        assert_eq!(steps, (MAX_FRAME_TIME / FIXED_DT) as u32);
+        assert!((0.0..1.0).contains(&timestep.alpha()));
+        // and the next normal frame is back to one or two
+        assert!(timestep.advance(1.0 / 60.0) <= 2);
+    }
+
+    #[test]
+    fn a_clock_going_backwards_adds_nothing() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        assert_eq!(timestep.advance(-1.0), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,67 @@
+// draw order for see-through objects
+// an opaque object can be drawn in any order, the depth test keeps the closest fragment; a blended one mixes with
+// whatever is already in the color target, so everything behind it has to be there first. the opaque cubes are drawn
+// before any glass, and the glass cubes among themselves furthest first (painter's algorithm), sorted again for every
+// window each frame since each has its own camera and they move
+// glass doesn't write depth, otherwise a near pane would hide the far panes meant to be seen through it
+use glam::{Mat4, Vec3};
+
+// how far in front of the camera `position` is, along the direction it looks
+// the view matrix puts the camera at the origin looking down -Z, so that distance is the view-space z negated
+// measured along the view axis like the depth buffer does, rather than as a straight-line distance
+pub fn view_depth(view: Mat4, position: Vec3) -> f32 {
+    -view.transform_point3(position).z
+}
+
+// indices into `positions` ordered back to front for a camera with this view matrix
+// sorting objects by their centres is exact for convex objects that don't overlap each other, which the glass cubes
+// are, intersecting or concave ones would need sorting per triangle (or order-independent transparency)
+pub fn back_to_front(view: Mat4, positions: &[Vec3]) -> Vec<usize> {
+    let depths: Vec<f32> = positions.iter().map(|&position| view_depth(view, position)).collect();
+    let mut order: Vec<usize> = (0..positions.len()).collect();
+    // furthest first, total_cmp so a NaN from a degenerate camera can't panic the sort
+    order.sort_by(|&a, &b| depths[b].total_cmp(&depths[a]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera;
+
+    // a row of glass cubes along X
+    const ROW: [Vec3; 4] = [Vec3::new(-3.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0)];
+
+    fn order_from(eye: Vec3) -> Vec<usize> {
+        back_to_front(camera::view(eye, Vec3::ZERO, Vec3::Y), &ROW)
+    }
+
+    #[test]
+    fn view_depth_is_the_distance_along_the_view_direction() {
+        let view = camera::view(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        assert!((view_depth(view, Vec3::ZERO) - 5.0).abs() < 1e-5);
+        // off to the side, still the same distance along -Z rather than the straight line to it
+        assert!((view_depth(view, Vec3::new(4.0, 3.0, 0.0)) - 5.0).abs() < 1e-5);
+        assert!(view_depth(view, Vec3::new(0.0, 0.0, 6.0)) < 0.0);
+    }
+
+    #[test]
+    fn furthest_first_from_every_side() {
+        // from +X the cube at -3 is furthest, from -X the one at +3
+        assert_eq!(order_from(Vec3::new(10.0, 2.0, 0.5)), [0, 1, 2, 3]);
+        assert_eq!(order_from(Vec3::new(-10.0, 2.0, 0.5)), [3, 2, 1, 0]);
+        // steeply down from above the +X end
+        assert_eq!(order_from(Vec3::new(6.0, 8.0, 1.0)), [0, 1, 2, 3]);
+        // side on, every cube at almost the same depth: the slight angle decides
+        assert_eq!(order_from(Vec3::new(0.5, 0.0, 10.0)), [0, 1, 2, 3]);
+        assert_eq!(order_from(Vec3::new(-0.5, 0.0, 10.0)), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn a_degenerate_camera_still_sorts() {
+        // eye on the target gives a NaN view matrix, the order is meaningless but every index is there once
+        let mut order = order_from(Vec3::ZERO);
+        order.sort();
+        assert_eq!(order, [0, 1, 2, 3]);
+    }
+}
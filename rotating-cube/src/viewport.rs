@@ -0,0 +1,100 @@
+// --render-size: draw the scene into a fixed-size rectangle centered in the window instead of
+// stretching it over the whole surface, the rest stays the clear color (letterbox/pillarbox bars)
+// the camera's aspect ratio comes from this rectangle, so the picture keeps its shape whatever the window does
+// --split-screen then cuts it in two, see halves()
+use glam::Vec2;
+
+use crate::render_stats::CountingPass;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn aspect(&self) -> f32 {
+        self.width / self.height
+    }
+
+    // whether `point` (physical pixels from the target's top-left corner) is inside
+    pub fn contains(&self, point: Vec2) -> bool {
+        (self.x..self.x + self.width).contains(&point.x) && (self.y..self.y + self.height).contains(&point.y)
+    }
+
+    // the rectangle a render pass should draw into, depth range is always the full 0..1
+    pub fn apply(&self, pass: &mut CountingPass<'_, '_>) {
+        pass.set_viewport(self.x, self.y, self.width, self.height, 0.0, 1.0);
+    }
+}
+
+// where to draw inside a `width` x `height` target, the whole target when no render size was asked for
+// a render size bigger than the target is scaled down to fit, keeping its aspect ratio, rather than being cropped
+pub fn fit(width: u32, height: u32, render_size: Option<(u32, u32)>) -> Viewport {
+    let (target_w, target_h) = (width as f32, height as f32);
+    let (render_w, render_h) = match render_size {
+        Some((w, h)) => (w as f32, h as f32),
+        None => (target_w, target_h),
+    };
+    let scale = (target_w / render_w).min(target_h / render_h).min(1.0);
+    // whole pixels so the edges of the picture stay sharp
+    let (w, h) = ((render_w * scale).round().max(1.0), (render_h * scale).round().max(1.0));
+    Viewport {
+        x: ((target_w - w) / 2.0).floor(),
+        y: ((target_h - h) / 2.0).floor(),
+        width: w,
+        height: h,
+    }
+}
+
+// --split-screen: the left and right halves of `viewport`, whole pixels like fit()'s so the seam between them is sharp
+// an odd width gives the right half the extra pixel, a 1 pixel wide viewport leaves it with the only one
+pub fn halves(viewport: Viewport) -> [Viewport; 2] {
+    let left = (viewport.width / 2.0).floor();
+    [
+        Viewport { width: left.max(1.0), ..viewport },
+        Viewport { x: viewport.x + left, width: (viewport.width - left).max(1.0), ..viewport },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport { x, y, width, height }
+    }
+
+    #[test]
+    fn fit_fills_the_target_without_a_render_size() {
+        assert_eq!(fit(800, 600, None), rect(0.0, 0.0, 800.0, 600.0));
+        assert_eq!(fit(1, 1, None), rect(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn fit_centers_a_smaller_render_size() {
+        // pillarboxed and letterboxed both
+        assert_eq!(fit(1000, 600, Some((640, 480))), rect(180.0, 60.0, 640.0, 480.0));
+        // an odd amount left over puts the extra pixel on the right and at the bottom
+        assert_eq!(fit(101, 101, Some((50, 50))), rect(25.0, 25.0, 50.0, 50.0));
+    }
+
+    #[test]
+    fn fit_scales_a_larger_render_size_down_keeping_its_shape() {
+        let fitted = fit(800, 600, Some((1600, 900)));
+        assert_eq!(fitted, rect(0.0, 75.0, 800.0, 450.0));
+        assert_eq!(fitted.aspect(), 16.0 / 9.0);
+        // never down to nothing
+        assert_eq!(fit(1, 1, Some((1000, 10))), rect(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn halves_split_on_whole_pixels() {
+        let [left, right] = halves(rect(10.0, 20.0, 101.0, 50.0));
+        assert_eq!(left, rect(10.0, 20.0, 50.0, 50.0));
+        assert_eq!(right, rect(60.0, 20.0, 51.0, 50.0));
+        assert_eq!(halves(rect(0.0, 0.0, 1.0, 1.0))[1].width, 1.0);
+    }
+}
@@ -0,0 +1,91 @@
+//Request bodies for POST: inline text (--body TEXT), standard input (--body -) or a file (--body-file PATH)
+//The Content-Type comes from --content-type when given, otherwise from the file extension, or for inline/stdin
+//text from whether it parses as JSON
+//Files above STREAM_THRESHOLD are streamed from disk in chunks instead of being read into memory first
+
+use std::path::{Path, PathBuf};
+
+use reqwest::header::HeaderValue;
+use reqwest::Body;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::ReaderStream; //turns an AsyncRead (the open file) into a Stream of byte chunks reqwest can send
+
+//Files up to this size are simply read in full, bigger ones are streamed
+const STREAM_THRESHOLD: u64 = 1024 * 1024;
+
+const JSON: &str = "application/json";
+const TEXT: &str = "text/plain; charset=utf-8";
+const BINARY: &str = "application/octet-stream";
+
+//Where the request body comes from
+pub enum BodySource {
+    Inline(String),
+    Stdin,
+    File(PathBuf),
+}
+
+//A body ready to send, `length` goes out as Content-Length so even a streamed file isn't sent chunked
+pub struct RequestBody {
+    pub body: Body,
+    pub content_type: HeaderValue,
+    pub length: u64,
+}
+
+impl BodySource {
+    //--body - means standard input, anything else is the body text itself
+    pub fn from_arg(value: String) -> Self {
+        if value == "-" {
+            BodySource::Stdin
+        } else {
+            BodySource::Inline(value)
+        }
+    }
+
+    //Read (or open, for a large file) the body, `content_type` overrides the detected type
+    pub async fn load(&self, content_type: Option<&HeaderValue>) -> std::io::Result<RequestBody> {
+        let (body, detected, length) = match self {
+            BodySource::Inline(text) => (Body::from(text.clone()), sniff(text.as_bytes()), text.len() as u64),
+            BodySource::Stdin => {
+                let mut bytes = Vec::new();
+                tokio::io::stdin().read_to_end(&mut bytes).await?;
+                let detected = sniff(&bytes);
+                let length = bytes.len() as u64;
+                (Body::from(bytes), detected, length)
+            }
+            BodySource::File(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                let length = file.metadata().await?.len();
+                let body = if length > STREAM_THRESHOLD {
+                    Body::wrap_stream(ReaderStream::new(file))
+                } else {
+                    let mut bytes = Vec::with_capacity(length as usize);
+                    file.read_to_end(&mut bytes).await?;
+                    Body::from(bytes)
+                };
+                (body, from_extension(path), length)
+            }
+        };
+
+        let content_type = content_type.cloned().unwrap_or_else(|| HeaderValue::from_static(detected));
+        Ok(RequestBody { body, content_type, length })
+    }
+}
+
+//.json is JSON, common text extensions are plain text, anything else is sent as opaque bytes
+fn from_extension(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "json" => JSON,
+        "txt" | "text" | "md" | "csv" | "log" => TEXT,
+        _ => BINARY,
+    }
+}
+
+//typed or piped text has no extension to go by, so look at it: valid JSON is sent as JSON, anything else as text
+fn sniff(bytes: &[u8]) -> &'static str {
+    if serde_json::from_slice::<serde_json::Value>(bytes).is_ok() {
+        JSON
+    } else {
+        TEXT
+    }
+}
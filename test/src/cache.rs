@@ -0,0 +1,142 @@
+//--cache-dir: HTTP conditional GET caching
+//A response that carries an ETag or Last-Modified header is saved to the cache directory, keyed by its URL
+//The next request for that URL sends the validators back as If-None-Match / If-Modified-Since, if the server
+//answers 304 Not Modified nothing but headers crossed the network and the saved body is used instead
+//A fresh 200 replaces the saved copy (or drops it when the new response has no validators)
+//
+//Each entry is two files named after a hash of the URL: <hash>.json with the URL and the response headers, and
+//<hash>.body with the raw body bytes
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
+use serde_json::{json, Value};
+
+use crate::stats::{Reporter, TimedResponse};
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+//A saved response, `headers` are the ones it was stored with (updated by later 304s)
+struct Entry {
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Cache {
+    //creates the directory if it doesn't exist yet
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    fn paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let key = format!("{:016x}", fnv1a(url.as_str().as_bytes()));
+        (self.dir.join(format!("{}.json", key)), self.dir.join(format!("{}.body", key)))
+    }
+
+    //None when there is no entry, it can't be read, or it belongs to a different URL with the same hash
+    fn lookup(&self, url: &Url) -> Option<Entry> {
+        let (meta_path, body_path) = self.paths(url);
+        let meta: Value = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+        if meta["url"].as_str() != Some(url.as_str()) {
+            return None;
+        }
+
+        let mut headers = HeaderMap::new();
+        for pair in meta["headers"].as_array()? {
+            let name = HeaderName::from_bytes(pair[0].as_str()?.as_bytes()).ok()?;
+            let value = HeaderValue::from_str(pair[1].as_str()?).ok()?;
+            headers.append(name, value);
+        }
+        let body = fs::read(body_path).ok()?;
+        Some(Entry { headers, body })
+    }
+
+    fn store(&self, url: &Url, headers: &HeaderMap, body: &[u8]) -> io::Result<()> {
+        let (meta_path, body_path) = self.paths(url);
+        //headers that aren't valid UTF-8 can't go into JSON and aren't needed for revalidation, leave them out
+        let pairs: Vec<Value> = headers
+            .iter()
+            .filter_map(|(name, value)| Some(json!([name.as_str(), value.to_str().ok()?])))
+            .collect();
+        //body first, so a crash in between leaves an entry without metadata, which lookup() ignores
+        fs::write(body_path, body)?;
+        fs::write(meta_path, serde_json::to_vec(&json!({ "url": url.as_str(), "headers": pairs }))?)
+    }
+
+    fn remove(&self, url: &Url) {
+        let (meta_path, body_path) = self.paths(url);
+        let _ = fs::remove_file(meta_path); //missing files are fine, there was simply nothing cached
+        let _ = fs::remove_file(body_path);
+    }
+}
+
+//GET `url` through the cache (if any): revalidate a saved copy, serve it on 304, update it on fresh content
+//On a 304 the returned response has the saved body and the saved headers refreshed with the ones the 304 carried
+pub async fn get(
+    client: &reqwest::Client,
+    reporter: &Reporter,
+    cache: Option<&Cache>,
+    url: Url,
+) -> Result<TimedResponse, reqwest::Error> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return reporter.get(client, url, HeaderMap::new()).await,
+    };
+
+    let entry = cache.lookup(&url);
+    let mut conditional = HeaderMap::new();
+    if let Some(entry) = &entry {
+        //the server picks which validator it trusts, If-None-Match wins when both are sent
+        if let Some(etag) = entry.headers.get(ETAG) {
+            conditional.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(modified) = entry.headers.get(LAST_MODIFIED) {
+            conditional.insert(IF_MODIFIED_SINCE, modified.clone());
+        }
+    }
+
+    let mut response = reporter.get(client, url.clone(), conditional).await?;
+
+    match (response.status, entry) {
+        (StatusCode::NOT_MODIFIED, Some(mut entry)) => {
+            reporter.note(format!("Not modified, using cached copy ({} bytes)", entry.body.len()));
+            //a 304 may carry newer headers (e.g. a new Date or Cache-Control), they replace the saved ones
+            for name in response.headers.keys() {
+                entry.headers.remove(name);
+            }
+            for (name, value) in &response.headers {
+                entry.headers.append(name.clone(), value.clone());
+            }
+            if let Err(err) = cache.store(&url, &entry.headers, &entry.body) {
+                reporter.note(format!("Could not update cache entry for {}: {}", url, err));
+            }
+            response.status = StatusCode::OK;
+            response.headers = entry.headers;
+            response.body = entry.body;
+        }
+        (status, _) if status.is_success() => {
+            //fresh content, keep it only if the server gave us something to revalidate it with next time
+            if response.headers.contains_key(ETAG) || response.headers.contains_key(LAST_MODIFIED) {
+                if let Err(err) = cache.store(&url, &response.headers, &response.body) {
+                    reporter.note(format!("Could not write cache entry for {}: {}", url, err));
+                }
+            } else {
+                cache.remove(&url);
+            }
+        }
+        _ => {} //errors leave the cache untouched, the saved copy may still be good next time
+    }
+
+    Ok(response)
+}
+
+//64-bit FNV-1a, a tiny hash whose output never changes between Rust versions, unlike std's DefaultHasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
@@ -0,0 +1,121 @@
+//--once: a smoke test for CI and liveness probes. The request is sent once and judged instead of printed: the status has
+//to be 2xx (or exactly --expect-status), and every --assert path=value has to hold for the JSON body
+//The result is a single line starting with OK or FAIL, and the exit code says the same: 0 passed, 1 failed
+//  OK 200 OK  GET https://example.com/health  84.2 ms
+//  FAIL 200 OK  GET https://example.com/health  84.2 ms  status: expected up, got "degraded"
+
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+
+use crate::cli::Args;
+use crate::stats::{Reporter, TimedResponse};
+
+//--assert path=value, e.g. --assert data.items.0.name=widget
+pub struct Assertion {
+    path: Vec<String>, //object keys and array indices, in order
+    expected: String,
+}
+
+impl Assertion {
+    //split on the first '=' so the expected value may contain more of them
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let (path, expected) = raw
+            .split_once('=')
+            .ok_or_else(|| format!("malformed --assert '{}', expected path=value", raw))?;
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            return Err(format!("malformed --assert path '{}', expected keys separated by dots like data.items.0.id", path));
+        }
+        Ok(Self { path: path.split('.').map(String::from).collect(), expected: expected.to_string() })
+    }
+
+    //None if the value at the path matches, otherwise why not
+    //a JSON string is compared as text, anything else against `expected` read as JSON, so id=1 matches 1 and 1.0,
+    //done=true matches the boolean and name=null matches null
+    fn check(&self, json: &Value) -> Option<String> {
+        let path = self.path.join(".");
+        let actual = match lookup(json, &self.path) {
+            Some(actual) => actual,
+            None => return Some(format!("{}: not found", path)),
+        };
+        let matches = match actual {
+            Value::String(text) => *text == self.expected,
+            other => serde_json::from_str::<Value>(&self.expected).is_ok_and(|expected| values_equal(other, &expected)),
+        };
+        (!matches).then(|| format!("{}: expected {}, got {}", path, self.expected, actual))
+    }
+}
+
+//numbers compare by value so 1 equals 1.0, serde_json keeps integers and floats apart otherwise
+fn values_equal(actual: &Value, expected: &Value) -> bool {
+    match (actual.as_f64(), expected.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => actual == expected,
+    }
+}
+
+//Walk a dotted path: a segment picks an object's key, or an array's element when it is a number
+fn lookup<'a>(json: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(json, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+//Send the request, print the one-line result and return whether it passed
+//a request that never got a response (DNS, refused connection, timeout) is a failure like a bad status
+pub async fn once(client: &reqwest::Client, reporter: &Reporter, args: &Args, url: Url) -> bool {
+    let method = if args.body.is_some() { "POST" } else { "GET" };
+    let result = match &args.body {
+        Some(source) => match source.load(args.content_type.as_ref()).await {
+            Ok(body) => reporter.post(client, url.clone(), HeaderMap::new(), body).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        },
+        None => reporter.get(client, url.clone(), HeaderMap::new()).await.map_err(|err| err.to_string()),
+    };
+
+    let timed = match result {
+        Ok(timed) => timed,
+        Err(err) => {
+            println!("FAIL {} {}  {}", method, url, err);
+            return false;
+        }
+    };
+
+    let summary = format!("{}  {} {}  {:.1} ms", timed.status, method, url, timed.elapsed.as_secs_f64() * 1000.0);
+    match failure(&timed, args.expect_status, &args.assertions) {
+        None => {
+            println!("OK {}", summary);
+            true
+        }
+        Some(reason) => {
+            println!("FAIL {}  {}", summary, reason);
+            false
+        }
+    }
+}
+
+//the first thing wrong with the response, None if it passed
+//`expect_status` None means any 2xx
+fn failure(timed: &TimedResponse, expect_status: Option<StatusCode>, assertions: &[Assertion]) -> Option<String> {
+    let status_ok = match expect_status {
+        Some(status) => timed.status == status,
+        None => timed.status.is_success(),
+    };
+    if !status_ok {
+        return Some(match expect_status {
+            Some(status) => format!("expected status {}", status.as_u16()),
+            None => "expected a 2xx status".to_string(),
+        });
+    }
+
+    if assertions.is_empty() {
+        return None;
+    }
+    let json = match serde_json::from_slice::<Value>(&timed.body) {
+        Ok(json) => json,
+        Err(err) => return Some(format!("body isn't JSON: {}", err)),
+    };
+    assertions.iter().find_map(|assertion| assertion.check(&json))
+}
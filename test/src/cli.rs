@@ -0,0 +1,182 @@
+//Command-line options for the HTTP client, parsed by hand from std::env::args() so no extra crate is needed
+//Usage: getting-rusty [URL] [--follow-pagination] [--max-pages N] [--header "Name: Value"]... [--verbose] [--json-stats]
+//                     [--cache-dir DIR] [--body TEXT | --body - | --body-file PATH] [--content-type TYPE]
+//                     [--max-redirects N | --no-follow] [--proxy URL]
+//                     [--once [--expect-status CODE] [--assert path=value]...]
+//Giving a body switches the request from GET to POST, "--body -" reads it from standard input
+
+use std::path::PathBuf;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::Policy;
+use reqwest::{StatusCode, Url};
+
+use crate::body::BodySource;
+use crate::check::Assertion;
+use crate::proxy;
+
+pub const DEFAULT_URL: &str = "https://jsonplaceholder.typicode.com/todos/1";
+
+//Options collected from the command line, anything not passed keeps its default
+pub struct Args {
+    pub url: String,
+    pub follow_pagination: bool,  //keep requesting rel="next" links from the Link header
+    pub max_pages: Option<usize>, //stop after this many pages even if a next link remains, None = no limit
+    pub headers: HeaderMap,       //extra headers sent with every request, --header can be repeated
+    pub verbose: bool,            //also print request and response headers
+    pub json_stats: bool,         //print the status/timing summary as JSON instead of a colored line, the only thing left on stdout
+    pub cache_dir: Option<PathBuf>, //revalidate responses saved here with ETag/Last-Modified instead of downloading them again
+    pub body: Option<BodySource>, //POST this instead of sending a GET
+    pub content_type: Option<HeaderValue>, //overrides the Content-Type guessed from the body
+    pub max_redirects: Option<usize>, //give up after this many redirects in a row, None = reqwest's default of 10
+    pub no_follow: bool,              //return 3xx responses as they are instead of following their Location
+    pub proxy: Option<Url>,           //send every request through this proxy, None = HTTP_PROXY/HTTPS_PROXY if set
+    pub once: bool,                   //smoke test: print one OK/FAIL line and exit non-zero on failure, see check.rs
+    pub expect_status: Option<StatusCode>, //--once passes with exactly this status, None = any 2xx
+    pub assertions: Vec<Assertion>,   //--once also requires these JSON fields to have these values
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_URL.to_string(),
+            follow_pagination: false,
+            max_pages: None,
+            headers: HeaderMap::new(),
+            verbose: false,
+            json_stats: false,
+            cache_dir: None,
+            body: None,
+            content_type: None,
+            max_redirects: None,
+            no_follow: false,
+            proxy: None,
+            once: false,
+            expect_status: None,
+            assertions: Vec::new(),
+        }
+    }
+}
+
+impl Args {
+    //skip(1) drops the program name, the rest is matched flag by flag
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--follow-pagination" => parsed.follow_pagination = true,
+                "--max-pages" => {
+                    let value = next_value(&mut args, "--max-pages")?;
+                    let pages = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("--max-pages expects a number, got '{}'", value))?;
+                    if pages == 0 {
+                        return Err("--max-pages must be at least 1".into());
+                    }
+                    parsed.max_pages = Some(pages);
+                }
+                "--header" | "-H" => {
+                    let value = next_value(&mut args, "--header")?;
+                    let (name, value) = parse_header(&value)?;
+                    //append rather than insert so the same header can be given several times, e.g. two Accept values
+                    parsed.headers.append(name, value);
+                }
+                "--verbose" | "-v" => parsed.verbose = true,
+                "--json-stats" => parsed.json_stats = true,
+                "--cache-dir" => parsed.cache_dir = Some(PathBuf::from(next_value(&mut args, "--cache-dir")?)),
+                "--body" | "--body-file" => {
+                    if parsed.body.is_some() {
+                        return Err("only one of --body and --body-file can be given".into());
+                    }
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.body = Some(if arg == "--body" { BodySource::from_arg(value) } else { BodySource::File(PathBuf::from(value)) });
+                }
+                "--content-type" => {
+                    let value = next_value(&mut args, "--content-type")?;
+                    let value = HeaderValue::from_str(&value).map_err(|_| format!("invalid --content-type '{}'", value))?;
+                    parsed.content_type = Some(value);
+                }
+                "--max-redirects" => {
+                    let value = next_value(&mut args, "--max-redirects")?;
+                    let redirects = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("--max-redirects expects a number, got '{}'", value))?;
+                    //a limit of 0 would turn every redirect into an error, --no-follow shows it instead
+                    if redirects == 0 {
+                        return Err("--max-redirects must be at least 1, use --no-follow to not follow redirects".into());
+                    }
+                    parsed.max_redirects = Some(redirects);
+                }
+                "--no-follow" => parsed.no_follow = true,
+                "--proxy" => parsed.proxy = Some(proxy::parse_url(&next_value(&mut args, "--proxy")?, "--proxy")?),
+                "--once" => parsed.once = true,
+                "--expect-status" => {
+                    let value = next_value(&mut args, "--expect-status")?;
+                    let status = value
+                        .parse::<u16>()
+                        .ok()
+                        .and_then(|code| StatusCode::from_u16(code).ok())
+                        .ok_or_else(|| format!("--expect-status expects an HTTP status code like 204, got '{}'", value))?;
+                    parsed.expect_status = Some(status);
+                }
+                "--assert" => parsed.assertions.push(Assertion::parse(&next_value(&mut args, "--assert")?)?),
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                //anything that isn't a flag is treated as the URL to request
+                url => parsed.url = url.to_string(),
+            }
+        }
+
+        //pagination walks GET links, there is no sensible way to re-send a body to every page
+        if parsed.follow_pagination && parsed.body.is_some() {
+            return Err("--follow-pagination can't be combined with --body or --body-file".into());
+        }
+
+        if parsed.no_follow && parsed.max_redirects.is_some() {
+            return Err("--max-redirects can't be combined with --no-follow".into());
+        }
+
+        if !parsed.once && (parsed.expect_status.is_some() || !parsed.assertions.is_empty()) {
+            return Err("--expect-status and --assert only apply with --once".into());
+        }
+        //a smoke test checks one live response, not a merged set of pages or a cached copy
+        if parsed.once && (parsed.follow_pagination || parsed.cache_dir.is_some() || parsed.json_stats) {
+            return Err("--once can't be combined with --follow-pagination, --cache-dir or --json-stats".into());
+        }
+
+        Ok(parsed)
+    }
+
+    //what the client does with a 3xx response
+    pub fn redirect_policy(&self) -> Policy {
+        if self.no_follow {
+            Policy::none()
+        } else {
+            Policy::limited(self.max_redirects.unwrap_or(10))
+        }
+    }
+}
+
+//Flags like --max-pages take the following argument as their value, error out if it is missing
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} expects a value", flag))
+}
+
+//Split "Name: Value" on the first colon and check both halves are legal HTTP header text
+pub fn parse_header(raw: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = raw
+        .split_once(':')
+        .ok_or_else(|| format!("malformed header '{}', expected \"Name: Value\"", raw))?;
+
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| format!("invalid header name '{}' in '{}'", name.trim(), raw))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|_| format!("invalid value for header '{}' in '{}'", name, raw))?;
+
+    Ok((name, value))
+}
@@ -36,20 +36,76 @@ loop {
  //Executor's job is to hold queue of pending futures and call them synchronously and then wait via the 'await' cmd
  //Waker/context notifies the executor of when a future can continue, as in if it returns a value
 
-use reqwest; //HTTP client lib
+mod body;
+mod cache;
+mod check;
+mod cli;
+mod pagination;
+mod proxy;
+mod stats;
+
+use cache::Cache;
+use cli::Args;
+use reqwest::header::HeaderMap;
+use stats::Reporter;
 use serde_json::Value; //Value is any JSON type, it is dynamic & gets used so strongly-typed struct isn't required
 
 #[tokio::main] //flag tells main function to make main function an async routine else it can't run any async functions
 async fn main() -> Result<(), Box<dyn std::error::Error>> { //any type of sub-error can be returned as long as it implements method of Error trait & return pointer to this error dynamically located on heap if fails, if success then nothing
-    println!("Sending request...");
+    let args = Args::parse()?;
+    //reuse a single client so connections are pooled across pages, --header values ride along on every request it sends
+    let mut builder = reqwest::Client::builder()
+        .default_headers(args.headers.clone())
+        .redirect(args.redirect_policy()); //follows up to 10 redirects unless --max-redirects or --no-follow say otherwise
+    let proxies = proxy::settings(args.proxy.as_ref())?;
+    for setting in &proxies {
+        builder = builder.proxy(setting.to_proxy()?);
+    }
+    let client = builder.build()?;
 
-    // Make an async GET request
-    let response = reqwest::get("https://jsonplaceholder.typicode.com/todos/1") //await response & '?' unwraps result, if success then return it, else if error return error 
-        .await? //await request
-        .json::<Value>() //parse JSON
-        .await?; //await parsing
+    //every request goes through the reporter so each one prints its status, time and size
+    //with --json-stats those are the only lines on stdout, everything else goes through note() to stderr
+    let reporter = Reporter::new(args.verbose, args.json_stats, args.headers.clone());
 
-    println!("Response JSON:\n{:#?}", response);
+    //a proxy that isn't listening fails here with its own message, not as a connection error on the first request
+    let target: reqwest::Url = args.url.parse()?;
+    if let Some(setting) = proxies.iter().find(|setting| setting.applies_to(&target)) {
+        if !args.once {
+            reporter.note(format!("Using proxy {}", setting));
+        }
+        setting.check_reachable().await?;
+    }
+
+    //--once prints its own OK/FAIL line instead of the summary and the JSON, the exit code is the result
+    if args.once {
+        let passed = check::once(&client, &reporter.without_summary(), &args, target).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    let cache = args.cache_dir.as_deref().map(Cache::open).transpose()?;
+
+    reporter.note("Sending request...");
+
+    let response = if args.follow_pagination {
+        //keep following rel="next" links and merge every page into one JSON array
+        pagination::fetch_all_pages(&client, &reporter, cache.as_ref(), &args.url, args.max_pages).await?
+    } else if let Some(source) = &args.body {
+        //a POST changes things on the server, its answer is never served from or saved to the cache
+        let body = source.load(args.content_type.as_ref()).await?;
+        let timed = reporter.post(&client, args.url.parse()?, HeaderMap::new(), body).await?;
+        if timed.is_redirect() {
+            return Ok(()); //--no-follow: the reporter printed the status and Location, a redirect's body is no JSON to parse
+        }
+        serde_json::from_slice::<Value>(&timed.body)?
+    } else {
+        // Make an async GET request
+        let timed = cache::get(&client, &reporter, cache.as_ref(), args.url.parse()?).await?; //await response & '?' unwraps result, if success then return it, else if error return error 
+        if timed.is_redirect() {
+            return Ok(());
+        }
+        serde_json::from_slice::<Value>(&timed.body)? //parse JSON from the body the reporter already read
+    };
+
+    reporter.note(format!("Response JSON:\n{:#?}", response));
 
     Ok(())
-}
\ No newline at end of file
+}
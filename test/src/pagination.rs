@@ -0,0 +1,157 @@
+//RFC 5988 pagination: servers return a Link header listing related pages, e.g.
+//Link: <https://api.example.com/items?page=2>; rel="next", <https://api.example.com/items?page=9>; rel="last"
+//Each page is requested in turn and the JSON arrays are concatenated into one
+
+use std::collections::HashSet;
+
+use reqwest::header::LINK;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::cache::{self, Cache};
+use crate::stats::Reporter;
+
+//Pull the rel="next" target out of a Link header value, None when there is no next page
+pub fn parse_next_link(header: &str) -> Option<String> {
+    //the URLs are found first by their <...>, a comma inside one (?ids=1,2) would otherwise split it in half
+    //each URL is followed by its ;-separated params, which run up to the next <
+    //an entry that can't be read is skipped, a rel="next" after it still counts
+    let mut rest = header;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = match rest.find(['<', '>']) {
+            Some(end) if rest.as_bytes()[end] == b'>' => end,
+            _ => continue, //no closing > before the next link starts, or at all
+        };
+        let url = &rest[..end];
+        rest = &rest[end + 1..];
+        let params = &rest[..rest.find('<').unwrap_or(rest.len())];
+
+        //rel can hold several space-separated values, e.g. rel="next last"
+        let is_next = params.split([';', ',']).any(|param| {
+            let (name, value) = match param.split_once('=') {
+                Some(pair) => pair,
+                None => return false,
+            };
+            name.trim().eq_ignore_ascii_case("rel")
+                && value.trim().trim_matches('"').split_whitespace().any(|rel| rel.eq_ignore_ascii_case("next"))
+        });
+
+        if is_next {
+            return Some(url.to_string());
+        }
+    }
+    None
+}
+
+//Follow rel="next" links starting at `url` until none remain or `max_pages` pages have been fetched
+//a next link back to a page already fetched also stops it, otherwise a server that links a page to itself (or two
+//pages to each other) would be followed forever when there is no --max-pages
+//Every page must be a JSON array, the combined array is returned
+pub async fn fetch_all_pages(
+    client: &reqwest::Client,
+    reporter: &Reporter,
+    cache: Option<&Cache>,
+    url: &str,
+    max_pages: Option<usize>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut combined = Vec::new();
+    let mut next = Some(Url::parse(url)?);
+    let mut pages = 0;
+    let mut visited = HashSet::new();
+
+    while let Some(page_url) = next.take() {
+        if max_pages.is_some_and(|max| pages >= max) {
+            reporter.note(format!("Stopping after --max-pages {} pages", pages));
+            break;
+        }
+        if !visited.insert(page_url.clone()) {
+            reporter.note(format!("Stopping, {} was already fetched", page_url));
+            break;
+        }
+
+        reporter.note(format!("Fetching page {}: {}", pages + 1, page_url));
+        let response = cache::get(client, reporter, cache, page_url.clone()).await?;
+        if response.status.is_client_error() || response.status.is_server_error() {
+            return Err(format!("page {} returned {}", page_url, response.status).into());
+        }
+        //only with --no-follow, a page that moved has no items of its own
+        if response.is_redirect() {
+            return Err(format!("page {} redirects to {} (not followed)", page_url, response.location().unwrap_or("nowhere")).into());
+        }
+
+        //relative links are resolved against the page URL
+        next = match response.headers.get(LINK).and_then(|v| v.to_str().ok()).and_then(parse_next_link) {
+            Some(link) => Some(page_url.join(&link)?),
+            None => None,
+        };
+
+        match serde_json::from_slice::<Value>(&response.body)? {
+            Value::Array(items) => combined.extend(items),
+            other => return Err(format!("page {} is not a JSON array: {}", page_url, other).into()),
+        }
+        pages += 1;
+    }
+
+    reporter.note(format!("Fetched {} page(s), {} item(s) total", pages, combined.len()));
+    Ok(Value::Array(combined))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn next_link_among_others() {
+        let header = r#"<https://api.example.com/items?page=2>; rel="next", <https://api.example.com/items?page=9>; rel="last""#;
+        assert_eq!(parse_next_link(header).as_deref(), Some("https://api.example.com/items?page=2"));
+        assert_eq!(parse_next_link(r#"<https://a.example/?page=1>; rel="prev""#), None);
+        //rel with several values, in any case
+        assert_eq!(parse_next_link(r#"</items?page=3>; title="x"; REL="last Next""#).as_deref(), Some("/items?page=3"));
+    }
+
+    #[test]
+    fn comma_inside_a_url() {
+        let header = r#"<https://api.example.com/items?ids=1,2&page=1>; rel="prev", <https://api.example.com/items?ids=1,2&page=3>; rel="next""#;
+        assert_eq!(parse_next_link(header).as_deref(), Some("https://api.example.com/items?ids=1,2&page=3"));
+    }
+
+    #[test]
+    fn malformed_entry_before_a_valid_one() {
+        //no <> at all, an unclosed <, and a param without a value, none of them hide the next link after them
+        for header in [
+            r#"https://a.example/?page=1; rel="prev", <https://a.example/?page=2>; rel="next""#,
+            r#"<https://a.example/?page=1; rel="prev", <https://a.example/?page=2>; rel="next""#,
+            r#"<https://a.example/?page=1>; rel, <https://a.example/?page=2>; rel="next""#,
+        ] {
+            assert_eq!(parse_next_link(header).as_deref(), Some("https://a.example/?page=2"), "{}", header);
+        }
+        assert_eq!(parse_next_link(r#"<https://a.example/?page=2; rel="next""#), None);
+    }
+
+    //a server whose every page is [1] with a next link back to itself
+    async fn self_linking_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nLink: </items>; rel=\"next\"\r\nContent-Length: 3\r\nConnection: close\r\n\r\n[1]";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/items", address)
+    }
+
+    #[tokio::test]
+    async fn a_page_linking_to_itself_is_fetched_once() {
+        let url = self_linking_server().await;
+        let client = reqwest::Client::new();
+        let reporter = Reporter::new(false, false, HeaderMap::new());
+        let items = fetch_all_pages(&client, &reporter, None, &url, None).await.unwrap();
+        assert_eq!(items, serde_json::json!([1]));
+    }
+}
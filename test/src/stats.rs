@@ -0,0 +1,150 @@
+//Status and timing summary printed after every request, a small step towards using the client as a debugging tool
+//The default is one colored line, e.g. "200 OK  84.2 ms  83 bytes" in green (red for 4xx/5xx)
+//--verbose adds the request and response headers curl-style (> sent, < received), --json-stats prints the summary as JSON
+//A response that came from somewhere else after following redirects also prints where it ended up, and a 3xx that
+//wasn't followed (--no-follow) prints where it points
+
+use std::time::{Duration, Instant};
+
+use colored::Colorize; //adds .green()/.red()/.dimmed() to strings
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::{Request, StatusCode, Url};
+use serde_json::json;
+
+use crate::body::RequestBody;
+
+//A finished request with its body read in full, so `elapsed` covers the whole download and not just the headers
+pub struct TimedResponse {
+    pub url: Url, //where the response came from, the end of the chain when redirects were followed
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    pub elapsed: Duration,
+}
+
+impl TimedResponse {
+    //a redirect handed back instead of followed, which only happens with --no-follow
+    //304 Not Modified is a 3xx too but answers a conditional request, it never points anywhere
+    pub fn is_redirect(&self) -> bool {
+        self.status.is_redirection() && self.status != StatusCode::NOT_MODIFIED
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.headers.get(LOCATION).and_then(|value| value.to_str().ok())
+    }
+}
+
+pub struct Reporter {
+    verbose: bool,
+    json: bool,
+    summary: bool, //false with --once, which prints its own one-line result instead
+    //the client's default headers, reqwest only merges them in when the request is sent so they are listed separately
+    default_headers: HeaderMap,
+}
+
+impl Reporter {
+    pub fn new(verbose: bool, json: bool, default_headers: HeaderMap) -> Self {
+        Self { verbose, json, summary: true, default_headers }
+    }
+
+    //a line for the person running it: stdout normally, stderr with --json-stats so stdout carries only the JSON
+    //lines and a script can parse it line by line
+    pub fn note(&self, line: impl std::fmt::Display) {
+        if self.json {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    //only the --verbose headers, for callers that print their own result
+    pub fn without_summary(mut self) -> Self {
+        self.summary = false;
+        self
+    }
+
+    //GET `url` with `headers` on top of the client's defaults, read the body and print the summary
+    //HTTP error statuses are reported but not turned into errors
+    pub async fn get(&self, client: &reqwest::Client, url: Url, headers: HeaderMap) -> Result<TimedResponse, reqwest::Error> {
+        let request = client.get(url).headers(headers).build()?;
+        self.execute(client, request).await
+    }
+
+    //POST `body` to `url`, same reporting as get()
+    pub async fn post(&self, client: &reqwest::Client, url: Url, headers: HeaderMap, body: RequestBody) -> Result<TimedResponse, reqwest::Error> {
+        let request = client
+            .post(url)
+            .headers(headers)
+            .header(CONTENT_TYPE, body.content_type)
+            .header(CONTENT_LENGTH, body.length)
+            .body(body.body)
+            .build()?;
+        self.execute(client, request).await
+    }
+
+    async fn execute(&self, client: &reqwest::Client, request: Request) -> Result<TimedResponse, reqwest::Error> {
+        let url = request.url().clone();
+        if self.verbose {
+            self.note(format!("> {} {}", request.method(), request.url()).dimmed());
+            for (name, value) in self.default_headers.iter().chain(request.headers()) {
+                self.note(format!("> {}: {}", name, value.to_str().unwrap_or("<binary>")).dimmed());
+            }
+        }
+
+        //the clock runs from sending until the last byte of the body has arrived
+        let start = Instant::now();
+        let response = client.execute(request).await?;
+        let final_url = response.url().clone();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?.to_vec();
+        let timed = TimedResponse { url: final_url, status, headers, body, elapsed: start.elapsed() };
+
+        self.report(&url, &timed);
+        Ok(timed)
+    }
+
+    fn report(&self, url: &Url, timed: &TimedResponse) {
+        if self.summary {
+            self.print_summary(url, timed);
+        }
+
+        if self.verbose {
+            for (name, value) in &timed.headers {
+                self.note(format!("< {}: {}", name, value.to_str().unwrap_or("<binary>")).dimmed());
+            }
+        }
+    }
+
+    fn print_summary(&self, url: &Url, timed: &TimedResponse) {
+        let millis = timed.elapsed.as_secs_f64() * 1000.0;
+        if self.json {
+            let stats = json!({
+                "url": url.as_str(),
+                "status": timed.status.as_u16(),
+                "elapsed_ms": millis,
+                "bytes": timed.body.len(),
+                "final_url": timed.url.as_str(),
+                "location": if timed.is_redirect() { timed.location() } else { None },
+            });
+            println!("{}", stats);
+        } else {
+            let line = format!("{}  {:.1} ms  {} bytes", timed.status, millis, timed.body.len());
+            //2xx and 3xx count as success, anything else is worth noticing
+            if timed.status.is_client_error() || timed.status.is_server_error() {
+                println!("{}", line.red().bold());
+            } else {
+                println!("{}", line.green().bold());
+            }
+            if timed.url != *url {
+                println!("Redirected to {}", timed.url);
+            }
+            if timed.is_redirect() {
+                match timed.location() {
+                    Some(location) => println!("{}", format!("Location: {} (not followed)", location).yellow()),
+                    None => println!("{}", "Redirect without a Location header".yellow()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// --triangles N [--draws D]: a micro-benchmark of how the number of draw calls affects a frame
+// all N triangles are uploaded once into a single vertex buffer, each frame draws them in D calls of about N/D each,
+// so running with the same N and a different D compares one large draw against many small ones with exactly the same
+// vertices and pixels
+//
+// what is timed is submit + present on the CPU. The GPU works through the frames on its own, but the swapchain only has
+// a couple of textures, so once it falls behind present() waits for one to come free and the time follows the GPU too
+// vsync is turned off while benchmarking (see surface_config in main.rs) so that wait isn't the monitor's refresh rate
+use std::time::{Duration, Instant};
+
+use rotating_cube::error::RenderError;
+
+// frames per report, a couple of seconds' worth at a few hundred fps
+const REPORT_FRAMES: usize = 300;
+
+// the part of each grid cell a triangle covers, the rest is a gap so they don't overlap
+const FILL: f32 = 0.8;
+
+// 2 floats of position + 3 of color
+const FLOATS_PER_VERTEX: usize = 5;
+const VERTEX_SIZE: u64 = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as u64;
+
+pub struct Bench {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    triangles: u32,
+    draws: u32,
+    timings: Vec<Duration>, // submit + present of each frame since the last report
+    since: Instant,         // when the first of those frames started, for the fps
+}
+
+impl Bench {
+    // fails when the triangles don't fit in one buffer on this device, splitting them over several would defeat the point
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, triangles: u32, draws: u32) -> Result<Self, RenderError> {
+        let size = triangles as u64 * 3 * VERTEX_SIZE;
+        let max = device.limits().max_buffer_size;
+        if size > max {
+            return Err(RenderError::BufferTooLarge { what: format!("{} triangles", triangles), size, max });
+        }
+
+        // mapped_at_creation lets us write straight into the buffer's memory instead of going through queue.write_buffer
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Benchmark Vertex Buffer"),
+            size,
+            usage: wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: true,
+        });
+        write_triangles(&mut vertex_buffer.slice(..).get_mapped_range_mut(), triangles);
+        vertex_buffer.unmap();
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("bench.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Benchmark Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Benchmark Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_SIZE,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // no culling, the triangles all face the camera anyway and the driver shouldn't get to skip any
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        println!("Benchmarking {} triangles in {} draw call(s) per frame", triangles, draws);
+        Ok(Self { pipeline, vertex_buffer, triangles, draws, timings: Vec::with_capacity(REPORT_FRAMES), since: Instant::now() })
+    }
+
+    // the same buffer is bound once, each draw call picks its own range of vertices out of it
+    // the triangles are shared out so no two draws differ by more than one, and none is left empty
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        let boundary = |draw: u32| (draw as u64 * self.triangles as u64 / self.draws as u64) as u32; //u64, N * D can overflow
+        for draw in 0..self.draws {
+            pass.draw(boundary(draw) * 3..boundary(draw + 1) * 3, 0..1);
+        }
+    }
+
+    // keep one frame's submit + present time, printing the numbers every REPORT_FRAMES frames
+    pub fn record(&mut self, elapsed: Duration) {
+        if self.timings.is_empty() {
+            self.since = Instant::now() - elapsed;
+        }
+        self.timings.push(elapsed);
+        if self.timings.len() < REPORT_FRAMES {
+            return;
+        }
+
+        // sorted so the min, max and 95th percentile can be read off by position
+        self.timings.sort();
+        let total: Duration = self.timings.iter().sum();
+        let average = total / self.timings.len() as u32;
+        let p95 = self.timings[self.timings.len() * 95 / 100];
+        let fps = self.timings.len() as f64 / self.since.elapsed().as_secs_f64();
+        println!(
+            "{} triangles / {} draws: submit+present avg {:.3} ms, min {:.3} ms, p95 {:.3} ms, max {:.3} ms ({:.0} fps)",
+            self.triangles,
+            self.draws,
+            ms(average),
+            ms(self.timings[0]),
+            ms(p95),
+            ms(self.timings[self.timings.len() - 1]),
+            fps
+        );
+        self.timings.clear();
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// lay the triangles out on a grid over the whole window (clip space -1..1), one per cell so the number of pixels
+// filled stays about the same whatever N is and the draw calls are what changes between runs
+// every vertex is written out as native-endian floats, the same layout the GPU reads them in
+fn write_triangles(bytes: &mut [u8], triangles: u32) {
+    let columns = (triangles as f64).sqrt().ceil() as u32;
+    let rows = triangles.div_ceil(columns);
+    let (cell_width, cell_height) = (2.0 / columns as f32, 2.0 / rows as f32);
+
+    let mut floats = bytes.chunks_exact_mut(4);
+    let mut push = |value: f32| floats.next().unwrap().copy_from_slice(&value.to_ne_bytes());
+    for index in 0..triangles {
+        let (column, row) = (index % columns, index / columns);
+        let left = -1.0 + column as f32 * cell_width + cell_width * (1.0 - FILL) / 2.0;
+        let bottom = -1.0 + row as f32 * cell_height + cell_height * (1.0 - FILL) / 2.0;
+        let (width, height) = (cell_width * FILL, cell_height * FILL);
+
+        // a color that drifts across the grid so neighbouring draws can be told apart
+        let color = [column as f32 / columns as f32, row as f32 / rows as f32, 0.5];
+        // counter-clockwise like wgpu's default front face
+        for (x, y) in [(left, bottom), (left + width, bottom), (left + width / 2.0, bottom + height)] {
+            push(x);
+            push(y);
+            color.iter().for_each(|&c| push(c));
+        }
+    }
+}
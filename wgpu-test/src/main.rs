@@ -16,12 +16,24 @@ let instance = wgpu::Instance::new(wgpu::Backends::all()); -> WGPU finds all ava
 */
 
 
-//import wgpu library
-use wgpu::util::DeviceExt;
+mod bench;
+mod options;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bench::Bench;
+use options::Options;
+//the event loop is rotating-cube's, State only says what to do with each event, see App below
+use rotating_cube::adapter::{self, AdapterRequest};
+use rotating_cube::error::RenderError;
+use rotating_cube::framework::{run_app, App};
 use winit::{
-    event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    dpi::PhysicalSize,
+    event::WindowEvent,
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder, WindowId},
 };
 
 //create State that keeps track of surface rendered, queue for frame buffer, device connection to GPU and general surface configs
@@ -31,70 +43,250 @@ struct State {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    //set from the device's error handler when the GPU goes away (driver reset, GPU unplugged), the next frame makes a new device
+    //Arc because the handler is a closure wgpu keeps and may call from another thread, AtomicBool so both sides can touch it
+    device_lost: Arc<AtomicBool>,
+    //the --triangles benchmark, drawn on top of the cleared frame. It lives on the device so it is made again with it
+    bench: Option<Bench>,
+    //what recreate_device() needs to find a GPU again, kept here since the event loop no longer has them in scope
+    instance: wgpu::Instance,
+    options: Options,
+    window: Window, //after the surface that draws into it so it is dropped last
 }
 
 //Implement the type
 impl State {
     // Async constructor
-    //use & to indicate we are borrowing the Window instance
-    async fn new(window: &winit::window::Window) -> Self {
-        // Create instance
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
-            
+    //the instance is created in main so --list-adapters can run before any window exists
+    //State takes the window (and the rest) over, run_app() needs it to own everything a frame uses
+    async fn new(instance: wgpu::Instance, window: Window, options: Options) -> Result<Self, RenderError> {
         // Create surface (unsafe because it interacts with OS window)
-        let surface = unsafe { instance.create_surface(window) }.unwrap();
-        
-        // Request an adapter (GPU)
-        //..Default::default() syntax indicates that all other fields of type (wgpu::RequestAdapterOptions here) are set to default values
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                compatible_surface: Some(&surface),
-                ..Default::default()
-            },
-        ).await.unwrap();
-        
-        // Request device and queue
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
-                limits: wgpu::Limits::default(),
-                label: None,
-            },
-            None,
-        ).await.unwrap();
+        let surface = unsafe { instance.create_surface(&window) }?;
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let (adapter, device, queue) = open_device(&instance, &surface, &options, &device_lost).await?;
+        let config = surface_config(&surface, &adapter, window.inner_size(), &options);
+        surface.configure(&device, &config);
+        let bench = create_bench(&device, config.format, &options)?;
         
-        // Get surface capabilities and choose a format
-        // search through all &Format types from surface_caps, generate vector via iter(), copy them to get reference, then run a closure (f.is_srgb()) that checks 
-        // if srgb surface found
-        let surface_caps = surface.get_capabilities(&adapter);
-        let format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        Ok(Self { surface, device, queue, config, device_lost, bench, instance, options, window })
+    }
+
+    //everything made on the lost device is useless now, but the surface belongs to the window and is kept:
+    //find an adapter again (the old one may be the GPU that disappeared), open a new device and point the surface at it
+    //a benchmark that fit on the old device but not on the new one is dropped, the window goes on being cleared
+    async fn recreate_device(&mut self) -> Result<(), RenderError> {
+        self.device_lost.store(false, Ordering::SeqCst);
+        let (adapter, device, queue) = open_device(&self.instance, &self.surface, &self.options, &self.device_lost).await?;
+        self.config = surface_config(&self.surface, &adapter, self.window.inner_size(), &self.options);
+        self.surface.configure(&device, &self.config);
+        self.bench = create_bench(&device, self.config.format, &self.options).unwrap_or_else(|err| {
+            eprintln!("Benchmark stopped: {}", err);
+            None
+        });
+        self.device = device;
+        self.queue = queue;
+        Ok(())
+    }
+
+    //clear the next swapchain texture to black, draw the benchmark's triangles if there are any, and show it
+    //the error is for the caller to decide on: the swapchain can be set up again, a device can't be without a new one
+    fn draw(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // Acquire next frame
+        let frame = self.surface.get_current_texture()?;
         
-        // Configure surface
-        let size = window.inner_size();
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+        //get window frame, apply texture, and encode this to commands to send to GPU to render 
+        //don't modify frame directly using GPU, create a view for it to modify 
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
+
+        // Begin render pass (clear screen to black)
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            if let Some(bench) = &self.bench {
+                bench.draw(&mut render_pass);
+            }
+        }
+
+        // Submit commands
+        //encoding above is the same few calls whatever the benchmark does, the time it measures starts here
+        let start = Instant::now();
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        if let Some(bench) = &mut self.bench {
+            bench.record(start.elapsed());
+        }
+        Ok(())
+    }
+}
+
+//run_app() calls these for every event and frame
+impl App for State {
+    //nothing to react to, closing and resizing come through their own methods
+    fn input(&mut self, _window: WindowId, _event: &WindowEvent) -> Result<(), RenderError> {
+        Ok(())
+    }
+
+    //a minimized window is 0x0, which a surface can't be configured to, it keeps the old size until it is restored
+    fn resize(&mut self, _window: WindowId, size: PhysicalSize<u32>) -> Result<(), RenderError> {
+        if size.width > 0 && size.height > 0 {
+            self.config.width = size.width;
+            self.config.height = size.height;
+            self.surface.configure(&self.device, &self.config);
+        }
+        Ok(())
+    }
+
+    //nothing moves, every frame is drawn the same
+    fn update(&mut self, _dt: f32) {}
+
+    fn render(&mut self, _alpha: f32) -> Result<(), RenderError> {
+        // nothing can be drawn with a lost device, get a new one first
+        // if that fails too (no GPU left at all) there is nothing to fall back to, the error ends the program
+        if self.device_lost.load(Ordering::SeqCst) {
+            println!("Recreating the GPU device");
+            pollster::block_on(self.recreate_device())?;
+        }
+        match self.draw() {
+            Ok(()) => {}
+            // the swapchain no longer matches the window, set it up again and draw the next frame
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => self.surface.configure(&self.device, &self.config),
+            // the GPU took too long to hand out a texture, skip this frame
+            Err(wgpu::SurfaceError::Timeout) => eprintln!("Timed out getting the next frame, skipping it"),
+            // usually means the device is gone as well, recreate it before the next frame
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                eprintln!("Out of memory getting the next frame");
+                self.device_lost.store(true, Ordering::SeqCst);
+            }
+        }
+        Ok(())
+    }
+
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+}
+
+//pick the adapter (GPU) and open a device and queue on it, at startup and again after the device was lost
+//the errors are rotating-cube's RenderError, the same ones its own adapter selection reports
+async fn open_device(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface,
+    options: &Options,
+    device_lost: &Arc<AtomicBool>,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), RenderError> {
+    // Request an adapter (GPU)
+    //--adapter picks one by name, otherwise wgpu chooses, the same search rotating-cube does
+    let request = AdapterRequest {
+        backends: options.backends,
+        name: options.adapter.clone(),
+        power_preference: options.power_preference, //--power low|high, high performance by default
+    };
+    let adapter = request.pick(instance, surface).await.ok_or_else(|| match &options.adapter {
+        Some(name) => RenderError::NoMatchingAdapter(name.clone()),
+        None => RenderError::NoAdapter,
+    })?;
+    let info = adapter.get_info();
+    println!("Using adapter: {} ({:?}, {:?})", info.name, info.device_type, info.backend);
+    
+    // Request device and queue
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            label: None,
+        },
+        None,
+    ).await?;
+
+    //wgpu 0.16 has no device-lost callback, once the device is gone every call on it reports an error instead
+    //those go to this handler rather than wgpu's default one, which would panic. Only a lost device (or running out of
+    //memory, which usually comes with it) is recovered from, any other error is a bug here and still panics
+    let lost = device_lost.clone();
+    device.on_uncaptured_error(Box::new(move |err| {
+        let is_lost = match &err {
+            wgpu::Error::OutOfMemory { .. } => true,
+            wgpu::Error::Validation { description, .. } => description.contains("device is lost"),
         };
-        surface.configure(&device, &config);
-        
-        Self { surface, device, queue, config }
+        if !is_lost {
+            panic!("wgpu error: {}", err);
+        }
+        //swap returns the old value, so a lost device that keeps erroring is only reported once
+        if !lost.swap(true, Ordering::SeqCst) {
+            eprintln!("GPU device lost: {}", err);
+        }
+    }));
+    Ok((adapter, device, queue))
+}
+
+//only made for --triangles, None leaves the window cleared as before
+fn create_bench(device: &wgpu::Device, format: wgpu::TextureFormat, options: &Options) -> Result<Option<Bench>, RenderError> {
+    if options.triangles == 0 {
+        return Ok(None);
+    }
+    Bench::new(device, format, options.triangles, options.draws).map(Some)
+}
+
+// Get surface capabilities and choose a format
+fn surface_config(surface: &wgpu::Surface, adapter: &wgpu::Adapter, size: winit::dpi::PhysicalSize<u32>, options: &Options) -> wgpu::SurfaceConfiguration {
+    // search through all &Format types from surface_caps, generate vector via iter(), copy them to get reference, then run a closure (f.is_srgb()) that checks 
+    // if srgb surface found
+    let surface_caps = surface.get_capabilities(adapter);
+    let format = surface_caps
+        .formats
+        .iter()
+        .copied()
+        .find(|f| f.is_srgb())
+        .unwrap_or(surface_caps.formats[0]);
+    
+    // Configure surface
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: size.width,
+        height: size.height,
+        //a benchmark waiting on vsync would only ever measure the refresh rate, AutoNoVsync falls back to
+        //Fifo (vsync) by itself where the surface can't do without
+        present_mode: if options.triangles > 0 { wgpu::PresentMode::AutoNoVsync } else { surface_caps.present_modes[0] },
+        alpha_mode: surface_caps.alpha_modes[0],
+        view_formats: vec![],
     }
 }
 
 fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // Create instance, restricted to the --backend the user asked for (all backends by default)
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: options.backends,
+        dx12_shader_compiler: Default::default(),
+    });
+
+    if options.list_adapters {
+        adapter::list_adapters(&instance, options.backends);
+        return;
+    }
+    if let Err(err) = adapter::check_backend_available(&instance, options.backends) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+
     // Create event loop and window
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -104,62 +296,14 @@ fn main() {
     
 
     // Initialize GPU state asynchronously
-    let mut state = pollster::block_on(State::new(&window));
-
-    // Start the event loop
-    // Create another closure called move that has event, control_flow as params
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-
-        match event {
-            // match window event where it is either closed or resized
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                WindowEvent::Resized(size) => {
-                    state.config.width = size.width;
-                    state.config.height = size.height;
-                    state.surface.configure(&state.device, &state.config);
-                }
-                _ => {}
-            },
-            // match redraw requested event where window is successfully redrawn or fails
-            Event::RedrawRequested(_) => {
-                // Acquire next frame
-                let frame = match state.surface.get_current_texture() {
-                    Ok(frame) => frame,
-                    Err(_) => {
-                        state.surface.configure(&state.device, &state.config);
-                        state.surface.get_current_texture().unwrap()
-                    }
-                };
-                
-                //get window frame, apply texture, and encode this to commands to send to GPU to render 
-                //don't modify frame directly using GPU, create a view for it to modify 
-                let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-                let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") });
-
-                // Begin render pass (clear screen to black)
-                {
-                    let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                store: true,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                    });
-                }
-
-                // Submit commands
-                state.queue.submit(Some(encoder.finish()));
-                frame.present();
-            }
-            Event::MainEventsCleared => window.request_redraw(),
-            _ => {}
+    let state = match pollster::block_on(State::new(instance, window, options)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-    });
+    };
+
+    // Start the event loop, run_app() redraws every pass of the loop and exits when the window is closed
+    run_app(event_loop, state);
 }
@@ -0,0 +1,85 @@
+// command-line options, parsed by hand from std::env::args() so no extra crate is needed
+// usage: wgpu-test [--backend vulkan|dx12|metal|gl] [--adapter NAME] [--list-adapters] [--power low|high]
+//        [--triangles N [--draws D]]
+
+use rotating_cube::adapter::{parse_backend, parse_power_preference};
+
+pub struct Options {
+    pub backends: wgpu::Backends, // graphics APIs wgpu may use, all of them unless --backend is given
+    pub adapter: Option<String>,  // pick the adapter whose name contains this text
+    pub list_adapters: bool,      // print the available adapters and exit
+    pub power_preference: wgpu::PowerPreference, // integrated (low) vs discrete (high) GPU when no --adapter is given
+    pub triangles: u32,                          // benchmark drawing this many triangles each frame, 0 just clears the window
+    pub draws: u32,                              // how many draw calls the triangles are split over, 1 unless --draws is given
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            adapter: None,
+            list_adapters: false,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            triangles: 0,
+            draws: 1,
+        }
+    }
+}
+
+impl Options {
+    // skip(1) drops the program name, the rest is matched flag by flag
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        let mut draws = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => {
+                    let value = next_value(&mut args, "--backend")?;
+                    //rotating-cube's parsers leave naming the flag to the caller, clap does it there
+                    options.backends = parse_backend(&value).map_err(|err| format!("--backend: {}", err))?;
+                }
+                "--adapter" => options.adapter = Some(next_value(&mut args, "--adapter")?),
+                "--list-adapters" => options.list_adapters = true,
+                "--power" => {
+                    let value = next_value(&mut args, "--power")?;
+                    options.power_preference = parse_power_preference(&value).map_err(|err| format!("--power: {}", err))?;
+                }
+                "--triangles" => options.triangles = parse_count(&next_value(&mut args, "--triangles")?, "--triangles")?,
+                "--draws" => draws = Some(parse_count(&next_value(&mut args, "--draws")?, "--draws")?),
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+
+        //checked once everything is parsed so the flags can come in either order
+        if let Some(draws) = draws {
+            if options.triangles == 0 {
+                return Err("--draws only applies to the --triangles benchmark".to_string());
+            }
+            if draws > options.triangles {
+                return Err(format!("--draws {} is more draw calls than there are triangles ({})", draws, options.triangles));
+            }
+            options.draws = draws;
+        }
+
+        Ok(options)
+    }
+}
+
+// flags like --backend take the following argument as their value, error out if it is missing
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} expects a value", flag))
+}
+
+// a count of at least 1, for --triangles and --draws
+fn parse_count(value: &str, flag: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("{} expects a whole number above 0, got '{}'", flag, value)),
+    }
+}